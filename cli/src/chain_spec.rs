@@ -29,7 +29,10 @@ use xpallet_gateway_bitcoin::{BtcParams, BtcTxVerifier};
 use xpallet_gateway_common::types::TrusteeInfoConfig;
 
 use crate::genesis::assets::{genesis_assets, init_assets, pcx, AssetParams};
-use crate::genesis::bitcoin::{btc_genesis_params, BtcGenesisParams, BtcTrusteeParams};
+use crate::genesis::bitcoin::{
+    btc_genesis_params, regtest_genesis_params, signet_genesis_params, BtcGenesisParams,
+    BtcTrusteeParams,
+};
 
 use chainx_runtime as chainx;
 use dev_runtime as dev;
@@ -249,6 +252,74 @@ pub fn local_testnet_config() -> Result<DevChainSpec, String> {
     ))
 }
 
+pub fn regtest_config() -> Result<DevChainSpec, String> {
+    let wasm_binary =
+        dev::WASM_BINARY.ok_or_else(|| "Development wasm binary not available".to_string())?;
+
+    let endowed_balance = 50 * DEV_DOLLARS;
+    let constructor = move || {
+        build_genesis(
+            wasm_binary,
+            vec![authority_keys_from_seed("Alice")],
+            get_account_id_from_seed::<sr25519::Public>("Alice"),
+            genesis_assets(),
+            endowed_gen![
+                ("Alice", endowed_balance),
+                ("Bob", endowed_balance),
+                ("Alice//stash", endowed_balance),
+                ("Bob//stash", endowed_balance),
+            ],
+            regtest_genesis_params(),
+            crate::genesis::bitcoin::local_testnet_trustees(),
+        )
+    };
+    Ok(DevChainSpec::from_genesis(
+        "Regtest",
+        "regtest",
+        ChainType::Development,
+        constructor,
+        vec![],
+        None,
+        Some("chainx-regtest"),
+        Some(as_properties(NetworkType::Testnet)),
+        Default::default(),
+    ))
+}
+
+pub fn signet_config() -> Result<DevChainSpec, String> {
+    let wasm_binary =
+        dev::WASM_BINARY.ok_or_else(|| "Development wasm binary not available".to_string())?;
+
+    let endowed_balance = 50 * DEV_DOLLARS;
+    let constructor = move || {
+        build_genesis(
+            wasm_binary,
+            vec![authority_keys_from_seed("Alice")],
+            get_account_id_from_seed::<sr25519::Public>("Alice"),
+            genesis_assets(),
+            endowed_gen![
+                ("Alice", endowed_balance),
+                ("Bob", endowed_balance),
+                ("Alice//stash", endowed_balance),
+                ("Bob//stash", endowed_balance),
+            ],
+            signet_genesis_params(),
+            crate::genesis::bitcoin::local_testnet_trustees(),
+        )
+    };
+    Ok(DevChainSpec::from_genesis(
+        "Signet",
+        "signet",
+        ChainType::Development,
+        constructor,
+        vec![],
+        None,
+        Some("chainx-signet"),
+        Some(as_properties(NetworkType::Testnet)),
+        Default::default(),
+    ))
+}
+
 pub fn mainnet_config() -> Result<ChainXChainSpec, String> {
     ChainXChainSpec::from_json_bytes(&include_bytes!("./res/chainx_regenesis.json")[..])
     // build_mainnet_config()
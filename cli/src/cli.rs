@@ -70,6 +70,76 @@ pub struct RunCmd {
 
     #[structopt(flatten)]
     pub logger: crate::logger::LoggerParams,
+
+    #[structopt(flatten)]
+    pub btc_relayer: BtcRelayerParams,
+}
+
+/// The options of the built-in Bitcoin relayer.
+#[derive(Debug, StructOpt)]
+pub struct BtcRelayerParams {
+    /// Enable the built-in Bitcoin relayer with the JSON-RPC endpoint of bitcoind.
+    ///
+    /// e.g. `http://127.0.0.1:8332`, the bitcoind node must be run with `-txindex`.
+    #[structopt(long, value_name = "URL", requires = "btc-relayer-seed-file")]
+    pub btc_relayer_url: Option<String>,
+
+    /// The rpc user of bitcoind.
+    #[structopt(long, value_name = "USER")]
+    pub btc_relayer_user: Option<String>,
+
+    /// The file containing the rpc password of bitcoind.
+    #[structopt(long, value_name = "PATH", parse(from_os_str))]
+    pub btc_relayer_password_file: Option<std::path::PathBuf>,
+
+    /// The file containing the secret uri of the account signing the relay transactions,
+    /// e.g. `//Alice`.
+    ///
+    /// The secrets are read from files to keep them out of the command line.
+    #[structopt(long, value_name = "PATH", parse(from_os_str))]
+    pub btc_relayer_seed_file: Option<std::path::PathBuf>,
+
+    /// The interval (in seconds) between two relay rounds.
+    #[structopt(long, value_name = "SECONDS", default_value = "30")]
+    pub btc_relayer_interval: u64,
+}
+
+impl BtcRelayerParams {
+    /// Returns the relayer configuration if the relayer is enabled.
+    pub fn config(&self) -> sc_cli::Result<Option<chainx_service::relayer::BtcRelayerConfig>> {
+        let url = match self.btc_relayer_url.clone() {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+        let seed = match self.btc_relayer_seed_file {
+            Some(ref path) => read_secret(path)?,
+            None => return Ok(None),
+        };
+        let password = self
+            .btc_relayer_password_file
+            .as_ref()
+            .map(|path| read_secret(path))
+            .transpose()?;
+        Ok(Some(chainx_service::relayer::BtcRelayerConfig {
+            url,
+            user: self.btc_relayer_user.clone(),
+            password,
+            seed,
+            interval: std::time::Duration::from_secs(self.btc_relayer_interval),
+        }))
+    }
+}
+
+/// Read the secret from the file, the trailing newline is trimmed.
+fn read_secret(path: &std::path::Path) -> sc_cli::Result<String> {
+    let secret = std::fs::read_to_string(path).map_err(|err| {
+        sc_cli::Error::Input(format!(
+            "Failed to read the secret from {}: {}",
+            path.display(),
+            err
+        ))
+    })?;
+    Ok(secret.trim_end().to_string())
 }
 
 impl Cli {
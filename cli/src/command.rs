@@ -201,6 +201,8 @@ fn load_spec(id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
         "dev" => Box::new(chain_spec::development_config()?),
         "malan" | "testnet" => Box::new(chain_spec::malan_config()?),
         "local" => Box::new(chain_spec::local_testnet_config()?),
+        "regtest" => Box::new(chain_spec::regtest_config()?),
+        "signet" => Box::new(chain_spec::signet_config()?),
         "benchmarks" => {
             #[cfg(feature = "runtime-benchmarks")]
             {
@@ -218,7 +220,7 @@ fn load_spec(id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
             let p = std::path::PathBuf::from(path);
             if !p.exists() {
                 // TODO more better hint
-                return Err("invalid path or just use --chain={dev, local, testnet, mainnet, malan, benchmarks}".into());
+                return Err("invalid path or just use --chain={dev, local, regtest, signet, testnet, mainnet, malan, benchmarks}".into());
             }
             Box::new(chain_spec::ChainXChainSpec::from_json_file(p)?)
         }
@@ -279,6 +281,7 @@ pub fn run() -> sc_cli::Result<()> {
             let runner = cli.create_runner(&cli)?;
             let chain_spec = &runner.config().chain_spec;
             set_default_ss58_version(chain_spec);
+            let btc_relayer = cli.run.btc_relayer.config()?;
 
             runner
                 .run_node_until_exit(|config| async move {
@@ -291,7 +294,7 @@ pub fn run() -> sc_cli::Result<()> {
 
                     match config.role {
                         Role::Light => service::build_light(config),
-                        _ => service::build_full(config),
+                        _ => service::build_full(config, btc_relayer),
                     }
                 })
                 .map_err(sc_cli::Error::Service)
@@ -58,6 +58,44 @@ pub fn btc_genesis_params(res: &str) -> BtcGenesisParams {
     params
 }
 
+/// Return the genesis params starting from the genesis block of regtest.
+pub fn regtest_genesis_params() -> BtcGenesisParams {
+    let params = BtcGenesisParams {
+        network: BtcNetwork::Regtest,
+        confirmation_number: 1,
+        height: 0,
+        hash: "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206".into(),
+        version: 1,
+        previous_header_hash: "0000000000000000000000000000000000000000000000000000000000000000"
+            .into(),
+        merkle_root_hash: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".into(),
+        time: 1296688602,
+        bits: BtcCompact::new(0x207fffff),
+        nonce: 2,
+    };
+    assert_eq!(params.header().hash(), params.hash());
+    params
+}
+
+/// Return the genesis params starting from the genesis block of the default signet.
+pub fn signet_genesis_params() -> BtcGenesisParams {
+    let params = BtcGenesisParams {
+        network: BtcNetwork::Signet,
+        confirmation_number: 4,
+        height: 0,
+        hash: "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6".into(),
+        version: 1,
+        previous_header_hash: "0000000000000000000000000000000000000000000000000000000000000000"
+            .into(),
+        merkle_root_hash: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".into(),
+        time: 1598918400,
+        bits: BtcCompact::new(0x1e0377ae),
+        nonce: 52613770,
+    };
+    assert_eq!(params.header().hash(), params.hash());
+    params
+}
+
 // (account_id, about, hot_key, cold_key)
 pub type BtcTrusteeParams = (AccountId, Vec<u8>, Vec<u8>, Vec<u8>);
 
@@ -7,6 +7,7 @@ impl sc_executor::NativeExecutionDispatch for ChainXExecutor {
     type ExtendHostFunctions = (
         frame_benchmarking::benchmarking::HostFunctions,
         xp_io::ss_58_codec::HostFunctions,
+        xp_io::btc_crypto::HostFunctions,
     );
 
     fn dispatch(method: &str, data: &[u8]) -> Option<Vec<u8>> {
@@ -23,6 +24,7 @@ impl sc_executor::NativeExecutionDispatch for DevExecutor {
     type ExtendHostFunctions = (
         frame_benchmarking::benchmarking::HostFunctions,
         xp_io::ss_58_codec::HostFunctions,
+        xp_io::btc_crypto::HostFunctions,
     );
 
     fn dispatch(method: &str, data: &[u8]) -> Option<Vec<u8>> {
@@ -39,6 +41,7 @@ impl sc_executor::NativeExecutionDispatch for MalanExecutor {
     type ExtendHostFunctions = (
         frame_benchmarking::benchmarking::HostFunctions,
         xp_io::ss_58_codec::HostFunctions,
+        xp_io::btc_crypto::HostFunctions,
     );
 
     fn dispatch(method: &str, data: &[u8]) -> Option<Vec<u8>> {
@@ -1,6 +1,6 @@
 // Copyright 2019-2020 ChainX Project Authors. Licensed under GPL-3.0.
 
-use sp_std::fmt::Debug;
+use sp_std::{fmt::Debug, prelude::Vec};
 
 use chainx_primitives::ReferralId;
 use frame_support::log::{debug, warn};
@@ -13,10 +13,9 @@ use light_bitcoin::{
 };
 
 use crate::{
-    types::{BtcDepositInfo, BtcTxMetaType, TrusteePair},
-    utils::{
-        extract_addr_from_transaction, extract_opreturn_data, extract_output_addr, is_trustee_addr,
-    },
+    memo::{extract_deposit_action, DepositAction},
+    types::{BtcDepositInfo, BtcSpentOutput, BtcTxMetaType, TrusteePair},
+    utils::{extract_opreturn_data, extract_output_addr, is_trustee_addr},
 };
 
 /// A helper struct for detecting the bitcoin transaction type.
@@ -40,22 +39,24 @@ impl BtcTxTypeDetector {
     /// Detect X-BTC transaction type.
     ///
     /// We would try to detect `Withdrawal`/`TrusteeTransition`/`HotAndCold` transaction types
-    /// when passing `Some(prev_tx)`, otherwise, we would just detect `Deposit` type.
+    /// when passing the previous transaction of the first input in `prev_txs`, otherwise,
+    /// we would just detect `Deposit` type.
     ///
     /// If the transaction type is `Deposit`, and parsing opreturn successfully,
-    /// we would use opreturn data as account info, otherwise, we would use input_addr, which is
-    /// extracted from `prev_tx`, as the account.
+    /// we would use opreturn data as account info, otherwise, we would use the input addresses,
+    /// which are extracted from `prev_txs`, to find the account.
     ///
-    // If we meet with `prev_tx`, we would parse tx's inputs/outputs into Option<Address>.
-    // e.g. notice the relay tx only has the first input
-    //        _________
-    //  addr |        | Some(addr)
-    //       |   tx   | Some(addr)
-    //       |________| None (OP_RETURN or something unknown)
+    // If we meet with `prev_txs`, we would parse tx's inputs into BtcSpentOutput and outputs
+    // into Option<Address>.
+    // e.g. the input whose previous tx is not relayed is Missing
+    //              _________
+    //  Address    |        | Some(addr)
+    //  Missing    |   tx   | Some(addr)
+    //  Unknown    |________| None (OP_RETURN or something unknown)
     pub fn detect_transaction_type<AccountId, Extractor>(
         &self,
         tx: &Transaction,
-        prev_tx: Option<&Transaction>,
+        prev_txs: &[Transaction],
         extract_account: Extractor,
         current_trustee_pair: TrusteePair,
         last_trustee_pair: Option<TrusteePair>,
@@ -64,11 +65,9 @@ impl BtcTxTypeDetector {
         AccountId: Debug,
         Extractor: Fn(&[u8]) -> Option<(AccountId, Option<ReferralId>)>,
     {
-        // extract input addr from the output of previous transaction
-        let input_addr = prev_tx.and_then(|prev_tx| {
-            let outpoint = &tx.inputs[0].previous_output;
-            extract_addr_from_transaction(prev_tx, outpoint.index as usize, self.network)
-        });
+        // extract input addrs from the outputs of previous transactions
+        let inputs = self.extract_input_addrs(tx, prev_txs);
+        let input_addr = inputs.first().and_then(BtcSpentOutput::address).cloned();
 
         // detect X-BTC `Withdrawal`/`HotAndCold`/`TrusteeTransition` transaction
         if let Some(input_addr) = input_addr {
@@ -95,7 +94,40 @@ impl BtcTxTypeDetector {
         }
 
         // detect X-BTC `Deposit` transaction
-        self.detect_deposit_transaction_type(tx, input_addr, extract_account, current_trustee_pair)
+        self.detect_deposit_transaction_type(tx, inputs, extract_account, current_trustee_pair)
+    }
+
+    /// Extract the output spent by each input of the transaction.
+    ///
+    /// The input is `Missing` if its previous transaction is not in `prev_txs`, or `Unknown` if
+    /// the address could not be extracted from the spent output.
+    pub fn extract_input_addrs(
+        &self,
+        tx: &Transaction,
+        prev_txs: &[Transaction],
+    ) -> Vec<BtcSpentOutput> {
+        let prev_txids = prev_txs
+            .iter()
+            .map(|prev_tx| prev_tx.hash())
+            .collect::<Vec<_>>();
+        tx.inputs
+            .iter()
+            .map(|input| {
+                let outpoint = &input.previous_output;
+                let prev_tx = match prev_txids.iter().position(|txid| *txid == outpoint.txid) {
+                    Some(index) => &prev_txs[index],
+                    None => return BtcSpentOutput::Missing,
+                };
+                match prev_tx.outputs.get(outpoint.index as usize) {
+                    Some(output) => match extract_output_addr(output, self.network) {
+                        Some(addr) => BtcSpentOutput::Address(addr, output.value),
+                        None => BtcSpentOutput::Unknown,
+                    },
+                    // the previous tx is not the one spent by the input
+                    None => BtcSpentOutput::Missing,
+                }
+            })
+            .collect()
     }
 
     /// Detect X-BTC `Deposit` transaction
@@ -143,7 +175,7 @@ impl BtcTxTypeDetector {
     pub fn detect_deposit_transaction_type<AccountId, Extractor>(
         &self,
         tx: &Transaction,
-        input_addr: Option<Address>,
+        inputs: Vec<BtcSpentOutput>,
         extract_account: Extractor,
         current_trustee_pair: TrusteePair,
     ) -> BtcTxMetaType<AccountId>
@@ -151,8 +183,9 @@ impl BtcTxTypeDetector {
         AccountId: Debug,
         Extractor: Fn(&[u8]) -> Option<(AccountId, Option<ReferralId>)>,
     {
-        let (op_return, deposit_value) =
-            self.parse_deposit_transaction_outputs(tx, extract_account, current_trustee_pair);
+        let input_addr = inputs.first().and_then(BtcSpentOutput::address).cloned();
+        let (op_return, action, deposit_value) =
+            self.parse_deposit_outputs(tx, extract_account, current_trustee_pair);
         // check if deposit value is greater than minimum deposit value.
         if deposit_value >= self.min_deposit {
             // if opreturn.is_none() && input_addr.is_none()
@@ -160,7 +193,9 @@ impl BtcTxTypeDetector {
             BtcTxMetaType::Deposit(BtcDepositInfo {
                 deposit_value,
                 op_return,
+                action,
                 input_addr,
+                inputs,
             })
         } else {
             warn!(
@@ -179,11 +214,35 @@ impl BtcTxTypeDetector {
         extract_account: Extractor,
         current_trustee_pair: TrusteePair,
     ) -> (Option<(AccountId, Option<ReferralId>)>, u64)
+    where
+        AccountId: Debug,
+        Extractor: Fn(&[u8]) -> Option<(AccountId, Option<ReferralId>)>,
+    {
+        let (account_info, _, deposit_value) =
+            self.parse_deposit_outputs(tx, extract_account, current_trustee_pair);
+        (account_info, deposit_value)
+    }
+
+    /// Parse the outputs of X-BTC `Deposit` transaction.
+    /// Return the account info and the follow-up action that extracted from OP_RETURN data,
+    /// and the deposit value.
+    #[allow(clippy::type_complexity)]
+    fn parse_deposit_outputs<AccountId, Extractor>(
+        &self,
+        tx: &Transaction,
+        extract_account: Extractor,
+        current_trustee_pair: TrusteePair,
+    ) -> (
+        Option<(AccountId, Option<ReferralId>)>,
+        Option<DepositAction>,
+        u64,
+    )
     where
         AccountId: Debug,
         Extractor: Fn(&[u8]) -> Option<(AccountId, Option<ReferralId>)>,
     {
         let mut account_info = None;
+        let mut action = None;
         // only handle first valid opreturn with account info, other opreturn would be dropped
         for opreturn_script in tx
             .outputs
@@ -195,11 +254,12 @@ impl BtcTxTypeDetector {
                 "[parse_deposit_transaction_outputs] opreturn_script:{:?}",
                 opreturn_script
             );
-            if let Some(info) = extract_opreturn_data(&opreturn_script)
-                .and_then(|opreturn| extract_account(&opreturn))
-            {
-                account_info = Some(info);
-                break;
+            if let Some(opreturn) = extract_opreturn_data(&opreturn_script) {
+                if let Some(info) = extract_account(&opreturn) {
+                    account_info = Some(info);
+                    action = extract_deposit_action(&opreturn);
+                    break;
+                }
             }
         }
 
@@ -215,10 +275,10 @@ impl BtcTxTypeDetector {
             }
         }
         debug!(
-            "[parse_deposit_transaction_outputs] account_info:{:?}, deposit_value:{}",
-            account_info, deposit_value
+            "[parse_deposit_transaction_outputs] account_info:{:?}, action:{:?}, deposit_value:{}",
+            account_info, action, deposit_value
         );
-        (account_info, deposit_value)
+        (account_info, action, deposit_value)
     }
 }
 
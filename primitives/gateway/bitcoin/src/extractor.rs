@@ -7,6 +7,8 @@ use sp_std::prelude::Vec;
 use chainx_primitives::ReferralId;
 use xp_gateway_common::from_ss58_check;
 
+use crate::memo::{DepositMemo, MemoAccount};
+
 pub use xp_gateway_common::AccountExtractor;
 
 /// A helper struct that implements the `AccountExtractor` trait for Bitcoin OP_RETURN data.
@@ -14,11 +16,24 @@ pub use xp_gateway_common::AccountExtractor;
 /// OP_RETURN data format:
 /// - `account`, e.g. 5VEW3R1T4LR3kDhYwXeeCnYrHRwRaH7E9V1KprypBe68XmY4
 /// - `account@referral`, e.g. 5VEW3R1T4LR3kDhYwXeeCnYrHRwRaH7E9V1KprypBe68XmY4@referral1
+/// - binary memo, see `DepositMemo`
 #[derive(PartialEq, Eq, Clone)]
 pub struct OpReturnExtractor;
 
 impl AccountExtractor<AccountId32, ReferralId> for OpReturnExtractor {
     fn extract_account(data: &[u8]) -> Option<(AccountId32, Option<ReferralId>)> {
+        if let Some(memo) = DepositMemo::decode_binary(data) {
+            let account = match memo.account {
+                MemoAccount::Raw(raw) => AccountId32::from(raw),
+                MemoAccount::Ss58(ss58) => from_ss58_check(&ss58)?,
+            };
+            debug!(
+                "[extract_account] binary memo, account:{:?}, referral:{:?}, action:{:?}",
+                account, memo.referral, memo.action
+            );
+            return Some((account, memo.referral));
+        }
+
         let account_and_referral = data
             .split(|x| *x == b'@')
             .map(|d| d.to_vec())
@@ -90,6 +105,31 @@ fn test_opreturn_extractor() {
         #[cfg(not(feature = "ss58check"))]
         assert_eq!(result, Some((addr.unchecked_into(), None)));
     }
+    {
+        set_default_ss58_version(mainnet);
+
+        // test for binary memo
+        let memo = DepositMemo {
+            account: MemoAccount::Raw(addr.0),
+            referral: Some(b"referral1".to_vec()),
+            action: None,
+        };
+        let result = OpReturnExtractor::extract_account(&memo.encode_binary());
+        assert_eq!(
+            result,
+            Some((addr.unchecked_into(), Some(b"referral1".to_vec())))
+        );
+
+        let memo = DepositMemo {
+            account: MemoAccount::Ss58(
+                b"5VEW3R1T4LR3kDhYwXeeCnYrHRwRaH7E9V1KprypBe68XmY4".to_vec(),
+            ),
+            referral: None,
+            action: None,
+        };
+        let result = OpReturnExtractor::extract_account(&memo.encode_binary());
+        assert_eq!(result, Some((addr.unchecked_into(), None)));
+    }
     {
         // test for checksum
         set_default_ss58_version(testnet);
@@ -7,10 +7,14 @@
 
 mod detector;
 mod extractor;
+mod memo;
 mod types;
 mod utils;
 
 pub use self::detector::BtcTxTypeDetector;
 pub use self::extractor::{AccountExtractor, OpReturnExtractor};
-pub use self::types::{BtcDepositInfo, BtcTxMetaType, BtcTxType};
+pub use self::memo::{
+    extract_deposit_action, DepositAction, DepositMemo, MemoAccount, MEMO_VERSION_1,
+};
+pub use self::types::{BtcDepositInfo, BtcSpentOutput, BtcTxMetaType, BtcTxType};
 pub use self::utils::*;
@@ -0,0 +1,105 @@
+// Copyright 2019-2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+use codec::{Decode, DecodeAll, Encode};
+use scale_info::TypeInfo;
+
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::Vec;
+
+use chainx_primitives::ReferralId;
+
+/// The version 1 of binary OP_RETURN memo.
+pub const MEMO_VERSION_1: u8 = 1;
+
+/// The account of the binary OP_RETURN memo.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum MemoAccount {
+    /// The raw 32 bytes account.
+    Raw([u8; 32]),
+    /// The SS58 encoded account.
+    Ss58(Vec<u8>),
+}
+
+/// The follow-up action of a deposit, which is performed after minting X-BTC.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum DepositAction {
+    /// Buy PCX at the lowest ask of the trading pair, paid only by the deposited X-BTC. The part
+    /// which is not filled at the lowest ask is canceled instead of resting in the order book.
+    BuyPcx {
+        /// The trading pair id, of which the base is PCX and the quote is X-BTC.
+        #[codec(compact)]
+        pair_id: u32,
+        /// The amount of PCX to buy.
+        #[codec(compact)]
+        amount: u128,
+    },
+}
+
+/// The binary OP_RETURN memo of a deposit.
+///
+/// Format: `version (1 byte) + SCALE encoded memo`, the first byte of legacy text memo
+/// (`account[@referral]`) is always a printable base58 char, which could never be a version.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct DepositMemo {
+    /// The account to receive the deposit.
+    pub account: MemoAccount,
+    /// The referral of the account.
+    pub referral: Option<ReferralId>,
+    /// The follow-up action after minting.
+    pub action: Option<DepositAction>,
+}
+
+impl DepositMemo {
+    /// Decode the binary memo, return `None` if the data is not a binary memo of known version.
+    pub fn decode_binary(data: &[u8]) -> Option<Self> {
+        let (version, payload) = data.split_first()?;
+        match *version {
+            MEMO_VERSION_1 => Self::decode_all(payload).ok(),
+            _ => None,
+        }
+    }
+
+    /// Encode the memo as the binary memo of the latest version.
+    pub fn encode_binary(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(1 + self.size_hint());
+        data.push(MEMO_VERSION_1);
+        self.encode_to(&mut data);
+        data
+    }
+}
+
+/// Extract the follow-up action of deposit from the OP_RETURN data.
+pub fn extract_deposit_action(data: &[u8]) -> Option<DepositAction> {
+    DepositMemo::decode_binary(data).and_then(|memo| memo.action)
+}
+
+#[test]
+fn test_deposit_memo() {
+    let memo = DepositMemo {
+        account: MemoAccount::Raw([1u8; 32]),
+        referral: Some(b"referral1".to_vec()),
+        action: Some(DepositAction::BuyPcx {
+            pair_id: 0,
+            amount: 100_000_000,
+        }),
+    };
+    let data = memo.encode_binary();
+    // fit in the standard OP_RETURN relay limit
+    assert!(data.len() <= 80);
+    assert_eq!(DepositMemo::decode_binary(&data), Some(memo.clone()));
+    assert_eq!(extract_deposit_action(&data), memo.action);
+
+    // unknown version
+    let mut unknown = data.clone();
+    unknown[0] = 2;
+    assert_eq!(DepositMemo::decode_binary(&unknown), None);
+    // trailing bytes
+    let mut trailing = data;
+    trailing.push(0);
+    assert_eq!(DepositMemo::decode_binary(&trailing), None);
+    // legacy text memo
+    assert_eq!(
+        DepositMemo::decode_binary(b"5VEW3R1T4LR3kDhYwXeeCnYrHRwRaH7E9V1KprypBe68XmY4@referral1"),
+        None
+    );
+}
@@ -6,11 +6,14 @@ use scale_info::TypeInfo;
 use serde::{Deserialize, Serialize};
 
 use sp_runtime::RuntimeDebug;
+use sp_std::prelude::Vec;
 
 use chainx_primitives::ReferralId;
 
 use light_bitcoin::keys::Address;
 
+use crate::memo::DepositAction;
+
 /// (hot trustee address, cold trustee address)
 pub type TrusteePair = (Address, Address);
 
@@ -63,6 +66,31 @@ pub struct BtcDepositInfo<AccountId> {
     pub deposit_value: u64,
     /// The parsed op_return data.
     pub op_return: Option<(AccountId, Option<ReferralId>)>,
+    /// The follow-up action in the binary op_return memo.
+    pub action: Option<DepositAction>,
     /// The input address of deposit transaction.
     pub input_addr: Option<Address>,
+    /// The output spent by each input of deposit transaction.
+    pub inputs: Vec<BtcSpentOutput>,
+}
+
+/// The output spent by an input of the transaction.
+#[derive(PartialEq, Eq, Clone, RuntimeDebug, TypeInfo)]
+pub enum BtcSpentOutput {
+    /// The previous transaction of the input is not provided.
+    Missing,
+    /// The address of the spent output could not be extracted, e.g. bare multisig.
+    Unknown,
+    /// The address and value of the spent output.
+    Address(Address, u64),
+}
+
+impl BtcSpentOutput {
+    /// Return the address of the spent output if it's extracted.
+    pub fn address(&self) -> Option<&Address> {
+        match self {
+            BtcSpentOutput::Address(addr, _) => Some(addr),
+            _ => None,
+        }
+    }
 }
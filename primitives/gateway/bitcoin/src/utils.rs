@@ -5,7 +5,8 @@ use sp_std::{cmp::Ordering, prelude::Vec};
 
 use light_bitcoin::{
     chain::{Transaction, TransactionOutput},
-    keys::{Address, Network},
+    keys::{Address, AddressTypes, Network, Type, XOnly},
+    primitives::{H160, H256},
     script::{Opcode, Script, ScriptType},
 };
 
@@ -21,11 +22,16 @@ pub fn extract_addr_from_transaction(
 }
 
 /// Extract address from a transaction output script.
-/// only support `p2pk`, `p2pkh` and `p2sh` output script
+/// support `p2pk`, `p2pkh`, `p2sh`, `p2wpkh`, `p2wsh` and `p2tr` output script
 pub fn extract_output_addr(output: &TransactionOutput, network: Network) -> Option<Address> {
     let script = Script::new(output.script_pubkey.clone());
 
-    // only support `p2pk`, `p2pkh` and `p2sh` script
+    // witness program is mapped to address directly
+    if let Some(address) = extract_witness_addr(&output.script_pubkey, network) {
+        return Some(address);
+    }
+
+    // only support `p2pk`, `p2pkh` and `p2sh` script for the legacy script
     let script_type = script.script_type();
     let script_addresses = script
         .extract_destinations()
@@ -44,12 +50,7 @@ pub fn extract_output_addr(output: &TransactionOutput, network: Network) -> Opti
     }
     let address = &script_addresses[0];
     match script_type {
-        ScriptType::PubKey
-        | ScriptType::PubKeyHash
-        | ScriptType::ScriptHash
-        | ScriptType::WitnessV0Keyhash
-        | ScriptType::WitnessV0Scripthash
-        | ScriptType::WitnessV1Taproot => {
+        ScriptType::PubKey | ScriptType::PubKeyHash | ScriptType::ScriptHash => {
             // find address in this transaction
             Some(Address {
                 network,
@@ -61,6 +62,34 @@ pub fn extract_output_addr(output: &TransactionOutput, network: Network) -> Opti
     }
 }
 
+/// Extract address from a witness program script.
+/// - `p2wpkh`: OP_0 + OP_PUSHBYTES_20 + <20 bytes key hash>
+/// - `p2wsh`: OP_0 + OP_PUSHBYTES_32 + <32 bytes script hash>
+/// - `p2tr`: OP_1 + OP_PUSHBYTES_32 + <32 bytes x-only public key>
+pub fn extract_witness_addr(script_pubkey: &[u8], network: Network) -> Option<Address> {
+    let (kind, hash) = match script_pubkey {
+        [0x00, 0x14, program @ ..] if program.len() == 20 => (
+            Type::P2WPKH,
+            AddressTypes::WitnessV0KeyHash(H160::from_slice(program)),
+        ),
+        [0x00, 0x20, program @ ..] if program.len() == 32 => (
+            Type::P2WSH,
+            AddressTypes::WitnessV0ScriptHash(H256::from_slice(program)),
+        ),
+        [0x51, 0x20, program @ ..] if program.len() == 32 => {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(program);
+            (Type::P2TR, AddressTypes::WitnessV1Taproot(XOnly(key)))
+        }
+        _ => return None,
+    };
+    Some(Address {
+        kind,
+        network,
+        hash,
+    })
+}
+
 /// Check if the `addr` is hot trustee address or cold trustee address.
 pub fn is_trustee_addr(addr: Address, trustee_pair: (Address, Address)) -> bool {
     let (hot_addr, cold_addr) = trustee_pair;
@@ -156,3 +185,46 @@ fn test_extract_opreturn_data() {
         b"5QZYGVVUPsp7cbqGUcHsRJUZrnmTuEyh6SLH6jdpfsFxgpRK@Laocius".to_vec()
     );
 }
+
+#[test]
+fn test_extract_witness_addr() {
+    use light_bitcoin::primitives::Bytes;
+
+    let cases = [
+        // p2wpkh
+        (
+            "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        ),
+        // p2wsh
+        (
+            "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+        ),
+        // p2tr
+        (
+            "51209a9ea267884f5549c206b2aec2bd56d98730f90532ea7f7154d4d4f923b7e3bb",
+            "bc1pn202yeugfa25nssxk2hv902kmxrnp7g9xt487u256n20jgahuwas6syxhp",
+        ),
+    ];
+    for (script, addr) in cases.iter() {
+        let output = TransactionOutput {
+            value: 0,
+            script_pubkey: Bytes::from(hex::decode(script).unwrap()),
+        };
+        let expected = addr.parse::<Address>().unwrap();
+        assert_eq!(
+            extract_output_addr(&output, Network::Mainnet),
+            Some(expected)
+        );
+    }
+
+    // witness program with invalid length
+    assert_eq!(
+        extract_witness_addr(
+            &hex::decode("0013751e76e8199196d454941c45d1b3a323f1433b").unwrap(),
+            Network::Mainnet
+        ),
+        None
+    );
+}
@@ -0,0 +1,105 @@
+// Copyright 2019-2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+//! BIP-340 Schnorr signature verification over the curve arithmetic of `libsecp256k1`.
+
+use std::convert::TryInto;
+
+use libsecp256k1::{
+    curve::{Affine, Field, Jacobian, Scalar},
+    ECMULT_CONTEXT,
+};
+use sp_core::hashing::sha2_256;
+
+/// hash = sha256(sha256(tag) + sha256(tag) + data)
+fn tagged_hash(tag: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = sha2_256(tag);
+    let mut input = Vec::with_capacity(64 + data.iter().map(|d| d.len()).sum::<usize>());
+    input.extend_from_slice(&tag_hash);
+    input.extend_from_slice(&tag_hash);
+    for d in data {
+        input.extend_from_slice(d);
+    }
+    sha2_256(&input)
+}
+
+/// The point with even y of the x-only public key.
+fn lift_x(pubkey: &[u8; 32]) -> Option<Affine> {
+    let mut x = Field::default();
+    if !x.set_b32(pubkey) {
+        return None;
+    }
+    let mut p = Affine::default();
+    if !p.set_xo_var(&x, false) {
+        return None;
+    }
+    Some(p)
+}
+
+pub fn verify(sig: &[u8; 64], msg: &[u8; 32], pubkey: &[u8; 32]) -> bool {
+    // P = lift_x(pubkey)
+    let p = match lift_x(pubkey) {
+        Some(p) => p,
+        None => return false,
+    };
+
+    let r_bytes: [u8; 32] = sig[..32].try_into().expect("the length is 32; qed");
+    let s_bytes: [u8; 32] = sig[32..].try_into().expect("the length is 32; qed");
+    // r must be less than the field size, and s must be less than the curve order
+    let mut r = Field::default();
+    if !r.set_b32(&r_bytes) {
+        return false;
+    }
+    let mut s = Scalar::default();
+    if bool::from(s.set_b32(&s_bytes)) {
+        return false;
+    }
+
+    // e = int(tagged_hash("BIP0340/challenge", r + pubkey + msg)) mod n
+    let e_bytes = tagged_hash(b"BIP0340/challenge", &[&r_bytes, pubkey, msg]);
+    let mut e = Scalar::default();
+    let _ = e.set_b32(&e_bytes);
+
+    // R = s * G - e * P
+    let mut rj = Jacobian::default();
+    ECMULT_CONTEXT.ecmult(&mut rj, &Jacobian::from_ge(&p), &-e, &s);
+    if rj.is_infinity() {
+        return false;
+    }
+    let mut ra = Affine::from_gej(&rj);
+    ra.x.normalize_var();
+    ra.y.normalize_var();
+    !ra.y.is_odd() && ra.x.eq_var(&r)
+}
+
+/// Check the BIP-341 output key commits to the internal key with the tweak, i.e.
+/// `Q = lift_x(internal_key) + int(tweak) * G`, and the parity of `Q.y` is `output_parity`.
+pub fn check_tweak(
+    internal_key: &[u8; 32],
+    tweak: &[u8; 32],
+    output_key: &[u8; 32],
+    output_parity: bool,
+) -> bool {
+    let p = match lift_x(internal_key) {
+        Some(p) => p,
+        None => return false,
+    };
+    let mut t = Scalar::default();
+    if bool::from(t.set_b32(tweak)) {
+        return false;
+    }
+    let mut q_x = Field::default();
+    if !q_x.set_b32(output_key) {
+        return false;
+    }
+
+    // Q = 1 * P + t * G
+    let mut qj = Jacobian::default();
+    ECMULT_CONTEXT.ecmult(&mut qj, &Jacobian::from_ge(&p), &Scalar::from_int(1), &t);
+    if qj.is_infinity() {
+        return false;
+    }
+    let mut qa = Affine::from_gej(&qj);
+    qa.x.normalize_var();
+    qa.y.normalize_var();
+    qa.y.is_odd() == output_parity && qa.x.eq_var(&q_x)
+}
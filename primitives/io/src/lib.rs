@@ -2,6 +2,9 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
+mod bip340;
+
 use codec::{Decode, Encode};
 
 use sp_core::crypto::AccountId32;
@@ -50,6 +53,40 @@ pub trait Ss58Codec {
     }
 }
 
+/// Verify the signatures of bitcoin transactions over the native secp256k1.
+///
+/// NOTE: these are host functions, the nodes must be upgraded to the version providing them
+/// before a runtime calling them is enacted, otherwise the outdated nodes could not execute
+/// the runtime.
+#[runtime_interface]
+pub trait BtcCrypto {
+    /// Verify an ECDSA signature (`r + s`) of the message hash with the compressed public key.
+    fn ecdsa_verify(sig: &[u8; 64], msg: &[u8; 32], pubkey: &[u8; 33]) -> bool {
+        let pubkey = match libsecp256k1::PublicKey::parse_compressed(pubkey) {
+            Ok(pubkey) => pubkey,
+            Err(_) => return false,
+        };
+        // keep same to the recovery of `sp_io`, which allows the overflowing `r` and `s`
+        let sig = libsecp256k1::Signature::parse_overflowing(sig);
+        libsecp256k1::verify(&libsecp256k1::Message::parse(msg), &sig, &pubkey)
+    }
+
+    /// Verify a BIP-340 Schnorr signature of the message with the x-only public key.
+    fn schnorr_verify(sig: &[u8; 64], msg: &[u8; 32], pubkey: &[u8; 32]) -> bool {
+        bip340::verify(sig, msg, pubkey)
+    }
+
+    /// Verify the BIP-341 output key is the internal key tweaked by `tweak`.
+    fn taproot_tweak_verify(
+        internal_key: &[u8; 32],
+        tweak: &[u8; 32],
+        output_key: &[u8; 32],
+        output_parity: bool,
+    ) -> bool {
+        bip340::check_tweak(internal_key, tweak, output_key, output_parity)
+    }
+}
+
 #[test]
 fn ss58_check() {
     use sp_core::crypto::{set_default_ss58_version, Ss58AddressFormat};
@@ -69,3 +106,79 @@ fn ss58_check() {
     assert_eq!(AsRef::<[u8]>::as_ref(&account), pubkey.as_slice());
     assert!(ss_58_codec::from_ss58check(addr44).is_err());
 }
+
+#[test]
+fn btc_crypto_verify() {
+    // BIP-340 test vectors
+    let cases = [
+        (
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0",
+        ),
+        (
+            "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+            "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+            "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+        ),
+    ];
+    for (pubkey, msg, sig) in cases.iter() {
+        let mut pubkey_bytes = [0u8; 32];
+        pubkey_bytes.copy_from_slice(&hex::decode(pubkey).unwrap());
+        let mut msg_bytes = [0u8; 32];
+        msg_bytes.copy_from_slice(&hex::decode(msg).unwrap());
+        let mut sig_bytes = [0u8; 64];
+        sig_bytes.copy_from_slice(&hex::decode(sig).unwrap());
+        assert!(btc_crypto::schnorr_verify(
+            &sig_bytes,
+            &msg_bytes,
+            &pubkey_bytes
+        ));
+        // tampered message
+        msg_bytes[0] ^= 1;
+        assert!(!btc_crypto::schnorr_verify(
+            &sig_bytes,
+            &msg_bytes,
+            &pubkey_bytes
+        ));
+    }
+}
+
+#[test]
+fn btc_crypto_taproot_tweak_verify() {
+    // the trustee MAST address of the taproot withdrawal fixtures
+    let internal_key =
+        hex::decode("e56a1792f348690cdeebe60e3db6c4e94d94e742c619f7278e52f6cbadf5efe9").unwrap();
+    let tweak =
+        hex::decode("d53382da789f1188250cb28468f0228c339757f99f739f7ca70842b1416a2b3b").unwrap();
+    let output_key =
+        hex::decode("9a9ea267884f5549c206b2aec2bd56d98730f90532ea7f7154d4d4f923b7e3bb").unwrap();
+    let mut internal_key_bytes = [0u8; 32];
+    internal_key_bytes.copy_from_slice(&internal_key);
+    let mut tweak_bytes = [0u8; 32];
+    tweak_bytes.copy_from_slice(&tweak);
+    let mut output_key_bytes = [0u8; 32];
+    output_key_bytes.copy_from_slice(&output_key);
+
+    assert!(btc_crypto::taproot_tweak_verify(
+        &internal_key_bytes,
+        &tweak_bytes,
+        &output_key_bytes,
+        false
+    ));
+    // wrong parity
+    assert!(!btc_crypto::taproot_tweak_verify(
+        &internal_key_bytes,
+        &tweak_bytes,
+        &output_key_bytes,
+        true
+    ));
+    // tampered tweak
+    tweak_bytes[0] ^= 1;
+    assert!(!btc_crypto::taproot_tweak_verify(
+        &internal_key_bytes,
+        &tweak_bytes,
+        &output_key_bytes,
+        false
+    ));
+}
@@ -100,6 +100,7 @@ where
     C::Api: xpallet_assets_rpc_runtime_api::XAssetsApi<Block, AccountId, Balance>,
     C::Api:
         xpallet_dex_spot_rpc_runtime_api::XSpotApi<Block, AccountId, Balance, BlockNumber, Balance>,
+    C::Api: xpallet_gateway_bitcoin_rpc_runtime_api::XGatewayBitcoinApi<Block, AccountId, Balance>,
     C::Api: xpallet_gateway_common_rpc_runtime_api::XGatewayCommonApi<Block, AccountId, Balance>,
     C::Api: xpallet_gateway_records_rpc_runtime_api::XGatewayRecordsApi<
         Block,
@@ -131,6 +132,7 @@ where
     use substrate_frame_rpc_system::{FullSystem, SystemApi};
     use xpallet_assets_rpc::{Assets, XAssetsApi};
     use xpallet_dex_spot_rpc::{XSpot, XSpotApi};
+    use xpallet_gateway_bitcoin_rpc::{XGatewayBitcoin, XGatewayBitcoinApi};
     use xpallet_gateway_common_rpc::{XGatewayCommon, XGatewayCommonApi};
     use xpallet_gateway_records_rpc::{XGatewayRecords, XGatewayRecordsApi};
     use xpallet_mining_asset_rpc::{XMiningAsset, XMiningAssetApi};
@@ -209,6 +211,9 @@ where
     io.extend_with(XGatewayRecordsApi::to_delegate(XGatewayRecords::new(
         client.clone(),
     )));
+    io.extend_with(XGatewayBitcoinApi::to_delegate(XGatewayBitcoin::new(
+        client.clone(),
+    )));
     io.extend_with(XGatewayCommonApi::to_delegate(XGatewayCommon::new(client)));
 
     Ok(io)
@@ -83,14 +83,19 @@ pub use xpallet_assets::{
 #[cfg(feature = "std")]
 pub use xpallet_gateway_bitcoin::h256_rev;
 pub use xpallet_gateway_bitcoin::{
-    hash_rev, BtcHeader, BtcNetwork, BtcParams, BtcTxVerifier, Compact as BtcCompact,
-    H256 as BtcHash,
+    hash_rev, BtcAddress, BtcDepositCache, BtcHeader, BtcHeaderIndex, BtcHeaderInfo, BtcNetwork,
+    BtcParams, BtcProofOfReserves, BtcTrusteeUtxo, BtcTxState, BtcTxVerifier,
+    BtcWithdrawalProposal, Compact as BtcCompact, H256 as BtcHash,
 };
 pub use xpallet_gateway_common::{
     trustees,
-    types::{GenericTrusteeIntentionProps, GenericTrusteeSessionInfo, TrusteeInfoConfig},
+    types::{
+        GenericTrusteeIntentionProps, GenericTrusteeSessionInfo, TrusteeInfoConfig, TrusteeStats,
+    },
+};
+pub use xpallet_gateway_records::{
+    ArchivedWithdrawal, Withdrawal, WithdrawalFilter, WithdrawalPage,
 };
-pub use xpallet_gateway_records::Withdrawal;
 pub use xpallet_mining_asset::MiningWeight;
 pub use xpallet_mining_staking::VoteWeight;
 
@@ -107,10 +112,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("chainx"),
     impl_name: create_runtime_str!("chainx-malan"),
     authoring_version: 1,
-    spec_version: 2,
+    spec_version: 3,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
-    transaction_version: 1,
+    transaction_version: 2,
 };
 
 /// The version information used to identify this runtime when compiled natively.
@@ -982,8 +987,13 @@ impl xpallet_assets::Config for Runtime {
     type WeightInfo = xpallet_assets::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+    pub const MaxArchivedWithdrawals: u32 = 10_000;
+}
+
 impl xpallet_gateway_records::Config for Runtime {
     type Event = Event;
+    type MaxArchivedWithdrawals = MaxArchivedWithdrawals;
     type WeightInfo = xpallet_gateway_records::weights::SubstrateWeight<Runtime>;
 }
 
@@ -994,15 +1004,31 @@ impl MultisigAddressFor<AccountId> for MultisigProvider {
     }
 }
 
+parameter_types! {
+    pub const TrusteeBond: Balance = 100 * DOLLARS;
+    pub const MaxRejectedValidProposals: u32 = 10;
+    pub const RejectionSlash: Perbill = Perbill::from_percent(10);
+    pub const MaxQueuedWithdrawalReleases: u32 = 50;
+}
+
 impl xpallet_gateway_common::Config for Runtime {
     type Event = Event;
     type Validator = XStaking;
     type DetermineMultisigAddress = MultisigProvider;
     type Bitcoin = XGatewayBitcoin;
     type BitcoinTrustee = XGatewayBitcoin;
+    type TrusteeBond = TrusteeBond;
+    type MaxRejectedValidProposals = MaxRejectedValidProposals;
+    type RejectionSlash = RejectionSlash;
+    type MaxQueuedWithdrawalReleases = MaxQueuedWithdrawalReleases;
     type WeightInfo = xpallet_gateway_common::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+    // Enough for one `put_order` and the `cancel_order` of its unfilled remainder.
+    pub const MaxDepositActionWeight: Weight = 2_500_000_000;
+}
+
 impl xpallet_gateway_bitcoin::Config for Runtime {
     type Event = Event;
     type UnixTime = Timestamp;
@@ -1011,6 +1037,8 @@ impl xpallet_gateway_bitcoin::Config for Runtime {
     type TrusteeOrigin = EnsureSignedBy<trustees::bitcoin::BtcTrusteeMultisig<Runtime>, AccountId>;
     type ReferralBinding = XGatewayCommon;
     type AddressBinding = XGatewayCommon;
+    type OnBtcDeposited = xpallet_gateway_bitcoin::DepositActionHandler<Runtime>;
+    type MaxDepositActionWeight = MaxDepositActionWeight;
     type WeightInfo = xpallet_gateway_bitcoin::weights::SubstrateWeight<Runtime>;
 }
 
@@ -1449,6 +1477,84 @@ impl_runtime_apis! {
         fn withdrawal_list_by_chain(chain: Chain) -> BTreeMap<u32, Withdrawal<AccountId, Balance, BlockNumber>> {
             XGatewayRecords::withdrawals_list_by_chain(chain)
         }
+
+        fn withdrawal_page(
+            filter: WithdrawalFilter<AccountId, BlockNumber>,
+            cursor: Option<u32>,
+            page_size: u32,
+        ) -> WithdrawalPage<AccountId, Balance, BlockNumber> {
+            XGatewayRecords::withdrawal_page(filter, cursor, page_size)
+        }
+
+        fn archived_withdrawal(id: u32) -> Option<ArchivedWithdrawal<AccountId, Balance, BlockNumber>> {
+            XGatewayRecords::archived_withdrawal(id)
+        }
+
+        fn archived_withdrawal_list(offset: u32, limit: u32) -> Vec<(u32, ArchivedWithdrawal<AccountId, Balance, BlockNumber>)> {
+            XGatewayRecords::archived_withdrawal_list(offset, limit)
+        }
+    }
+
+    impl xpallet_gateway_bitcoin_rpc_runtime_api::XGatewayBitcoinApi<Block, AccountId, Balance> for Runtime {
+        fn trustee_utxos() -> Vec<(BtcHash, u32, BtcTrusteeUtxo)> {
+            XGatewayBitcoin::trustee_utxos()
+        }
+
+        fn proof_of_reserves() -> BtcProofOfReserves<Balance> {
+            XGatewayBitcoin::proof_of_reserves()
+        }
+
+        fn best_index() -> BtcHeaderIndex {
+            XGatewayBitcoin::best_index()
+        }
+
+        fn confirmed_index() -> Option<BtcHeaderIndex> {
+            XGatewayBitcoin::confirmed_index()
+        }
+
+        fn header(hash: BtcHash) -> Option<BtcHeaderInfo> {
+            XGatewayBitcoin::headers(hash)
+        }
+
+        fn main_chain_header(height: u32) -> Option<BtcHeaderInfo> {
+            XGatewayBitcoin::main_chain_header(height)
+        }
+
+        fn tx_state(txid: BtcHash) -> Option<BtcTxState> {
+            XGatewayBitcoin::tx_state(txid)
+        }
+
+        fn is_tx_processed(txid: BtcHash) -> bool {
+            XGatewayBitcoin::is_tx_processed(txid)
+        }
+
+        fn pending_deposits(addr: Option<BtcAddress>) -> Vec<(BtcAddress, Vec<BtcDepositCache>)> {
+            XGatewayBitcoin::pending_deposit_list(addr)
+        }
+
+        fn withdrawal_proposal(id: u32) -> Option<BtcWithdrawalProposal<AccountId>> {
+            XGatewayBitcoin::withdrawal_proposal(id)
+        }
+
+        fn withdrawal_proposals() -> Vec<(u32, BtcWithdrawalProposal<AccountId>)> {
+            XGatewayBitcoin::withdrawal_proposal_list()
+        }
+
+        fn verify_address(addr: BtcAddress) -> Result<(), DispatchError> {
+            XGatewayBitcoin::verify_network_address(&addr).map(|_| ())
+        }
+
+        fn network_id() -> BtcNetwork {
+            XGatewayBitcoin::network_id()
+        }
+
+        fn min_deposit() -> u64 {
+            XGatewayBitcoin::btc_min_deposit()
+        }
+
+        fn trustee_address_pairs() -> Vec<(BtcAddress, BtcAddress)> {
+            XGatewayBitcoin::trustee_address_pairs()
+        }
     }
 
     impl xpallet_gateway_common_rpc_runtime_api::XGatewayCommonApi<Block, AccountId, Balance> for Runtime {
@@ -1485,6 +1591,10 @@ impl_runtime_apis! {
             let _ = XGatewayCommon::generate_multisig_addr(chain, &info)?;
             Ok(info)
         }
+
+        fn trustee_stats(chain: Chain, session_number: Option<u32>) -> Vec<(AccountId, TrusteeStats)> {
+            XGatewayCommon::trustee_stats(chain, session_number)
+        }
     }
 
     #[cfg(feature = "runtime-benchmarks")]
@@ -15,6 +15,7 @@ use sc_network::{Event, NetworkService};
 use sc_service::{config::Configuration, error::Error as ServiceError, RpcHandlers, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sp_api::ConstructRuntimeApi;
+use sp_core::{sr25519, Pair};
 use sp_runtime::traits::Block as BlockT;
 
 use chainx_primitives::Block;
@@ -22,6 +23,12 @@ use chainx_primitives::Block;
 mod client;
 use client::RuntimeApiCollection;
 
+pub mod relayer;
+use relayer::{
+    BitcoindClient, BtcRelayer, BtcRelayerConfig, ChainXExtrinsicBuilder, ClientRelayChain,
+    DevExtrinsicBuilder, MalanExtrinsicBuilder, RelayExtrinsicBuilder,
+};
+
 type LightBackend = sc_service::TLightBackendWithHash<Block, sp_runtime::traits::BlakeTwo256>;
 
 type LightClient<RuntimeApi, Executor> = sc_service::TLightClientWithBackend<
@@ -243,9 +250,11 @@ where
         Arc<sc_transaction_pool::FullPool<Block, FullClient<RuntimeApi, Executor>>>,
 }
 
-/// Creates a full service from the configuration.
+/// Creates a full service from the configuration, the built-in Bitcoin relayer is started
+/// if its configuration is given.
 pub fn new_full_base<RuntimeApi, Executor>(
     mut config: Configuration,
+    btc_relayer: Option<(BtcRelayerConfig, Box<dyn RelayExtrinsicBuilder>)>,
 ) -> Result<NewFullBase<RuntimeApi, Executor>, ServiceError>
 where
     RuntimeApi:
@@ -325,6 +334,23 @@ where
         telemetry: telemetry.as_mut(),
     })?;
 
+    if let Some((relayer_config, builder)) = btc_relayer {
+        let signer = sr25519::Pair::from_string(&relayer_config.seed, None).map_err(|err| {
+            ServiceError::Other(format!("Invalid seed of the btc relayer: {:?}", err))
+        })?;
+        let bitcoind = BitcoindClient::new(
+            relayer_config.url,
+            relayer_config.user,
+            relayer_config.password,
+        );
+        let chain =
+            ClientRelayChain::new(client.clone(), transaction_pool.clone(), signer, builder);
+        task_manager.spawn_handle().spawn(
+            "btc-relayer",
+            BtcRelayer::new(bitcoind, chain).run(relayer_config.interval),
+        );
+    }
+
     let (block_import, grandpa_link, babe_link) = import_setup;
 
     if let sc_service::config::Role::Authority { .. } = &role {
@@ -469,7 +495,10 @@ where
 }
 
 /// Builds a new service for a full client.
-pub fn new_full<RuntimeApi, Executor>(config: Configuration) -> Result<TaskManager, ServiceError>
+pub fn new_full<RuntimeApi, Executor>(
+    config: Configuration,
+    btc_relayer: Option<(BtcRelayerConfig, Box<dyn RelayExtrinsicBuilder>)>,
+) -> Result<TaskManager, ServiceError>
 where
     RuntimeApi:
         ConstructRuntimeApi<Block, FullClient<RuntimeApi, Executor>> + Send + Sync + 'static,
@@ -477,7 +506,8 @@ where
         RuntimeApiCollection<StateBackend = sc_client_api::StateBackendFor<FullBackend, Block>>,
     Executor: NativeExecutionDispatch + 'static,
 {
-    new_full_base(config).map(|base: NewFullBase<RuntimeApi, Executor>| base.task_manager)
+    new_full_base(config, btc_relayer)
+        .map(|base: NewFullBase<RuntimeApi, Executor>| base.task_manager)
 }
 
 pub struct NewLightBase<RuntimeApi, Executor>
@@ -709,13 +739,34 @@ impl IdentifyVariant for Box<dyn sc_service::ChainSpec> {
     }
 }
 
-pub fn build_full(config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn build_full(
+    config: Configuration,
+    btc_relayer: Option<BtcRelayerConfig>,
+) -> Result<TaskManager, ServiceError> {
     if config.chain_spec.is_chainx() {
-        new_full::<chainx_runtime::RuntimeApi, chainx_executor::ChainXExecutor>(config)
+        let btc_relayer = btc_relayer.map(|c| {
+            (
+                c,
+                Box::new(ChainXExtrinsicBuilder) as Box<dyn RelayExtrinsicBuilder>,
+            )
+        });
+        new_full::<chainx_runtime::RuntimeApi, chainx_executor::ChainXExecutor>(config, btc_relayer)
     } else if config.chain_spec.is_malan() {
-        new_full::<malan_runtime::RuntimeApi, chainx_executor::MalanExecutor>(config)
+        let btc_relayer = btc_relayer.map(|c| {
+            (
+                c,
+                Box::new(MalanExtrinsicBuilder) as Box<dyn RelayExtrinsicBuilder>,
+            )
+        });
+        new_full::<malan_runtime::RuntimeApi, chainx_executor::MalanExecutor>(config, btc_relayer)
     } else {
-        new_full::<dev_runtime::RuntimeApi, chainx_executor::DevExecutor>(config)
+        let btc_relayer = btc_relayer.map(|c| {
+            (
+                c,
+                Box::new(DevExtrinsicBuilder) as Box<dyn RelayExtrinsicBuilder>,
+            )
+        });
+        new_full::<dev_runtime::RuntimeApi, chainx_executor::DevExecutor>(config, btc_relayer)
     }
 }
 
@@ -0,0 +1,168 @@
+// Copyright 2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+//! A minimal client of the Bitcoin Core JSON-RPC interface.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use hyper::{
+    client::HttpConnector,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Body, Client, Request,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use light_bitcoin::{
+    chain::{Block as BtcBlock, BlockHeader as BtcHeader, Transaction},
+    merkle::PartialMerkleTree,
+    primitives::{hash_rev, H256},
+    serialization::{deserialize, Deserializable, Reader},
+};
+
+use super::Error;
+
+/// The size of the serialized block header, which is the prefix of the `gettxoutproof` result.
+const HEADER_SIZE: usize = 80;
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// The client of a bitcoind node, the node must be run with `-txindex` so that the previous
+/// transactions could be fetched.
+pub struct BitcoindClient {
+    url: String,
+    auth: Option<String>,
+    client: Client<HttpConnector>,
+    next_id: AtomicU64,
+}
+
+impl BitcoindClient {
+    /// Create a client with the url of the bitcoind JSON-RPC endpoint, e.g. `http://127.0.0.1:8332`.
+    pub fn new(url: String, user: Option<String>, password: Option<String>) -> Self {
+        let auth = user.map(|user| {
+            let credential = format!("{}:{}", user, password.unwrap_or_default());
+            format!("Basic {}", base64::encode(credential))
+        });
+        Self {
+            url,
+            auth,
+            client: Client::new(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({
+            "jsonrpc": "1.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        let mut request = Request::post(&self.url).header(CONTENT_TYPE, "application/json");
+        if let Some(auth) = &self.auth {
+            request = request.header(AUTHORIZATION, auth.as_str());
+        }
+        let request = request
+            .body(Body::from(body.to_string()))
+            .map_err(|err| Error::Bitcoind(err.to_string()))?;
+
+        // bitcoind responds the rpc errors with a non-200 status code, so just parse the body
+        let response = self.client.request(request).await?;
+        let bytes = hyper::body::to_bytes(response.into_body()).await?;
+        let response: RpcResponse = serde_json::from_slice(&bytes)
+            .map_err(|err| Error::Bitcoind(format!("Invalid response of {}: {}", method, err)))?;
+        if let Some(err) = response.error {
+            return Err(Error::Bitcoind(format!(
+                "{} failed, code:{}, message:{}",
+                method, err.code, err.message
+            )));
+        }
+        serde_json::from_value(response.result.unwrap_or(Value::Null))
+            .map_err(|err| Error::Bitcoind(format!("Invalid result of {}: {}", method, err)))
+    }
+
+    async fn request_raw<T: Deserializable>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Error> {
+        let raw = self.request::<String>(method, params).await?;
+        let bytes = hex::decode(&raw).map_err(|err| Error::Decode(err.to_string()))?;
+        deserialize(Reader::new(&bytes))
+            .map_err(|err| Error::Decode(format!("{} result: {:?}", method, err)))
+    }
+
+    /// Return the height of the best block.
+    pub async fn block_count(&self) -> Result<u32, Error> {
+        self.request("getblockcount", json!([])).await
+    }
+
+    /// Return the hash of the block at the height on the best chain.
+    pub async fn block_hash(&self, height: u32) -> Result<H256, Error> {
+        let hash = self
+            .request::<String>("getblockhash", json!([height]))
+            .await?;
+        decode_hash(&hash)
+    }
+
+    pub async fn block_header(&self, hash: H256) -> Result<BtcHeader, Error> {
+        self.request_raw("getblockheader", json!([encode_hash(hash), false]))
+            .await
+    }
+
+    pub async fn block(&self, hash: H256) -> Result<BtcBlock, Error> {
+        self.request_raw("getblock", json!([encode_hash(hash), 0]))
+            .await
+    }
+
+    pub async fn raw_transaction(&self, txid: H256) -> Result<Transaction, Error> {
+        self.request_raw("getrawtransaction", json!([encode_hash(txid), false]))
+            .await
+    }
+
+    /// Return the merkle proof matching all the txs in the block.
+    pub async fn tx_out_proof(
+        &self,
+        txids: &[H256],
+        block_hash: H256,
+    ) -> Result<PartialMerkleTree, Error> {
+        let txids = txids
+            .iter()
+            .map(|txid| encode_hash(*txid))
+            .collect::<Vec<_>>();
+        let raw = self
+            .request::<String>("gettxoutproof", json!([txids, encode_hash(block_hash)]))
+            .await?;
+        let bytes = hex::decode(&raw).map_err(|err| Error::Decode(err.to_string()))?;
+        // the proof is a serialized `CMerkleBlock`, i.e. the block header and the merkle tree
+        if bytes.len() <= HEADER_SIZE {
+            return Err(Error::Decode(format!("Invalid tx out proof: {}", raw)));
+        }
+        deserialize(Reader::new(&bytes[HEADER_SIZE..]))
+            .map_err(|err| Error::Decode(format!("gettxoutproof result: {:?}", err)))
+    }
+}
+
+/// Encode the hash in the bitcoin explorer format (byte reversed).
+pub fn encode_hash(hash: H256) -> String {
+    hex::encode(hash_rev(hash))
+}
+
+/// Decode the hash in the bitcoin explorer format (byte reversed).
+pub fn decode_hash(hash: &str) -> Result<H256, Error> {
+    let bytes = hex::decode(hash).map_err(|err| Error::Decode(err.to_string()))?;
+    if bytes.len() != H256::len_bytes() {
+        return Err(Error::Decode(format!("Invalid hash: {}", hash)));
+    }
+    Ok(hash_rev(H256::from_slice(&bytes)))
+}
@@ -0,0 +1,291 @@
+// Copyright 2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+//! The relay chain view backed by the local client and transaction pool.
+
+use std::sync::{Arc, Mutex};
+
+use codec::Encode;
+use frame_system_rpc_runtime_api::AccountNonceApi;
+use sc_transaction_pool_api::{TransactionPool, TransactionSource};
+use sp_api::{Core, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::{sr25519, Pair};
+use sp_runtime::{
+    generic::{BlockId, Era},
+    traits::IdentifyAccount,
+    MultiAddress, MultiSignature, MultiSigner, OpaqueExtrinsic,
+};
+
+use chainx_primitives::{AccountId, Balance, Block, BlockNumber, Hash, Index};
+use xpallet_gateway_bitcoin::{BtcHeaderIndex, BtcRelayedTxInfo, H256};
+use xpallet_gateway_bitcoin_rpc_runtime_api::XGatewayBitcoinApi;
+
+use super::{parse_btc_address, Error, RelayChain, RelayContext};
+
+/// The call of the bitcoin relay.
+pub enum RelayCall {
+    PushHeaders(Vec<Vec<u8>>),
+    PushTransactions(Vec<(Vec<u8>, BtcRelayedTxInfo, Vec<Vec<u8>>)>),
+}
+
+/// The chain state committed by the signature of an extrinsic.
+pub struct SigningContext {
+    pub genesis_hash: Hash,
+    pub best_hash: Hash,
+    pub best_number: BlockNumber,
+    pub spec_version: u32,
+    pub transaction_version: u32,
+}
+
+/// Build the signed extrinsic of the relay call for a specific runtime.
+pub trait RelayExtrinsicBuilder: Send + Sync {
+    fn build(
+        &self,
+        call: RelayCall,
+        signer: &sr25519::Pair,
+        nonce: Index,
+        context: &SigningContext,
+    ) -> OpaqueExtrinsic;
+}
+
+macro_rules! impl_relay_extrinsic_builder {
+    ($builder:ident, $runtime:ident) => {
+        pub struct $builder;
+
+        impl RelayExtrinsicBuilder for $builder {
+            fn build(
+                &self,
+                call: RelayCall,
+                signer: &sr25519::Pair,
+                nonce: Index,
+                context: &SigningContext,
+            ) -> OpaqueExtrinsic {
+                use $runtime::{BlockHashCount, Runtime};
+
+                let call: $runtime::Call = match call {
+                    RelayCall::PushHeaders(headers) => {
+                        xpallet_gateway_bitcoin::Call::push_headers { headers }.into()
+                    }
+                    RelayCall::PushTransactions(txs) => {
+                        xpallet_gateway_bitcoin::Call::push_transactions { txs }.into()
+                    }
+                };
+                // take the biggest period possible.
+                let period = BlockHashCount::get()
+                    .checked_next_power_of_two()
+                    .map(|c| c / 2)
+                    .unwrap_or(2) as u64;
+                let extra: $runtime::SignedExtra = (
+                    frame_system::CheckSpecVersion::<Runtime>::new(),
+                    frame_system::CheckTxVersion::<Runtime>::new(),
+                    frame_system::CheckGenesis::<Runtime>::new(),
+                    frame_system::CheckEra::<Runtime>::from(Era::mortal(
+                        period,
+                        context.best_number.into(),
+                    )),
+                    frame_system::CheckNonce::<Runtime>::from(nonce),
+                    frame_system::CheckWeight::<Runtime>::new(),
+                    pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(0),
+                    $runtime::BaseFilter,
+                    $runtime::impls::ChargeExtraFee,
+                );
+                // the additional signed data can not be read from the runtime storage here
+                let raw_payload = $runtime::SignedPayload::from_raw(
+                    call,
+                    extra,
+                    (
+                        context.spec_version,
+                        context.transaction_version,
+                        context.genesis_hash,
+                        context.best_hash,
+                        (),
+                        (),
+                        (),
+                        (),
+                        (),
+                    ),
+                );
+                let signature = raw_payload.using_encoded(|payload| signer.sign(payload));
+                let (call, extra, _) = raw_payload.deconstruct();
+                let account = MultiSigner::from(signer.public()).into_account();
+                $runtime::UncheckedExtrinsic::new_signed(
+                    call,
+                    MultiAddress::Id(account),
+                    MultiSignature::Sr25519(signature),
+                    extra,
+                )
+                .into()
+            }
+        }
+    };
+}
+
+impl_relay_extrinsic_builder!(ChainXExtrinsicBuilder, chainx_runtime);
+impl_relay_extrinsic_builder!(MalanExtrinsicBuilder, malan_runtime);
+impl_relay_extrinsic_builder!(DevExtrinsicBuilder, dev_runtime);
+
+/// The relay chain backed by the client and the transaction pool of the node.
+pub struct ClientRelayChain<C, P> {
+    client: Arc<C>,
+    pool: Arc<P>,
+    signer: sr25519::Pair,
+    account: AccountId,
+    builder: Box<dyn RelayExtrinsicBuilder>,
+    /// The nonce of the next extrinsic, the submitted extrinsics may not be included yet.
+    next_nonce: Mutex<Index>,
+}
+
+impl<C, P> ClientRelayChain<C, P> {
+    pub fn new(
+        client: Arc<C>,
+        pool: Arc<P>,
+        signer: sr25519::Pair,
+        builder: Box<dyn RelayExtrinsicBuilder>,
+    ) -> Self {
+        let account = MultiSigner::from(signer.public()).into_account();
+        Self {
+            client,
+            pool,
+            signer,
+            account,
+            builder,
+            next_nonce: Mutex::new(0),
+        }
+    }
+}
+
+impl<C, P> ClientRelayChain<C, P>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: XGatewayBitcoinApi<Block, AccountId, Balance>
+        + AccountNonceApi<Block, AccountId, Index>
+        + Core<Block>,
+    P: TransactionPool<Block = Block>,
+{
+    fn best_block(&self) -> BlockId<Block> {
+        BlockId::hash(self.client.info().best_hash)
+    }
+
+    async fn submit(&self, call: RelayCall) -> Result<(), Error> {
+        let info = self.client.info();
+        let at = BlockId::hash(info.best_hash);
+        let api = self.client.runtime_api();
+        let version = api.version(&at).map_err(runtime_error)?;
+        let onchain_nonce = api
+            .account_nonce(&at, self.account.clone())
+            .map_err(runtime_error)?;
+        let nonce = {
+            let mut next_nonce = self.next_nonce.lock().expect("nonce lock poisoned; qed");
+            let nonce = onchain_nonce.max(*next_nonce);
+            *next_nonce = nonce + 1;
+            nonce
+        };
+
+        let context = SigningContext {
+            genesis_hash: info.genesis_hash,
+            best_hash: info.best_hash,
+            best_number: info.best_number,
+            spec_version: version.spec_version,
+            transaction_version: version.transaction_version,
+        };
+        let xt = self.builder.build(call, &self.signer, nonce, &context);
+        self.pool
+            .submit_one(&at, TransactionSource::External, xt)
+            .await
+            .map(|_| ())
+            .map_err(|err| {
+                // the nonce is not used, reset it to the onchain one in the next submission
+                *self.next_nonce.lock().expect("nonce lock poisoned; qed") = onchain_nonce;
+                Error::Chain(format!("Failed to submit extrinsic: {}", err))
+            })
+    }
+}
+
+fn runtime_error(err: impl std::fmt::Debug) -> Error {
+    Error::Chain(format!("Runtime api error: {:?}", err))
+}
+
+#[async_trait::async_trait]
+impl<C, P> RelayChain for ClientRelayChain<C, P>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync,
+    C::Api: XGatewayBitcoinApi<Block, AccountId, Balance>
+        + AccountNonceApi<Block, AccountId, Index>
+        + Core<Block>,
+    P: TransactionPool<Block = Block>,
+{
+    fn best_index(&self) -> Result<BtcHeaderIndex, Error> {
+        self.client
+            .runtime_api()
+            .best_index(&self.best_block())
+            .map_err(runtime_error)
+    }
+
+    fn confirmed_index(&self) -> Result<Option<BtcHeaderIndex>, Error> {
+        self.client
+            .runtime_api()
+            .confirmed_index(&self.best_block())
+            .map_err(runtime_error)
+    }
+
+    fn main_chain_hash(&self, height: u32) -> Result<Option<H256>, Error> {
+        self.client
+            .runtime_api()
+            .main_chain_header(&self.best_block(), height)
+            .map(|info| info.map(|info| info.header.hash()))
+            .map_err(runtime_error)
+    }
+
+    fn is_tx_processed(&self, txid: H256) -> Result<bool, Error> {
+        self.client
+            .runtime_api()
+            .is_tx_processed(&self.best_block(), txid)
+            .map_err(runtime_error)
+    }
+
+    fn relay_context(&self) -> Result<RelayContext, Error> {
+        let at = self.best_block();
+        let api = self.client.runtime_api();
+        let parse = |addr: &[u8]| {
+            parse_btc_address(addr).ok_or_else(|| {
+                Error::Decode(format!(
+                    "Invalid trustee address: {}",
+                    String::from_utf8_lossy(addr)
+                ))
+            })
+        };
+        let trustee_pairs = api
+            .trustee_address_pairs(&at)
+            .map_err(runtime_error)?
+            .iter()
+            .map(|(hot, cold)| Ok((parse(hot)?, parse(cold)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let trustee_utxos = api
+            .trustee_utxos(&at)
+            .map_err(runtime_error)?
+            .into_iter()
+            .map(|(txid, index, _)| (txid, index))
+            .collect();
+        Ok(RelayContext {
+            network: api.network_id(&at).map_err(runtime_error)?.into(),
+            min_deposit: api.min_deposit(&at).map_err(runtime_error)?,
+            trustee_pairs,
+            trustee_utxos,
+            trustee_utxos_initialized: api
+                .proof_of_reserves(&at)
+                .map_err(runtime_error)?
+                .trustee_utxos_initialized,
+        })
+    }
+
+    async fn submit_headers(&self, headers: Vec<Vec<u8>>) -> Result<(), Error> {
+        self.submit(RelayCall::PushHeaders(headers)).await
+    }
+
+    async fn submit_transactions(
+        &self,
+        txs: Vec<(Vec<u8>, BtcRelayedTxInfo, Vec<Vec<u8>>)>,
+    ) -> Result<(), Error> {
+        self.submit(RelayCall::PushTransactions(txs)).await
+    }
+}
@@ -0,0 +1,393 @@
+// Copyright 2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+//! The built-in Bitcoin relayer, which relays the headers of the Bitcoin main chain and the
+//! transactions touching the trustee addresses from a bitcoind node to ChainX.
+
+mod bitcoind;
+mod chain;
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Duration;
+
+use futures_timer::Delay;
+
+use light_bitcoin::{
+    chain::Transaction,
+    keys::{Address, DisplayLayout, Network},
+    primitives::H256,
+    serialization::serialize,
+};
+
+use xp_gateway_bitcoin::{extract_output_addr, is_trustee_addr, BtcTxMetaType, BtcTxTypeDetector};
+use xpallet_gateway_bitcoin::{
+    BtcHeaderIndex, BtcRelayedTxInfo, MAX_HEADERS_PER_BATCH, MAX_TXS_PER_BATCH,
+};
+
+pub use self::bitcoind::BitcoindClient;
+pub use self::chain::{
+    ChainXExtrinsicBuilder, ClientRelayChain, DevExtrinsicBuilder, MalanExtrinsicBuilder,
+    RelayExtrinsicBuilder,
+};
+
+const LOG_TARGET: &str = "btc-relayer";
+
+/// The max depth to look for the common ancestor of ChainX and bitcoind when they are forked.
+const MAX_REORG_DEPTH: u32 = 100;
+
+/// The max count of blocks to scan for the relay transactions in a round.
+const MAX_SCAN_BLOCKS: u32 = 10;
+
+/// The count of confirmed blocks to scan again when the relayer starts, so that the txs confirmed
+/// while the relayer was down are relayed, the processed txs are skipped.
+const RESCAN_BLOCKS: u32 = 144;
+
+/// The count of rounds to wait for the submitted headers to be included.
+const MAX_PENDING_ROUNDS: u32 = 3;
+
+/// The configuration of the built-in Bitcoin relayer.
+#[derive(Clone, Debug)]
+pub struct BtcRelayerConfig {
+    /// The url of the bitcoind JSON-RPC endpoint.
+    pub url: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// The secret uri of the relayer account which signs the relay transactions.
+    pub seed: String,
+    /// The interval between two relay rounds.
+    pub interval: Duration,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Http(hyper::Error),
+    Bitcoind(String),
+    Decode(String),
+    Chain(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Http(err) => write!(f, "Http error: {}", err),
+            Error::Bitcoind(err) => write!(f, "Bitcoind error: {}", err),
+            Error::Decode(err) => write!(f, "Decode error: {}", err),
+            Error::Chain(err) => write!(f, "Chain error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<hyper::Error> for Error {
+    fn from(err: hyper::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+/// The view of the bitcoin light client on ChainX needed by the relayer.
+#[async_trait::async_trait]
+pub trait RelayChain {
+    fn best_index(&self) -> Result<BtcHeaderIndex, Error>;
+
+    fn confirmed_index(&self) -> Result<Option<BtcHeaderIndex>, Error>;
+
+    /// Return the hash of the relayed header on the main chain at the height.
+    fn main_chain_hash(&self, height: u32) -> Result<Option<H256>, Error>;
+
+    fn is_tx_processed(&self, txid: H256) -> Result<bool, Error>;
+
+    fn relay_context(&self) -> Result<RelayContext, Error>;
+
+    async fn submit_headers(&self, headers: Vec<Vec<u8>>) -> Result<(), Error>;
+
+    /// Submit a batch of `(raw_tx, relayed_info, prev_txs)`.
+    async fn submit_transactions(
+        &self,
+        txs: Vec<(Vec<u8>, BtcRelayedTxInfo, Vec<Vec<u8>>)>,
+    ) -> Result<(), Error>;
+}
+
+/// The state on ChainX used to detect the transactions to relay.
+#[derive(Clone, Debug)]
+pub struct RelayContext {
+    pub network: Network,
+    pub min_deposit: u64,
+    /// The hot and cold addresses of the current trustees, followed by the last trustees.
+    pub trustee_pairs: Vec<(Address, Address)>,
+    /// The unspent outputs of the trustee addresses.
+    pub trustee_utxos: BTreeSet<(H256, u32)>,
+    /// Whether the trustee utxo set is initialized, i.e. it's complete.
+    pub trustee_utxos_initialized: bool,
+}
+
+impl RelayContext {
+    /// Whether the tx pays to or spends from the trustee addresses, the previous tx is needed
+    /// to detect the exact type.
+    pub fn is_candidate(&self, tx: &Transaction) -> bool {
+        if tx.is_coinbase() {
+            return false;
+        }
+        let spends_trustee_utxo = tx.inputs.iter().any(|input| {
+            let outpoint = &input.previous_output;
+            self.trustee_utxos
+                .contains(&(outpoint.txid, outpoint.index))
+        });
+        spends_trustee_utxo
+            || tx
+                .outputs
+                .iter()
+                .filter_map(|output| extract_output_addr(output, self.network))
+                .any(|addr| {
+                    self.trustee_pairs
+                        .iter()
+                        .any(|pair| is_trustee_addr(addr.clone(), pair.clone()))
+                })
+    }
+
+    /// Whether the spends of the trustee addresses could only be found by the scripts of the
+    /// spent outputs, since the trustee utxo set is empty or incomplete.
+    pub fn needs_prev_outputs(&self) -> bool {
+        !self.trustee_utxos_initialized || self.trustee_utxos.is_empty()
+    }
+
+    /// Whether any input of the tx spends an output paying to the trustee addresses.
+    pub fn spends_trustee_addr(&self, tx: &Transaction, prev_txs: &[Transaction]) -> bool {
+        tx.inputs.iter().any(|input| {
+            let outpoint = &input.previous_output;
+            prev_txs
+                .iter()
+                .find(|prev_tx| prev_tx.hash() == outpoint.txid)
+                .and_then(|prev_tx| prev_tx.outputs.get(outpoint.index as usize))
+                .and_then(|output| extract_output_addr(output, self.network))
+                .map(|addr| {
+                    self.trustee_pairs
+                        .iter()
+                        .any(|pair| is_trustee_addr(addr.clone(), pair.clone()))
+                })
+                .unwrap_or(false)
+        })
+    }
+
+    /// Whether the tx would be processed by ChainX, i.e. not an `Irrelevance` tx.
+    pub fn should_relay(&self, tx: &Transaction, prev_txs: &[Transaction]) -> bool {
+        let current_trustee_pair = match self.trustee_pairs.first() {
+            Some(pair) => pair.clone(),
+            None => return false,
+        };
+        let last_trustee_pair = self.trustee_pairs.get(1).cloned();
+        let detector = BtcTxTypeDetector::new(self.network, self.min_deposit);
+        // the account is not needed for detecting the tx type
+        let meta_type = detector.detect_transaction_type::<(), _>(
+            tx,
+            prev_txs,
+            |_| None,
+            current_trustee_pair,
+            last_trustee_pair,
+        );
+        !matches!(meta_type, BtcTxMetaType::Irrelevance)
+    }
+}
+
+/// Parse the btc address in base58 or bech32 format.
+pub fn parse_btc_address(addr: &[u8]) -> Option<Address> {
+    let addr = std::str::from_utf8(addr).ok()?;
+    bs58::decode(addr)
+        .into_vec()
+        .ok()
+        .and_then(|layout| Address::from_layout(&layout).ok())
+        .or_else(|| addr.parse().ok())
+}
+
+/// The relayer syncing the headers and the transactions from bitcoind to ChainX.
+pub struct BtcRelayer<C> {
+    bitcoind: BitcoindClient,
+    chain: C,
+    /// The height of the last submitted header and the rounds waiting for it to be included.
+    pending_headers: Option<(u32, u32)>,
+    /// The height of the next block to scan for the relay transactions.
+    next_scan_height: Option<u32>,
+}
+
+impl<C: RelayChain> BtcRelayer<C> {
+    pub fn new(bitcoind: BitcoindClient, chain: C) -> Self {
+        Self {
+            bitcoind,
+            chain,
+            pending_headers: None,
+            next_scan_height: None,
+        }
+    }
+
+    /// Relay the headers and the transactions periodically.
+    pub async fn run(mut self, interval: Duration) {
+        log::info!(target: LOG_TARGET, "Bitcoin relayer started");
+        loop {
+            if let Err(err) = self.sync_headers().await {
+                log::warn!(target: LOG_TARGET, "Failed to relay headers: {}", err);
+            }
+            if let Err(err) = self.relay_transactions().await {
+                log::warn!(target: LOG_TARGET, "Failed to relay transactions: {}", err);
+            }
+            Delay::new(interval).await;
+        }
+    }
+
+    /// Submit the headers between the best header on ChainX and the tip of bitcoind, at most
+    /// `MAX_HEADERS_PER_BATCH` in a round. Return the count of the submitted headers.
+    pub async fn sync_headers(&mut self) -> Result<u32, Error> {
+        let best = self.chain.best_index()?;
+        if let Some((height, rounds)) = self.pending_headers {
+            if best.height < height && rounds < MAX_PENDING_ROUNDS {
+                self.pending_headers = Some((height, rounds + 1));
+                return Ok(0);
+            }
+        }
+        self.pending_headers = None;
+
+        let tip = self.bitcoind.block_count().await?;
+        let ancestor = self.common_ancestor(best.height.min(tip)).await?;
+        if ancestor >= tip {
+            return Ok(0);
+        }
+
+        let end = tip.min(ancestor + MAX_HEADERS_PER_BATCH);
+        let mut headers = Vec::new();
+        for height in ancestor + 1..=end {
+            let hash = self.bitcoind.block_hash(height).await?;
+            let header = self.bitcoind.block_header(hash).await?;
+            headers.push(serialize(&header).take());
+        }
+        let count = end - ancestor;
+        log::info!(
+            target: LOG_TARGET,
+            "Submit {} headers (#{}..=#{}), bitcoind tip:#{}",
+            count,
+            ancestor + 1,
+            end,
+            tip
+        );
+        self.chain.submit_headers(headers).await?;
+        self.pending_headers = Some((end, 0));
+        Ok(count)
+    }
+
+    /// Find the highest height not above `height` where ChainX and bitcoind agree on the block.
+    async fn common_ancestor(&self, mut height: u32) -> Result<u32, Error> {
+        let start = height;
+        loop {
+            let relayed = self.chain.main_chain_hash(height)?;
+            if relayed == Some(self.bitcoind.block_hash(height).await?) {
+                return Ok(height);
+            }
+            if relayed.is_none() || height == 0 || start - height >= MAX_REORG_DEPTH {
+                return Err(Error::Chain(format!(
+                    "No common ancestor with bitcoind from #{} to #{}",
+                    start, height
+                )));
+            }
+            log::debug!(
+                target: LOG_TARGET,
+                "Header #{} is forked from bitcoind",
+                height
+            );
+            height -= 1;
+        }
+    }
+
+    /// Submit the unprocessed transactions touching the trustee addresses in the confirmed
+    /// blocks. Return the count of the submitted transactions.
+    pub async fn relay_transactions(&mut self) -> Result<u32, Error> {
+        let confirmed = match self.chain.confirmed_index()? {
+            Some(confirmed) => confirmed,
+            None => return Ok(0),
+        };
+        let start = *self
+            .next_scan_height
+            .get_or_insert_with(|| confirmed.height.saturating_sub(RESCAN_BLOCKS));
+        if start > confirmed.height {
+            return Ok(0);
+        }
+
+        let context = self.chain.relay_context()?;
+        let end = confirmed.height.min(start + MAX_SCAN_BLOCKS - 1);
+        let mut count = 0;
+        for height in start..=end {
+            if let Some(block_hash) = self.chain.main_chain_hash(height)? {
+                count += self.relay_block_transactions(&context, block_hash).await?;
+            }
+            self.next_scan_height = Some(height + 1);
+        }
+        Ok(count)
+    }
+
+    async fn relay_block_transactions(
+        &self,
+        context: &RelayContext,
+        block_hash: H256,
+    ) -> Result<u32, Error> {
+        let block = self.bitcoind.block(block_hash).await?;
+        let needs_prev_outputs = context.needs_prev_outputs();
+        let mut relayed = vec![];
+        for tx in block.transactions.iter() {
+            let is_candidate = context.is_candidate(tx);
+            // the trustee spends without change could only be found by the previous txs
+            if !is_candidate && (!needs_prev_outputs || tx.is_coinbase()) {
+                continue;
+            }
+            if self.chain.is_tx_processed(tx.hash())? {
+                continue;
+            }
+            // the previous txs of all inputs are needed to attribute the deposit
+            let mut prev_txs: Vec<Transaction> = Vec::with_capacity(tx.inputs.len());
+            for input in tx.inputs.iter() {
+                let prev_txid = input.previous_output.txid;
+                if prev_txs.iter().all(|prev_tx| prev_tx.hash() != prev_txid) {
+                    prev_txs.push(self.bitcoind.raw_transaction(prev_txid).await?);
+                }
+            }
+            if !is_candidate && !context.spends_trustee_addr(tx, &prev_txs) {
+                continue;
+            }
+            if context.should_relay(tx, &prev_txs) {
+                relayed.push((tx, prev_txs));
+            }
+        }
+        if relayed.is_empty() {
+            return Ok(0);
+        }
+
+        // all the txs in the block share one merkle proof
+        let txids = relayed.iter().map(|(tx, _)| tx.hash()).collect::<Vec<_>>();
+        let merkle_proof = self.bitcoind.tx_out_proof(&txids, block_hash).await?;
+        for batch in relayed.chunks(MAX_TXS_PER_BATCH as usize) {
+            log::info!(
+                target: LOG_TARGET,
+                "Submit {} txs in block {}",
+                batch.len(),
+                bitcoind::encode_hash(block_hash)
+            );
+            let relayed_info = BtcRelayedTxInfo {
+                block_hash,
+                merkle_proof: merkle_proof.clone(),
+            };
+            let txs = batch
+                .iter()
+                .map(|(tx, prev_txs)| {
+                    (
+                        serialize(*tx).take(),
+                        relayed_info.clone(),
+                        prev_txs
+                            .iter()
+                            .map(|prev_tx| serialize(prev_tx).take())
+                            .collect(),
+                    )
+                })
+                .collect();
+            self.chain.submit_transactions(txs).await?;
+        }
+        Ok(relayed.len() as u32)
+    }
+}
@@ -0,0 +1,502 @@
+// Copyright 2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use serde_json::{json, Value};
+
+use light_bitcoin::{
+    chain::{Block as BtcBlock, BlockHeader as BtcHeader, OutPoint, Transaction, TransactionInput},
+    keys::{Address, Network},
+    merkle::PartialMerkleTree,
+    primitives::H256,
+    serialization::{deserialize, serialize, Reader},
+};
+
+use xpallet_gateway_bitcoin::{BtcHeaderIndex, BtcRelayedTxInfo};
+
+use super::bitcoind::{decode_hash, encode_hash};
+use super::*;
+
+// Type is p2tr. Address format is Mainnet.
+const DEPOSIT_HOT_ADDR: &str = "bc1pn202yeugfa25nssxk2hv902kmxrnp7g9xt487u256n20jgahuwas6syxhp";
+// Type is p2sh. Address format is Mainnet.
+const DEPOSIT_COLD_ADDR: &str = "3Ac85hjgeyNX96Q4BqUoAH5bh6gARxRDJm";
+
+// https://signet.bitcoinexplorer.org/tx/1f8e0f7dfa37b184244d022cdf2bc7b8e0bac8b52143ea786fa3f7bbe049eeae#JSON
+const DEPOSIT_PREV: &str = "020000000001014be640313b023c3c731b7e89c3f97bebcebf9772ea2f7747e5604f4483a447b601000000000000000002a0860100000000002251209a9ea267884f5549c206b2aec2bd56d98730f90532ea7f7154d4d4f923b7e3bbc027090000000000225120c9929543dfa1e0bb84891acd47bfa6546b05e26b7a04af8eb6765fcc969d565f01404dc68b31efc1468f84db7e9716a84c19bbc53c2d252fd1d72fa6469e860a74486b0990332b69718dbcb5acad9d48634d23ee9c215ab15fb16f4732bed1770fdf00000000";
+// https://signet.bitcoinexplorer.org/tx/8e5d37c768acc4f3e794a10ad27bf0256237c80c22fa67117e3e3e1aec22ea5f#JSON
+const DEPOSIT: &str = "02000000000101aeee49e0bbf7a36f78ea4321b5c8bae0b8c72bdf2c024d2484b137fa7d0f8e1f01000000000000000003a0860100000000002251209a9ea267884f5549c206b2aec2bd56d98730f90532ea7f7154d4d4f923b7e3bb0000000000000000326a3035516a706f3772516e7751657479736167477a6334526a376f737758534c6d4d7141754332416255364c464646476a38801a060000000000225120c9929543dfa1e0bb84891acd47bfa6546b05e26b7a04af8eb6765fcc969d565f01409e325889515ed47099fdd7098e6fafdc880b21456d3f368457de923f4229286e34cef68816348a0581ae5885ede248a35ac4b09da61a7b9b90f34c200872d2e300000000";
+// The merkle proof of `DEPOSIT` in the block #63299.
+const DEPOSIT_PROOF: &str = "0a000000050a59b195a68a29037580798ca0414941eb46eaf7607db2d0da1ff89e9570ce455fea22ec1a3e3e7e1167fa220cc8376225f07bd20aa194e7f3c4ac68c7375d8e0a35e47541de7d0aa7312dabcf3bc9f06603e832427b8e4fe9a97a309f8cd7141687d11a3fd8f21e2105a52a3c36a17ea870e326ecddb23221d4cc0398b6c44bdcce3f191919a31f4cfaca5a786cc8315db76683ad6b8008f2ed9b348df76a0d022f00";
+const DEPOSIT_HEIGHT: u32 = 63299;
+
+fn generate_blocks_63290_63310() -> BTreeMap<u32, BtcHeader> {
+    let headers =
+        include_str!("../../../xpallets/gateway/bitcoin/src/res/headers-63290-63310.json");
+    let headers: Vec<(u32, String)> = serde_json::from_str(headers).unwrap();
+    headers
+        .into_iter()
+        .map(|(height, header_hex)| {
+            let data = hex::decode(header_hex).unwrap();
+            let header = deserialize(Reader::new(&data)).unwrap();
+            (height, header)
+        })
+        .collect()
+}
+
+/// The state of the mocked bitcoind node.
+#[derive(Default)]
+struct MockBitcoind {
+    headers: BTreeMap<u32, BtcHeader>,
+    block_txs: BTreeMap<H256, Vec<Transaction>>,
+    txs: BTreeMap<H256, Transaction>,
+    /// The merkle proofs keyed by the matched txids.
+    proofs: BTreeMap<Vec<H256>, Vec<u8>>,
+}
+
+impl MockBitcoind {
+    fn new() -> Self {
+        let headers = generate_blocks_63290_63310();
+        let deposit: Transaction = DEPOSIT.parse().unwrap();
+        let prev: Transaction = DEPOSIT_PREV.parse().unwrap();
+        let block_hash = headers[&DEPOSIT_HEIGHT].hash();
+
+        let mut proof = serialize(&headers[&DEPOSIT_HEIGHT]).take();
+        proof.extend(hex::decode(DEPOSIT_PROOF).unwrap());
+
+        let mut bitcoind = Self {
+            headers,
+            ..Default::default()
+        };
+        bitcoind.block_txs.insert(block_hash, vec![deposit.clone()]);
+        bitcoind.proofs.insert(vec![deposit.hash()], proof);
+        bitcoind.txs.insert(prev.hash(), prev);
+        bitcoind.txs.insert(deposit.hash(), deposit);
+        bitcoind
+    }
+
+    fn header(&self, hash: H256) -> Option<&BtcHeader> {
+        self.headers.values().find(|header| header.hash() == hash)
+    }
+
+    fn handle(&self, method: &str, params: &[Value]) -> Result<Value, (i64, &'static str)> {
+        let hash_param = |index: usize| {
+            params
+                .get(index)
+                .and_then(Value::as_str)
+                .and_then(|hash| decode_hash(hash).ok())
+                .ok_or((-8, "Invalid parameter"))
+        };
+        let not_found = (-5, "Not found");
+        match method {
+            "getblockcount" => Ok(json!(self.headers.keys().last().unwrap())),
+            "getblockhash" => {
+                let height = params[0].as_u64().unwrap() as u32;
+                let header = self.headers.get(&height).ok_or((-8, "Out of range"))?;
+                Ok(json!(encode_hash(header.hash())))
+            }
+            "getblockheader" => {
+                let header = self.header(hash_param(0)?).ok_or(not_found)?;
+                Ok(json!(hex::encode(serialize(header).take())))
+            }
+            "getblock" => {
+                let hash = hash_param(0)?;
+                let header = self.header(hash).ok_or(not_found)?;
+                let txs = self.block_txs.get(&hash).cloned().unwrap_or_default();
+                let block = BtcBlock::new(*header, txs);
+                Ok(json!(hex::encode(serialize(&block).take())))
+            }
+            "getrawtransaction" => {
+                let tx = self.txs.get(&hash_param(0)?).ok_or(not_found)?;
+                Ok(json!(hex::encode(serialize(tx).take())))
+            }
+            "gettxoutproof" => {
+                let txids = params[0]
+                    .as_array()
+                    .and_then(|txids| {
+                        txids
+                            .iter()
+                            .map(|txid| txid.as_str().and_then(|txid| decode_hash(txid).ok()))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or((-8, "Invalid parameter"))?;
+                let proof = self.proofs.get(&txids).ok_or(not_found)?;
+                Ok(json!(hex::encode(proof)))
+            }
+            _ => Err((-32601, "Method not found")),
+        }
+    }
+}
+
+/// Start a mocked bitcoind JSON-RPC server, return its url.
+fn spawn_bitcoind(bitcoind: MockBitcoind) -> String {
+    let bitcoind = Arc::new(bitcoind);
+    let make_service = make_service_fn(move |_| {
+        let bitcoind = bitcoind.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let bitcoind = bitcoind.clone();
+                async move {
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    let request: Value = serde_json::from_slice(&body).unwrap();
+                    let method = request["method"].as_str().unwrap();
+                    let params = request["params"].as_array().cloned().unwrap_or_default();
+                    let (status, response) = match bitcoind.handle(method, &params) {
+                        Ok(result) => (200, json!({ "result": result, "error": null })),
+                        Err((code, message)) => (
+                            500,
+                            json!({ "result": null, "error": { "code": code, "message": message } }),
+                        ),
+                    };
+                    Response::builder()
+                        .status(status)
+                        .body(Body::from(response.to_string()))
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+    url
+}
+
+/// Run the future on a runtime which drives the mocked bitcoind server.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// The state of the mocked bitcoin light client on ChainX.
+#[derive(Default)]
+struct MockChainState {
+    main_chain: BTreeMap<u32, H256>,
+    confirmed: Option<u32>,
+    processed: BTreeSet<H256>,
+    submitted_headers: Vec<Vec<u8>>,
+    submitted_txs: Vec<Vec<(Vec<u8>, BtcRelayedTxInfo, Vec<Vec<u8>>)>>,
+}
+
+#[derive(Default)]
+struct MockChain(Mutex<MockChainState>);
+
+impl MockChain {
+    fn with_main_chain(main_chain: BTreeMap<u32, H256>) -> Self {
+        Self(Mutex::new(MockChainState {
+            main_chain,
+            ..Default::default()
+        }))
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<MockChainState> {
+        self.0.lock().unwrap()
+    }
+}
+
+#[async_trait::async_trait]
+impl RelayChain for MockChain {
+    fn best_index(&self) -> Result<BtcHeaderIndex, Error> {
+        let state = self.state();
+        let (height, hash) = state.main_chain.iter().last().unwrap();
+        Ok(BtcHeaderIndex {
+            hash: *hash,
+            height: *height,
+        })
+    }
+
+    fn confirmed_index(&self) -> Result<Option<BtcHeaderIndex>, Error> {
+        let state = self.state();
+        Ok(state.confirmed.map(|height| BtcHeaderIndex {
+            hash: state.main_chain[&height],
+            height,
+        }))
+    }
+
+    fn main_chain_hash(&self, height: u32) -> Result<Option<H256>, Error> {
+        Ok(self.state().main_chain.get(&height).copied())
+    }
+
+    fn is_tx_processed(&self, txid: H256) -> Result<bool, Error> {
+        Ok(self.state().processed.contains(&txid))
+    }
+
+    fn relay_context(&self) -> Result<RelayContext, Error> {
+        Ok(RelayContext {
+            network: Network::Mainnet,
+            min_deposit: 0,
+            trustee_pairs: vec![(
+                DEPOSIT_HOT_ADDR.parse::<Address>().unwrap(),
+                DEPOSIT_COLD_ADDR.parse::<Address>().unwrap(),
+            )],
+            trustee_utxos: BTreeSet::new(),
+            trustee_utxos_initialized: false,
+        })
+    }
+
+    async fn submit_headers(&self, headers: Vec<Vec<u8>>) -> Result<(), Error> {
+        let mut state = self.state();
+        for raw in headers.iter() {
+            let header: BtcHeader = deserialize(Reader::new(raw)).unwrap();
+            let (prev_height, _) = state
+                .main_chain
+                .iter()
+                .find(|(_, hash)| **hash == header.previous_header_hash)
+                .map(|(height, hash)| (*height, *hash))
+                .expect("the header must extend the relayed chain; qed");
+            // the submitted header becomes the new tip, drop the forked headers
+            state.main_chain.split_off(&(prev_height + 1));
+            state.main_chain.insert(prev_height + 1, header.hash());
+        }
+        state.submitted_headers.extend(headers);
+        Ok(())
+    }
+
+    async fn submit_transactions(
+        &self,
+        txs: Vec<(Vec<u8>, BtcRelayedTxInfo, Vec<Vec<u8>>)>,
+    ) -> Result<(), Error> {
+        self.state().submitted_txs.push(txs);
+        Ok(())
+    }
+}
+
+fn relayer(url: String, chain: MockChain) -> BtcRelayer<MockChain> {
+    BtcRelayer::new(BitcoindClient::new(url, None, None), chain)
+}
+
+fn main_chain(headers: &BTreeMap<u32, BtcHeader>, end: u32) -> BTreeMap<u32, H256> {
+    headers
+        .range(..=end)
+        .map(|(height, header)| (*height, header.hash()))
+        .collect()
+}
+
+#[test]
+fn test_bitcoind_client() {
+    block_on(async {
+        let url = spawn_bitcoind(MockBitcoind::new());
+        let client = BitcoindClient::new(url, Some("user".into()), Some("password".into()));
+        let headers = generate_blocks_63290_63310();
+
+        assert_eq!(client.block_count().await.unwrap(), 63310);
+        let hash = client.block_hash(63300).await.unwrap();
+        assert_eq!(hash, headers[&63300].hash());
+        assert_eq!(client.block_header(hash).await.unwrap(), headers[&63300]);
+        assert!(client.block(hash).await.unwrap().transactions.is_empty());
+
+        let deposit: Transaction = DEPOSIT.parse().unwrap();
+        let block_hash = headers[&DEPOSIT_HEIGHT].hash();
+        let block = client.block(block_hash).await.unwrap();
+        let txids = block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .collect::<Vec<_>>();
+        assert_eq!(txids, vec![deposit.hash()]);
+        let proof: PartialMerkleTree =
+            deserialize(Reader::new(&hex::decode(DEPOSIT_PROOF).unwrap())).unwrap();
+        assert_eq!(
+            client
+                .tx_out_proof(&[deposit.hash()], block_hash)
+                .await
+                .unwrap(),
+            proof
+        );
+
+        // the rpc error of bitcoind
+        match client.block_hash(63311).await {
+            Err(Error::Bitcoind(err)) => assert!(err.contains("code:-8")),
+            _ => unreachable!("the height is out of range"),
+        }
+        assert!(client.raw_transaction(H256::repeat_byte(1)).await.is_err());
+    })
+}
+
+#[test]
+fn test_sync_headers() {
+    block_on(async {
+        let url = spawn_bitcoind(MockBitcoind::new());
+        let headers = generate_blocks_63290_63310();
+        let mut relayer = relayer(url, MockChain::with_main_chain(main_chain(&headers, 63290)));
+
+        assert_eq!(relayer.sync_headers().await.unwrap(), 20);
+        assert_eq!(relayer.chain.best_index().unwrap().height, 63310);
+        assert_eq!(
+            relayer.chain.state().main_chain,
+            main_chain(&headers, 63310)
+        );
+        assert_eq!(relayer.chain.state().submitted_headers.len(), 20);
+
+        // no more headers
+        assert_eq!(relayer.sync_headers().await.unwrap(), 0);
+    })
+}
+
+#[test]
+fn test_sync_headers_with_fork() {
+    block_on(async {
+        let url = spawn_bitcoind(MockBitcoind::new());
+        let headers = generate_blocks_63290_63310();
+        // ChainX is on a fork from #63301
+        let mut relayed = main_chain(&headers, 63300);
+        for height in 63301..=63305 {
+            relayed.insert(height, H256::repeat_byte(height as u8));
+        }
+        let mut relayer = relayer(url, MockChain::with_main_chain(relayed));
+
+        assert_eq!(relayer.sync_headers().await.unwrap(), 10);
+        assert_eq!(
+            relayer.chain.state().main_chain,
+            main_chain(&headers, 63310)
+        );
+    })
+}
+
+#[test]
+fn test_sync_headers_without_common_ancestor() {
+    block_on(async {
+        let url = spawn_bitcoind(MockBitcoind::new());
+        let mut relayed = BTreeMap::new();
+        relayed.insert(63300, H256::repeat_byte(1));
+        let mut relayer = relayer(url, MockChain::with_main_chain(relayed));
+
+        assert!(matches!(relayer.sync_headers().await, Err(Error::Chain(_))));
+        assert!(relayer.chain.state().submitted_headers.is_empty());
+    })
+}
+
+#[test]
+fn test_relay_transactions() {
+    block_on(async {
+        let url = spawn_bitcoind(MockBitcoind::new());
+        let headers = generate_blocks_63290_63310();
+        let mut relayer = relayer(url, MockChain::with_main_chain(main_chain(&headers, 63310)));
+
+        // nothing is confirmed
+        assert_eq!(relayer.relay_transactions().await.unwrap(), 0);
+
+        // the recent confirmed blocks are scanned again after the relayer starts
+        relayer.chain.state().confirmed = Some(DEPOSIT_HEIGHT - 1);
+        assert_eq!(relayer.relay_transactions().await.unwrap(), 0);
+        assert_eq!(
+            relayer.next_scan_height,
+            Some(DEPOSIT_HEIGHT - 1 - RESCAN_BLOCKS + MAX_SCAN_BLOCKS)
+        );
+
+        relayer.chain.state().confirmed = Some(DEPOSIT_HEIGHT + 2);
+        let mut count = 0;
+        while relayer.next_scan_height <= Some(DEPOSIT_HEIGHT + 2) {
+            count += relayer.relay_transactions().await.unwrap();
+        }
+        assert_eq!(count, 1);
+        assert_eq!(relayer.next_scan_height, Some(DEPOSIT_HEIGHT + 3));
+
+        let deposit: Transaction = DEPOSIT.parse().unwrap();
+        let prev: Transaction = DEPOSIT_PREV.parse().unwrap();
+        let proof: PartialMerkleTree =
+            deserialize(Reader::new(&hex::decode(DEPOSIT_PROOF).unwrap())).unwrap();
+        assert_eq!(
+            relayer.chain.state().submitted_txs,
+            vec![vec![(
+                serialize(&deposit).take(),
+                BtcRelayedTxInfo {
+                    block_hash: headers[&DEPOSIT_HEIGHT].hash(),
+                    merkle_proof: proof,
+                },
+                vec![serialize(&prev).take()],
+            )]]
+        );
+
+        // the scanned blocks are not scanned again
+        assert_eq!(relayer.relay_transactions().await.unwrap(), 0);
+    })
+}
+
+#[test]
+fn test_skip_processed_transactions() {
+    block_on(async {
+        let url = spawn_bitcoind(MockBitcoind::new());
+        let headers = generate_blocks_63290_63310();
+        let mut relayer = relayer(url, MockChain::with_main_chain(main_chain(&headers, 63310)));
+
+        let deposit: Transaction = DEPOSIT.parse().unwrap();
+        relayer.chain.state().processed.insert(deposit.hash());
+        relayer.next_scan_height = Some(DEPOSIT_HEIGHT);
+        relayer.chain.state().confirmed = Some(DEPOSIT_HEIGHT);
+
+        assert_eq!(relayer.relay_transactions().await.unwrap(), 0);
+        assert!(relayer.chain.state().submitted_txs.is_empty());
+    })
+}
+
+#[test]
+fn test_relay_context() {
+    let context = MockChain::default().relay_context().unwrap();
+    let deposit: Transaction = DEPOSIT.parse().unwrap();
+    let prev: Transaction = DEPOSIT_PREV.parse().unwrap();
+    assert!(context.is_candidate(&deposit));
+    assert!(context.should_relay(&deposit, &[prev.clone()]));
+
+    // nothing to relay without the trustees
+    let mut context = context;
+    context.trustee_pairs.clear();
+    assert!(!context.is_candidate(&deposit));
+    assert!(!context.should_relay(&deposit, &[prev]));
+}
+
+#[test]
+fn test_relay_context_without_trustee_utxos() {
+    let mut context = MockChain::default().relay_context().unwrap();
+    let deposit: Transaction = DEPOSIT.parse().unwrap();
+    // spend the output of the hot address without change
+    let withdrawal = Transaction {
+        version: 2,
+        inputs: vec![TransactionInput {
+            previous_output: OutPoint {
+                txid: deposit.hash(),
+                index: 0,
+            },
+            script_sig: Default::default(),
+            sequence: 0xffffffff,
+            script_witness: vec![],
+        }],
+        outputs: vec![deposit.outputs[2].clone()],
+        lock_time: 0,
+    };
+    assert!(!context.is_candidate(&withdrawal));
+    assert!(context.needs_prev_outputs());
+    assert!(context.spends_trustee_addr(&withdrawal, &[deposit.clone()]));
+    assert!(context.should_relay(&withdrawal, &[deposit.clone()]));
+    // the deposit spends the output of a normal address
+    let prev: Transaction = DEPOSIT_PREV.parse().unwrap();
+    assert!(!context.spends_trustee_addr(&deposit, &[prev]));
+
+    // the initialized trustee utxo set is used instead
+    context.trustee_utxos_initialized = true;
+    assert!(context.needs_prev_outputs());
+    context.trustee_utxos.insert((deposit.hash(), 0));
+    assert!(!context.needs_prev_outputs());
+    assert!(context.is_candidate(&withdrawal));
+}
+
+#[test]
+fn test_parse_btc_address() {
+    assert_eq!(
+        parse_btc_address(DEPOSIT_HOT_ADDR.as_bytes()),
+        DEPOSIT_HOT_ADDR.parse().ok()
+    );
+    assert_eq!(
+        parse_btc_address(DEPOSIT_COLD_ADDR.as_bytes()),
+        DEPOSIT_COLD_ADDR.parse().ok()
+    );
+    assert_eq!(parse_btc_address(b"invalid"), None);
+}
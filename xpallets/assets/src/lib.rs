@@ -426,12 +426,15 @@ impl<T: Config> Pallet<T> {
     }
 
     pub fn total_reserved_balance(who: &T::AccountId, id: &AssetId) -> BalanceOf<T> {
-        use AssetType::{Reserved, ReservedDexSpot, ReservedWithdrawal};
+        use AssetType::{Reserved, ReservedDexSpot, ReservedQueuedWithdrawal, ReservedWithdrawal};
 
         let total_balances = Self::asset_balance(who, id);
         let balance_for = |ty: AssetType| total_balances.get(&ty).copied().unwrap_or_default();
 
-        balance_for(Reserved) + balance_for(ReservedWithdrawal) + balance_for(ReservedDexSpot)
+        balance_for(Reserved)
+            + balance_for(ReservedWithdrawal)
+            + balance_for(ReservedDexSpot)
+            + balance_for(ReservedQueuedWithdrawal)
     }
 
     /// Sets the free balance of `who` without sanity checks and triggering the asset changed hook.
@@ -18,12 +18,13 @@ use frame_support::traits::LockIdentifier;
 
 use crate::{Config, Error};
 
-const ASSET_TYPES: [AssetType; 5] = [
+const ASSET_TYPES: [AssetType; 6] = [
     AssetType::Usable,
     AssetType::Locked,
     AssetType::Reserved,
     AssetType::ReservedWithdrawal,
     AssetType::ReservedDexSpot,
+    AssetType::ReservedQueuedWithdrawal,
 ];
 
 /// Concrete type of non-native asset balance.
@@ -47,6 +48,8 @@ pub enum AssetType {
     ReservedWithdrawal,
     /// Reserved balance for creating order in DEX.
     ReservedDexSpot,
+    /// Reserved balance of the withdrawal delayed by the rate limit.
+    ReservedQueuedWithdrawal,
 }
 
 impl AssetType {
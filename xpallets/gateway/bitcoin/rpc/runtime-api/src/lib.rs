@@ -0,0 +1,60 @@
+// Copyright 2019-2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::too_many_arguments, clippy::unnecessary_mut_passed)]
+
+use sp_std::prelude::*;
+
+use codec::Codec;
+use sp_runtime::DispatchError;
+
+pub use xpallet_gateway_bitcoin::{
+    hash_rev, BtcAddress, BtcDepositCache, BtcHeader, BtcHeaderIndex, BtcHeaderInfo, BtcNetwork,
+    BtcProofOfReserves, BtcTrusteeUtxo, BtcTxResult, BtcTxState, BtcTxType, BtcWithdrawalProposal,
+    VoteResult, H256,
+};
+
+sp_api::decl_runtime_apis! {
+    pub trait XGatewayBitcoinApi<AccountId, Balance>
+    where
+        AccountId: Codec,
+        Balance: Codec,
+    {
+        fn trustee_utxos() -> Vec<(H256, u32, BtcTrusteeUtxo)>;
+
+        fn proof_of_reserves() -> BtcProofOfReserves<Balance>;
+
+        fn best_index() -> BtcHeaderIndex;
+
+        fn confirmed_index() -> Option<BtcHeaderIndex>;
+
+        fn header(hash: H256) -> Option<BtcHeaderInfo>;
+
+        /// Get the header on the main chain at the height.
+        fn main_chain_header(height: u32) -> Option<BtcHeaderInfo>;
+
+        fn tx_state(txid: H256) -> Option<BtcTxState>;
+
+        fn is_tx_processed(txid: H256) -> bool;
+
+        /// Get the pending deposits of the address, or of all the addresses if `None`.
+        fn pending_deposits(addr: Option<BtcAddress>) -> Vec<(BtcAddress, Vec<BtcDepositCache>)>;
+
+        fn withdrawal_proposal(id: u32) -> Option<BtcWithdrawalProposal<AccountId>>;
+
+        fn withdrawal_proposals() -> Vec<(u32, BtcWithdrawalProposal<AccountId>)>;
+
+        /// Verify the address is a valid btc address of the current network.
+        fn verify_address(addr: BtcAddress) -> Result<(), DispatchError>;
+
+        /// Get the bitcoin network of the light client.
+        fn network_id() -> BtcNetwork;
+
+        /// Get the minimum amount (in satoshi) of a deposit.
+        fn min_deposit() -> u64;
+
+        /// Get the hot and cold addresses of the current trustees, followed by the ones of the
+        /// last trustees if any.
+        fn trustee_address_pairs() -> Vec<(BtcAddress, BtcAddress)>;
+    }
+}
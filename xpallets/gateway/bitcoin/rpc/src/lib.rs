@@ -0,0 +1,405 @@
+// Copyright 2019-2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
+
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+use light_bitcoin::serialization::serialize;
+
+use xp_rpc::{hex_decode_error_into_rpc_err, runtime_error_into_rpc_err, Result};
+
+use xpallet_gateway_bitcoin_rpc_runtime_api::{
+    hash_rev, BtcAddress, BtcDepositCache, BtcHeaderIndex, BtcHeaderInfo, BtcProofOfReserves,
+    BtcTrusteeUtxo, BtcTxState, BtcWithdrawalProposal, VoteResult,
+    XGatewayBitcoinApi as GatewayBitcoinRuntimeApi, H256,
+};
+
+pub struct XGatewayBitcoin<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> XGatewayBitcoin<C, B> {
+    /// Create new `XGatewayBitcoin` with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// The hashes of the bitcoin block and tx are in the bitcoin explorer format (byte reversed).
+#[rpc]
+pub trait XGatewayBitcoinApi<BlockHash, AccountId, Balance>
+where
+    Balance: Display + FromStr,
+{
+    /// Return the unspent outputs of the trustee hot and cold addresses
+    #[rpc(name = "xgatewaybitcoin_trusteeUtxos")]
+    fn trustee_utxos(&self, at: Option<BlockHash>) -> Result<Vec<RpcTrusteeUtxo>>;
+
+    /// Return the BTC held by trustee addresses against the X-BTC issued
+    #[rpc(name = "xgatewaybitcoin_proofOfReserves")]
+    fn proof_of_reserves(&self, at: Option<BlockHash>) -> Result<RpcProofOfReserves<Balance>>;
+
+    /// Return the best header index of the relayed bitcoin chain
+    #[rpc(name = "xgatewaybitcoin_bestIndex")]
+    fn best_index(&self, at: Option<BlockHash>) -> Result<RpcBtcHeaderIndex>;
+
+    /// Return the confirmed header index of the relayed bitcoin chain
+    #[rpc(name = "xgatewaybitcoin_confirmedIndex")]
+    fn confirmed_index(&self, at: Option<BlockHash>) -> Result<Option<RpcBtcHeaderIndex>>;
+
+    /// Return the relayed header of the block hash, include the forked header
+    #[rpc(name = "xgatewaybitcoin_header")]
+    fn header(&self, hash: String, at: Option<BlockHash>) -> Result<Option<RpcBtcHeader>>;
+
+    /// Return the relayed header on the main chain at the height
+    #[rpc(name = "xgatewaybitcoin_headerByHeight")]
+    fn header_by_height(&self, height: u32, at: Option<BlockHash>) -> Result<Option<RpcBtcHeader>>;
+
+    /// Return the type and result of the processed tx
+    #[rpc(name = "xgatewaybitcoin_txState")]
+    fn tx_state(&self, txid: String, at: Option<BlockHash>) -> Result<Option<BtcTxState>>;
+
+    /// Return whether the tx has been processed successfully
+    #[rpc(name = "xgatewaybitcoin_isTxProcessed")]
+    fn is_tx_processed(&self, txid: String, at: Option<BlockHash>) -> Result<bool>;
+
+    /// Return the pending deposits of the address, or of all the addresses if the address is
+    /// not specified
+    #[rpc(name = "xgatewaybitcoin_pendingDeposits")]
+    fn pending_deposits(
+        &self,
+        addr: Option<String>,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<RpcPendingDeposit>>;
+
+    /// Return the withdrawal proposal in flight
+    #[rpc(name = "xgatewaybitcoin_withdrawalProposal")]
+    fn withdrawal_proposal(
+        &self,
+        id: u32,
+        at: Option<BlockHash>,
+    ) -> Result<Option<RpcWithdrawalProposal<AccountId>>>;
+
+    /// Return all the withdrawal proposals in flight
+    #[rpc(name = "xgatewaybitcoin_withdrawalProposals")]
+    fn withdrawal_proposals(
+        &self,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<RpcWithdrawalProposal<AccountId>>>;
+
+    /// Return whether the address is a valid btc address of the current network
+    #[rpc(name = "xgatewaybitcoin_verifyAddress")]
+    fn verify_address(&self, addr: String, at: Option<BlockHash>) -> Result<bool>;
+}
+
+impl<C, Block, AccountId, Balance> XGatewayBitcoinApi<<Block as BlockT>::Hash, AccountId, Balance>
+    for XGatewayBitcoin<C, Block>
+where
+    C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: GatewayBitcoinRuntimeApi<Block, AccountId, Balance>,
+    Block: BlockT,
+    AccountId: Clone + Display + FromStr + Codec,
+    Balance: Clone + Display + FromStr + Codec,
+{
+    fn trustee_utxos(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<RpcTrusteeUtxo>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.trustee_utxos(&at)
+            .map(|utxos| utxos.into_iter().map(Into::into).collect())
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn proof_of_reserves(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<RpcProofOfReserves<Balance>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.proof_of_reserves(&at)
+            .map(Into::into)
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn best_index(&self, at: Option<<Block as BlockT>::Hash>) -> Result<RpcBtcHeaderIndex> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.best_index(&at)
+            .map(Into::into)
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn confirmed_index(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<RpcBtcHeaderIndex>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.confirmed_index(&at)
+            .map(|index| index.map(Into::into))
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn header(
+        &self,
+        hash: String,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<RpcBtcHeader>> {
+        let hash = decode_btc_hash(&hash)?;
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.header(&at, hash)
+            .map(|header| header.map(Into::into))
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn header_by_height(
+        &self,
+        height: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<RpcBtcHeader>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.main_chain_header(&at, height)
+            .map(|header| header.map(Into::into))
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn tx_state(
+        &self,
+        txid: String,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<BtcTxState>> {
+        let txid = decode_btc_hash(&txid)?;
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.tx_state(&at, txid).map_err(runtime_error_into_rpc_err)
+    }
+
+    fn is_tx_processed(&self, txid: String, at: Option<<Block as BlockT>::Hash>) -> Result<bool> {
+        let txid = decode_btc_hash(&txid)?;
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.is_tx_processed(&at, txid)
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn pending_deposits(
+        &self,
+        addr: Option<String>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<RpcPendingDeposit>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.pending_deposits(&at, addr.map(String::into_bytes))
+            .map(|list| {
+                list.into_iter()
+                    .flat_map(|(addr, deposits)| {
+                        deposits
+                            .into_iter()
+                            .map(move |deposit| RpcPendingDeposit::new(&addr, deposit))
+                    })
+                    .collect()
+            })
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn withdrawal_proposal(
+        &self,
+        id: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<RpcWithdrawalProposal<AccountId>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.withdrawal_proposal(&at, id)
+            .map(|proposal| proposal.map(|proposal| (id, proposal).into()))
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn withdrawal_proposals(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<RpcWithdrawalProposal<AccountId>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.withdrawal_proposals(&at)
+            .map(|proposals| proposals.into_iter().map(Into::into).collect())
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn verify_address(&self, addr: String, at: Option<<Block as BlockT>::Hash>) -> Result<bool> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        Ok(api
+            .verify_address(&at, addr.into_bytes())
+            .map_err(runtime_error_into_rpc_err)?
+            .is_ok())
+    }
+}
+
+/// Decode the hash in the bitcoin explorer format (byte reversed).
+fn decode_btc_hash(hash: &str) -> Result<H256> {
+    let hash = hash.strip_prefix("0x").unwrap_or(hash);
+    let bytes = hex::decode(hash).map_err(hex_decode_error_into_rpc_err)?;
+    if bytes.len() != H256::len_bytes() {
+        return Err(hex_decode_error_into_rpc_err(format!(
+            "Invalid hash length: {}",
+            bytes.len()
+        )));
+    }
+    Ok(hash_rev(H256::from_slice(&bytes)))
+}
+
+/// Encode the hash in the bitcoin explorer format (byte reversed).
+fn encode_btc_hash(hash: H256) -> String {
+    hex::encode(hash_rev(hash))
+}
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcTrusteeUtxo {
+    /// the tx hash in the bitcoin explorer format (byte reversed)
+    pub txid: String,
+    pub index: u32,
+    pub value: u64,
+    pub addr: String,
+}
+
+impl From<(H256, u32, BtcTrusteeUtxo)> for RpcTrusteeUtxo {
+    fn from((txid, index, utxo): (H256, u32, BtcTrusteeUtxo)) -> Self {
+        Self {
+            txid: encode_btc_hash(txid),
+            index,
+            value: utxo.value,
+            addr: String::from_utf8_lossy(&utxo.addr).into_owned(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcProofOfReserves<Balance: Display + FromStr> {
+    #[serde(with = "xp_rpc::serde_num_str")]
+    pub total_issuance: Balance,
+    pub trustee_balance: u64,
+    pub pending_deposits: u64,
+    #[serde(with = "xp_rpc::serde_num_str")]
+    pub pending_withdrawals: Balance,
+    pub trustee_utxos_initialized: bool,
+    #[serde(with = "xp_rpc::serde_num_str")]
+    pub surplus: Balance,
+    #[serde(with = "xp_rpc::serde_num_str")]
+    pub deficit: Balance,
+}
+
+impl<Balance: Display + FromStr> From<BtcProofOfReserves<Balance>> for RpcProofOfReserves<Balance> {
+    fn from(reserves: BtcProofOfReserves<Balance>) -> Self {
+        Self {
+            total_issuance: reserves.total_issuance,
+            trustee_balance: reserves.trustee_balance,
+            pending_deposits: reserves.pending_deposits,
+            pending_withdrawals: reserves.pending_withdrawals,
+            trustee_utxos_initialized: reserves.trustee_utxos_initialized,
+            surplus: reserves.surplus,
+            deficit: reserves.deficit,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBtcHeaderIndex {
+    pub hash: String,
+    pub height: u32,
+}
+
+impl From<BtcHeaderIndex> for RpcBtcHeaderIndex {
+    fn from(index: BtcHeaderIndex) -> Self {
+        Self {
+            hash: encode_btc_hash(index.hash),
+            height: index.height,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBtcHeader {
+    pub hash: String,
+    pub height: u32,
+    pub version: u32,
+    pub previous_header_hash: String,
+    pub merkle_root_hash: String,
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl From<BtcHeaderInfo> for RpcBtcHeader {
+    fn from(info: BtcHeaderInfo) -> Self {
+        Self {
+            hash: encode_btc_hash(info.header.hash()),
+            height: info.height,
+            version: info.header.version,
+            previous_header_hash: encode_btc_hash(info.header.previous_header_hash),
+            merkle_root_hash: encode_btc_hash(info.header.merkle_root_hash),
+            time: info.header.time,
+            bits: info.header.bits.into(),
+            nonce: info.header.nonce,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPendingDeposit {
+    pub addr: String,
+    pub txid: String,
+    pub value: u64,
+}
+
+impl RpcPendingDeposit {
+    fn new(addr: &BtcAddress, deposit: BtcDepositCache) -> Self {
+        Self {
+            addr: String::from_utf8_lossy(addr).into_owned(),
+            txid: encode_btc_hash(deposit.txid),
+            value: deposit.balance,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcWithdrawalProposal<AccountId> {
+    pub id: u32,
+    pub sig_state: VoteResult,
+    pub withdrawal_id_list: Vec<u32>,
+    pub txid: String,
+    /// the hex of the raw tx
+    pub tx: String,
+    pub trustee_list: Vec<(AccountId, bool)>,
+}
+
+impl<AccountId> From<(u32, BtcWithdrawalProposal<AccountId>)> for RpcWithdrawalProposal<AccountId> {
+    fn from((id, proposal): (u32, BtcWithdrawalProposal<AccountId>)) -> Self {
+        Self {
+            id,
+            sig_state: proposal.sig_state,
+            withdrawal_id_list: proposal.withdrawal_id_list,
+            txid: encode_btc_hash(proposal.tx.hash()),
+            tx: hex::encode(serialize(&proposal.tx).take()),
+            trustee_list: proposal.trustee_list,
+        }
+    }
+}
@@ -3,24 +3,26 @@
 use codec::{Decode, Encode};
 use frame_benchmarking::{benchmarks, whitelisted_caller};
 use frame_system::RawOrigin;
-use sp_runtime::{AccountId32, SaturatedConversion};
+use sp_runtime::{traits::Zero, AccountId32, SaturatedConversion};
 use sp_std::{collections::btree_map::BTreeMap, prelude::*};
 
 use chainx_primitives::AssetId;
 use xp_gateway_bitcoin::BtcTxType;
-use xpallet_assets::{BalanceOf, Pallet as XAssets};
+use xpallet_assets::{AssetType, BalanceOf, Pallet as XAssets};
 use xpallet_gateway_records::{Pallet as XGatewayRecords, WithdrawalState};
 
 use light_bitcoin::{
     chain::{BlockHeader, Transaction},
     merkle::PartialMerkleTree,
-    primitives::H256,
+    primitives::{hash_rev, H256, U256},
+    script::Script,
     serialization::{self, Reader, SERIALIZE_TRANSACTION_WITNESS},
 };
 
 use crate::{
-    types::*, Call, Config, Pallet, PendingDeposits, TransactionOutputArray, TxState,
-    WithdrawalProposal,
+    tx::validator::parse_and_check_signed_tx_impl, types::*, BtcNetwork, Call, Config, NetworkId,
+    Pallet, PendingDepositExpiry, PendingDeposits, ReorgedDeposits, TransactionOutputArray,
+    TrusteeUtxos, TxState, Verifier, WithdrawalProposals, MAX_HEADERS_PER_BATCH,
 };
 
 const ASSET_ID: AssetId = xp_protocol::X_BTC;
@@ -70,6 +72,14 @@ fn withdraw_tx() -> (Transaction, BtcRelayedTxInfo, Transaction) {
     (tx, info, prev_tx)
 }
 
+fn signed_multisig_tx() -> (Transaction, Script) {
+    // 4-of-7 multisig redeem script
+    const REDEEM_SCRIPT: &str = "542102e2b2720a9e54617ba87fca287c3d7f9124154d30fa8dc9cd260b6b254e1d7aea210219fc860933a1362bc5e0a0bbe1b33a47aedf904765f4a85cd166ba1d767927ee2102b921cb319a14c6887b12cee457453f720e88808a735a578d6c57aba0c74e5af32102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210346aa7ade0b567b34182cacf9444deb44ee829e14705dc87175107dd09d5dbf4021034d3e7f87e69c6c71df6052b44f9ed99a3d811613140ebf09f8fdaf904a2e1de856ae";
+    // the tx with one signature
+    const RAW_TX: &str = "0100000001abbd850cf083bbfa367081718c7efd911e56ffd849ae48e812c861adf253ef6101000000fd1b010048304502210081d668bf752424c89e208cf9789e7449c080a2cd9fda6a518ac36d81e5d760dd02206353eaad7e587602ab6665a5788a2c831e9d08bf8685dd250370dbd978a54665014ccf542102e2b2720a9e54617ba87fca287c3d7f9124154d30fa8dc9cd260b6b254e1d7aea210219fc860933a1362bc5e0a0bbe1b33a47aedf904765f4a85cd166ba1d767927ee2102b921cb319a14c6887b12cee457453f720e88808a735a578d6c57aba0c74e5af32102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210346aa7ade0b567b34182cacf9444deb44ee829e14705dc87175107dd09d5dbf4021034d3e7f87e69c6c71df6052b44f9ed99a3d811613140ebf09f8fdaf904a2e1de856aeffffffff03d2622000000000001976a914b9944df543bc909b527351311c5a01a78a3271e788acff40330e0000000017a9149079c3650e5a9799afa552cbbcc280e45d52117c8777d778e50000000017a914d246f700f4969106291a75ba85ad863cae68d6678700000000";
+    (RAW_TX.parse().unwrap(), REDEEM_SCRIPT.parse().unwrap())
+}
+
 // push header 63290 - 63310
 fn prepare_headers<T: Config>(caller: &T::AccountId) {
     for (height, header) in generate_blocks_63290_63310() {
@@ -84,6 +94,33 @@ fn prepare_headers<T: Config>(caller: &T::AccountId) {
     }
 }
 
+/// Mine `n` headers on the best header, the network is switched to regtest so that each header
+/// mined more than 20 minutes after its parent is valid with the minimum difficulty.
+fn mine_headers<T: Config>(n: u32) -> Vec<BlockHeader> {
+    NetworkId::<T>::put(BtcNetwork::Regtest);
+    let bits = Pallet::<T>::params_info().max_bits();
+    let target = bits.to_u256().unwrap();
+    let best_hash = Pallet::<T>::best_index().hash;
+    let mut parent = Pallet::<T>::headers(&best_hash).unwrap().header;
+    (0..n)
+        .map(|_| {
+            let mut header = BlockHeader {
+                version: 0x20000000,
+                previous_header_hash: parent.hash(),
+                merkle_root_hash: Default::default(),
+                time: parent.time + 20 * 60 + 1,
+                bits,
+                nonce: 0,
+            };
+            while U256::from(hash_rev(header.hash()).as_bytes()) > target {
+                header.nonce += 1;
+            }
+            parent = header;
+            header
+        })
+        .collect()
+}
+
 benchmarks! {
     push_header {
         let receiver: T::AccountId = whitelisted_caller();
@@ -97,6 +134,20 @@ benchmarks! {
         assert!(Pallet::<T>::headers(&hash).is_some());
     }
 
+    push_headers {
+        let n in 1 .. MAX_HEADERS_PER_BATCH;
+        let receiver: T::AccountId = whitelisted_caller();
+        let headers = mine_headers::<T>(n);
+        let hash = headers.last().unwrap().hash();
+        let headers_raw = headers
+            .iter()
+            .map(|header| serialization::serialize(header).into())
+            .collect::<Vec<Vec<u8>>>();
+    }: _(RawOrigin::Signed(receiver), headers_raw)
+    verify {
+        assert_eq!(Pallet::<T>::best_index().hash, hash);
+    }
+
     push_transaction {
         let n = 1024 * 1024 * 500; // 500KB length
         let l = 1024 * 1024 * 500; // 500KB length
@@ -120,11 +171,11 @@ benchmarks! {
             tx,
             trustee_list: vec![],
         };
-        WithdrawalProposal::<T>::put(proposal);
+        WithdrawalProposals::<T>::insert(0, proposal);
 
-    }: _(RawOrigin::Signed(caller), tx_raw, info, Some(prev_tx_raw))
+    }: _(RawOrigin::Signed(caller), tx_raw, info, vec![prev_tx_raw])
     verify {
-        assert!(WithdrawalProposal::<T>::get().is_none());
+        assert!(WithdrawalProposals::<T>::get(0).is_none());
         assert_eq!(
             TxState::<T>::get(tx_hash),
             Some(BtcTxState {
@@ -148,6 +199,11 @@ benchmarks! {
             outputs: vec![prev_tx.outputs[0].clone()],
         };
         let spent_outputs_raw = serialization::serialize(&transaction_output).into();
+        let outpoint = &tx.inputs[0].previous_output;
+        TrusteeUtxos::<T>::insert(outpoint.txid, outpoint.index, BtcTrusteeUtxo {
+            value: prev_tx.outputs[0].value,
+            addr: b"tb1pn202yeugfa25nssxk2hv902kmxrnp7g9xt487u256n20jgahuwasdcjfdw".to_vec(),
+        });
 
         XGatewayRecords::<T>::deposit(&caller, ASSET_ID, 100_000u32.saturated_into()).unwrap();
         XGatewayRecords::<T>::withdraw(&caller, ASSET_ID, 50_000u32.saturated_into(), b"tb1pexff2s7l58sthpyfrtx500ax234stcnt0gz2lr4kwe0ue95a2e0srxsc68".to_vec(), b"".to_vec().into()).unwrap();
@@ -156,7 +212,7 @@ benchmarks! {
 
     }: _(RawOrigin::Signed(caller), vec![0], tx_raw, spent_outputs_raw)
     verify {
-        assert_eq!(WithdrawalProposal::<T>::get().unwrap().sig_state, VoteResult::Finish);
+        assert_eq!(WithdrawalProposals::<T>::get(0).unwrap().sig_state, VoteResult::Finish);
     }
 
     set_best_index {
@@ -203,6 +259,28 @@ benchmarks! {
         assert_eq!(XAssets::<T>::usable_balance(&receiver, &ASSET_ID), (100000000u32 + 200000000u32 + 300000000u32).into());
     }
 
+    claim_pending_deposit {
+        let n in 1 .. 100;
+        let caller = alice::<T>();
+        let addr = b"mh4wHJc3SEud5GRu1Gbu2ZF6g2i5pcRBVb".to_vec();
+        // BIP-137 signature of the genesis hash (0x4545...45) and Alice's account id
+        let signature = hex::decode("209fe2e37dfc1ff07b0f8ce0f820a74b980cc2d2eee6072a9d4e4dbf53b6624a5750e747e743619063229dcffbfd22c29e0c395e2c5ac451f36de3c5bee4a7583b").unwrap();
+        let genesis_hash = T::Hash::decode(&mut &[0x45u8; 32][..]).unwrap();
+        frame_system::BlockHash::<T>::insert(T::BlockNumber::zero(), genesis_hash);
+        NetworkId::<T>::put(BtcNetwork::Testnet);
+        let deposits = (0..n)
+            .map(|i| BtcDepositCache {
+                txid: H256::from_low_u64_be(i as u64 + 1),
+                balance: 100000000,
+            })
+            .collect::<Vec<_>>();
+        PendingDeposits::<T>::insert(&addr, deposits);
+    }: _(RawOrigin::Signed(caller.clone()), addr.clone(), signature, n)
+    verify {
+        assert!(Pallet::<T>::pending_deposits(&addr).is_empty());
+        assert_eq!(XAssets::<T>::usable_balance(&caller, &ASSET_ID), (100000000u64 * n as u64).saturated_into());
+    }
+
     remove_proposal {
         let (tx, _, _) = withdraw_tx();
         let proposal = BtcWithdrawalProposal::<T::AccountId> {
@@ -211,10 +289,10 @@ benchmarks! {
             tx,
             trustee_list: vec![],
         };
-        WithdrawalProposal::<T>::put(proposal);
-    }: _(RawOrigin::Root)
+        WithdrawalProposals::<T>::insert(0, proposal);
+    }: _(RawOrigin::Root, 0)
     verify {
-        assert!(WithdrawalProposal::<T>::get().is_none());
+        assert!(WithdrawalProposals::<T>::get(0).is_none());
     }
 
     set_btc_withdrawal_fee {
@@ -228,6 +306,118 @@ benchmarks! {
     }: _(RawOrigin::Root,  2000000)
     verify {
     }
+
+    resolve_reorged_deposit {
+        let receiver: T::AccountId = whitelisted_caller();
+        let txid = H256::repeat_byte(1);
+        let amount: BalanceOf<T> = 100_000u32.into();
+        XGatewayRecords::<T>::deposit(&receiver, ASSET_ID, amount).unwrap();
+        XAssets::<T>::move_balance(&ASSET_ID, &receiver, AssetType::Usable, &receiver, AssetType::Locked, amount).unwrap();
+        ReorgedDeposits::<T>::insert(txid, BtcReorgedDeposit {
+            block_hash: H256::repeat_byte(2),
+            who: receiver.clone(),
+            credited: amount,
+            frozen: amount,
+        });
+    }: _(RawOrigin::Root, txid, true)
+    verify {
+        assert!(Pallet::<T>::reorged_deposits(&txid).is_none());
+        assert_eq!(XAssets::<T>::usable_balance(&receiver, &ASSET_ID), amount);
+    }
+
+    set_header_retention_depth {
+        let depth = Pallet::<T>::params_info().retargeting_interval()
+            + Pallet::<T>::confirmation_number();
+    }: _(RawOrigin::Root, depth)
+    verify {
+        assert_eq!(Pallet::<T>::header_retention_depth(), depth);
+    }
+
+    set_pending_deposit_expiry {
+    }: _(RawOrigin::Root, 1008)
+    verify {
+        assert_eq!(Pallet::<T>::pending_deposit_expiry(), 1008);
+    }
+
+    expire_pending_deposits {
+        let addr = b"3AWmpzJ1kSF1cktFTDEb3qmLcdN8YydxA7".to_vec();
+        let v = vec![
+            BtcDepositCache {
+                txid: H256::repeat_byte(1),
+                balance: 100000000,
+            },
+            BtcDepositCache {
+                txid: H256::repeat_byte(2),
+                balance: 200000000,
+            },
+        ];
+        PendingDeposits::<T>::insert(&addr, v);
+        PendingDepositExpiry::<T>::put(1);
+    }: _(RawOrigin::Root, addr.clone())
+    verify {
+        assert!(Pallet::<T>::pending_deposits(&addr).is_empty());
+        assert_eq!(Pallet::<T>::expired_deposits(&addr).len(), 2);
+    }
+
+    set_withdrawal_fee_rate {
+    }: _(RawOrigin::Root, 1, 100)
+    verify {
+        assert_eq!(Pallet::<T>::withdrawal_fee_rate(), (1, 100));
+    }
+
+    set_trustee_utxo {
+        let txid = H256::repeat_byte(1);
+        let utxo = BtcTrusteeUtxo {
+            value: 100_000,
+            addr: b"tb1pn202yeugfa25nssxk2hv902kmxrnp7g9xt487u256n20jgahuwasdcjfdw".to_vec(),
+        };
+    }: _(RawOrigin::Root, txid, 0, Some(utxo.clone()))
+    verify {
+        assert_eq!(Pallet::<T>::trustee_utxo(txid, 0), Some(utxo));
+    }
+
+    report_unauthorized_spend {
+        let n = 1;
+        let caller = alice::<T>();
+        prepare_headers::<T>(&caller);
+        // the withdrawal tx is not approved by any proposal, the trustees are revealed by the
+        // script path spending in the witness
+        let (tx, info, prev_tx) = withdraw_tx();
+        let tx_hash = tx.hash();
+        let tx_raw = serialization::serialize_with_flags(&tx, SERIALIZE_TRANSACTION_WITNESS).into();
+        let prev_tx_raw = serialization::serialize(&prev_tx).into();
+    }: _(RawOrigin::Signed(caller), tx_raw, info, vec![prev_tx_raw])
+    verify {
+        assert!(Pallet::<T>::reported_spend(&tx_hash));
+    }
+
+    verify_sig_recover {
+        let (tx, script) = signed_multisig_tx();
+        Verifier::<T>::put(BtcTxVerifier::Recover);
+    }: {
+        assert_eq!(parse_and_check_signed_tx_impl::<T>(&tx, script), Ok(1));
+    }
+
+    verify_sig_runtime_interface {
+        let (tx, script) = signed_multisig_tx();
+        Verifier::<T>::put(BtcTxVerifier::RuntimeInterface);
+    }: {
+        assert_eq!(parse_and_check_signed_tx_impl::<T>(&tx, script), Ok(1));
+    }
+
+    prune_header {
+        let caller = alice::<T>();
+        prepare_headers::<T>(&caller);
+        let height = 63291;
+        let hash = Pallet::<T>::block_hash_for(height)[0];
+        let params = Pallet::<T>::params_info();
+    }: {
+        crate::header::prune_header::<T>(height, &params);
+    }
+    verify {
+        assert!(Pallet::<T>::headers(&hash).is_none());
+        assert!(Pallet::<T>::block_hash_for(height).is_empty());
+    }
 }
 
 #[cfg(test)]
@@ -240,13 +430,25 @@ mod tests {
     fn test_benchmarks() {
         ExtBuilder::default().build().execute_with(|| {
             assert_ok!(Pallet::<Test>::test_benchmark_push_header());
+            assert_ok!(Pallet::<Test>::test_benchmark_push_headers());
             assert_ok!(Pallet::<Test>::test_benchmark_push_transaction());
             assert_ok!(Pallet::<Test>::test_benchmark_create_taproot_withdraw_tx());
             assert_ok!(Pallet::<Test>::test_benchmark_set_best_index());
             assert_ok!(Pallet::<Test>::test_benchmark_set_confirmed_index());
             assert_ok!(Pallet::<Test>::test_benchmark_remove_pending());
+            assert_ok!(Pallet::<Test>::test_benchmark_claim_pending_deposit());
             assert_ok!(Pallet::<Test>::test_benchmark_set_btc_withdrawal_fee());
             assert_ok!(Pallet::<Test>::test_benchmark_set_btc_deposit_limit());
+            assert_ok!(Pallet::<Test>::test_benchmark_resolve_reorged_deposit());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_header_retention_depth());
+            assert_ok!(Pallet::<Test>::test_benchmark_prune_header());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_withdrawal_fee_rate());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_trustee_utxo());
+            assert_ok!(Pallet::<Test>::test_benchmark_report_unauthorized_spend());
+            assert_ok!(Pallet::<Test>::test_benchmark_verify_sig_recover());
+            assert_ok!(Pallet::<Test>::test_benchmark_verify_sig_runtime_interface());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_pending_deposit_expiry());
+            assert_ok!(Pallet::<Test>::test_benchmark_expire_pending_deposits());
         });
     }
 }
@@ -10,11 +10,10 @@ use sp_std::{cmp, convert::TryFrom};
 
 use light_bitcoin::{
     chain::BlockHeader as BtcHeader,
-    keys::Network,
     primitives::{hash_rev, Compact, H256, U256},
 };
 
-use crate::types::{BtcHeaderInfo, BtcParams};
+use crate::types::{BtcHeaderInfo, BtcNetwork, BtcParams};
 use crate::{Config, Error, Pallet};
 
 pub struct HeaderVerifier<'a> {
@@ -39,10 +38,11 @@ impl<'a> HeaderVerifier<'a> {
 
     pub fn check<T: Config>(&self) -> DispatchResult {
         let params: BtcParams = Pallet::<T>::params_info();
-        let network_id: Network = Pallet::<T>::network_id();
-        if let Network::Mainnet = network_id {
-            self.work.check::<T>(&params)?;
-        }
+        let network_id: BtcNetwork = Pallet::<T>::network_id();
+        self.work.check::<T>(&params, network_id)?;
+        // the block signature of signet is committed in the coinbase transaction, which can not
+        // be checked with the header only, the signet headers are trusted as far as the proof
+        // of work is valid under the signet limit.
         self.proof_of_work.check::<T>(&params)?;
         // ignore this in benchmarks
         #[cfg(not(feature = "runtime-benchmarks"))]
@@ -67,9 +67,8 @@ impl<'a> HeaderWork<'a> {
         HeaderWork { info }
     }
 
-    fn check<T: Config>(&self, params: &BtcParams) -> DispatchResult {
-        let previous_header_hash = self.info.header.previous_header_hash;
-        let work = work_required::<T>(previous_header_hash, self.info.height, params);
+    fn check<T: Config>(&self, params: &BtcParams, network: BtcNetwork) -> DispatchResult {
+        let work = work_required::<T>(&self.info.header, self.info.height, params, network);
         match work {
             RequiredWork::Value(work) => {
                 if work != self.info.header.bits {
@@ -88,20 +87,24 @@ impl<'a> HeaderWork<'a> {
 }
 
 pub fn work_required<T: Config>(
-    parent_hash: H256,
+    header: &BtcHeader,
     height: u32,
     params: &BtcParams,
+    network: BtcNetwork,
 ) -> RequiredWork {
     let max_bits = params.max_bits();
     if height == 0 {
         return RequiredWork::Value(max_bits);
     }
 
-    let parent_header: BtcHeader = Pallet::<T>::headers(&parent_hash)
+    let parent_header: BtcHeader = Pallet::<T>::headers(&header.previous_header_hash)
         .expect("pre header must exist here")
         .header;
 
     if is_retarget_height(height, params) {
+        if network.no_retargeting() {
+            return RequiredWork::Value(parent_header.bits);
+        }
         let new_work = work_required_retarget::<T>(parent_header, height, params);
         info!(
             target: "runtime::bitcoin",
@@ -110,6 +113,11 @@ pub fn work_required<T: Config>(
         );
         return new_work;
     }
+
+    if network.allow_min_difficulty_blocks() {
+        return work_required_min_difficulty::<T>(header, parent_header, height - 1, params);
+    }
+
     debug!(
         target: "runtime::bitcoin",
         "[work_required] Use old work required, old bits:{:?}",
@@ -122,6 +130,50 @@ fn is_retarget_height(height: u32, params: &BtcParams) -> bool {
     height % params.retargeting_interval() == 0
 }
 
+/// The max number of ancestors looked up for the last block not mined with the minimum
+/// difficulty.
+const MAX_MIN_DIFFICULTY_LOOKBACK: u32 = 144;
+
+/// The special rule of testnet and regtest: a block mined more than twice the target spacing
+/// after its parent could use the minimum difficulty, otherwise the difficulty is the one of
+/// the last block not mined with the special rule.
+///
+/// The work is not checked if that block is beyond `MAX_MIN_DIFFICULTY_LOOKBACK` ancestors.
+fn work_required_min_difficulty<T: Config>(
+    header: &BtcHeader,
+    parent_header: BtcHeader,
+    parent_height: u32,
+    params: &BtcParams,
+) -> RequiredWork {
+    let max_bits = params.max_bits();
+    if header.time
+        > parent_header
+            .time
+            .saturating_add(params.target_spacing_seconds() * 2)
+    {
+        return RequiredWork::Value(max_bits);
+    }
+
+    let (_, genesis_height) = Pallet::<T>::genesis_info();
+    let mut last_header = parent_header;
+    let mut last_height = parent_height;
+    let mut lookback = 0;
+    while last_height % params.retargeting_interval() != 0 && last_header.bits == max_bits {
+        if last_height <= genesis_height || lookback >= MAX_MIN_DIFFICULTY_LOOKBACK {
+            // the last block with the normal difficulty is unknown
+            return RequiredWork::NotCheck;
+        }
+        last_header = match Pallet::<T>::headers(&last_header.previous_header_hash) {
+            Some(info) => info.header,
+            // the ancestor may have been pruned
+            None => return RequiredWork::NotCheck,
+        };
+        last_height -= 1;
+        lookback += 1;
+    }
+    RequiredWork::Value(last_header.bits)
+}
+
 /// Algorithm used for retargeting work every 2 weeks
 fn work_required_retarget<T: Config>(
     parent_header: BtcHeader,
@@ -141,7 +193,16 @@ fn work_required_retarget<T: Config>(
         // retarget_header = genesis_header;
         return RequiredWork::NotCheck;
     } else {
+        // the retarget header is kept as the checkpoint when it's pruned
         let hash_list = Pallet::<T>::block_hash_for(&retarget_num);
+        if hash_list.is_empty() {
+            warn!(
+                target: "runtime::bitcoin",
+                "[work_required_retarget] Retarget header #{} is missing, skip work check",
+                retarget_num
+            );
+            return RequiredWork::NotCheck;
+        }
         for h in hash_list {
             // look up in main chain
             if Pallet::<T>::main_chain(h) {
@@ -2,20 +2,34 @@
 
 mod header_proof;
 
-use frame_support::log::{error, info};
+use frame_support::{
+    log::{error, info},
+    traits::Get,
+    weights::Weight,
+};
 use sp_runtime::DispatchResult;
 use sp_std::{cmp::Ordering, prelude::*};
 
 use light_bitcoin::primitives::{hash_rev, H256};
 
-use crate::types::{BtcHeaderIndex, BtcHeaderInfo};
-use crate::{Config, ConfirmedIndex, Error, MainChain, Pallet};
+use crate::types::{BtcHeaderCheckpoint, BtcHeaderIndex, BtcHeaderInfo, BtcParams};
+use crate::weights::WeightInfo;
+use crate::{
+    tx, BlockHashFor, BlockTxs, Config, ConfirmedIndex, Error, Headers, MainChain, NextPruneHeight,
+    Pallet, RetargetCheckpoint, TxDepositOf, TxSpentTrusteeUtxos,
+};
 
 pub use self::header_proof::HeaderVerifier;
 
+/// Max count of heights which would be pruned in a block.
+const MAX_PRUNED_HEIGHTS_PER_BLOCK: u32 = 32;
+
 /// Look back the headers to pick the confirmed index,
 /// return the header indexes on the look back path.
 ///
+/// `extra` is the count of headers to look back beyond the confirmations, it's used when a batch
+/// of headers is applied, so that all the headers in the batch are on the look back path.
+///
 /// The definition of block confirmation count:
 /// confirmed_height = now_height - (confirmations - 1)
 ///           |--- confirmations = 4 ---|
@@ -25,9 +39,11 @@ pub use self::header_proof::HeaderVerifier;
 ///
 fn look_back_confirmed_header<T: Config>(
     header_info: &BtcHeaderInfo,
+    extra: u32,
 ) -> (Option<BtcHeaderIndex>, Vec<BtcHeaderIndex>) {
     let confirmations = Pallet::<T>::confirmation_number();
-    let mut chain = Vec::with_capacity(confirmations as usize);
+    let depth = confirmations.saturating_add(extra);
+    let mut chain = Vec::with_capacity(depth as usize);
     let mut prev_hash = header_info.header.previous_header_hash;
 
     // put current header
@@ -35,8 +51,8 @@ fn look_back_confirmed_header<T: Config>(
         hash: header_info.header.hash(),
         height: header_info.height,
     });
-    // e.g. when confirmations is 4, loop 3 times max
-    for cnt in 1..confirmations {
+    // e.g. when confirmations is 4 and extra is 0, loop 3 times max
+    for cnt in 1..depth {
         if let Some(current_info) = Pallet::<T>::headers(&prev_hash) {
             chain.push(BtcHeaderIndex {
                 hash: prev_hash,
@@ -56,16 +72,25 @@ fn look_back_confirmed_header<T: Config>(
             break;
         }
     }
-    if chain.len() == confirmations as usize {
-        // confirmations must more than 0, thus, chain.last() must be some
-        (chain.last().cloned(), chain)
+    if confirmations > 0 && chain.len() >= confirmations as usize {
+        // the confirmed header is the `confirmations`th header on the look back path
+        (chain.get(confirmations as usize - 1).cloned(), chain)
     } else {
         (None, chain)
     }
 }
 
 pub fn update_confirmed_header<T: Config>(header_info: &BtcHeaderInfo) -> Option<BtcHeaderIndex> {
-    let (confirmed, chain) = look_back_confirmed_header::<T>(header_info);
+    update_confirmed_header_batch::<T>(header_info, 0)
+}
+
+/// Update the confirmed header for the last header of a batch, `extra` is the count of headers
+/// in the batch which are higher than the previous best header, excluding the last one.
+pub fn update_confirmed_header_batch<T: Config>(
+    header_info: &BtcHeaderInfo,
+    extra: u32,
+) -> Option<BtcHeaderIndex> {
+    let (confirmed, chain) = look_back_confirmed_header::<T>(header_info, extra);
     for index in chain {
         set_main_chain::<T>(index.height, index.hash);
     }
@@ -84,14 +109,27 @@ fn set_main_chain<T: Config>(height: u32, main_hash: H256) {
     for hash in hashes {
         if hash == main_hash {
             MainChain::<T>::insert(&hash, true);
-        } else {
-            MainChain::<T>::remove(&hash);
+        } else if MainChain::<T>::take(&hash) {
+            // the block is flipped out of the main chain, the txs of it should not be trusted
+            // any more if it has been confirmed
+            if let Some(confirmed) = ConfirmedIndex::<T>::get() {
+                if height <= confirmed.height {
+                    error!(
+                        target: "runtime::bitcoin",
+                        "[set_main_chain] Confirmed block ({:?}) at height {} is reorged, confirmed:{:?}",
+                        hash_rev(hash),
+                        height,
+                        confirmed
+                    );
+                    tx::freeze_reorged_deposits::<T>(hash);
+                }
+            }
         }
     }
 }
 
 pub fn check_confirmed_header<T: Config>(header_info: &BtcHeaderInfo) -> DispatchResult {
-    let (confirmed, _) = look_back_confirmed_header::<T>(header_info);
+    let (confirmed, _) = look_back_confirmed_header::<T>(header_info, 0);
     if let Some(current_confirmed) = ConfirmedIndex::<T>::get() {
         if let Some(now_confirmed) = confirmed {
             return match current_confirmed.height.cmp(&now_confirmed.height) {
@@ -138,3 +176,87 @@ pub fn check_confirmed_header<T: Config>(header_info: &BtcHeaderInfo) -> Dispatc
     // do not have confirmed yet.
     Ok(())
 }
+
+/// Prune the headers lower than `ConfirmedIndex - HeaderRetentionDepth`, from `NextPruneHeight`,
+/// as many as the `remaining_weight` allows. Return the consumed weight.
+pub fn prune_headers<T: Config>(remaining_weight: Weight) -> Weight {
+    let base_weight = T::DbWeight::get().reads_writes(5, 1);
+    let prune_weight = <T as Config>::WeightInfo::prune_header();
+    if remaining_weight < base_weight.saturating_add(prune_weight) {
+        return 0;
+    }
+
+    // 0 means never prune
+    let depth = Pallet::<T>::header_retention_depth();
+    if depth == 0 {
+        return T::DbWeight::get().reads(1);
+    }
+    let confirmed = match Pallet::<T>::confirmed_index() {
+        Some(confirmed) => confirmed,
+        None => return T::DbWeight::get().reads(2),
+    };
+    let target = confirmed.height.saturating_sub(depth);
+    let (_, genesis_height) = Pallet::<T>::genesis_info();
+    // the genesis header is never pruned
+    let from = Pallet::<T>::next_prune_height().max(genesis_height + 1);
+    if from >= target {
+        return T::DbWeight::get().reads(4);
+    }
+
+    let params = Pallet::<T>::params_info();
+    let mut consumed = base_weight;
+    let mut height = from;
+    while height < target
+        && height - from < MAX_PRUNED_HEIGHTS_PER_BLOCK
+        && consumed.saturating_add(prune_weight) <= remaining_weight
+    {
+        prune_header::<T>(height, &params);
+        consumed = consumed.saturating_add(prune_weight);
+        height += 1;
+    }
+    NextPruneHeight::<T>::put(height);
+    info!(
+        target: "runtime::bitcoin",
+        "[prune_headers] Prune headers from height {} to {}, confirmed height:{}, retention depth:{}",
+        from,
+        height,
+        confirmed.height,
+        depth
+    );
+    consumed
+}
+
+/// Remove the main chain header and all forked headers of the height, as well as the deposit
+/// and spent trustee utxo records of them. If the main chain header is a retarget boundary, keep it as the checkpoint
+/// for the next retarget instead, and remove the previous checkpoint header.
+pub fn prune_header<T: Config>(height: u32, params: &BtcParams) {
+    let is_retarget_height = height % params.retargeting_interval() == 0;
+    for hash in BlockHashFor::<T>::take(height) {
+        // the txs of the pruned block could not be reorged any more
+        for txid in BlockTxs::<T>::take(&hash) {
+            TxDepositOf::<T>::remove(&txid);
+            TxSpentTrusteeUtxos::<T>::remove(&txid);
+        }
+        let is_main_chain = MainChain::<T>::get(&hash);
+        match Headers::<T>::get(&hash) {
+            Some(info) if is_main_chain && is_retarget_height => {
+                if let Some(checkpoint) = RetargetCheckpoint::<T>::get() {
+                    BlockHashFor::<T>::remove(checkpoint.height);
+                    MainChain::<T>::remove(&checkpoint.hash);
+                    Headers::<T>::remove(&checkpoint.hash);
+                }
+                RetargetCheckpoint::<T>::put(BtcHeaderCheckpoint {
+                    height,
+                    hash,
+                    time: info.header.time,
+                    bits: info.header.bits,
+                });
+                BlockHashFor::<T>::insert(height, vec![hash]);
+            }
+            _ => {
+                MainChain::<T>::remove(&hash);
+                Headers::<T>::remove(&hash);
+            }
+        }
+    }
+}
@@ -4,7 +4,11 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
 mod header;
+mod signed_message;
+pub mod traits;
 pub mod trustee;
 mod tx;
 mod types;
@@ -17,47 +21,73 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
-use sp_runtime::SaturatedConversion;
+use alloc::string::ToString;
+use sp_runtime::{
+    traits::{Saturating, Zero},
+    SaturatedConversion,
+};
 use sp_std::prelude::*;
 
+use frame_support::traits::StorageVersion;
 use orml_utilities::with_transaction_result;
 
 #[cfg(feature = "std")]
 pub use light_bitcoin::primitives::h256_rev;
 pub use light_bitcoin::{
     chain::BlockHeader as BtcHeader,
-    keys::Network as BtcNetwork,
     primitives::{hash_rev, Compact, H256, H264},
 };
 use light_bitcoin::{
     chain::{Transaction, TransactionOutputArray},
-    keys::{Address, DisplayLayout},
+    keys::{Address, DisplayLayout, Network},
     serialization::{deserialize, Reader},
 };
 
 use chainx_primitives::{AssetId, ReferralId};
+use xp_gateway_bitcoin::extract_output_addr;
+pub use xp_gateway_bitcoin::{BtcTxType, DepositAction};
 use xp_gateway_common::AccountExtractor;
-use xpallet_assets::{BalanceOf, Chain, ChainT, WithdrawalLimit};
+use xpallet_assets::{AssetType, BalanceOf, Chain, ChainT, WithdrawalLimit};
 use xpallet_gateway_common::{
     traits::{AddressBinding, ReferralBinding, TrusteeSession},
     trustees::bitcoin::BtcTrusteeAddrInfo,
+    types::TrusteeOffence,
 };
 use xpallet_support::try_addr;
 
-pub use self::types::{BtcAddress, BtcParams, BtcTxVerifier, BtcWithdrawalProposal};
+pub use self::traits::{DepositActionHandler, OnBtcDeposited};
+pub use self::types::{
+    BtcAddress, BtcDepositCache, BtcHeaderIndex, BtcHeaderInfo, BtcNetwork, BtcParams,
+    BtcProofOfReserves, BtcRelayedTxInfo, BtcTrusteeUtxo, BtcTxResult, BtcTxState, BtcTxVerifier,
+    BtcWithdrawalProposal, VoteResult,
+};
 pub use self::weights::WeightInfo;
 use self::{
     trustee::{get_current_trustee_address_pair, get_last_trustee_address_pair},
     tx::remove_pending_deposit,
-    types::{
-        BtcDepositCache, BtcHeaderIndex, BtcHeaderInfo, BtcRelayedTx, BtcRelayedTxInfo,
-        BtcTxResult, BtcTxState,
-    },
+    types::{BtcHeaderCheckpoint, BtcRelayedTx, BtcReorgedDeposit},
 };
 
 pub use pallet::*;
 use sp_core::sp_std::str::FromStr;
 
+/// Max count of headers in a `push_headers` batch, about one day of bitcoin blocks.
+pub const MAX_HEADERS_PER_BATCH: u32 = 144;
+
+/// Max count of transactions in a `push_transactions` batch.
+pub const MAX_TXS_PER_BATCH: u32 = 64;
+
+/// Max count of withdrawal proposals in flight.
+pub const MAX_WITHDRAWAL_PROPOSALS: u32 = 16;
+
+/// The number of blocks the trustees could vote on an unfinished withdrawal proposal or fee bump
+/// before the trustees not voting are counted as missed, about one day.
+pub const PROPOSAL_VOTE_TIMEOUT: u32 = 14_400;
+
+/// The storage version 1 moves the single withdrawal proposal into `WithdrawalProposals` and
+/// tracks the height of pending deposits.
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 // syntactic sugar for native log.
 #[macro_export]
 macro_rules! log {
@@ -80,6 +110,7 @@ pub mod pallet {
 
     #[pallet::pallet]
     #[pallet::generate_store(pub(crate) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(PhantomData<T>);
 
     #[pallet::config]
@@ -93,11 +124,34 @@ pub mod pallet {
         type TrusteeOrigin: EnsureOrigin<Self::Origin, Success = Self::AccountId>;
         type ReferralBinding: ReferralBinding<Self::AccountId>;
         type AddressBinding: AddressBinding<Self::AccountId, BtcAddress>;
+        /// The handler of the follow-up action carried by the deposit memo.
+        type OnBtcDeposited: OnBtcDeposited<Self::AccountId, BalanceOf<Self>>;
+        /// The maximum weight of a single deposit follow-up action.
+        #[pallet::constant]
+        type MaxDepositActionWeight: Get<Weight>;
         type WeightInfo: WeightInfo;
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            trustee::record_stalled_proposals::<T>(now)
+        }
+
+        fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            header::prune_headers::<T>(remaining_weight)
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            if StorageVersion::get::<Pallet<T>>() >= STORAGE_VERSION {
+                return T::DbWeight::get().reads(1);
+            }
+            let weight = trustee::migrate_withdrawal_proposal::<T>()
+                .saturating_add(tx::migrate_pending_deposit_height::<T>());
+            STORAGE_VERSION.put::<Pallet<T>>();
+            weight.saturating_add(T::DbWeight::get().reads_writes(1, 1))
+        }
+    }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
@@ -115,39 +169,148 @@ pub mod pallet {
             Ok(Pays::No.into())
         }
 
+        /// Push a batch of contiguous headers, each header must be the child of the previous one
+        /// and the first one must extend a known header. If any header in the batch is invalid,
+        /// the whole batch would be rejected.
+        #[pallet::weight(<T as Config>::WeightInfo::push_headers(headers.len() as u32))]
+        pub fn push_headers(
+            origin: OriginFor<T>,
+            headers: Vec<Vec<u8>>,
+        ) -> DispatchResultWithPostInfo {
+            let from = ensure_signed(origin)?;
+            ensure!(
+                !headers.is_empty() && headers.len() <= MAX_HEADERS_PER_BATCH as usize,
+                Error::<T>::InvalidHeaderBatchSize
+            );
+            let headers = headers
+                .iter()
+                .map(|header| {
+                    deserialize(header.as_slice()).map_err(|_| Error::<T>::DeserializeErr)
+                })
+                .collect::<Result<Vec<BtcHeader>, _>>()?;
+            log!(
+                debug,
+                "[push_headers] from:{:?}, count:{}, first:{:?}",
+                from,
+                headers.len(),
+                headers.first().map(|header| header.hash())
+            );
+
+            Self::apply_push_headers(headers)?;
+
+            // Relayer does not pay a fee.
+            Ok(Pays::No.into())
+        }
+
         /// if use `RelayTx` struct would export in metadata, cause complex in front-end
-        #[pallet::weight(<T as Config>::WeightInfo::push_transaction())]
+        ///
+        /// `prev_txs` are the transactions of the outputs spent by the inputs of `raw_tx`,
+        /// the one of the first input is needed to detect the withdrawal, and the ones of all
+        /// inputs are needed to attribute a deposit without opreturn.
+        #[pallet::weight(<T as Config>::WeightInfo::push_transaction().saturating_add(T::MaxDepositActionWeight::get()))]
         pub fn push_transaction(
             origin: OriginFor<T>,
             raw_tx: Vec<u8>,
             relayed_info: BtcRelayedTxInfo,
-            prev_tx: Option<Vec<u8>>,
+            prev_txs: Vec<Vec<u8>>,
         ) -> DispatchResultWithPostInfo {
             let _from = ensure_signed(origin)?;
             let raw_tx = Self::deserialize_tx(raw_tx.as_slice())?;
-            let prev_tx = if let Some(prev_tx) = prev_tx {
-                Some(Self::deserialize_tx(prev_tx.as_slice())?)
-            } else {
-                None
-            };
+            let prev_txs = prev_txs
+                .iter()
+                .map(|prev_tx| Self::deserialize_tx(prev_tx.as_slice()))
+                .collect::<Result<Vec<_>, _>>()?;
             let relay_tx = relayed_info.into_relayed_tx(raw_tx);
             log!(
                 debug,
-                "[push_transaction] from:{:?}, relay_tx:{:?}, prev_tx:{:?}",
+                "[push_transaction] from:{:?}, relay_tx:{:?}, prev_txs:{:?}",
                 _from,
                 relay_tx,
-                prev_tx
+                prev_txs
             );
 
-            Self::apply_push_transaction(relay_tx, prev_tx)?;
+            Self::apply_push_transaction(relay_tx, prev_txs)?;
 
             Ok(Pays::No.into())
         }
 
+        /// Push a batch of transactions, each item is `(raw_tx, relayed_info, prev_txs)` as the
+        /// arguments of `push_transaction`. The items in the same block could share the merkle
+        /// proof which matches all of them, or use the proof of the tx only.
+        ///
+        /// Each item has the same side effects as `push_transaction`, a failed item would not
+        /// revert the others, and the result of each item is emitted in `BatchTxApplied`. The
+        /// whole batch would be rejected only if any item could not be deserialized.
+        ///
+        /// The batch is free as `push_transaction` only if all the items succeed, otherwise the
+        /// fee of the actual weight is charged.
+        #[pallet::weight(
+            <T as Config>::WeightInfo::push_transaction()
+                .saturating_add(T::MaxDepositActionWeight::get())
+                .saturating_mul(txs.len() as Weight)
+        )]
+        pub fn push_transactions(
+            origin: OriginFor<T>,
+            txs: Vec<(Vec<u8>, BtcRelayedTxInfo, Vec<Vec<u8>>)>,
+        ) -> DispatchResultWithPostInfo {
+            let from = ensure_signed(origin)?;
+            ensure!(
+                !txs.is_empty() && txs.len() <= MAX_TXS_PER_BATCH as usize,
+                Error::<T>::InvalidTxBatchSize
+            );
+            let txs = txs
+                .into_iter()
+                .map(|(raw_tx, relayed_info, prev_txs)| {
+                    let raw_tx = Self::deserialize_tx(raw_tx.as_slice())?;
+                    let prev_txs = prev_txs
+                        .iter()
+                        .map(|prev_tx| Self::deserialize_tx(prev_tx.as_slice()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok((relayed_info.into_relayed_tx(raw_tx), prev_txs))
+                })
+                .collect::<Result<Vec<_>, Error<T>>>()?;
+            log!(
+                debug,
+                "[push_transactions] from:{:?}, count:{}",
+                from,
+                txs.len()
+            );
+
+            let mut succeeded = 0u32;
+            let count = txs.len() as u32;
+            for (index, (relay_tx, prev_txs)) in txs.into_iter().enumerate() {
+                let tx_hash = relay_tx.raw.hash();
+                // not in a storage layer, the failed tx is recorded as `push_transaction` does
+                let result = Self::apply_push_transaction(relay_tx, prev_txs);
+                match result {
+                    Ok(_) => succeeded += 1,
+                    Err(err) => log!(
+                        warn,
+                        "[push_transactions] Apply tx (index:{}, hash:{:?}) failed:{:?}",
+                        index,
+                        tx_hash,
+                        err
+                    ),
+                }
+                Self::deposit_event(Event::<T>::BatchTxApplied(index as u32, tx_hash, result));
+            }
+
+            // the deposit action is only executed by the succeeded items
+            let actual_weight = <T as Config>::WeightInfo::push_transaction()
+                .saturating_mul(count.into())
+                .saturating_add(T::MaxDepositActionWeight::get().saturating_mul(succeeded.into()));
+            let pays_fee = if succeeded == count {
+                Pays::No
+            } else {
+                Pays::Yes
+            };
+            Ok((Some(actual_weight), pays_fee).into())
+        }
+
         /// Trustee create a proposal for a withdrawal list. `tx` is the proposal withdrawal transaction.
         /// The `tx` would have a sign for current creator or do not have sign. if creator do not sign
         /// for this transaction, he could do `sign_withdraw_tx` later.
-        #[pallet::weight(<T as Config>::WeightInfo::create_withdraw_tx())]
+        #[pallet::weight(<T as Config>::WeightInfo::create_withdraw_tx().saturating_add(Pallet::<T>::verify_sig_weight()))]
         pub fn create_withdraw_tx(
             origin: OriginFor<T>,
             withdrawal_id_list: Vec<u32>,
@@ -171,7 +334,7 @@ pub mod pallet {
         }
 
         /// Trustee create a proposal for a withdrawal list. `tx` is the proposal withdrawal transaction.
-        #[pallet::weight(<T as Config>::WeightInfo::create_withdraw_tx())]
+        #[pallet::weight(<T as Config>::WeightInfo::create_withdraw_tx().saturating_add(Pallet::<T>::verify_sig_weight()))]
         pub fn create_taproot_withdraw_tx(
             origin: OriginFor<T>,
             withdrawal_id_list: Vec<u32>,
@@ -193,8 +356,12 @@ pub mod pallet {
         /// Trustees sign a withdrawal proposal. If `tx` is None, means this trustee vote to reject
         /// this proposal. If `tx` is Some(), the inner part must be a valid transaction with this
         /// trustee signature.
-        #[pallet::weight(<T as Config>::WeightInfo::sign_withdraw_tx())]
-        pub fn sign_withdraw_tx(origin: OriginFor<T>, tx: Option<Vec<u8>>) -> DispatchResult {
+        #[pallet::weight(<T as Config>::WeightInfo::sign_withdraw_tx().saturating_add(Pallet::<T>::verify_sig_weight()))]
+        pub fn sign_withdraw_tx(
+            origin: OriginFor<T>,
+            proposal_id: u32,
+            tx: Option<Vec<u8>>,
+        ) -> DispatchResult {
             let from = ensure_signed(origin)?;
             Self::ensure_trustee(&from)?;
 
@@ -205,15 +372,72 @@ pub mod pallet {
             };
             log!(
                 debug,
-                "[sign_withdraw_tx] from:{:?}, vote_tx:{:?}",
+                "[sign_withdraw_tx] from:{:?}, proposal_id:{}, vote_tx:{:?}",
                 from,
+                proposal_id,
                 tx
             );
 
-            Self::apply_sig_withdraw(from, tx)?;
+            Self::apply_sig_withdraw(from, proposal_id, tx)?;
             Ok(())
         }
 
+        /// Trustee create a proposal to replace the finished withdrawal proposal transaction with a
+        /// higher fee (replace-by-fee). `tx` must spend the same inputs and pay the same outputs,
+        /// only the change is reduced. The original proposal is kept until one of them is observed
+        /// on-chain. `spent_outputs` are only used to verify the taproot signature, the fee is
+        /// computed from the trustee utxos.
+        #[pallet::weight(<T as Config>::WeightInfo::bump_withdrawal_fee().saturating_add(Pallet::<T>::verify_sig_weight()))]
+        pub fn bump_withdrawal_fee(
+            origin: OriginFor<T>,
+            proposal_id: u32,
+            tx: Vec<u8>,
+            spent_outputs: Vec<u8>,
+        ) -> DispatchResult {
+            let from = ensure_signed(origin)?;
+            Self::ensure_trustee(&from)?;
+
+            let tx = Self::deserialize_tx(tx.as_slice())?;
+            let spent_outputs = Self::deserialize_spent_outputs(spent_outputs.as_slice())?.outputs;
+            log!(
+                debug,
+                "[bump_withdrawal_fee] from:{:?}, proposal_id:{}, tx:{:?}, spent_outputs:{:?}",
+                from,
+                proposal_id,
+                tx,
+                spent_outputs
+            );
+
+            Self::apply_bump_withdrawal_fee(from, proposal_id, tx, spent_outputs)
+        }
+
+        /// Trustees sign the fee bump proposal, same as `sign_withdraw_tx`. If the fee bump
+        /// proposal is rejected, only the fee bump is dropped.
+        #[pallet::weight(<T as Config>::WeightInfo::sign_withdraw_tx().saturating_add(Pallet::<T>::verify_sig_weight()))]
+        pub fn sign_fee_bump_tx(
+            origin: OriginFor<T>,
+            proposal_id: u32,
+            tx: Option<Vec<u8>>,
+        ) -> DispatchResult {
+            let from = ensure_signed(origin)?;
+            Self::ensure_trustee(&from)?;
+
+            let tx = if let Some(raw_tx) = tx {
+                Some(Self::deserialize_tx(raw_tx.as_slice())?)
+            } else {
+                None
+            };
+            log!(
+                debug,
+                "[sign_fee_bump_tx] from:{:?}, proposal_id:{}, vote_tx:{:?}",
+                from,
+                proposal_id,
+                tx
+            );
+
+            Self::apply_sig_fee_bump(from, proposal_id, tx)
+        }
+
         /// Dangerous! Be careful to set BestIndex
         #[pallet::weight(<T as Config>::WeightInfo::set_best_index())]
         pub fn set_best_index(origin: OriginFor<T>, index: BtcHeaderIndex) -> DispatchResult {
@@ -247,34 +471,94 @@ pub mod pallet {
                 remove_pending_deposit::<T>(&addr, &w);
             } else {
                 log!(info, "[remove_pending] Release pending deposit directly, not deposit to someone, addr:{:?}", try_addr(&addr));
-                PendingDeposits::<T>::remove(&addr);
+                for record in PendingDeposits::<T>::take(&addr) {
+                    tx::release_pending_deposit::<T>(&record.txid, &addr);
+                }
             }
             Ok(())
         }
 
-        /// Dangerous! remove current withdrawal proposal directly. Please check business logic before
+        /// Claim the pending deposits of a btc address by proving the ownership of the address.
+        /// The `signature` is a Bitcoin signed message (BIP-137) of the hex encoded genesis hash
+        /// of the chain and account id of origin (e.g. `0x9a7e...:0xd435...`), the address would
+        /// be bound to the origin after claiming.
+        ///
+        /// `deposit_count` is the witness of the count of pending deposits of the address.
+        #[pallet::weight(<T as Config>::WeightInfo::claim_pending_deposit(*deposit_count))]
+        pub fn claim_pending_deposit(
+            origin: OriginFor<T>,
+            addr: BtcAddress,
+            signature: Vec<u8>,
+            #[pallet::compact] deposit_count: u32,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let address = Self::verify_network_address(&addr)?;
+            // use the normalized address as the key of pending deposits
+            let addr = address.to_string().into_bytes();
+            let pending = PendingDeposits::<T>::decode_len(&addr).unwrap_or_default();
+            ensure!(pending > 0, Error::<T>::NoPendingDeposit);
+            ensure!(
+                pending <= deposit_count as usize,
+                Error::<T>::InvalidDepositCount
+            );
+
+            let genesis_hash = frame_system::Pallet::<T>::block_hash(T::BlockNumber::zero());
+            let message = signed_message::account_message(genesis_hash.as_ref(), &who.encode());
+            ensure!(
+                signed_message::verify_signed_message(&address, &message, &signature),
+                Error::<T>::InvalidSignedMessage
+            );
+
+            log!(
+                info,
+                "[claim_pending_deposit] Claim pending deposits, who:{:?}, addr:{:?}",
+                who,
+                try_addr(&addr)
+            );
+            remove_pending_deposit::<T>(&addr, &who);
+            T::AddressBinding::update_binding(Self::chain(), addr, who);
+            Ok(())
+        }
+
+        /// Dangerous! remove a withdrawal proposal directly. Please check business logic before
         /// do this operation.
         #[pallet::weight(<T as Config>::WeightInfo::remove_proposal())]
-        pub fn remove_proposal(origin: OriginFor<T>) -> DispatchResult {
+        pub fn remove_proposal(origin: OriginFor<T>, proposal_id: u32) -> DispatchResult {
             ensure_root(origin)?;
-            WithdrawalProposal::<T>::kill();
+            if let Some(proposal) = WithdrawalProposals::<T>::take(proposal_id) {
+                trustee::record_removed_proposal_votes::<T>(proposal_id, false, &proposal);
+            }
+            if let Some(fee_bump) = FeeBumpProposals::<T>::take(proposal_id) {
+                trustee::record_removed_proposal_votes::<T>(proposal_id, true, &fee_bump);
+            }
+            ProposalRefunds::<T>::remove(proposal_id);
+            ProposalSessions::<T>::remove(proposal_id);
             Ok(())
         }
 
-        /// Dangerous! force replace current withdrawal proposal transaction. Please check business
+        /// Dangerous! force replace a withdrawal proposal transaction. Please check business
         /// logic before do this operation. Must make sure current proposal transaction is invalid
         /// (e.g. when created a proposal, the inputs are not in double spend state, but after other
         /// trustees finish signing, the inputs are in double spend due other case. Thus could create
         /// a new valid transaction which outputs same to current proposal to replace current proposal
         /// transaction.)
         #[pallet::weight(<T as Config>::WeightInfo::force_replace_proposal_tx())]
-        pub fn force_replace_proposal_tx(origin: OriginFor<T>, tx: Vec<u8>) -> DispatchResult {
+        pub fn force_replace_proposal_tx(
+            origin: OriginFor<T>,
+            proposal_id: u32,
+            tx: Vec<u8>,
+        ) -> DispatchResult {
             T::TrusteeOrigin::try_origin(origin)
                 .map(|_| ())
                 .or_else(ensure_root)?;
             let tx = Self::deserialize_tx(tx.as_slice())?;
-            log!(debug, "[force_replace_proposal_tx] new_tx:{:?}", tx);
-            Self::force_replace_withdraw_tx(tx)
+            log!(
+                debug,
+                "[force_replace_proposal_tx] proposal_id:{}, new_tx:{:?}",
+                proposal_id,
+                tx
+            );
+            Self::force_replace_withdraw_tx(proposal_id, tx)
         }
 
         /// Set bitcoin withdrawal fee
@@ -302,6 +586,161 @@ pub mod pallet {
             BtcMinDeposit::<T>::put(value);
             Ok(())
         }
+
+        /// Resolve a deposit which was reorged out of the main chain. If `release` is true, the
+        /// frozen balance would be released to the depositor, otherwise it would be burned.
+        #[pallet::weight(<T as Config>::WeightInfo::resolve_reorged_deposit())]
+        pub fn resolve_reorged_deposit(
+            origin: OriginFor<T>,
+            txid: H256,
+            release: bool,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            with_transaction_result(|| {
+                let deposit =
+                    ReorgedDeposits::<T>::take(&txid).ok_or(Error::<T>::NoReorgedDeposit)?;
+                log!(
+                    info,
+                    "[resolve_reorged_deposit] txid:{:?}, deposit:{:?}, release:{}",
+                    txid,
+                    deposit,
+                    release
+                );
+                if release {
+                    // the released deposit must not be credited again by re-inclusion
+                    TxState::<T>::insert(
+                        &txid,
+                        BtcTxState {
+                            tx_type: BtcTxType::Deposit,
+                            result: BtcTxResult::Success,
+                        },
+                    );
+                }
+                tx::resolve_reorged_deposit::<T>(deposit, release)
+            })
+        }
+
+        /// Set the retention depth of headers. Headers lower than `ConfirmedIndex - depth` would
+        /// be pruned when the block is idle, 0 means never prune.
+        ///
+        /// A non-zero depth must keep at least a retarget interval plus the confirmation number
+        /// of headers, otherwise the retarget work could not be calculated any more.
+        #[pallet::weight(<T as Config>::WeightInfo::set_header_retention_depth())]
+        pub fn set_header_retention_depth(
+            origin: OriginFor<T>,
+            #[pallet::compact] depth: u32,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            let min_depth = Self::params_info()
+                .retargeting_interval()
+                .saturating_add(Self::confirmation_number());
+            ensure!(
+                depth == 0 || depth >= min_depth,
+                Error::<T>::InvalidRetentionDepth
+            );
+            HeaderRetentionDepth::<T>::put(depth);
+            Ok(())
+        }
+
+        /// Set the count of bitcoin blocks since inclusion before a pending deposit expires,
+        /// 0 means never expire.
+        #[pallet::weight(<T as Config>::WeightInfo::set_pending_deposit_expiry())]
+        pub fn set_pending_deposit_expiry(
+            origin: OriginFor<T>,
+            #[pallet::compact] expiry: u32,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            PendingDepositExpiry::<T>::put(expiry);
+            Ok(())
+        }
+
+        /// Allow root or trustees to move the expired pending deposits of an address into the
+        /// refund list, then trustees could refund them to the address in the next withdrawal
+        /// proposal. The refund pays the withdrawal fee, so the expired deposits must be more
+        /// than the fee in total.
+        #[pallet::weight(<T as Config>::WeightInfo::expire_pending_deposits())]
+        pub fn expire_pending_deposits(origin: OriginFor<T>, addr: BtcAddress) -> DispatchResult {
+            T::TrusteeOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+            tx::expire_pending_deposits::<T>(&addr)
+        }
+
+        /// Set the band of fee rate (satoshi per virtual byte) for withdrawal transactions,
+        /// `max` 0 means no upper bound.
+        #[pallet::weight(<T as Config>::WeightInfo::set_withdrawal_fee_rate())]
+        pub fn set_withdrawal_fee_rate(
+            origin: OriginFor<T>,
+            #[pallet::compact] min: u64,
+            #[pallet::compact] max: u64,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(max == 0 || min <= max, Error::<T>::InvalidFeeRateBand);
+            WithdrawalFeeRate::<T>::put((min, max));
+            Ok(())
+        }
+
+        /// Set or remove an unspent output of the trustee addresses, used to fix the trustee
+        /// utxo set which is maintained by the relayed transactions.
+        #[pallet::weight(<T as Config>::WeightInfo::set_trustee_utxo())]
+        pub fn set_trustee_utxo(
+            origin: OriginFor<T>,
+            txid: H256,
+            index: u32,
+            utxo: Option<BtcTrusteeUtxo>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            match utxo {
+                Some(utxo) => TrusteeUtxos::<T>::insert(txid, index, utxo),
+                None => TrusteeUtxos::<T>::remove(txid, index),
+            }
+            Ok(())
+        }
+
+        /// Mark whether the trustee utxo set is complete. On an upgraded chain, root seeds the
+        /// existing unspent outputs of trustee addresses by `set_trustee_utxo` and then
+        /// initializes the set, after that the withdrawal transactions could only spend them.
+        #[pallet::weight(<T as Config>::WeightInfo::set_trustee_utxo())]
+        pub fn set_trustee_utxos_initialized(
+            origin: OriginFor<T>,
+            initialized: bool,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            TrusteeUtxosInitialized::<T>::put(initialized);
+            Ok(())
+        }
+
+        /// Report a confirmed transaction which spent the funds of trustee addresses without
+        /// the approval of a withdrawal proposal, the trustees signing the spend would be slashed.
+        ///
+        /// `prev_txs` are the transactions of the outputs spent by the inputs of `raw_tx`,
+        /// every input spending a trustee address must be covered by them. The signers of the
+        /// multisig address are recovered from the signatures, and the ones of the taproot
+        /// address are identified by the revealed leaf, or all the trustees for the key path.
+        #[pallet::weight(<T as Config>::WeightInfo::report_unauthorized_spend(prev_txs.len() as u32))]
+        pub fn report_unauthorized_spend(
+            origin: OriginFor<T>,
+            raw_tx: Vec<u8>,
+            relayed_info: BtcRelayedTxInfo,
+            prev_txs: Vec<Vec<u8>>,
+        ) -> DispatchResult {
+            let _from = ensure_signed(origin)?;
+            let raw_tx = Self::deserialize_tx(raw_tx.as_slice())?;
+            let prev_txs = prev_txs
+                .iter()
+                .map(|prev_tx| Self::deserialize_tx(prev_tx.as_slice()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let relay_tx = relayed_info.into_relayed_tx(raw_tx);
+            log!(
+                debug,
+                "[report_unauthorized_spend] from:{:?}, relay_tx:{:?}, prev_txs:{:?}",
+                _from,
+                relay_tx,
+                prev_txs
+            );
+
+            Self::apply_report_unauthorized_spend(relay_tx, prev_txs)
+        }
     }
 
     /// Error for the XBridge Bitcoin module
@@ -381,6 +820,60 @@ pub mod pallet {
         NoWithdrawalRecord,
         /// already vote for this withdrawal proposal
         DuplicateVote,
+        /// the header batch is empty or exceeds the max count
+        InvalidHeaderBatchSize,
+        /// headers in the batch are not a contiguous chain
+        NonContiguousHeaders,
+        /// no reorged deposit for this tx
+        NoReorgedDeposit,
+        /// the retention depth can't keep the headers needed by retarget and confirmation
+        InvalidRetentionDepth,
+        /// no pending deposit for this address
+        NoPendingDeposit,
+        /// the signed message can't prove the ownership of the address
+        InvalidSignedMessage,
+        /// the witness count of pending deposits is less than the actual count
+        InvalidDepositCount,
+        /// no expired pending deposit for this address
+        NoExpiredDeposit,
+        /// the expired deposits of this address are being refunded
+        RefundInProgress,
+        /// the expired deposits of this address could not cover the withdrawal fee
+        DustRefund,
+        /// the min fee rate is greater than the max fee rate
+        InvalidFeeRateBand,
+        /// the spent outputs do not match the inputs of withdrawal transaction
+        InvalidSpentOutputs,
+        /// the fee rate of withdrawal transaction is out of the band
+        FeeRateOutOfBand,
+        /// the withdrawal proposal is not fully signed yet
+        UnfinishedProposal,
+        /// a fee bump proposal is in progress
+        FeeBumpInProgress,
+        /// the tx is not a fee bump of the withdrawal proposal transaction
+        InvalidFeeBump,
+        /// the withdrawal proposal transaction does not signal replaceability (BIP125)
+        NotReplaceable,
+        /// the count of withdrawal proposals in flight exceeds the max count
+        TooManyProposals,
+        /// the withdrawal applications are in other withdrawal proposal
+        OverlappingWithdrawals,
+        /// the inputs of tx are spent by other withdrawal proposal
+        ConflictingInputs,
+        /// the input of tx is not an unspent output of trustee addresses
+        UnknownTrusteeUtxo,
+        /// the weight of deposit action exceeds the limit
+        DepositActionOverweight,
+        /// the spend has been reported before
+        DuplicateReport,
+        /// the spend was approved by the trustees
+        AuthorizedSpend,
+        /// the tx does not spend the funds of trustee addresses
+        NotTrusteeSpend,
+        /// the count of transactions in a batch is zero or exceeds the max count
+        InvalidTxBatchSize,
+        /// the trustees signing the spend could not be identified
+        UnknownSpendSigners,
     }
 
     #[pallet::event]
@@ -398,16 +891,37 @@ pub mod pallet {
         UnclaimedDeposit(H256, BtcAddress),
         /// A unclaimed deposit record was removed. [depositor, deposit_amount, tx_hash, btc_address]
         PendingDepositRemoved(T::AccountId, BalanceOf<T>, H256, BtcAddress),
-        /// A new withdrawal proposal was created. [proposer, withdrawal_ids]
-        WithdrawalProposalCreated(T::AccountId, Vec<u32>),
-        /// A trustee voted/vetoed a withdrawal proposal. [trustee, vote_status]
-        WithdrawalProposalVoted(T::AccountId, bool),
-        /// A withdrawal proposal was dropped. [reject_count, total_count, withdrawal_ids]
-        WithdrawalProposalDropped(u32, u32, Vec<u32>),
-        /// The proposal has been processed successfully and is waiting for broadcasting. [tx_hash]
-        WithdrawalProposalCompleted(H256),
+        /// A new withdrawal proposal was created. [proposal_id, proposer, withdrawal_ids]
+        WithdrawalProposalCreated(u32, T::AccountId, Vec<u32>),
+        /// A trustee voted/vetoed a withdrawal proposal. [proposal_id, trustee, vote_status]
+        WithdrawalProposalVoted(u32, T::AccountId, bool),
+        /// A withdrawal proposal was dropped. [proposal_id, reject_count, total_count, withdrawal_ids]
+        WithdrawalProposalDropped(u32, u32, u32, Vec<u32>),
+        /// The proposal has been processed successfully and is waiting for broadcasting. [proposal_id, tx_hash]
+        WithdrawalProposalCompleted(u32, H256),
         /// A fatal error happened during the withdrwal process. [tx_hash, proposal_hash]
         WithdrawalFatalErr(H256, H256),
+        /// A deposit was applied from a block which is flipped out of the main chain, the credited
+        /// balance was frozen for governance resolution. [tx_hash, block_hash, who, frozen_amount]
+        DepositReorged(H256, H256, T::AccountId, BalanceOf<T>),
+        /// A pending deposit was expired and is waiting for refund. [tx_hash, btc_address, deposit_amount]
+        PendingDepositExpired(H256, BtcAddress, BalanceOf<T>),
+        /// The expired deposits of an address were refunded. [tx_hash, btc_address, refund_amount]
+        ExpiredDepositRefunded(H256, BtcAddress, BalanceOf<T>),
+        /// A fee bump of the withdrawal proposal was created. [proposal_id, proposer, original_tx_hash, new_tx_hash]
+        WithdrawalFeeBumpCreated(u32, T::AccountId, H256, H256),
+        /// A fee bump of the withdrawal proposal was dropped. [proposal_id, reject_count, total_count]
+        WithdrawalFeeBumpDropped(u32, u32, u32),
+        /// A trustee transition tx moved the funds of previous trustees. [tx_hash, draining_session_number, remaining_balance]
+        TrusteeTransitionProcessed(H256, u32, u64),
+        /// The follow-up action of a deposit was performed. [tx_hash, who, action]
+        DepositActionExecuted(H256, T::AccountId, DepositAction),
+        /// The follow-up action of a deposit failed, the deposit itself was kept. [tx_hash, who, action, error]
+        DepositActionFailed(H256, T::AccountId, DepositAction, DispatchError),
+        /// An unauthorized spend of the trustee funds was reported, the trustees were slashed. [tx_hash, trustees]
+        UnauthorizedSpendReported(H256, Vec<T::AccountId>),
+        /// A transaction of a `push_transactions` batch was applied or rejected. [index, tx_hash, result]
+        BatchTxApplied(u32, H256, DispatchResult),
     }
 
     /// best header info
@@ -441,17 +955,127 @@ pub mod pallet {
     #[pallet::getter(fn tx_state)]
     pub(crate) type TxState<T: Config> = StorageMap<_, Identity, H256, BtcTxState>;
 
+    /// the txs processed successfully or updating the trustee utxo set in a block,
+    /// block_hash => tx_hash list
+    #[pallet::storage]
+    #[pallet::getter(fn block_txs)]
+    pub(crate) type BlockTxs<T: Config> = StorageMap<_, Identity, H256, Vec<H256>, ValueQuery>;
+
+    /// the trustee utxos spent by a tx, which are restored once the block of the tx is flipped
+    /// out of the main chain, tx_hash => [(tx_hash, output_index, utxo)]
+    #[pallet::storage]
+    #[pallet::getter(fn tx_spent_trustee_utxos)]
+    pub(crate) type TxSpentTrusteeUtxos<T: Config> =
+        StorageMap<_, Identity, H256, Vec<(H256, u32, BtcTrusteeUtxo)>, ValueQuery>;
+
+    /// processed trustee txs whose block is flipped out of the main chain, tx_hash => block_hash,
+    /// their trustee utxo updates are applied again once they are included by the new main chain
+    #[pallet::storage]
+    #[pallet::getter(fn reorged_trustee_txs)]
+    pub(crate) type ReorgedTrusteeTxs<T: Config> = StorageMap<_, Identity, H256, H256>;
+
+    /// the credited account and balance of a deposit tx
+    #[pallet::storage]
+    #[pallet::getter(fn tx_deposit_of)]
+    pub(crate) type TxDepositOf<T: Config> =
+        StorageMap<_, Identity, H256, (T::AccountId, BalanceOf<T>)>;
+
+    /// deposits whose block is flipped out of the main chain, wait for governance resolution
+    #[pallet::storage]
+    #[pallet::getter(fn reorged_deposits)]
+    pub(crate) type ReorgedDeposits<T: Config> =
+        StorageMap<_, Identity, H256, BtcReorgedDeposit<T::AccountId, BalanceOf<T>>>;
+
     /// unclaimed deposit info, addr => tx_hash, btc value,
     #[pallet::storage]
     #[pallet::getter(fn pending_deposits)]
     pub(crate) type PendingDeposits<T: Config> =
         StorageMap<_, Blake2_128Concat, BtcAddress, Vec<BtcDepositCache>, ValueQuery>;
 
-    /// withdrawal tx outs for account, tx_hash => outs ( out index => withdrawal account )
+    /// the height of the block which includes a pending deposit, tx_hash => height
+    #[pallet::storage]
+    #[pallet::getter(fn pending_deposit_height)]
+    pub(crate) type PendingDepositHeight<T: Config> = StorageMap<_, Identity, H256, u32>;
+
+    /// the input addresses still holding a share of a pending deposit, tx_hash => addresses
+    #[pallet::storage]
+    #[pallet::getter(fn pending_deposit_addrs)]
+    pub(crate) type PendingDepositAddrs<T: Config> =
+        StorageMap<_, Identity, H256, Vec<BtcAddress>, ValueQuery>;
+
+    /// the count of bitcoin blocks since inclusion before a pending deposit expires, 0 means
+    /// never expire
+    #[pallet::storage]
+    #[pallet::getter(fn pending_deposit_expiry)]
+    pub(crate) type PendingDepositExpiry<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// expired pending deposits waiting for refund to the sender address, addr => tx_hash, btc value
+    #[pallet::storage]
+    #[pallet::getter(fn expired_deposits)]
+    pub(crate) type ExpiredDeposits<T: Config> =
+        StorageMap<_, Blake2_128Concat, BtcAddress, Vec<BtcDepositCache>, ValueQuery>;
+
+    /// the addresses of expired deposits refunded by a withdrawal proposal,
+    /// proposal_id => addr list
+    #[pallet::storage]
+    #[pallet::getter(fn proposal_refunds)]
+    pub(crate) type ProposalRefunds<T: Config> =
+        StorageMap<_, Twox64Concat, u32, Vec<BtcAddress>, ValueQuery>;
+
+    /// the id of next withdrawal proposal
+    #[pallet::storage]
+    #[pallet::getter(fn next_proposal_id)]
+    pub(crate) type NextProposalId<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// withdrawal proposals in flight, proposal_id => proposal
     #[pallet::storage]
     #[pallet::getter(fn withdrawal_proposal)]
-    pub(crate) type WithdrawalProposal<T: Config> =
-        StorageValue<_, BtcWithdrawalProposal<T::AccountId>>;
+    pub(crate) type WithdrawalProposals<T: Config> =
+        StorageMap<_, Twox64Concat, u32, BtcWithdrawalProposal<T::AccountId>>;
+
+    /// the trustee session which the withdrawal proposal is created in,
+    /// proposal_id => session number
+    #[pallet::storage]
+    #[pallet::getter(fn proposal_session)]
+    pub(crate) type ProposalSessions<T: Config> = StorageMap<_, Twox64Concat, u32, u32>;
+
+    /// the block before which the trustees should vote on the unfinished withdrawal proposal
+    /// or fee bump, (proposal_id, is_fee_bump) => block number
+    #[pallet::storage]
+    #[pallet::getter(fn proposal_vote_deadline)]
+    pub(crate) type ProposalVoteDeadlines<T: Config> =
+        StorageMap<_, Twox64Concat, (u32, bool), BlockNumberFor<T>>;
+
+    /// the unfinished withdrawal proposals or fee bumps whose votes have been recorded since
+    /// they are stalled, (proposal_id, is_fee_bump) => ()
+    #[pallet::storage]
+    pub(crate) type StalledProposals<T: Config> = StorageMap<_, Twox64Concat, (u32, bool), ()>;
+
+    /// the proposal replacing the withdrawal proposal transaction with a higher fee,
+    /// proposal_id => fee bump proposal
+    #[pallet::storage]
+    #[pallet::getter(fn fee_bump_proposal)]
+    pub(crate) type FeeBumpProposals<T: Config> =
+        StorageMap<_, Twox64Concat, u32, BtcWithdrawalProposal<T::AccountId>>;
+
+    /// unspent outputs of the trustee hot and cold addresses, (tx_hash, output_index) => utxo
+    #[pallet::storage]
+    #[pallet::getter(fn trustee_utxo)]
+    pub(crate) type TrusteeUtxos<T: Config> =
+        StorageDoubleMap<_, Identity, H256, Twox64Concat, u32, BtcTrusteeUtxo>;
+
+    /// whether `TrusteeUtxos` holds all the unspent outputs of trustee addresses, it's true for
+    /// a new chain, and set by root after seeding the utxos on an upgraded chain.
+    /// The inputs of withdrawal transactions are only checked against `TrusteeUtxos` once it's
+    /// initialized.
+    #[pallet::storage]
+    #[pallet::getter(fn trustee_utxos_initialized)]
+    pub(crate) type TrusteeUtxosInitialized<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// the unauthorized spends of trustee funds which have been reported
+    #[pallet::storage]
+    #[pallet::getter(fn reported_spend)]
+    pub(crate) type ReportedSpends<T: Config> = StorageMap<_, Identity, H256, bool, ValueQuery>;
 
     /// get GenesisInfo (header, height)
     #[pallet::storage]
@@ -463,7 +1087,7 @@ pub mod pallet {
     #[pallet::getter(fn params_info)]
     pub(crate) type ParamsInfo<T: Config> = StorageValue<_, BtcParams, ValueQuery>;
 
-    ///  NetworkId for testnet or mainnet
+    ///  NetworkId for mainnet, testnet, regtest or signet
     #[pallet::storage]
     #[pallet::getter(fn network_id)]
     pub(crate) type NetworkId<T: Config> = StorageValue<_, BtcNetwork, ValueQuery>;
@@ -489,6 +1113,12 @@ pub mod pallet {
     pub(crate) type BtcMinDeposit<T: Config> =
         StorageValue<_, u64, ValueQuery, DefaultForMinDeposit<T>>;
 
+    /// the band of fee rate (satoshi per virtual byte) for withdrawal transactions, (min, max),
+    /// max 0 means no upper bound
+    #[pallet::storage]
+    #[pallet::getter(fn withdrawal_fee_rate)]
+    pub(crate) type WithdrawalFeeRate<T: Config> = StorageValue<_, (u64, u64), ValueQuery>;
+
     /// max withdraw account count in bitcoin withdrawal transaction
     #[pallet::storage]
     #[pallet::getter(fn max_withdrawal_count)]
@@ -498,6 +1128,21 @@ pub mod pallet {
     #[pallet::getter(fn verifier)]
     pub(crate) type Verifier<T: Config> = StorageValue<_, BtcTxVerifier, ValueQuery>;
 
+    /// the count of headers kept below the confirmed header, 0 means never prune
+    #[pallet::storage]
+    #[pallet::getter(fn header_retention_depth)]
+    pub(crate) type HeaderRetentionDepth<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// the lowest height which has not been pruned
+    #[pallet::storage]
+    #[pallet::getter(fn next_prune_height)]
+    pub(crate) type NextPruneHeight<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// the latest pruned retarget boundary header, needed by the next retarget
+    #[pallet::storage]
+    #[pallet::getter(fn retarget_checkpoint)]
+    pub(crate) type RetargetCheckpoint<T: Config> = StorageValue<_, BtcHeaderCheckpoint>;
+
     #[pallet::genesis_config]
     pub struct GenesisConfig<T: Config> {
         pub genesis_hash: H256,
@@ -554,6 +1199,8 @@ pub mod pallet {
             BtcWithdrawalFee::<T>::put(self.btc_withdrawal_fee);
             MaxWithdrawalCount::<T>::put(self.max_withdrawal_count);
             Verifier::<T>::put(self.verifier);
+            // there is no trustee fund on a new chain
+            TrusteeUtxosInitialized::<T>::put(true);
 
             // init trustee (not this action should ha)
             if !self.genesis_trustees.is_empty() {
@@ -615,6 +1262,19 @@ pub mod pallet {
     }
 
     impl<T: Config> Pallet<T> {
+        /// The weight of verifying the signatures of a withdrawal transaction by the current
+        /// verifier, which is not included in the weights of the trustee calls.
+        pub(crate) fn verify_sig_weight() -> Weight {
+            match Self::verifier() {
+                BtcTxVerifier::Recover => <T as Config>::WeightInfo::verify_sig_recover(),
+                BtcTxVerifier::RuntimeInterface => {
+                    <T as Config>::WeightInfo::verify_sig_runtime_interface()
+                }
+                #[cfg(any(feature = "runtime-benchmarks", test))]
+                BtcTxVerifier::Test => 0,
+            }
+        }
+
         pub fn verify_btc_address(data: &[u8]) -> Result<Address, DispatchError> {
             let result = Self::verify_bs58_address(data);
             if result.is_ok() {
@@ -649,7 +1309,126 @@ pub mod pallet {
             deserialize(Reader::new(input)).map_err(|_| Error::<T>::DeserializeErr)
         }
 
-        pub(crate) fn apply_push_header(header: BtcHeader) -> DispatchResult {
+        /// Return the unspent outputs of the trustee hot and cold addresses.
+        pub fn trustee_utxos() -> Vec<(H256, u32, BtcTrusteeUtxo)> {
+            TrusteeUtxos::<T>::iter().collect()
+        }
+
+        /// Return the BTC held by trustee addresses against the X-BTC issued, pending deposits
+        /// and withdrawals in flight.
+        pub fn proof_of_reserves() -> BtcProofOfReserves<BalanceOf<T>> {
+            let id: AssetId = <Self as ChainT<_>>::ASSET_ID;
+            let total_issuance = AssetType::iter().fold(BalanceOf::<T>::default(), |acc, ty| {
+                acc.saturating_add(xpallet_assets::Pallet::<T>::total_asset_balance_of(
+                    &id, *ty,
+                ))
+            });
+            let trustee_balance = TrusteeUtxos::<T>::iter_values()
+                .map(|utxo| utxo.value)
+                .fold(0u64, |acc, value| acc.saturating_add(value));
+            let pending_deposits = PendingDeposits::<T>::iter_values()
+                .chain(ExpiredDeposits::<T>::iter_values())
+                .flatten()
+                .map(|record| record.balance)
+                .fold(0u64, |acc, value| acc.saturating_add(value));
+            let pending_withdrawals = xpallet_gateway_records::Pallet::<T>::withdrawal_list()
+                .values()
+                .filter(|withdrawal| withdrawal.asset_id == id)
+                .fold(BalanceOf::<T>::default(), |acc, withdrawal| {
+                    acc.saturating_add(withdrawal.balance)
+                });
+
+            let trustee_utxos_initialized = Self::trustee_utxos_initialized();
+            // the reserves are unknown until the trustee utxo set is initialized
+            let (surplus, deficit) = if trustee_utxos_initialized {
+                let reserves: BalanceOf<T> = trustee_balance.saturated_into();
+                let liabilities = total_issuance.saturating_add(pending_deposits.saturated_into());
+                (
+                    reserves.saturating_sub(liabilities),
+                    liabilities.saturating_sub(reserves),
+                )
+            } else {
+                Default::default()
+            };
+            BtcProofOfReserves {
+                total_issuance,
+                trustee_balance,
+                pending_deposits,
+                pending_withdrawals,
+                trustee_utxos_initialized,
+                surplus,
+                deficit,
+            }
+        }
+
+        /// Return the header on the main chain at the height, `None` if the header is not relayed
+        /// yet or has been pruned.
+        pub fn main_chain_header(height: u32) -> Option<BtcHeaderInfo> {
+            Self::block_hash_for(height)
+                .into_iter()
+                .find(|hash| Self::main_chain(hash))
+                .and_then(Self::headers)
+        }
+
+        /// Return whether the tx has been processed successfully, a failed tx could be pushed
+        /// again.
+        pub fn is_tx_processed(txid: H256) -> bool {
+            Self::tx_state(txid).map_or(false, |state| state.result == BtcTxResult::Success)
+        }
+
+        /// Return the pending deposits of the address, or of all the addresses if `None`.
+        pub fn pending_deposit_list(
+            addr: Option<BtcAddress>,
+        ) -> Vec<(BtcAddress, Vec<BtcDepositCache>)> {
+            match addr {
+                Some(addr) => {
+                    // the pending deposits are keyed by the normalized address
+                    let addr = Self::verify_btc_address(&addr)
+                        .map(|address| address.to_string().into_bytes())
+                        .unwrap_or(addr);
+                    let deposits = Self::pending_deposits(&addr);
+                    if deposits.is_empty() {
+                        vec![]
+                    } else {
+                        vec![(addr, deposits)]
+                    }
+                }
+                None => PendingDeposits::<T>::iter().collect(),
+            }
+        }
+
+        /// Return the withdrawal proposals in flight.
+        pub fn withdrawal_proposal_list() -> Vec<(u32, BtcWithdrawalProposal<T::AccountId>)> {
+            let mut proposals = WithdrawalProposals::<T>::iter().collect::<Vec<_>>();
+            proposals.sort_by_key(|(id, _)| *id);
+            proposals
+        }
+
+        /// Return the hot and cold addresses of the current trustees, followed by the ones of the
+        /// last trustees if any.
+        pub fn trustee_address_pairs() -> Vec<(BtcAddress, BtcAddress)> {
+            let mut pairs = Vec::new();
+            if let Ok(current) = T::TrusteeSessionProvider::current_trustee_session() {
+                pairs.push((current.hot_address.addr, current.cold_address.addr));
+                if let Ok(last) = T::TrusteeSessionProvider::last_trustee_session() {
+                    pairs.push((last.hot_address.addr, last.cold_address.addr));
+                }
+            }
+            pairs
+        }
+
+        /// Check the btc address is valid for the current network.
+        pub fn verify_network_address(data: &[u8]) -> Result<Address, DispatchError> {
+            let address = Self::verify_btc_address(data)?;
+            ensure!(
+                address.network == Network::from(Self::network_id()),
+                Error::<T>::InvalidAddress
+            );
+            Ok(address)
+        }
+
+        /// Check the header and insert it into storage, do not touch the best and confirmed index.
+        fn check_and_insert_header(header: BtcHeader) -> Result<BtcHeaderInfo, DispatchError> {
             // current should not exist
             if Self::headers(&header.hash()).is_some() {
                 log!(
@@ -678,24 +1457,30 @@ pub mod pallet {
             let header_verifier = header::HeaderVerifier::new::<T>(&header_info);
             header_verifier.check::<T>()?;
 
-            with_transaction_result(|| {
-                // insert into storage
-                let hash = header_info.header.hash();
-                // insert valid header into storage
-                Headers::<T>::insert(&hash, header_info.clone());
-                // storage height => block list (contains forked header hash)
-                BlockHashFor::<T>::mutate(header_info.height, |v| {
-                    if !v.contains(&hash) {
-                        v.push(hash);
-                    }
-                });
+            // insert into storage
+            let hash = header_info.header.hash();
+            // insert valid header into storage
+            Headers::<T>::insert(&hash, header_info.clone());
+            // storage height => block list (contains forked header hash)
+            BlockHashFor::<T>::mutate(header_info.height, |v| {
+                if !v.contains(&hash) {
+                    v.push(hash);
+                }
+            });
 
-                log!(debug,
+            log!(debug,
                 "[apply_push_header] Verify successfully, insert header to storage [height:{}, hash:{:?}, all hashes of the height:{:?}]",
                 header_info.height,
                 hash,
                 Self::block_hash_for(header_info.height)
             );
+            Ok(header_info)
+        }
+
+        pub(crate) fn apply_push_header(header: BtcHeader) -> DispatchResult {
+            with_transaction_result(|| {
+                let header_info = Self::check_and_insert_header(header)?;
+                let hash = header_info.header.hash();
 
                 let best_index = Self::best_index();
 
@@ -731,9 +1516,62 @@ pub mod pallet {
             })
         }
 
+        /// Apply a batch of contiguous headers atomically, the best index and confirmed index
+        /// are only updated once by the last header of the batch.
+        pub(crate) fn apply_push_headers(headers: Vec<BtcHeader>) -> DispatchResult {
+            for pair in headers.windows(2) {
+                if pair[1].previous_header_hash != pair[0].hash() {
+                    log!(
+                        error,
+                        "[apply_push_headers] Header ({:?}) is not the child of header ({:?})",
+                        pair[1].hash(),
+                        pair[0].hash()
+                    );
+                    return Err(Error::<T>::NonContiguousHeaders.into());
+                }
+            }
+
+            with_transaction_result(|| {
+                let best_index = Self::best_index();
+                let mut last_info = None;
+                for header in headers {
+                    let header_info = Self::check_and_insert_header(header)?;
+                    if header_info.height <= best_index.height {
+                        // forked chain, same check as a single forked header
+                        header::check_confirmed_header::<T>(&header_info)?;
+                    }
+                    Self::deposit_event(Event::<T>::HeaderInserted(header_info.header.hash()));
+                    last_info = Some(header_info);
+                }
+
+                let last_info = match last_info {
+                    Some(info) => info,
+                    None => return Ok(()),
+                };
+                if last_info.height > best_index.height {
+                    // all the headers higher than the previous best must be on the look back path
+                    let extra = last_info.height - best_index.height - 1;
+                    let confirmed_index =
+                        header::update_confirmed_header_batch::<T>(&last_info, extra);
+                    let new_best_index = BtcHeaderIndex {
+                        hash: last_info.header.hash(),
+                        height: last_info.height,
+                    };
+                    log!(
+                        info,
+                        "[apply_push_headers] Update new best:{:?}, confirm:{:?}",
+                        new_best_index,
+                        confirmed_index
+                    );
+                    BestIndex::<T>::put(new_best_index);
+                }
+                Ok(())
+            })
+        }
+
         pub(crate) fn apply_push_transaction(
             tx: BtcRelayedTx,
-            prev_tx: Option<Transaction>,
+            prev_txs: Vec<Transaction>,
         ) -> DispatchResult {
             let tx_hash = tx.raw.hash();
             let block_hash = tx.block_hash;
@@ -747,7 +1585,7 @@ pub mod pallet {
             })?;
             let merkle_root = header_info.header.merkle_root_hash;
             // verify, check merkle proof
-            tx::validate_transaction::<T>(&tx, merkle_root, prev_tx.as_ref())?;
+            tx::validate_transaction::<T>(&tx, merkle_root, &prev_txs)?;
 
             // ensure the tx should belong to the main chain, means should submit main chain tx,
             // e.g. a tx may be packed in main chain block, and forked chain block, only submit main chain tx
@@ -767,7 +1605,9 @@ pub mod pallet {
             match Self::tx_state(&tx_hash) {
                 None => { /* do nothing */ }
                 Some(state) => {
-                    if state.result == BtcTxResult::Success {
+                    if state.result == BtcTxResult::Success
+                        && !ReorgedTrusteeTxs::<T>::contains_key(&tx_hash)
+                    {
                         log!(error,
                         "[apply_push_transaction] Reject processed tx (hash:{:?}, type:{:?}, result:{:?})",
                         tx_hash, state.tx_type, state.result
@@ -777,24 +1617,214 @@ pub mod pallet {
                 }
             }
 
-            let network = Pallet::<T>::network_id();
+            let network = Network::from(Pallet::<T>::network_id());
             let min_deposit = Pallet::<T>::btc_min_deposit();
             let current_trustee_pair = get_current_trustee_address_pair::<T>()?;
             let last_trustee_pair = get_last_trustee_address_pair::<T>().ok();
+
+            if let Some(deposit) = Self::reorged_deposits(&tx_hash) {
+                // the reorged deposit is included by the new main chain again, release the
+                // frozen balance instead of crediting it twice
+                tx::restore_reorged_deposit::<T>(tx_hash, deposit)?;
+                ReorgedDeposits::<T>::remove(&tx_hash);
+                tx::apply_trustee_utxos::<T>(
+                    &tx.raw,
+                    network,
+                    current_trustee_pair,
+                    last_trustee_pair,
+                );
+                let state = BtcTxState {
+                    tx_type: BtcTxType::Deposit,
+                    result: BtcTxResult::Success,
+                };
+                TxState::<T>::insert(&tx_hash, state);
+                BlockTxs::<T>::append(&block_hash, tx_hash);
+                Self::deposit_event(Event::<T>::TxProcessed(tx_hash, block_hash, state));
+                return Ok(());
+            }
+            if let Some(reorged_block) = ReorgedTrusteeTxs::<T>::take(&tx_hash) {
+                // the reorged trustee tx is included by the new main chain again, it has been
+                // processed, so only the trustee utxo updates of it are applied again
+                log!(
+                    info,
+                    "[apply_push_transaction] Reorged trustee tx ({:?}) of block ({:?}) is included again",
+                    tx_hash,
+                    reorged_block
+                );
+                tx::apply_trustee_utxos::<T>(
+                    &tx.raw,
+                    network,
+                    current_trustee_pair,
+                    last_trustee_pair,
+                );
+                BlockTxs::<T>::append(&block_hash, tx_hash);
+                if let Some(state) = Self::tx_state(&tx_hash) {
+                    Self::deposit_event(Event::<T>::TxProcessed(tx_hash, block_hash, state));
+                }
+                return Ok(());
+            }
+
             let state = tx::process_tx::<T>(
                 tx.raw,
-                prev_tx,
+                prev_txs,
+                height,
                 network,
                 min_deposit,
                 current_trustee_pair,
                 last_trustee_pair,
             );
             TxState::<T>::insert(&tx_hash, state);
+            // a failed tx may update the trustee utxo set as well, which is reverted on reorg
+            if (state.result == BtcTxResult::Success || tx::updates_trustee_utxos::<T>(&tx_hash))
+                && !Self::block_txs(&block_hash).contains(&tx_hash)
+            {
+                BlockTxs::<T>::append(&block_hash, tx_hash);
+            }
             Self::deposit_event(Event::<T>::TxProcessed(tx_hash, block_hash, state));
             match state.result {
                 BtcTxResult::Success => Ok(()),
                 BtcTxResult::Failure => Err(Error::<T>::ProcessTxFailed.into()),
             }
         }
+
+        pub(crate) fn apply_report_unauthorized_spend(
+            tx: BtcRelayedTx,
+            prev_txs: Vec<Transaction>,
+        ) -> DispatchResult {
+            let tx_hash = tx.raw.hash();
+            ensure!(!Self::reported_spend(&tx_hash), Error::<T>::DuplicateReport);
+            let header_info = Pallet::<T>::headers(&tx.block_hash)
+                .ok_or("Tx's block header must already exist")?;
+            // verify, check merkle proof and that every prev tx is spent by an input
+            tx::validate_transaction::<T>(&tx, header_info.header.merkle_root_hash, &prev_txs)?;
+            ensure!(Self::main_chain(&tx.block_hash), Error::<T>::UnconfirmedTx);
+            let confirmed = Self::confirmed_index().ok_or(Error::<T>::UnconfirmedTx)?;
+            ensure!(
+                header_info.height <= confirmed.height,
+                Error::<T>::UnconfirmedTx
+            );
+
+            Self::slash_unauthorized_spend(&tx.raw, &prev_txs)
+        }
+
+        /// Slash the trustees who signed the inputs of `tx` spending the trustee addresses, unless
+        /// the spend is authorized.
+        pub(crate) fn slash_unauthorized_spend(
+            tx: &Transaction,
+            prev_txs: &[Transaction],
+        ) -> DispatchResult {
+            let tx_hash = tx.hash();
+            let current_trustee_pair = get_current_trustee_address_pair::<T>().ok();
+            let sessions = [
+                (
+                    current_trustee_pair.clone(),
+                    T::TrusteeSessionProvider::current_trustee_session().ok(),
+                ),
+                (
+                    get_last_trustee_address_pair::<T>().ok(),
+                    T::TrusteeSessionProvider::last_trustee_session().ok(),
+                ),
+            ];
+
+            // the signers of every input spending the address of any session
+            let network = Network::from(Pallet::<T>::network_id());
+            let mut spent_addrs = Vec::new();
+            let mut trustees = Vec::new();
+            for (index, input) in tx.inputs.iter().enumerate() {
+                let spent = &input.previous_output;
+                let spent_addr = match prev_txs
+                    .iter()
+                    .find(|prev_tx| prev_tx.hash() == spent.txid)
+                    .and_then(|prev_tx| prev_tx.outputs.get(spent.index as usize))
+                    .and_then(|output| extract_output_addr(output, network))
+                {
+                    Some(addr) => addr,
+                    None => continue,
+                };
+                for (pair, session) in sessions.iter() {
+                    if let (Some((hot, cold)), Some(session)) = (pair, session) {
+                        let addr_info = if hot.hash == spent_addr.hash {
+                            &session.hot_address
+                        } else if cold.hash == spent_addr.hash {
+                            &session.cold_address
+                        } else {
+                            continue;
+                        };
+                        let signers =
+                            trustee::input_signers::<T>(tx, index, &spent_addr, addr_info, session);
+                        log!(
+                            debug,
+                            "[slash_unauthorized_spend] Input {} of {:?} is signed by {:?}",
+                            index,
+                            tx_hash,
+                            signers
+                        );
+                        for signer in signers {
+                            if !trustees.contains(&signer) {
+                                trustees.push(signer);
+                            }
+                        }
+                        spent_addrs.push(spent_addr.clone());
+                    }
+                }
+            }
+            ensure!(!spent_addrs.is_empty(), Error::<T>::NotTrusteeSpend);
+            ensure!(!trustees.is_empty(), Error::<T>::UnknownSpendSigners);
+
+            ensure!(
+                !Self::is_authorized_spend(tx, current_trustee_pair),
+                Error::<T>::AuthorizedSpend
+            );
+
+            ReportedSpends::<T>::insert(&tx_hash, true);
+            for trustee in trustees.iter() {
+                T::TrusteeSessionProvider::report_offence(
+                    trustee,
+                    TrusteeOffence::UnauthorizedSpend,
+                );
+            }
+            log!(
+                warn,
+                "[slash_unauthorized_spend] Unauthorized spend (hash:{:?}) of trustee addresses:{:?}",
+                tx_hash,
+                spent_addrs
+            );
+            Self::deposit_event(Event::<T>::UnauthorizedSpendReported(tx_hash, trustees));
+            Ok(())
+        }
+
+        /// A spend is authorized if it is the tx of a withdrawal proposal (or its fee bump),
+        /// a processed withdrawal/trustee transition, or it moves all the funds to current trustees.
+        fn is_authorized_spend(
+            tx: &Transaction,
+            current_trustee_pair: Option<(Address, Address)>,
+        ) -> bool {
+            let tx_hash = tx.hash();
+            if let Some(state) = Self::tx_state(&tx_hash) {
+                if state.result == BtcTxResult::Success
+                    && matches!(
+                        state.tx_type,
+                        BtcTxType::Withdrawal | BtcTxType::TrusteeTransition
+                    )
+                {
+                    return true;
+                }
+            }
+            if WithdrawalProposals::<T>::iter_values()
+                .chain(FeeBumpProposals::<T>::iter_values())
+                .any(|proposal| proposal.tx.hash() == tx_hash)
+            {
+                return true;
+            }
+            let network = Network::from(Pallet::<T>::network_id());
+            match current_trustee_pair {
+                Some((hot, cold)) => tx.outputs.iter().all(|output| {
+                    extract_output_addr(output, network)
+                        .map(|addr| addr.hash == hot.hash || addr.hash == cold.hash)
+                        .unwrap_or(false)
+                }),
+                None => false,
+            }
+        }
     }
 }
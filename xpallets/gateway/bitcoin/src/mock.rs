@@ -5,9 +5,15 @@ use std::{cell::RefCell, collections::BTreeMap, time::Duration};
 
 use hex_literal::hex;
 
+use codec::Encode;
 #[cfg(feature = "std")]
 use frame_support::traits::GenesisBuild;
-use frame_support::{parameter_types, sp_io, traits::UnixTime, weights::Weight};
+use frame_support::{
+    dispatch::{DispatchError, DispatchResult},
+    parameter_types, sp_io,
+    traits::UnixTime,
+    weights::Weight,
+};
 use frame_system::EnsureSignedBy;
 use sp_core::H256;
 use sp_keyring::sr25519;
@@ -19,6 +25,7 @@ use sp_runtime::{
 
 use chainx_primitives::AssetId;
 use xp_assets_registrar::Chain;
+use xp_gateway_bitcoin::DepositAction;
 pub use xp_protocol::{X_BTC, X_ETH};
 use xpallet_assets::AssetRestrictions;
 use xpallet_assets_registrar::AssetInfo;
@@ -26,15 +33,14 @@ use xpallet_gateway_common::types::TrusteeInfoConfig;
 
 use light_bitcoin::{
     chain::BlockHeader as BtcHeader,
-    keys::Network as BtcNetwork,
     primitives::{h256_rev, Compact},
     serialization::{self, Reader},
 };
 
 use crate::{
     self as xpallet_gateway_bitcoin,
-    types::{BtcParams, BtcTxVerifier},
-    Config, Error,
+    types::{BtcNetwork, BtcParams, BtcTxVerifier},
+    Config, Error, OnBtcDeposited,
 };
 
 /// The AccountId alias in this test module.
@@ -134,17 +140,33 @@ impl xpallet_assets::Config for Test {
     type WeightInfo = ();
 }
 
+parameter_types! {
+    pub const MaxArchivedWithdrawals: u32 = 100;
+}
+
 impl xpallet_gateway_records::Config for Test {
     type Event = ();
+    type MaxArchivedWithdrawals = MaxArchivedWithdrawals;
     type WeightInfo = ();
 }
 
+parameter_types! {
+    pub static TrusteeBond: Balance = 0;
+    pub const MaxRejectedValidProposals: u32 = 1;
+    pub const RejectionSlash: Perbill = Perbill::from_percent(10);
+    pub const MaxQueuedWithdrawalReleases: u32 = 2;
+}
+
 impl xpallet_gateway_common::Config for Test {
     type Event = ();
     type Validator = ();
     type DetermineMultisigAddress = ();
     type Bitcoin = XGatewayBitcoin;
     type BitcoinTrustee = XGatewayBitcoin;
+    type TrusteeBond = TrusteeBond;
+    type MaxRejectedValidProposals = MaxRejectedValidProposals;
+    type RejectionSlash = RejectionSlash;
+    type MaxQueuedWithdrawalReleases = MaxQueuedWithdrawalReleases;
     type WeightInfo = ();
 }
 
@@ -167,6 +189,40 @@ impl UnixTime for Timestamp {
     }
 }
 
+/// The storage key recording the last deposit action performed by `MockDepositHandler`.
+pub const DEPOSIT_ACTION_KEY: &[u8] = b":mock:deposit_action";
+
+pub struct MockDepositHandler;
+impl OnBtcDeposited<AccountId, Balance> for MockDepositHandler {
+    fn weight(action: &DepositAction) -> Weight {
+        match action {
+            // only the trading pair 0 exists in the mock
+            DepositAction::BuyPcx { pair_id: 0, .. } => 10,
+            DepositAction::BuyPcx { .. } => 100,
+        }
+    }
+
+    fn on_deposited(
+        _txid: light_bitcoin::primitives::H256,
+        _who: &AccountId,
+        value: Balance,
+        action: &DepositAction,
+    ) -> DispatchResult {
+        sp_io::storage::set(DEPOSIT_ACTION_KEY, &action.encode());
+        match *action {
+            // 1 X-BTC per PCX, the purchase could only be paid by the deposited X-BTC
+            DepositAction::BuyPcx { amount, .. } if amount > value => {
+                Err(DispatchError::Other("InsufficientDeposit"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+parameter_types! {
+    pub const MaxDepositActionWeight: Weight = 50;
+}
+
 impl Config for Test {
     type Event = ();
     type UnixTime = Timestamp;
@@ -179,6 +235,8 @@ impl Config for Test {
     >;
     type ReferralBinding = XGatewayCommon;
     type AddressBinding = XGatewayCommon;
+    type OnBtcDeposited = MockDepositHandler;
+    type MaxDepositActionWeight = MaxDepositActionWeight;
     type WeightInfo = ();
 }
 
@@ -397,7 +455,7 @@ pub fn load_mainnet_btc_genesis_header_info() -> ((BtcHeader, u32), H256, BtcNet
             63290,
         ),
         h256_rev("0000012504d3007ab7954a6baef767e522bb0d55771acb0fa46f9f4182fd0a0e"),
-        BtcNetwork::Testnet,
+        BtcNetwork::Signet,
     )
 }
 
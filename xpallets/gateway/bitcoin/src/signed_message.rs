@@ -0,0 +1,152 @@
+// Copyright 2019-2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+//! Verify the Bitcoin signed message (BIP-137) for proving the ownership of a btc address.
+
+use sp_std::prelude::*;
+
+use light_bitcoin::{
+    crypto::{dhash160, dhash256},
+    keys::{Address, AddressTypes, Type},
+    primitives::H256,
+};
+
+const MESSAGE_MAGIC: &[u8] = b"\x18Bitcoin Signed Message:\n";
+
+/// Build the message that the depositor should sign, i.e. the hex strings (with `0x` prefix) of
+/// the genesis hash and the encoded account id, joined by `:`.
+///
+/// The genesis hash is the domain separator, so that the signature for one chain could not be
+/// replayed on another chain (e.g. testnet and mainnet) by the same account.
+pub fn account_message(genesis_hash: &[u8], account: &[u8]) -> Vec<u8> {
+    let mut message = hex_with_prefix(genesis_hash);
+    message.push(b':');
+    message.extend(hex_with_prefix(account));
+    message
+}
+
+fn hex_with_prefix(data: &[u8]) -> Vec<u8> {
+    let mut hex = vec![0u8; 2 + data.len() * 2];
+    hex[..2].copy_from_slice(b"0x");
+    // the length of output is always twice of the input
+    let _ = hex::encode_to_slice(data, &mut hex[2..]);
+    hex
+}
+
+/// Calculate the hash of the Bitcoin signed message.
+/// hash = dhash256(magic + compact_size(message.len()) + message)
+pub fn message_hash(message: &[u8]) -> H256 {
+    let mut data = MESSAGE_MAGIC.to_vec();
+    let len = message.len();
+    if len < 0xfd {
+        data.push(len as u8);
+    } else if len <= 0xffff {
+        data.push(0xfd);
+        data.extend_from_slice(&(len as u16).to_le_bytes());
+    } else {
+        data.push(0xfe);
+        data.extend_from_slice(&(len as u32).to_le_bytes());
+    }
+    data.extend_from_slice(message);
+    dhash256(&data)
+}
+
+/// Verify the BIP-137 compact signature of the `message` against the `address`.
+///
+/// The signature is `header + r + s` (65 bytes), the header indicates the type of key:
+/// - 27-30: p2pkh with uncompressed public key
+/// - 31-34: p2pkh with compressed public key
+/// - 35-38: p2sh-p2wpkh
+/// - 39-42: p2wpkh
+///
+/// Like most wallets, the header of compressed key is not strictly bound to the address type.
+/// The full BIP-322 proof (e.g. for p2wsh and p2tr) is not supported yet.
+pub fn verify_signed_message(address: &Address, message: &[u8], signature: &[u8]) -> bool {
+    if signature.len() != 65 {
+        return false;
+    }
+    let header = signature[0];
+    if !(27..=42).contains(&header) {
+        return false;
+    }
+    let mut sig = [0u8; 65];
+    sig[..64].copy_from_slice(&signature[1..]);
+    sig[64] = (header - 27) & 3;
+    let hash = message_hash(message);
+
+    if header < 31 {
+        let pubkey = match sp_io::crypto::secp256k1_ecdsa_recover(&sig, hash.as_fixed_bytes()) {
+            Ok(pubkey) => pubkey,
+            Err(_) => return false,
+        };
+        let mut uncompressed = [0u8; 65];
+        uncompressed[0] = 0x04;
+        uncompressed[1..].copy_from_slice(&pubkey);
+        return address.kind == Type::P2PKH
+            && address.hash == AddressTypes::Legacy(dhash160(&uncompressed));
+    }
+
+    let pubkey =
+        match sp_io::crypto::secp256k1_ecdsa_recover_compressed(&sig, hash.as_fixed_bytes()) {
+            Ok(pubkey) => pubkey,
+            Err(_) => return false,
+        };
+    let key_hash = dhash160(&pubkey);
+    match address.kind {
+        Type::P2PKH => address.hash == AddressTypes::Legacy(key_hash),
+        Type::P2SH => {
+            // redeem script of p2sh-p2wpkh: OP_0 + OP_PUSHBYTES_20 + <20 bytes key hash>
+            let mut redeem_script = vec![0x00, 0x14];
+            redeem_script.extend_from_slice(key_hash.as_bytes());
+            address.hash == AddressTypes::Legacy(dhash160(&redeem_script))
+        }
+        Type::P2WPKH => address.hash == AddressTypes::WitnessV0KeyHash(key_hash),
+        _ => false,
+    }
+}
+
+#[test]
+fn test_account_message() {
+    assert_eq!(
+        account_message(&[0x01, 0xff], &[0x12, 0xab, 0x00]),
+        b"0x01ff:0x12ab00".to_vec()
+    );
+}
+
+#[test]
+fn test_verify_signed_message() {
+    // genesis hash of the mock chain and account id of Alice
+    let message = account_message(
+        &[0x45; 32],
+        &hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d").unwrap(),
+    );
+    let p2pkh = "mh4wHJc3SEud5GRu1Gbu2ZF6g2i5pcRBVb"
+        .parse::<Address>()
+        .unwrap();
+    let p2wpkh = "tb1qzyzfwn9xt4fzu74uguhvrtk4k3rqrlhvtkh3g8"
+        .parse::<Address>()
+        .unwrap();
+    let p2pkh_sig = hex::decode("209fe2e37dfc1ff07b0f8ce0f820a74b980cc2d2eee6072a9d4e4dbf53b6624a5750e747e743619063229dcffbfd22c29e0c395e2c5ac451f36de3c5bee4a7583b").unwrap();
+    let p2wpkh_sig = hex::decode("289fe2e37dfc1ff07b0f8ce0f820a74b980cc2d2eee6072a9d4e4dbf53b6624a5750e747e743619063229dcffbfd22c29e0c395e2c5ac451f36de3c5bee4a7583b").unwrap();
+
+    assert!(verify_signed_message(&p2pkh, &message, &p2pkh_sig));
+    assert!(verify_signed_message(&p2wpkh, &message, &p2wpkh_sig));
+    // compressed key header is not bound to the address type
+    assert!(verify_signed_message(&p2wpkh, &message, &p2pkh_sig));
+    // wrong message
+    assert!(!verify_signed_message(&p2pkh, b"0x00", &p2pkh_sig));
+    // the signature is not for other chains
+    let other_chain = account_message(
+        &[0x46; 32],
+        &hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d").unwrap(),
+    );
+    assert!(!verify_signed_message(&p2pkh, &other_chain, &p2pkh_sig));
+    // uncompressed key header
+    let mut sig = p2pkh_sig.clone();
+    sig[0] = 27;
+    assert!(!verify_signed_message(&p2pkh, &message, &sig));
+    // invalid header
+    sig[0] = 43;
+    assert!(!verify_signed_message(&p2pkh, &message, &sig));
+    // invalid length
+    assert!(!verify_signed_message(&p2pkh, &message, &p2pkh_sig[..64]));
+}
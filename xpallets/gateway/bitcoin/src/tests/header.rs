@@ -1,18 +1,19 @@
 // Copyright 2019-2020 ChainX Project Authors. Licensed under GPL-3.0.
 
-use frame_support::{assert_noop, assert_ok};
+use frame_support::{assert_noop, assert_ok, traits::Hooks, weights::Weight};
 
 use light_bitcoin::{
     chain::{h256, BlockHeader},
-    keys::Network,
+    primitives::{h256_rev, hash_rev, Compact, U256},
     serialization,
 };
 
 use crate::mock::{
-    generate_blocks_478557_478563, generate_blocks_63290_63310, ExtBuilder, XGatewayBitcoin,
+    generate_blocks_478557_478563, generate_blocks_63290_63310, ExtBuilder, Test, XGatewayBitcoin,
     XGatewayBitcoinErr,
 };
-use crate::types::BtcHeaderIndex;
+use crate::types::{BtcHeaderIndex, BtcNetwork, BtcParams};
+use crate::{HeaderRetentionDepth, ParamsInfo};
 
 #[test]
 fn test_genesis() {
@@ -39,7 +40,7 @@ fn test_genesis() {
 fn test_insert_headers() {
     let (base_height, c1, _) = generate_blocks_478557_478563();
     ExtBuilder::default()
-        .build_mock((*c1.get(0).unwrap(), base_height), Network::Mainnet)
+        .build_mock((*c1.get(0).unwrap(), base_height), BtcNetwork::Mainnet)
         .execute_with(|| {
             assert_noop!(
                 XGatewayBitcoin::apply_push_header(*c1.get(0).unwrap()),
@@ -73,7 +74,7 @@ fn test_insert_forked_headers_from_genesis_height() {
     // |---- b --- b
     let (base_height, c1, forked) = generate_blocks_478557_478563();
     ExtBuilder::default()
-        .build_mock((*c1.get(1).unwrap(), base_height + 1), Network::Mainnet)
+        .build_mock((*c1.get(1).unwrap(), base_height + 1), BtcNetwork::Mainnet)
         .execute_with(|| {
             // note: confirm block is 4
             assert_noop!(
@@ -148,7 +149,7 @@ fn test_insert_forked_headers() {
     //       |---- b --- b
     let (base_height, c1, forked) = generate_blocks_478557_478563();
     ExtBuilder::default()
-        .build_mock((*c1.get(0).unwrap(), base_height), Network::Mainnet)
+        .build_mock((*c1.get(0).unwrap(), base_height), BtcNetwork::Mainnet)
         .execute_with(|| {
             assert_ok!(XGatewayBitcoin::apply_push_header(*c1.get(1).unwrap()));
             assert_ok!(XGatewayBitcoin::apply_push_header(*c1.get(2).unwrap()));
@@ -189,6 +190,127 @@ fn test_insert_forked_headers() {
         });
 }
 
+#[test]
+fn test_main_chain_header() {
+    let (base_height, c1, forked) = generate_blocks_478557_478563();
+    ExtBuilder::default()
+        .build_mock((*c1.get(0).unwrap(), base_height), BtcNetwork::Mainnet)
+        .execute_with(|| {
+            let main_chain_hash =
+                |height| XGatewayBitcoin::main_chain_header(height).map(|info| info.header.hash());
+            for header in &c1[1..4] {
+                assert_ok!(XGatewayBitcoin::apply_push_header(*header));
+            }
+            assert_ok!(XGatewayBitcoin::apply_push_header(*forked.get(2).unwrap()));
+            assert_ok!(XGatewayBitcoin::apply_push_header(*forked.get(3).unwrap()));
+            assert_eq!(main_chain_hash(base_height + 2), Some(c1[2].hash()));
+            assert_eq!(main_chain_hash(base_height + 4), None);
+
+            // switch to the forked chain
+            assert_ok!(XGatewayBitcoin::apply_push_header(*forked.get(4).unwrap()));
+            assert_eq!(main_chain_hash(base_height + 1), Some(c1[1].hash()));
+            assert_eq!(main_chain_hash(base_height + 2), Some(forked[2].hash()));
+            assert_eq!(main_chain_hash(base_height + 4), Some(forked[4].hash()));
+        });
+}
+
+#[test]
+fn test_verify_network_address() {
+    let (base_height, c1, _) = generate_blocks_478557_478563();
+    ExtBuilder::default()
+        .build_mock((*c1.get(0).unwrap(), base_height), BtcNetwork::Mainnet)
+        .execute_with(|| {
+            assert_ok!(XGatewayBitcoin::verify_network_address(
+                b"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"
+            ));
+            assert_noop!(
+                XGatewayBitcoin::verify_network_address(b"mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn"),
+                XGatewayBitcoinErr::InvalidAddress
+            );
+            assert!(XGatewayBitcoin::verify_network_address(b"invalid").is_err());
+        });
+}
+
+#[test]
+fn test_push_headers_batch() {
+    let (base_height, c1, _) = generate_blocks_478557_478563();
+    ExtBuilder::default()
+        .build_mock((*c1.get(0).unwrap(), base_height), BtcNetwork::Mainnet)
+        .execute_with(|| {
+            assert_noop!(
+                XGatewayBitcoin::apply_push_headers(vec![c1[1], c1[3]]),
+                XGatewayBitcoinErr::NonContiguousHeaders
+            );
+
+            assert_ok!(XGatewayBitcoin::apply_push_headers(c1[1..6].to_vec()));
+            let best_index = XGatewayBitcoin::best_index();
+            assert_eq!(best_index.hash, c1.get(5).unwrap().hash());
+            let confirmed_index = XGatewayBitcoin::confirmed_index().unwrap();
+            assert_eq!(confirmed_index.hash, c1.get(2).unwrap().hash());
+            should_in_mainchain(&c1, true);
+
+            // the whole batch is rejected if the first header exists
+            assert_noop!(
+                XGatewayBitcoin::apply_push_headers(c1[5..].to_vec()),
+                XGatewayBitcoinErr::ExistingHeader
+            );
+        })
+}
+
+#[test]
+fn test_push_forked_headers_batch() {
+    // e.g.
+    // b0
+    // b --- b --- b --- b --- b
+    //       |---- b --- b --- b
+    let (base_height, c1, forked) = generate_blocks_478557_478563();
+    ExtBuilder::default()
+        .build_mock((*c1.get(0).unwrap(), base_height), BtcNetwork::Mainnet)
+        .execute_with(|| {
+            assert_ok!(XGatewayBitcoin::apply_push_headers(c1[1..4].to_vec()));
+            let confirmed_index = XGatewayBitcoin::confirmed_index().unwrap();
+            assert_eq!(confirmed_index.hash, c1.get(0).unwrap().hash());
+
+            // the forked batch overtakes the normal chain
+            assert_ok!(XGatewayBitcoin::apply_push_headers(forked[2..5].to_vec()));
+            let best_index = XGatewayBitcoin::best_index();
+            assert_eq!(best_index.hash, forked.get(4).unwrap().hash());
+            let confirmed_index = XGatewayBitcoin::confirmed_index().unwrap();
+            assert_eq!(confirmed_index.hash, c1.get(1).unwrap().hash());
+            should_in_mainchain(&c1[2..4], false);
+            should_in_mainchain(&forked[1..5], true);
+
+            // switch back to the normal chain
+            assert_ok!(XGatewayBitcoin::apply_push_headers(c1[4..6].to_vec()));
+            let best_index = XGatewayBitcoin::best_index();
+            assert_eq!(best_index.hash, c1.get(5).unwrap().hash());
+            let confirmed_index = XGatewayBitcoin::confirmed_index().unwrap();
+            assert_eq!(confirmed_index.hash, c1.get(2).unwrap().hash());
+            should_in_mainchain(&c1[0..6], true);
+            should_in_mainchain(&forked[2..5], false);
+        });
+}
+
+#[test]
+fn test_push_headers_batch_is_atomic() {
+    let (base_height, c1, forked) = generate_blocks_478557_478563();
+    ExtBuilder::default()
+        .build_mock((*c1.get(1).unwrap(), base_height + 1), BtcNetwork::Mainnet)
+        .execute_with(|| {
+            assert_ok!(XGatewayBitcoin::apply_push_headers(c1[2..6].to_vec()));
+            let confirmed_index = XGatewayBitcoin::confirmed_index().unwrap();
+            assert_eq!(confirmed_index.hash, c1.get(2).unwrap().hash());
+
+            // forked 2 and 3 are valid, but forked 5 would move confirmed to an ancient fork,
+            // so that nothing in this batch is inserted.
+            assert_noop!(
+                XGatewayBitcoin::apply_push_headers(forked[2..6].to_vec()),
+                XGatewayBitcoinErr::AncientFork,
+            );
+            assert!(XGatewayBitcoin::headers(&forked.get(2).unwrap().hash()).is_none());
+        })
+}
+
 #[test]
 fn test_change_difficulty() {
     ExtBuilder::default().build_and_execute(|| {
@@ -206,6 +328,116 @@ fn test_change_difficulty() {
     })
 }
 
+#[test]
+fn test_prune_headers() {
+    ExtBuilder::default().build_and_execute(|| {
+        let headers = generate_blocks_63290_63310();
+        for i in 63291..63310 {
+            assert_ok!(XGatewayBitcoin::apply_push_header(headers[&i]));
+        }
+        assert_eq!(XGatewayBitcoin::confirmed_index().unwrap().height, 63306);
+        // retarget every 4 blocks, thus 63292 is a retarget boundary
+        ParamsInfo::<Test>::put(BtcParams::new(
+            545259519,
+            2 * 60 * 60,
+            4 * 10 * 60,
+            10 * 60,
+            4,
+        ));
+
+        // never prune by default
+        XGatewayBitcoin::on_idle(1, Weight::max_value());
+        assert!(XGatewayBitcoin::headers(&headers[&63290].hash()).is_some());
+        assert_eq!(XGatewayBitcoin::retarget_checkpoint(), None);
+
+        // the depth must keep a retarget interval plus the confirmation number of headers
+        assert_noop!(
+            XGatewayBitcoin::set_header_retention_depth(frame_system::RawOrigin::Root.into(), 7),
+            XGatewayBitcoinErr::InvalidRetentionDepth
+        );
+        let origin = frame_system::RawOrigin::Root.into();
+        assert_ok!(XGatewayBitcoin::set_header_retention_depth(origin, 10));
+        XGatewayBitcoin::on_idle(1, Weight::max_value());
+        assert_eq!(XGatewayBitcoin::next_prune_height(), 63296);
+        // the genesis header and the checkpoint header are kept
+        for i in [63290, 63292, 63296, 63297, 63298, 63299, 63300].iter() {
+            let hash = headers[i].hash();
+            assert!(XGatewayBitcoin::headers(&hash).is_some());
+            assert!(XGatewayBitcoin::main_chain(&hash));
+            assert_eq!(XGatewayBitcoin::block_hash_for(i), vec![hash]);
+        }
+        for i in [63291, 63293, 63294, 63295].iter() {
+            let hash = headers[i].hash();
+            assert!(XGatewayBitcoin::headers(&hash).is_none());
+            assert!(!XGatewayBitcoin::main_chain(&hash));
+            assert!(XGatewayBitcoin::block_hash_for(i).is_empty());
+        }
+
+        let checkpoint = XGatewayBitcoin::retarget_checkpoint().unwrap();
+        assert_eq!(checkpoint.height, 63292);
+        assert_eq!(checkpoint.hash, headers[&63292].hash());
+        assert_eq!(checkpoint.time, headers[&63292].time);
+        assert_eq!(checkpoint.bits, headers[&63292].bits);
+
+        // go on when the confirmed header moves
+        assert_ok!(XGatewayBitcoin::apply_push_header(headers[&63310]));
+        XGatewayBitcoin::on_idle(2, Weight::max_value());
+        assert_eq!(XGatewayBitcoin::next_prune_height(), 63297);
+        assert!(XGatewayBitcoin::headers(&headers[&63296].hash()).is_none());
+    })
+}
+
+#[test]
+fn test_retarget_after_pruning() {
+    let genesis = regtest_genesis();
+    let max_bits = Compact::new(0x207fffff);
+    // the bits of the next retarget, each retarget interval is mined in 7 target spacings
+    let next_bits = |bits: Compact| -> Compact {
+        let target: U256 = bits.into();
+        (target * U256::from(7) / U256::from(8)).into()
+    };
+    ExtBuilder::default()
+        .build_mock((genesis, 0), BtcNetwork::Mainnet)
+        .execute_with(|| {
+            // retarget every 8 blocks
+            let params = BtcParams::new(0x207fffff, 2 * 60 * 60, 8 * 10 * 60, 10 * 60, 4);
+            ParamsInfo::<Test>::put(params);
+
+            let mut headers = vec![genesis];
+            let mut bits = max_bits;
+            for height in 1..16 {
+                if height % 8 == 0 {
+                    bits = next_bits(bits);
+                }
+                let header = mine_header(&headers[height - 1], bits.into(), 10 * 60);
+                assert_ok!(XGatewayBitcoin::apply_push_header(header));
+                headers.push(header);
+            }
+            assert_eq!(XGatewayBitcoin::confirmed_index().unwrap().height, 12);
+
+            HeaderRetentionDepth::<Test>::put(1);
+            XGatewayBitcoin::on_idle(1, Weight::max_value());
+            assert_eq!(XGatewayBitcoin::next_prune_height(), 11);
+            for (height, header) in headers.iter().enumerate().take(11) {
+                let kept = height == 0 || height == 8;
+                assert_eq!(XGatewayBitcoin::headers(&header.hash()).is_some(), kept);
+            }
+            let checkpoint = XGatewayBitcoin::retarget_checkpoint().unwrap();
+            assert_eq!(checkpoint.hash, headers[8].hash());
+            assert_eq!(checkpoint.bits, next_bits(max_bits));
+
+            // the retarget boundary header after pruning is still checked
+            let invalid = mine_header(&headers[15], bits.into(), 10 * 60);
+            assert_noop!(
+                XGatewayBitcoin::apply_push_header(invalid),
+                XGatewayBitcoinErr::HeaderNBitsNotMatch
+            );
+            let header = mine_header(&headers[15], next_bits(bits).into(), 10 * 60);
+            assert_ok!(XGatewayBitcoin::apply_push_header(header));
+            assert_eq!(XGatewayBitcoin::best_index().height, 16);
+        })
+}
+
 #[test]
 fn test_call() {
     ExtBuilder::default().build_and_execute(|| {
@@ -214,5 +446,156 @@ fn test_call() {
         let v = serialization::serialize(&headers[&(63290 + 1)]);
         let v = v.take();
         assert_ok!(XGatewayBitcoin::push_header(origin, v));
+
+        let origin = frame_system::RawOrigin::Signed(Default::default()).into();
+        let v = (63292..63296)
+            .map(|height| serialization::serialize(&headers[&height]).take())
+            .collect::<Vec<_>>();
+        assert_ok!(XGatewayBitcoin::push_headers(origin, v));
+        assert_eq!(XGatewayBitcoin::best_index().height, 63295);
+
+        let origin = frame_system::RawOrigin::Signed(Default::default()).into();
+        assert_noop!(
+            XGatewayBitcoin::push_headers(origin, vec![]),
+            XGatewayBitcoinErr::InvalidHeaderBatchSize
+        );
     })
 }
+
+fn regtest_genesis() -> BlockHeader {
+    BlockHeader {
+        version: 1,
+        previous_header_hash: Default::default(),
+        merkle_root_hash: h256_rev(
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+        ),
+        time: 1296688602,
+        bits: Compact::new(0x207fffff),
+        nonce: 2,
+    }
+}
+
+/// Mine a header on the parent, the difficulty must be trivial.
+fn mine_header(parent: &BlockHeader, bits: u32, spacing: u32) -> BlockHeader {
+    let target = Compact::new(bits).to_u256().unwrap();
+    let mut header = BlockHeader {
+        version: 0x20000000,
+        previous_header_hash: parent.hash(),
+        merkle_root_hash: Default::default(),
+        time: parent.time + spacing,
+        bits: Compact::new(bits),
+        nonce: 0,
+    };
+    while U256::from(hash_rev(header.hash()).as_bytes()) > target {
+        header.nonce += 1;
+    }
+    header
+}
+
+#[test]
+fn test_regtest_headers() {
+    let genesis = regtest_genesis();
+    assert_eq!(
+        genesis.hash(),
+        h256_rev("0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206")
+    );
+    // the next header is at the retarget height
+    ExtBuilder::default()
+        .build_mock((genesis, 2015), BtcNetwork::Regtest)
+        .execute_with(|| {
+            // no retargeting on regtest
+            let header = mine_header(&genesis, 0x207fffff, 10 * 60);
+            assert_ok!(XGatewayBitcoin::apply_push_header(header));
+
+            let mut invalid = mine_header(&header, 0x207fffff, 10 * 60);
+            invalid.bits = Compact::new(0x1f7fffff);
+            assert_noop!(
+                XGatewayBitcoin::apply_push_header(invalid),
+                XGatewayBitcoinErr::HeaderNBitsNotMatch
+            );
+
+            let header = mine_header(&header, 0x207fffff, 30 * 60);
+            assert_ok!(XGatewayBitcoin::apply_push_header(header));
+            assert_eq!(XGatewayBitcoin::best_index().height, 2017);
+        })
+}
+
+#[test]
+fn test_min_difficulty_blocks() {
+    let headers = generate_blocks_63290_63310();
+    for &network in &[BtcNetwork::Testnet, BtcNetwork::Regtest] {
+        ExtBuilder::default()
+            .build_mock((headers[&63290], 63290), network)
+            .execute_with(|| {
+                for height in 63291..=63300 {
+                    assert_ok!(XGatewayBitcoin::apply_push_header(headers[&height]));
+                }
+                // #63301 is mined 1311 seconds after its parent, so that the minimum difficulty
+                // is required
+                assert_noop!(
+                    XGatewayBitcoin::apply_push_header(headers[&63301]),
+                    XGatewayBitcoinErr::HeaderNBitsNotMatch
+                );
+
+                // the minimum difficulty is not allowed within 20 minutes
+                let invalid = mine_header(&headers[&63300], 0x207fffff, 20 * 60);
+                assert_noop!(
+                    XGatewayBitcoin::apply_push_header(invalid),
+                    XGatewayBitcoinErr::HeaderNBitsNotMatch
+                );
+
+                let min_difficulty = mine_header(&headers[&63300], 0x207fffff, 20 * 60 + 1);
+                assert_ok!(XGatewayBitcoin::apply_push_header(min_difficulty));
+
+                // the next block within 20 minutes falls back to the last normal difficulty
+                let invalid = mine_header(&min_difficulty, 0x207fffff, 10 * 60);
+                assert_noop!(
+                    XGatewayBitcoin::apply_push_header(invalid),
+                    XGatewayBitcoinErr::HeaderNBitsNotMatch
+                );
+                let next = mine_header(&min_difficulty, 0x207fffff, 30 * 60);
+                assert_ok!(XGatewayBitcoin::apply_push_header(next));
+                assert_eq!(XGatewayBitcoin::best_index().height, 63302);
+            });
+    }
+}
+
+#[test]
+fn test_signet_headers() {
+    let headers = generate_blocks_63290_63310();
+    ExtBuilder::default()
+        .build_mock((headers[&63290], 63290), BtcNetwork::Signet)
+        .execute_with(|| {
+            // the minimum difficulty rule is not used on signet
+            for height in 63291..=63310 {
+                assert_ok!(XGatewayBitcoin::apply_push_header(headers[&height]));
+            }
+            assert_eq!(XGatewayBitcoin::best_index().height, 63310);
+
+            let mut invalid = headers[&63301];
+            invalid.bits = Compact::new(0x207fffff);
+            assert_noop!(
+                XGatewayBitcoin::apply_push_header(invalid),
+                XGatewayBitcoinErr::HeaderNBitsNotMatch
+            );
+        })
+}
+
+#[test]
+fn test_btc_params_from_network() {
+    assert_eq!(
+        BtcParams::from_network(BtcNetwork::Mainnet).max_bits(),
+        Compact::new(0x1d00ffff)
+    );
+    assert_eq!(
+        BtcParams::from_network(BtcNetwork::Testnet).max_bits(),
+        Compact::new(0x1d00ffff)
+    );
+    assert_eq!(
+        BtcParams::from_network(BtcNetwork::Signet).max_bits(),
+        Compact::new(0x1e0377ae)
+    );
+    let regtest = BtcParams::from_network(BtcNetwork::Regtest);
+    assert_eq!(regtest.max_bits(), Compact::new(0x207fffff));
+    assert_eq!(regtest.retargeting_interval(), 2016);
+}
@@ -4,14 +4,46 @@ mod header;
 mod trustee;
 mod tx;
 
+use std::collections::BTreeMap;
+
 use sp_core::crypto::{set_default_ss58_version, Ss58AddressFormat};
 
 use xp_gateway_common::AccountExtractor;
 
-use light_bitcoin::script::Script;
+use light_bitcoin::{
+    chain::BlockHeader as BtcHeader,
+    merkle::PartialMerkleTree,
+    script::Script,
+    serialization::{self, Reader},
+};
 
 use crate::mock::{Test, XGatewayBitcoin};
-use crate::Config;
+use crate::{header::update_confirmed_header, types::BtcHeaderInfo, BlockHashFor, Config, Headers};
+
+/// The merkle proof of the deposit and withdrawal txs in the mock block #63299.
+fn block_63299_proof() -> PartialMerkleTree {
+    let raw_proof = hex::decode("0a000000050a59b195a68a29037580798ca0414941eb46eaf7607db2d0da1ff89e9570ce455fea22ec1a3e3e7e1167fa220cc8376225f07bd20aa194e7f3c4ac68c7375d8e0a35e47541de7d0aa7312dabcf3bc9f06603e832427b8e4fe9a97a309f8cd7141687d11a3fd8f21e2105a52a3c36a17ea870e326ecddb23221d4cc0398b6c44bdcce3f191919a31f4cfaca5a786cc8315db76683ad6b8008f2ed9b348df76a0d022f00").unwrap();
+    serialization::deserialize(Reader::new(&raw_proof)).unwrap()
+}
+
+/// Build a fork of #63299..#63302 on top of #63298 and confirm its tip, which reorgs the main
+/// chain block #63299 out. Return the forked headers in order.
+fn reorg_block_63299(headers: &BTreeMap<u32, BtcHeader>) -> Vec<BtcHeaderInfo> {
+    let mut prev_hash = headers[&63298].hash();
+    let mut forked = vec![];
+    for i in 63299..63303 {
+        let mut header = headers[&i];
+        header.previous_header_hash = prev_hash;
+        header.nonce = header.nonce.wrapping_add(1);
+        prev_hash = header.hash();
+        let info = BtcHeaderInfo { header, height: i };
+        Headers::<Test>::insert(&prev_hash, info.clone());
+        BlockHashFor::<Test>::append(i, prev_hash);
+        forked.push(info);
+    }
+    update_confirmed_header::<Test>(forked.last().unwrap());
+    forked
+}
 
 #[test]
 pub fn test_verify_btc_address() {
@@ -1,21 +1,42 @@
 // Copyright 2019-2020 ChainX Project Authors. Licensed under GPL-3.0.
 
-use frame_support::assert_noop;
+use frame_support::{
+    assert_noop, assert_ok,
+    dispatch::DispatchError,
+    traits::{Hooks, OnRuntimeUpgrade, StorageVersion},
+};
 use hex_literal::hex;
+use sp_runtime::AccountId32;
 
 use light_bitcoin::{
-    chain::Transaction,
+    chain::{OutPoint, Transaction, TransactionInput, TransactionOutput},
     crypto::dhash160,
     keys::{Address, AddressTypes, Network, Public, Type},
     mast::Mast,
+    primitives::H256,
     script::{Builder, Opcode},
     serialization::{self, Reader},
 };
 
-use xpallet_gateway_common::traits::TrusteeForChain;
+use xpallet_assets::Chain;
+use xpallet_gateway_common::{
+    traits::{TrusteeForChain, TrusteeSession},
+    trustees::bitcoin::BtcTrusteeSessionManager,
+    types::{TrusteeInfoConfig, TrusteeOffence, TrusteeStats},
+    DrainingSessionOf,
+};
 
-use crate::mock::{ExtBuilder, Test, XGatewayBitcoin, XGatewayBitcoinErr};
-use crate::{trustee::create_multi_address, tx::validator::parse_and_check_signed_tx_impl};
+use crate::mock::{
+    alice, bob, charlie, trustees, Balances, ExtBuilder, Test, TrusteeBond, XGatewayBitcoin,
+    XGatewayBitcoinErr, XGatewayCommon,
+};
+use crate::{
+    trustee::{create_multi_address, record_proposal_votes, record_removed_proposal_votes},
+    tx::{trustee_transition, validator::parse_and_check_signed_tx_impl},
+    types::{BtcTrusteeUtxo, BtcTxResult, BtcTxVerifier, BtcWithdrawalProposal, VoteResult},
+    FeeBumpProposals, ProposalSessions, ProposalVoteDeadlines, StalledProposals, TrusteeUtxos,
+    TrusteeUtxosInitialized, Verifier, WithdrawalProposals,
+};
 use sp_std::convert::TryInto;
 
 #[test]
@@ -159,6 +180,36 @@ fn test_verify_tx_sign() {
     });
 }
 
+#[test]
+fn test_verifiers_agree() {
+    use crate::{types::BtcTxVerifier, Verifier};
+
+    let multisig_3 = "522102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210244d81efeb4171b1a8a433b87dd202117f94e44c909c49e42e77b69b5a6ce7d0d2103a36339f413da869df12b1ab0def91749413a0dee87f0bfa85ba7196e6cdad10253ae";
+    let multisig_7 = "542102e2b2720a9e54617ba87fca287c3d7f9124154d30fa8dc9cd260b6b254e1d7aea210219fc860933a1362bc5e0a0bbe1b33a47aedf904765f4a85cd166ba1d767927ee2102b921cb319a14c6887b12cee457453f720e88808a735a578d6c57aba0c74e5af32102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210346aa7ade0b567b34182cacf9444deb44ee829e14705dc87175107dd09d5dbf4021034d3e7f87e69c6c71df6052b44f9ed99a3d811613140ebf09f8fdaf904a2e1de856ae";
+    let cases: Vec<(&str, &str, Result<u32, DispatchError>)> = vec![
+        // 2-of-3 with 3 inputs
+        ("010000000317840b38d466580696e9cb065c7a7aa55cb58cd5eb2526a10c3a30cc06d4b50a05000000fdfd0000483045022100dabbf878df8cacb23c08a8b5414cd64392a3f84777db4c01d8eec1e06d2e03fb0220502bd6e3960b68452699a40debfd92ac02e45d1526a2b570f5b28abdb496706401473044022047c58c3ad586d93f4b4caf65230a21e0ff70475b66affb8d4f92e916e6f6f664022029231b30472a949648dd99585ccbb169ccc2c007ad5387f580d41affdc8b37b6014c69522102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210244d81efeb4171b1a8a433b87dd202117f94e44c909c49e42e77b69b5a6ce7d0d2103a36339f413da869df12b1ab0def91749413a0dee87f0bfa85ba7196e6cdad10253aeffffffff853c87b1ecb4e881f323fec5314cb8623ca15de1341694e8352f99c434e7046a02000000fdfe0000483045022100b1b2233f70434f4079c1a8be1be5843b4dfe1edea30a3533aa94781af9984b2e02201ef78527ced51c7b122568666b9499d9cd2d4c3e704f5a54ebe433489c91b20101483045022100bde660b2f6f3c6fa512794377564289cbfcbeab6ecba1fe3b0b1531ebaa7d00a02207ea5435312280e0b502de715a6cbff7de866ba508a5fe8a644b88540ed471aee014c69522102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210244d81efeb4171b1a8a433b87dd202117f94e44c909c49e42e77b69b5a6ce7d0d2103a36339f413da869df12b1ab0def91749413a0dee87f0bfa85ba7196e6cdad10253aeffffffff442214a2d5a31195d6849005699892f60d48d89bca15bdb4ad6349c083e9936202000000fdfd000047304402205960c277575a7d2bb719211fe9cee0dd398c5a64d3a258fb0f877ae176dd11af02206cc0be53b1d5ea59477f9d2103ce06b61608561ac466c72235e86b26fe45734d01483045022100dcbd79d6f2d9504e2ea1578b7fdc9f98dadc018708acb4b87bd8b154312edfaa022043197a5b72219dc9603a81146a65c724a09022229ada2e3101a002dbd834b591014c69522102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210244d81efeb4171b1a8a433b87dd202117f94e44c909c49e42e77b69b5a6ce7d0d2103a36339f413da869df12b1ab0def91749413a0dee87f0bfa85ba7196e6cdad10253aeffffffff0340ebd201000000001976a9148e2fbed4fc7481a9a51f2bfe204301a122473f2f88ac406fdf25000000001976a914ede61104eddc07594f0c0cf43fecb9675353d16288ac91a3f6070000000017a914cb94110435d0635223eebe25ed2aaabc03781c458700000000", multisig_3, Ok(2)),
+        // 4-of-7 with 1 signature
+        ("0100000001abbd850cf083bbfa367081718c7efd911e56ffd849ae48e812c861adf253ef6101000000fd1b010048304502210081d668bf752424c89e208cf9789e7449c080a2cd9fda6a518ac36d81e5d760dd02206353eaad7e587602ab6665a5788a2c831e9d08bf8685dd250370dbd978a54665014ccf542102e2b2720a9e54617ba87fca287c3d7f9124154d30fa8dc9cd260b6b254e1d7aea210219fc860933a1362bc5e0a0bbe1b33a47aedf904765f4a85cd166ba1d767927ee2102b921cb319a14c6887b12cee457453f720e88808a735a578d6c57aba0c74e5af32102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210346aa7ade0b567b34182cacf9444deb44ee829e14705dc87175107dd09d5dbf4021034d3e7f87e69c6c71df6052b44f9ed99a3d811613140ebf09f8fdaf904a2e1de856aeffffffff03d2622000000000001976a914b9944df543bc909b527351311c5a01a78a3271e788acff40330e0000000017a9149079c3650e5a9799afa552cbbcc280e45d52117c8777d778e50000000017a914d246f700f4969106291a75ba85ad863cae68d6678700000000", multisig_7, Ok(1)),
+        // 4-of-7 with a tampered signature
+        ("0100000001abbd850cf083bbfa367081718c7efd911e56ffd849ae48e812c861adf253ef6101000000fd1b010048304502210081d668be752424c89e208cf9789e7449c080a2cd9fda6a518ac36d81e5d760dd02206353eaad7e587602ab6665a5788a2c831e9d08bf8685dd250370dbd978a54665014ccf542102e2b2720a9e54617ba87fca287c3d7f9124154d30fa8dc9cd260b6b254e1d7aea210219fc860933a1362bc5e0a0bbe1b33a47aedf904765f4a85cd166ba1d767927ee2102b921cb319a14c6887b12cee457453f720e88808a735a578d6c57aba0c74e5af32102df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6210346aa7ade0b567b34182cacf9444deb44ee829e14705dc87175107dd09d5dbf4021034d3e7f87e69c6c71df6052b44f9ed99a3d811613140ebf09f8fdaf904a2e1de856aeffffffff03d2622000000000001976a914b9944df543bc909b527351311c5a01a78a3271e788acff40330e0000000017a9149079c3650e5a9799afa552cbbcc280e45d52117c8777d778e50000000017a914d246f700f4969106291a75ba85ad863cae68d6678700000000", multisig_7, Err(XGatewayBitcoinErr::VerifySignFailed.into())),
+    ];
+    ExtBuilder::default().build_and_execute(|| {
+        for (tx_hex, script_hex, expect) in cases {
+            let tx: Transaction = tx_hex.parse().unwrap();
+            let script: light_bitcoin::script::Script = script_hex.parse().unwrap();
+
+            Verifier::<Test>::put(BtcTxVerifier::Recover);
+            let recover = parse_and_check_signed_tx_impl::<Test>(&tx, script.clone());
+            Verifier::<Test>::put(BtcTxVerifier::RuntimeInterface);
+            let runtime_interface = parse_and_check_signed_tx_impl::<Test>(&tx, script);
+
+            assert_eq!(recover, expect);
+            assert_eq!(runtime_interface, expect);
+        }
+    });
+}
+
 #[test]
 fn test_create_taproot_address() {
     let mut hot_keys = Vec::new();
@@ -175,7 +226,7 @@ fn test_create_taproot_address() {
             .collect::<Vec<_>>();
         let threshold_addr: Address = Mast::new(pks, 2_usize)
             .unwrap()
-            .generate_address(&crate::Pallet::<Test>::network_id().to_string())
+            .generate_address(&Network::from(crate::Pallet::<Test>::network_id()).to_string())
             .unwrap()
             .parse()
             .unwrap();
@@ -185,3 +236,385 @@ fn test_create_taproot_address() {
         )
     })
 }
+
+#[test]
+fn test_trustee_transition_draining() {
+    ExtBuilder::default().build_and_execute(|| {
+        let hot: Address = "tb1pn202yeugfa25nssxk2hv902kmxrnp7g9xt487u256n20jgahuwasdcjfdw"
+            .parse()
+            .unwrap();
+        let cold: Address = "tb1pexff2s7l58sthpyfrtx500ax234stcnt0gz2lr4kwe0ue95a2e0srxsc68"
+            .parse()
+            .unwrap();
+        let txid = H256::repeat_byte(1);
+        DrainingSessionOf::<Test>::insert(Chain::Bitcoin, 0);
+
+        // could not transition again before the previous trustee session is drained
+        assert_noop!(
+            XGatewayCommon::transition_trustee_session(
+                frame_system::RawOrigin::Root.into(),
+                Chain::Bitcoin,
+                vec![]
+            ),
+            xpallet_gateway_common::Error::<Test>::SessionDraining
+        );
+
+        TrusteeUtxos::<Test>::insert(
+            txid,
+            0,
+            BtcTrusteeUtxo {
+                value: 50000,
+                addr: hot.to_string().into_bytes(),
+            },
+        );
+        assert_eq!(
+            trustee_transition::<Test>(H256::repeat_byte(2), Some((hot.clone(), cold.clone()))),
+            BtcTxResult::Success
+        );
+        assert_eq!(XGatewayCommon::draining_session_of(Chain::Bitcoin), Some(0));
+
+        // all the balance of previous trustees is moved
+        TrusteeUtxos::<Test>::remove(txid, 0);
+        // the balance is unknown before the trustee utxo set is initialized
+        TrusteeUtxosInitialized::<Test>::put(false);
+        assert_eq!(
+            trustee_transition::<Test>(H256::repeat_byte(3), Some((hot.clone(), cold.clone()))),
+            BtcTxResult::Success
+        );
+        assert_eq!(XGatewayCommon::draining_session_of(Chain::Bitcoin), Some(0));
+
+        TrusteeUtxosInitialized::<Test>::put(true);
+        assert_eq!(
+            trustee_transition::<Test>(H256::repeat_byte(3), Some((hot, cold))),
+            BtcTxResult::Success
+        );
+        assert_eq!(XGatewayCommon::draining_session_of(Chain::Bitcoin), None);
+    });
+}
+
+#[test]
+fn test_trustee_stats() {
+    ExtBuilder::default().build_and_execute(|| {
+        // the dropped proposal: alice signed, bob rejected and charlie missed
+        BtcTrusteeSessionManager::<Test>::record_trustee_votes(
+            0,
+            &[(alice(), true), (bob(), false)],
+            false,
+        );
+        // the completed proposal: charlie is not needed to sign
+        BtcTrusteeSessionManager::<Test>::record_trustee_votes(
+            0,
+            &[(alice(), true), (bob(), true)],
+            true,
+        );
+        assert_eq!(
+            XGatewayCommon::trustee_stats(Chain::Bitcoin, None),
+            vec![
+                (
+                    alice(),
+                    TrusteeStats {
+                        seen: 2,
+                        signed: 2,
+                        rejected: 0,
+                        missed: 0
+                    }
+                ),
+                (
+                    bob(),
+                    TrusteeStats {
+                        seen: 2,
+                        signed: 1,
+                        rejected: 1,
+                        missed: 0
+                    }
+                ),
+                (
+                    charlie(),
+                    TrusteeStats {
+                        seen: 2,
+                        signed: 0,
+                        rejected: 0,
+                        missed: 1
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            XGatewayCommon::trustee_stats(Chain::Bitcoin, Some(1)),
+            vec![]
+        );
+
+        // the inactive trustee is removed from the candidates of the next session
+        assert_ok!(XGatewayCommon::set_max_missed_proposals(
+            frame_system::RawOrigin::Root.into(),
+            Chain::Bitcoin,
+            Some(0)
+        ));
+        assert_ok!(XGatewayCommon::set_trustee_info_config(
+            frame_system::RawOrigin::Root.into(),
+            Chain::Bitcoin,
+            TrusteeInfoConfig {
+                min_trustee_count: 2,
+                max_trustee_count: 15,
+            }
+        ));
+        assert_ok!(XGatewayCommon::transition_trustee_session(
+            frame_system::RawOrigin::Root.into(),
+            Chain::Bitcoin,
+            vec![alice(), bob(), charlie()]
+        ));
+        assert_eq!(
+            XGatewayCommon::trustee_session_info_of(Chain::Bitcoin, 1)
+                .unwrap()
+                .0
+                .trustee_list,
+            vec![alice(), bob()]
+        );
+
+        // the votes of the proposal created in the draining session are charged to that session
+        ProposalSessions::<Test>::insert(0, 0);
+        record_proposal_votes::<Test>(0, false, &[(alice(), true), (bob(), true)], true);
+        assert_eq!(
+            XGatewayCommon::trustee_stats_of((Chain::Bitcoin, 0), alice()).seen,
+            3
+        );
+        assert_eq!(
+            XGatewayCommon::trustee_stats(Chain::Bitcoin, Some(1)),
+            vec![]
+        );
+    });
+}
+
+#[test]
+fn test_stalled_proposal_votes() {
+    ExtBuilder::default().build_and_execute(|| {
+        let empty_tx = Transaction {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![],
+            lock_time: 0,
+        };
+        let proposal = BtcWithdrawalProposal::new(
+            VoteResult::Unfinish,
+            vec![],
+            empty_tx,
+            vec![(alice(), true)],
+        );
+        WithdrawalProposals::<Test>::insert(0, proposal.clone());
+        ProposalSessions::<Test>::insert(0, 0);
+        ProposalVoteDeadlines::<Test>::insert((0, false), 10);
+
+        // nothing is recorded before the deadline
+        XGatewayBitcoin::on_initialize(9);
+        assert_eq!(XGatewayCommon::trustee_stats(Chain::Bitcoin, None), vec![]);
+
+        // the trustees not voting on the stalled proposal are counted as missed
+        XGatewayBitcoin::on_initialize(10);
+        let stats = |signed, missed| TrusteeStats {
+            seen: 1,
+            signed,
+            rejected: 0,
+            missed,
+        };
+        let stalled_stats = vec![
+            (alice(), stats(1, 0)),
+            (bob(), stats(0, 1)),
+            (charlie(), stats(0, 1)),
+        ];
+        assert_eq!(
+            XGatewayCommon::trustee_stats(Chain::Bitcoin, None),
+            stalled_stats
+        );
+        assert_eq!(XGatewayBitcoin::proposal_vote_deadline((0, false)), None);
+        assert!(StalledProposals::<Test>::contains_key((0, false)));
+
+        // the stalled proposal is kept, and its votes are not recorded again when it's dropped
+        assert_eq!(
+            XGatewayBitcoin::withdrawal_proposal(0),
+            Some(proposal.clone())
+        );
+        record_proposal_votes::<Test>(0, false, &[(alice(), true), (bob(), false)], false);
+        assert_eq!(
+            XGatewayCommon::trustee_stats(Chain::Bitcoin, None),
+            stalled_stats
+        );
+        assert!(!StalledProposals::<Test>::contains_key((0, false)));
+
+        // the trustees not voting on the replaced fee bump are counted as missed
+        FeeBumpProposals::<Test>::insert(0, proposal.clone());
+        ProposalVoteDeadlines::<Test>::insert((0, true), 20);
+        record_removed_proposal_votes::<Test>(0, true, &proposal);
+        assert_eq!(
+            XGatewayCommon::trustee_stats_of((Chain::Bitcoin, 0), bob()),
+            TrusteeStats {
+                seen: 2,
+                signed: 0,
+                rejected: 0,
+                missed: 2
+            }
+        );
+        assert_eq!(XGatewayBitcoin::proposal_vote_deadline((0, true)), None);
+    });
+}
+
+#[test]
+fn test_trustee_bond_and_slash() {
+    ExtBuilder::default().build_and_execute(|| {
+        let treasury = AccountId32::default();
+        TrusteeBond::set(100);
+        Balances::make_free_balance_be(&alice(), 1000);
+
+        // the bond is reserved when setting up the trustee
+        let (_, about, hot, cold) = trustees().remove(0);
+        assert_ok!(XGatewayCommon::setup_trustee_impl(
+            alice(),
+            Chain::Bitcoin,
+            about,
+            hot,
+            cold
+        ));
+        assert_eq!(
+            XGatewayCommon::trustee_bond_of(alice(), Chain::Bitcoin),
+            100
+        );
+        assert_eq!(Balances::reserved_balance(alice()), 100);
+
+        // slash 10% after rejecting valid proposals more than once
+        BtcTrusteeSessionManager::<Test>::report_offence(
+            &alice(),
+            TrusteeOffence::RejectValidProposal,
+        );
+        assert_eq!(Balances::free_balance(&treasury), 0);
+        BtcTrusteeSessionManager::<Test>::report_offence(
+            &alice(),
+            TrusteeOffence::RejectValidProposal,
+        );
+        assert_eq!(Balances::free_balance(&treasury), 10);
+        assert_eq!(XGatewayCommon::trustee_bond_of(alice(), Chain::Bitcoin), 90);
+
+        // the bond is locked while in charge of the trustee session
+        assert_noop!(
+            XGatewayCommon::withdraw_trustee_bond(
+                frame_system::RawOrigin::Signed(alice()).into(),
+                Chain::Bitcoin
+            ),
+            xpallet_gateway_common::Error::<Test>::TrusteeBondLocked
+        );
+
+        // slash all the bond for the unauthorized spend
+        BtcTrusteeSessionManager::<Test>::report_offence(
+            &alice(),
+            TrusteeOffence::UnauthorizedSpend,
+        );
+        assert_eq!(Balances::free_balance(&treasury), 100);
+        assert_eq!(XGatewayCommon::trustee_bond_of(alice(), Chain::Bitcoin), 0);
+        assert_eq!(Balances::reserved_balance(alice()), 0);
+        assert_eq!(Balances::free_balance(alice()), 900);
+    });
+}
+
+#[test]
+fn test_slash_unauthorized_spend_signers() {
+    ExtBuilder::default().build_and_execute(|| {
+        TrusteeBond::set(100);
+        for (who, about, hot, cold) in trustees() {
+            Balances::make_free_balance_be(&who, 1000);
+            assert_ok!(XGatewayCommon::setup_trustee_impl(
+                who,
+                Chain::Bitcoin,
+                about,
+                hot,
+                cold
+            ));
+        }
+        // the signatures are matched to the public keys in order without verification
+        Verifier::<Test>::put(BtcTxVerifier::Test);
+
+        // spend the cold address by the 2-of-3 multisig
+        let session = BtcTrusteeSessionManager::<Test>::current_trustee_session().unwrap();
+        let redeem_script = session.cold_address.redeem_script.clone();
+        let prev_tx = Transaction {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![TransactionOutput {
+                value: 100_000,
+                script_pubkey: Builder::default()
+                    .push_opcode(Opcode::OP_HASH160)
+                    .push_bytes(dhash160(&redeem_script).as_bytes())
+                    .push_opcode(Opcode::OP_EQUAL)
+                    .into_script()
+                    .to_bytes(),
+            }],
+            lock_time: 0,
+        };
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint {
+                    txid: prev_tx.hash(),
+                    index: 0,
+                },
+                script_sig: Builder::default()
+                    .push_opcode(Opcode::OP_0)
+                    .push_bytes(&[1; 71])
+                    .push_bytes(&[2; 71])
+                    .push_bytes(&redeem_script)
+                    .into_script()
+                    .to_bytes(),
+                sequence: 0xffffffff,
+                script_witness: vec![],
+            }],
+            outputs: vec![TransactionOutput {
+                value: 90_000,
+                script_pubkey: Builder::default()
+                    .push_opcode(Opcode::OP_DUP)
+                    .push_opcode(Opcode::OP_HASH160)
+                    .push_bytes(&[0; 20])
+                    .push_opcode(Opcode::OP_EQUALVERIFY)
+                    .push_opcode(Opcode::OP_CHECKSIG)
+                    .into_script()
+                    .to_bytes(),
+            }],
+            lock_time: 0,
+        };
+
+        assert_ok!(XGatewayBitcoin::slash_unauthorized_spend(&tx, &[prev_tx]));
+        // only the signers are slashed, the trustee not signing keeps the bond
+        let (signers, others) = session.trustee_list.split_at(2);
+        for signer in signers {
+            assert_eq!(XGatewayCommon::trustee_bond_of(signer, Chain::Bitcoin), 0);
+        }
+        assert_eq!(
+            XGatewayCommon::trustee_bond_of(&others[0], Chain::Bitcoin),
+            100
+        );
+        assert_eq!(Balances::reserved_balance(&others[0]), 100);
+    });
+}
+
+#[test]
+fn test_migrate_trustee_bonds() {
+    ExtBuilder::default().build_and_execute(|| {
+        TrusteeBond::set(100);
+        Balances::make_free_balance_be(&alice(), 1000);
+        Balances::make_free_balance_be(&bob(), 1000);
+        assert_eq!(XGatewayCommon::trustee_bond_of(alice(), Chain::Bitcoin), 0);
+
+        // the genesis trustees in charge are bonded by the migration if they could afford it
+        StorageVersion::new(0).put::<XGatewayCommon>();
+        XGatewayCommon::on_runtime_upgrade();
+        assert_eq!(
+            XGatewayCommon::trustee_bond_of(alice(), Chain::Bitcoin),
+            100
+        );
+        assert_eq!(Balances::reserved_balance(bob()), 100);
+        assert_eq!(
+            XGatewayCommon::trustee_bond_of(charlie(), Chain::Bitcoin),
+            0
+        );
+        assert_eq!(
+            StorageVersion::get::<XGatewayCommon>(),
+            StorageVersion::new(1)
+        );
+    });
+}
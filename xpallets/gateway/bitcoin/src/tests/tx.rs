@@ -2,27 +2,36 @@
 
 #![allow(non_upper_case_globals)]
 
-use frame_support::{assert_noop, assert_ok};
+use frame_support::{assert_noop, assert_ok, weights::Pays};
 use sp_core::crypto::{set_default_ss58_version, Ss58AddressFormat};
 
 use light_bitcoin::{
-    chain::Transaction,
+    chain::{Transaction, TransactionOutput},
     keys::{Address, Network},
-    merkle::PartialMerkleTree,
-    serialization::{self, Reader},
+    primitives::H256,
+    serialization,
 };
 
 use xp_gateway_bitcoin::{AccountExtractor, BtcTxMetaType, BtcTxType, BtcTxTypeDetector};
+use xpallet_assets::Chain;
+use xpallet_gateway_common::traits::AddressBinding;
 
+use super::{block_63299_proof, reorg_block_63299};
 use crate::mock::*;
 
 use crate::{
-    tx::process_tx,
+    trustee::{
+        check_fee_bump_tx, check_withdraw_fee_rate, check_withdraw_tx, check_withdraw_tx_impl,
+        tx_vsize,
+    },
+    tx::{deposit_action, migrate_pending_deposit_height, process_tx, remove_pending_deposit},
     types::{
-        BtcDepositCache, BtcRelayedTxInfo, BtcTxResult, BtcTxState, BtcWithdrawalProposal,
-        VoteResult,
+        BtcDepositCache, BtcProofOfReserves, BtcRelayedTxInfo, BtcTrusteeUtxo, BtcTxResult,
+        BtcTxState, BtcWithdrawalProposal, VoteResult,
     },
-    Config, WithdrawalProposal,
+    weights::WeightInfo,
+    BlockTxs, BtcMinDeposit, Config, FeeBumpProposals, PendingDepositHeight, PendingDeposits,
+    ProposalRefunds, TrusteeUtxos, TxState, WithdrawalProposals,
 };
 
 // Tyoe is p2tr. Address farmat is Mainnet.:
@@ -63,7 +72,7 @@ lazy_static::lazy_static! {
 
 fn mock_detect_transaction_type<T: Config>(
     tx: &Transaction,
-    prev_tx: Option<&Transaction>,
+    prev_txs: &[Transaction],
 ) -> BtcTxMetaType<T::AccountId> {
     let btc_tx_detector = BtcTxTypeDetector::new(Network::Mainnet, 0);
     let current_trustee_pair = (
@@ -72,7 +81,7 @@ fn mock_detect_transaction_type<T: Config>(
     );
     btc_tx_detector.detect_transaction_type::<T::AccountId, _>(
         tx,
-        prev_tx,
+        prev_txs,
         |script| T::AccountExtractor::extract_account(script),
         current_trustee_pair,
         None,
@@ -82,39 +91,47 @@ fn mock_detect_transaction_type<T: Config>(
 #[test]
 fn test_detect_tx_type() {
     set_default_ss58_version(Ss58AddressFormat::ChainXAccount);
-    match mock_detect_transaction_type::<Test>(&deposit_taproot1, None) {
+    match mock_detect_transaction_type::<Test>(&deposit_taproot1, &[]) {
         BtcTxMetaType::Deposit(info) => {
             assert!(info.input_addr.is_none() && info.op_return.is_none())
         }
         _ => unreachable!("wrong type"),
     }
-    match mock_detect_transaction_type::<Test>(&deposit_taproot2, None) {
+    match mock_detect_transaction_type::<Test>(&deposit_taproot2, &[]) {
         BtcTxMetaType::Deposit(info) => {
             assert!(info.input_addr.is_none() && info.op_return.is_some())
         }
         _ => unreachable!("wrong type"),
     }
 
-    match mock_detect_transaction_type::<Test>(&deposit_taproot1, Some(&deposit_taproot1_prev)) {
+    match mock_detect_transaction_type::<Test>(&deposit_taproot1, &[deposit_taproot1_prev.clone()])
+    {
         BtcTxMetaType::Deposit(info) => {
             assert!(info.input_addr.is_some() && info.op_return.is_none())
         }
         _ => unreachable!("wrong type"),
     }
 
-    match mock_detect_transaction_type::<Test>(&deposit_taproot2, Some(&deposit_taproot2_prev)) {
+    match mock_detect_transaction_type::<Test>(&deposit_taproot2, &[deposit_taproot2_prev.clone()])
+    {
         BtcTxMetaType::Deposit(info) => {
             assert!(info.input_addr.is_some() && info.op_return.is_some())
         }
         _ => unreachable!("wrong type"),
     }
 
-    match mock_detect_transaction_type::<Test>(&withdraw_taproot1, Some(&withdraw_taproot1_prev)) {
+    match mock_detect_transaction_type::<Test>(
+        &withdraw_taproot1,
+        &[withdraw_taproot1_prev.clone()],
+    ) {
         BtcTxMetaType::Withdrawal => {}
         _ => unreachable!("wrong type"),
     }
 
-    match mock_detect_transaction_type::<Test>(&withdraw_taproot2, Some(&withdraw_taproot2_prev)) {
+    match mock_detect_transaction_type::<Test>(
+        &withdraw_taproot2,
+        &[withdraw_taproot2_prev.clone()],
+    ) {
         BtcTxMetaType::Withdrawal => {}
         _ => unreachable!("wrong type"),
     }
@@ -122,14 +139,14 @@ fn test_detect_tx_type() {
     // hot_to_cold
     // if not pass a prev, would judge to a deposit, but this deposit could not be handled due to
     // opreturn and input_addr are all none, or if all send to cold, it would be Irrelevance
-    match mock_detect_transaction_type::<Test>(&hot_to_cold, None) {
+    match mock_detect_transaction_type::<Test>(&hot_to_cold, &[]) {
         BtcTxMetaType::Deposit(info) => {
             assert!(info.input_addr.is_none() && info.op_return.is_none())
         }
         _ => unreachable!("wrong type"),
     }
     // then if provide prev, it would be judge to a HotAndCold
-    match mock_detect_transaction_type::<Test>(&hot_to_cold, Some(&hot_to_cold_prev)) {
+    match mock_detect_transaction_type::<Test>(&hot_to_cold, &[hot_to_cold_prev.clone()]) {
         BtcTxMetaType::HotAndCold => {}
         _ => unreachable!("wrong type"),
     }
@@ -137,20 +154,20 @@ fn test_detect_tx_type() {
     // // cold_to_hot
     // // if not pass a prev, would judge to a deposit, but this deposit could not be handled due to
     // // opreturn and input_addr are all none
-    // match mock_detect_transaction_type::<Test>(&cold_to_hot, None) {
+    // match mock_detect_transaction_type::<Test>(&cold_to_hot, &[]) {
     //     BtcTxMetaType::Deposit(info) => {
     //         assert!(info.input_addr.is_none() && info.op_return.is_none())
     //     }
     //     _ => unreachable!("wrong type"),
     // }
     // // then if provide prev, it would be judge to a HotAndCold
-    // match mock_detect_transaction_type::<Test>(&cold_to_hot, Some(&cold_to_hot_prev)) {
+    // match mock_detect_transaction_type::<Test>(&cold_to_hot, &[cold_to_hot_prev.clone()]) {
     //     BtcTxMetaType::HotAndCold => {}
     //     _ => unreachable!("wrong type"),
     // }
 }
 
-fn mock_process_tx<T: Config>(tx: Transaction, prev_tx: Option<Transaction>) -> BtcTxState {
+fn mock_process_tx<T: Config>(tx: Transaction, prev_txs: Vec<Transaction>) -> BtcTxState {
     let network = Network::Mainnet;
     let min_deposit = 0;
     let current_trustee_pair = (
@@ -160,7 +177,8 @@ fn mock_process_tx<T: Config>(tx: Transaction, prev_tx: Option<Transaction>) ->
     let previous_trustee_pair = None;
     process_tx::<T>(
         tx,
-        prev_tx,
+        prev_txs,
+        XGatewayBitcoin::best_index().height,
         network,
         min_deposit,
         current_trustee_pair,
@@ -173,12 +191,12 @@ fn test_process_tx() {
     set_default_ss58_version(Ss58AddressFormat::ChainXAccount);
     ExtBuilder::default().build_and_execute(|| {
         // without op return and input address
-        let r = mock_process_tx::<Test>(deposit_taproot1.clone(), None);
+        let r = mock_process_tx::<Test>(deposit_taproot1.clone(), vec![]);
         assert_eq!(r.result, BtcTxResult::Failure);
         // without op return and with input address
         let r = mock_process_tx::<Test>(
             deposit_taproot1.clone(),
-            Some(deposit_taproot1_prev.clone()),
+            vec![deposit_taproot1_prev.clone()],
         );
         assert_eq!(r.result, BtcTxResult::Success);
         assert_eq!(
@@ -190,59 +208,191 @@ fn test_process_tx() {
         );
 
         // withdraw
-        WithdrawalProposal::<Test>::put(BtcWithdrawalProposal {
-            sig_state: VoteResult::Unfinish,
-            withdrawal_id_list: vec![],
-            tx: withdraw_taproot1.clone(),
-            trustee_list: vec![],
-        });
-
-        let r = mock_process_tx::<Test>(withdraw_taproot1.clone(), None);
+        WithdrawalProposals::<Test>::insert(
+            0,
+            BtcWithdrawalProposal {
+                sig_state: VoteResult::Unfinish,
+                withdrawal_id_list: vec![],
+                tx: withdraw_taproot1.clone(),
+                trustee_list: vec![],
+            },
+        );
+
+        let r = mock_process_tx::<Test>(withdraw_taproot1.clone(), vec![]);
         assert_eq!(r.result, BtcTxResult::Failure);
         let r = mock_process_tx::<Test>(
             withdraw_taproot1.clone(),
-            Some(withdraw_taproot1_prev.clone()),
+            vec![withdraw_taproot1_prev.clone()],
         );
         assert_eq!(r.result, BtcTxResult::Success);
 
         // with op return and without input address
-        let r = mock_process_tx::<Test>(deposit_taproot2.clone(), None);
+        let r = mock_process_tx::<Test>(deposit_taproot2.clone(), vec![]);
         assert_eq!(r.result, BtcTxResult::Success);
         assert_eq!(XAssets::usable_balance(&op_account, &X_BTC), 100000);
         assert_eq!(XGatewayCommon::bound_addrs(&op_account), Default::default());
         // with op return and input address
         let r = mock_process_tx::<Test>(
             deposit_taproot2.clone(),
-            Some(deposit_taproot2_prev.clone()),
+            vec![deposit_taproot2_prev.clone()],
         );
         assert_eq!(r.result, BtcTxResult::Success);
         assert_eq!(XAssets::usable_balance(&op_account, &X_BTC), 300000);
 
         // withdraw
-        WithdrawalProposal::<Test>::put(BtcWithdrawalProposal {
-            sig_state: VoteResult::Unfinish,
-            withdrawal_id_list: vec![],
-            tx: withdraw_taproot2.clone(),
-            trustee_list: vec![],
-        });
-
-        let r = mock_process_tx::<Test>(withdraw_taproot2.clone(), None);
+        WithdrawalProposals::<Test>::insert(
+            0,
+            BtcWithdrawalProposal {
+                sig_state: VoteResult::Unfinish,
+                withdrawal_id_list: vec![],
+                tx: withdraw_taproot2.clone(),
+                trustee_list: vec![],
+            },
+        );
+
+        let r = mock_process_tx::<Test>(withdraw_taproot2.clone(), vec![]);
         assert_eq!(r.result, BtcTxResult::Failure);
         let r = mock_process_tx::<Test>(
             withdraw_taproot2.clone(),
-            Some(withdraw_taproot2_prev.clone()),
+            vec![withdraw_taproot2_prev.clone()],
         );
         assert_eq!(r.result, BtcTxResult::Success);
 
         // hot and cold
-        let r = mock_process_tx::<Test>(hot_to_cold.clone(), None);
+        let r = mock_process_tx::<Test>(hot_to_cold.clone(), vec![]);
         assert_eq!(r.result, BtcTxResult::Failure);
-        let r = mock_process_tx::<Test>(hot_to_cold.clone(), Some(hot_to_cold_prev.clone()));
+        let r = mock_process_tx::<Test>(hot_to_cold.clone(), vec![hot_to_cold_prev.clone()]);
         assert_eq!(r.tx_type, BtcTxType::HotAndCold);
         assert_eq!(r.result, BtcTxResult::Success);
     })
 }
 
+#[test]
+fn test_multi_input_deposit() {
+    set_default_ss58_version(Ss58AddressFormat::ChainXAccount);
+    ExtBuilder::default().build_and_execute(|| {
+        let taproot_addr = deposit_taproot1_input_account.to_vec();
+        let p2pkh_addr = b"1BTspLHhLMYxBfoeWAQkTcQ1ezWinkHtZV".to_vec();
+        let prev_tx = |value: u64, script_pubkey: &str| Transaction {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![TransactionOutput {
+                value,
+                script_pubkey: hex::decode(script_pubkey).unwrap().into(),
+            }],
+            lock_time: 0,
+        };
+        let taproot_prev = prev_tx(
+            300000,
+            "5120c9929543dfa1e0bb84891acd47bfa6546b05e26b7a04af8eb6765fcc969d565f",
+        );
+        let p2pkh_prev = prev_tx(100000, "76a91472c67df1c505f4e434f98210cb1fe8e20840f8a188ac");
+        // deposit to the hot address without op return, spending the outputs of both addresses
+        let deposit_tx = |value: u64| {
+            let mut tx = deposit_taproot1.clone();
+            tx.inputs = [&taproot_prev, &p2pkh_prev]
+                .iter()
+                .map(|prev| {
+                    let mut input = deposit_taproot1.inputs[0].clone();
+                    input.previous_output.txid = prev.hash();
+                    input.previous_output.index = 0;
+                    input
+                })
+                .collect();
+            tx.outputs = vec![TransactionOutput {
+                value,
+                script_pubkey: deposit_taproot1.outputs[0].script_pubkey.clone(),
+            }];
+            tx
+        };
+
+        // the previous txs of all inputs are needed
+        let tx = deposit_tx(200000);
+        let r = mock_process_tx::<Test>(tx.clone(), vec![taproot_prev.clone()]);
+        assert_eq!(r.result, BtcTxResult::Failure);
+        assert!(XGatewayBitcoin::pending_deposits(&taproot_addr).is_empty());
+
+        // neither address is bound, park the deposit in proportion to the spent value
+        let r = mock_process_tx::<Test>(tx.clone(), vec![taproot_prev.clone(), p2pkh_prev.clone()]);
+        assert_eq!(r.result, BtcTxResult::Success);
+        assert_eq!(
+            XGatewayBitcoin::pending_deposits(&taproot_addr),
+            vec![BtcDepositCache {
+                txid: tx.hash(),
+                balance: 150000,
+            }]
+        );
+        assert_eq!(
+            XGatewayBitcoin::pending_deposits(&p2pkh_addr),
+            vec![BtcDepositCache {
+                txid: tx.hash(),
+                balance: 50000,
+            }]
+        );
+        assert_eq!(
+            XGatewayBitcoin::pending_deposit_addrs(&tx.hash()),
+            vec![taproot_addr.clone(), p2pkh_addr.clone()]
+        );
+
+        // the inclusion height is kept until all the shares are released
+        remove_pending_deposit::<Test>(&taproot_addr, &alice());
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 150000);
+        assert!(XGatewayBitcoin::pending_deposit_height(&tx.hash()).is_some());
+        remove_pending_deposit::<Test>(&p2pkh_addr, &bob());
+        assert_eq!(XAssets::usable_balance(&bob(), &X_BTC), 50000);
+        assert!(XGatewayBitcoin::pending_deposit_height(&tx.hash()).is_none());
+        assert!(XGatewayBitcoin::pending_deposit_addrs(&tx.hash()).is_empty());
+
+        // both addresses are bound to the same account
+        XGatewayCommon::update_binding(Chain::Bitcoin, taproot_addr.clone(), alice());
+        XGatewayCommon::update_binding(Chain::Bitcoin, p2pkh_addr.clone(), alice());
+        let tx = deposit_tx(100000);
+        let r = mock_process_tx::<Test>(tx, vec![taproot_prev.clone(), p2pkh_prev.clone()]);
+        assert_eq!(r.result, BtcTxResult::Success);
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 250000);
+        assert!(XGatewayBitcoin::pending_deposits(&taproot_addr).is_empty());
+
+        // the addresses are bound to different accounts
+        XGatewayCommon::update_binding(Chain::Bitcoin, p2pkh_addr.clone(), bob());
+        let tx = deposit_tx(40000);
+        let r = mock_process_tx::<Test>(tx.clone(), vec![taproot_prev.clone(), p2pkh_prev]);
+        assert_eq!(r.result, BtcTxResult::Success);
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 250000);
+        assert_eq!(XAssets::usable_balance(&bob(), &X_BTC), 50000);
+        assert_eq!(
+            XGatewayBitcoin::pending_deposits(&taproot_addr),
+            vec![BtcDepositCache {
+                txid: tx.hash(),
+                balance: 30000,
+            }]
+        );
+        assert_eq!(
+            XGatewayBitcoin::pending_deposits(&p2pkh_addr),
+            vec![BtcDepositCache {
+                txid: tx.hash(),
+                balance: 10000,
+            }]
+        );
+
+        // the input spending a bare multisig output is ineligible, the deposit goes to the
+        // account bound to the other input
+        let multisig_prev = prev_tx(
+            100000,
+            "51210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f817982102c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee552ae",
+        );
+        let mut tx = deposit_tx(60000);
+        tx.inputs[1].previous_output.txid = multisig_prev.hash();
+        let r = mock_process_tx::<Test>(tx.clone(), vec![taproot_prev, multisig_prev.clone()]);
+        assert_eq!(r.result, BtcTxResult::Success);
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 310000);
+
+        // only the ineligible input
+        tx.inputs.remove(0);
+        let r = mock_process_tx::<Test>(tx, vec![multisig_prev]);
+        assert_eq!(r.result, BtcTxResult::Failure);
+    });
+}
+
 #[test]
 fn test_push_tx_call() {
     set_default_ss58_version(Ss58AddressFormat::ChainXAccount);
@@ -252,8 +402,7 @@ fn test_push_tx_call() {
     let headers = generate_blocks_63290_63310();
     let block_hash = headers[&63299].hash();
 
-    let raw_proof = hex::decode("0a000000050a59b195a68a29037580798ca0414941eb46eaf7607db2d0da1ff89e9570ce455fea22ec1a3e3e7e1167fa220cc8376225f07bd20aa194e7f3c4ac68c7375d8e0a35e47541de7d0aa7312dabcf3bc9f06603e832427b8e4fe9a97a309f8cd7141687d11a3fd8f21e2105a52a3c36a17ea870e326ecddb23221d4cc0398b6c44bdcce3f191919a31f4cfaca5a786cc8315db76683ad6b8008f2ed9b348df76a0d022f00").unwrap();
-    let proof: PartialMerkleTree = serialization::deserialize(Reader::new(&raw_proof)).unwrap();
+    let proof = block_63299_proof();
 
     ExtBuilder::default().build_and_execute(|| {
         let confirmed = XGatewayBitcoin::confirmation_number();
@@ -266,11 +415,33 @@ fn test_push_tx_call() {
             merkle_proof: proof,
         };
 
+        // the previous tx must be spent by an input of the tx
+        assert_noop!(
+            XGatewayBitcoin::push_transaction(
+                frame_system::RawOrigin::Signed(Default::default()).into(),
+                tx.clone().into(),
+                info.clone(),
+                vec![serialization::serialize(&*hot_to_cold_prev).into()],
+            ),
+            XGatewayBitcoinErr::InvalidPrevTx,
+        );
+        // and could not be duplicated
+        let prev_tx: Vec<u8> = serialization::serialize(&*deposit_taproot2_prev).into();
+        assert_noop!(
+            XGatewayBitcoin::push_transaction(
+                frame_system::RawOrigin::Signed(Default::default()).into(),
+                tx.clone().into(),
+                info.clone(),
+                vec![prev_tx.clone(), prev_tx],
+            ),
+            XGatewayBitcoinErr::InvalidPrevTx,
+        );
+
         assert_ok!(XGatewayBitcoin::push_transaction(
             frame_system::RawOrigin::Signed(Default::default()).into(),
             tx.clone().into(),
             info.clone(),
-            None,
+            vec![],
         ));
 
         // reject replay
@@ -279,9 +450,1016 @@ fn test_push_tx_call() {
                 frame_system::RawOrigin::Signed(Default::default()).into(),
                 tx.clone().into(),
                 info,
-                None,
+                vec![],
             ),
             XGatewayBitcoinErr::ReplayedTx,
         );
     });
 }
+
+#[test]
+fn test_push_txs_batch() {
+    set_default_ss58_version(Ss58AddressFormat::ChainXAccount);
+    let tx: Vec<u8> = serialization::serialize(&*deposit_taproot2).into();
+    let headers = generate_blocks_63290_63310();
+    let block_hash = headers[&63299].hash();
+
+    let proof = block_63299_proof();
+
+    ExtBuilder::default().build_and_execute(|| {
+        let confirmed = XGatewayBitcoin::confirmation_number();
+        for i in 63291..=63299 + confirmed {
+            assert_ok!(XGatewayBitcoin::apply_push_header(headers[&i]));
+        }
+        let info = BtcRelayedTxInfo {
+            block_hash,
+            merkle_proof: proof,
+        };
+        let bad_prev: Vec<u8> = serialization::serialize(&*hot_to_cold_prev).into();
+
+        let batch_results = || {
+            System::events()
+                .into_iter()
+                .filter_map(|record| match record.event {
+                    Event::XGatewayBitcoin(crate::Event::BatchTxApplied(
+                        index,
+                        tx_hash,
+                        result,
+                    )) => {
+                        assert_eq!(tx_hash, deposit_taproot2.hash());
+                        Some((index, result))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_noop!(
+            XGatewayBitcoin::push_transactions(
+                frame_system::RawOrigin::Signed(Default::default()).into(),
+                vec![],
+            ),
+            XGatewayBitcoinErr::InvalidTxBatchSize,
+        );
+        // the batch is rejected if any item could not be deserialized
+        assert_noop!(
+            XGatewayBitcoin::push_transactions(
+                frame_system::RawOrigin::Signed(Default::default()).into(),
+                vec![
+                    (tx.clone(), info.clone(), vec![]),
+                    (vec![0x01], info.clone(), vec![]),
+                ],
+            ),
+            XGatewayBitcoinErr::DeserializeErr,
+        );
+
+        // the failed tx is recorded as `push_transaction` does, so that it could be replayed
+        let min_deposit = XGatewayBitcoin::btc_min_deposit();
+        BtcMinDeposit::<Test>::put(100001);
+        let post_info = XGatewayBitcoin::push_transactions(
+            frame_system::RawOrigin::Signed(Default::default()).into(),
+            vec![(tx.clone(), info.clone(), vec![])],
+        )
+        .unwrap();
+        assert_eq!(post_info.pays_fee, Pays::Yes);
+        assert_eq!(
+            XGatewayBitcoin::tx_state(&deposit_taproot2.hash()).map(|state| state.result),
+            Some(BtcTxResult::Failure)
+        );
+        assert_eq!(
+            batch_results(),
+            vec![(0, Err(XGatewayBitcoinErr::ProcessTxFailed.into()))]
+        );
+        BtcMinDeposit::<Test>::put(min_deposit);
+        System::reset_events();
+
+        // the failed items do not block the others, and the fee is charged for the actual weight
+        let post_info = XGatewayBitcoin::push_transactions(
+            frame_system::RawOrigin::Signed(Default::default()).into(),
+            vec![
+                (tx.clone(), info.clone(), vec![bad_prev]),
+                (tx.clone(), info.clone(), vec![]),
+                (tx, info, vec![]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(post_info.pays_fee, Pays::Yes);
+        assert_eq!(
+            post_info.actual_weight,
+            Some(<() as WeightInfo>::push_transaction() * 3 + MaxDepositActionWeight::get())
+        );
+        assert_eq!(XAssets::usable_balance(&op_account, &X_BTC), 100000);
+        assert_eq!(
+            XGatewayBitcoin::tx_state(&deposit_taproot2.hash()).map(|state| state.result),
+            Some(BtcTxResult::Success)
+        );
+        assert_eq!(
+            batch_results(),
+            vec![
+                (0, Err(XGatewayBitcoinErr::InvalidPrevTx.into())),
+                (1, Ok(())),
+                (2, Err(XGatewayBitcoinErr::ReplayedTx.into())),
+            ]
+        );
+    });
+}
+
+#[test]
+fn test_reorged_deposit() {
+    set_default_ss58_version(Ss58AddressFormat::ChainXAccount);
+    let tx = serialization::serialize(&deposit_taproot2.clone());
+    let headers = generate_blocks_63290_63310();
+    let block_hash = headers[&63299].hash();
+
+    let proof = block_63299_proof();
+
+    ExtBuilder::default().build_and_execute(|| {
+        let confirmed = XGatewayBitcoin::confirmation_number();
+        for i in 63291..=63299 + confirmed {
+            assert_ok!(XGatewayBitcoin::apply_push_header(headers[&i]));
+        }
+        let info = BtcRelayedTxInfo {
+            block_hash,
+            merkle_proof: proof,
+        };
+        assert_ok!(XGatewayBitcoin::push_transaction(
+            frame_system::RawOrigin::Signed(Default::default()).into(),
+            tx.into(),
+            info,
+            vec![],
+        ));
+        assert_eq!(
+            XGatewayBitcoin::block_txs(&block_hash),
+            vec![deposit_taproot2.hash()]
+        );
+        assert_eq!(XAssets::usable_balance(&op_account, &X_BTC), 100000);
+
+        // a deep reorg flips the confirmed block of the deposit out of the main chain
+        reorg_block_63299(&headers);
+        assert!(!XGatewayBitcoin::main_chain(&block_hash));
+
+        let deposit = XGatewayBitcoin::reorged_deposits(&deposit_taproot2.hash()).unwrap();
+        assert_eq!(deposit.block_hash, block_hash);
+        assert_eq!(deposit.who, op_account.clone());
+        assert_eq!(deposit.frozen, 100000);
+        assert_eq!(XAssets::usable_balance(&op_account, &X_BTC), 0);
+        assert_eq!(XAssets::locked_balance(&op_account, &X_BTC), 100000);
+        // the deposit could be pushed again with the new main chain
+        assert!(XGatewayBitcoin::tx_state(&deposit_taproot2.hash()).is_none());
+
+        // governance releases the frozen balance
+        assert_ok!(XGatewayBitcoin::resolve_reorged_deposit(
+            frame_system::RawOrigin::Root.into(),
+            deposit_taproot2.hash(),
+            true,
+        ));
+        assert_eq!(XAssets::usable_balance(&op_account, &X_BTC), 100000);
+        assert_eq!(XAssets::locked_balance(&op_account, &X_BTC), 0);
+        assert_eq!(
+            XGatewayBitcoin::tx_state(&deposit_taproot2.hash()).map(|state| state.result),
+            Some(BtcTxResult::Success)
+        );
+        assert_noop!(
+            XGatewayBitcoin::resolve_reorged_deposit(
+                frame_system::RawOrigin::Root.into(),
+                deposit_taproot2.hash(),
+                true,
+            ),
+            XGatewayBitcoinErr::NoReorgedDeposit,
+        );
+    });
+}
+
+#[test]
+fn test_reincluded_reorged_deposit() {
+    set_default_ss58_version(Ss58AddressFormat::ChainXAccount);
+    let tx = serialization::serialize(&deposit_taproot2.clone());
+    let headers = generate_blocks_63290_63310();
+    let block_hash = headers[&63299].hash();
+
+    let proof = block_63299_proof();
+
+    ExtBuilder::default().build_and_execute(|| {
+        let confirmed = XGatewayBitcoin::confirmation_number();
+        for i in 63291..=63299 + confirmed {
+            assert_ok!(XGatewayBitcoin::apply_push_header(headers[&i]));
+        }
+        let info = BtcRelayedTxInfo {
+            block_hash,
+            merkle_proof: proof.clone(),
+        };
+        assert_ok!(XGatewayBitcoin::push_transaction(
+            frame_system::RawOrigin::Signed(Default::default()).into(),
+            tx.clone().into(),
+            info,
+            vec![],
+        ));
+
+        // the forked block includes the same txs
+        let forked = reorg_block_63299(&headers);
+        assert_eq!(XAssets::locked_balance(&op_account, &X_BTC), 100000);
+
+        // the deposit is released rather than credited twice
+        let forked_hash = forked[0].header.hash();
+        let info = BtcRelayedTxInfo {
+            block_hash: forked_hash,
+            merkle_proof: proof,
+        };
+        assert_ok!(XGatewayBitcoin::push_transaction(
+            frame_system::RawOrigin::Signed(Default::default()).into(),
+            tx.into(),
+            info,
+            vec![],
+        ));
+        assert_eq!(XAssets::usable_balance(&op_account, &X_BTC), 100000);
+        assert_eq!(XAssets::locked_balance(&op_account, &X_BTC), 0);
+        assert!(XGatewayBitcoin::reorged_deposits(&deposit_taproot2.hash()).is_none());
+        assert_eq!(
+            XGatewayBitcoin::block_txs(&forked_hash),
+            vec![deposit_taproot2.hash()]
+        );
+        assert_eq!(
+            XGatewayBitcoin::tx_deposit_of(&deposit_taproot2.hash()),
+            Some((op_account.clone(), 100000))
+        );
+    });
+}
+
+#[test]
+fn test_reorged_withdrawal() {
+    let headers = generate_blocks_63290_63310();
+    let block_hash = headers[&63299].hash();
+
+    ExtBuilder::default().build_and_execute(|| {
+        let confirmed = XGatewayBitcoin::confirmation_number();
+        for i in 63291..=63299 + confirmed {
+            assert_ok!(XGatewayBitcoin::apply_push_header(headers[&i]));
+        }
+        let hot_utxo = |value: u64| BtcTrusteeUtxo {
+            value,
+            addr: DEPOSIT_HOT_ADDR.as_bytes().to_vec(),
+        };
+
+        // deposit to the hot address
+        let r = mock_process_tx::<Test>(
+            deposit_taproot1.clone(),
+            vec![deposit_taproot1_prev.clone()],
+        );
+        assert_eq!(r.result, BtcTxResult::Success);
+
+        // the withdrawal spends the deposit in the block, and the change is back to the hot
+        // address
+        WithdrawalProposals::<Test>::insert(
+            0,
+            BtcWithdrawalProposal::new(
+                VoteResult::Finish,
+                vec![],
+                withdraw_taproot1.clone(),
+                vec![],
+            ),
+        );
+        let r = mock_process_tx::<Test>(
+            withdraw_taproot1.clone(),
+            vec![withdraw_taproot1_prev.clone()],
+        );
+        assert_eq!(r.result, BtcTxResult::Success);
+        TxState::<Test>::insert(withdraw_taproot1.hash(), r);
+        BlockTxs::<Test>::append(block_hash, withdraw_taproot1.hash());
+        assert_eq!(
+            XGatewayBitcoin::trustee_utxos(),
+            vec![(withdraw_taproot1.hash(), 1, hot_utxo(40000))]
+        );
+        assert_eq!(
+            XGatewayBitcoin::tx_spent_trustee_utxos(&withdraw_taproot1.hash()),
+            vec![(deposit_taproot1.hash(), 0, hot_utxo(100000))]
+        );
+
+        // the reorg restores the spent utxo and removes the change
+        reorg_block_63299(&headers);
+        assert_eq!(
+            XGatewayBitcoin::trustee_utxos(),
+            vec![(deposit_taproot1.hash(), 0, hot_utxo(100000))]
+        );
+        assert!(XGatewayBitcoin::tx_spent_trustee_utxos(&withdraw_taproot1.hash()).is_empty());
+        assert!(XGatewayBitcoin::block_txs(&block_hash).is_empty());
+        // the withdrawal is not processed twice, but its utxo updates wait for the new main chain
+        assert_eq!(
+            XGatewayBitcoin::tx_state(&withdraw_taproot1.hash()),
+            Some(r)
+        );
+        assert_eq!(
+            XGatewayBitcoin::reorged_trustee_txs(&withdraw_taproot1.hash()),
+            Some(block_hash)
+        );
+    });
+}
+
+#[test]
+fn test_claim_pending_deposit() {
+    ExtBuilder::default().build_and_execute(|| {
+        // testnet p2pkh address of a key, and its BIP-137 signature of the mock genesis hash and
+        // Alice's account id
+        let addr = b"mh4wHJc3SEud5GRu1Gbu2ZF6g2i5pcRBVb".to_vec();
+        let signature = hex::decode("209fe2e37dfc1ff07b0f8ce0f820a74b980cc2d2eee6072a9d4e4dbf53b6624a5750e747e743619063229dcffbfd22c29e0c395e2c5ac451f36de3c5bee4a7583b").unwrap();
+
+        assert_noop!(
+            XGatewayBitcoin::claim_pending_deposit(
+                frame_system::RawOrigin::Signed(alice()).into(),
+                addr.clone(),
+                signature.clone(),
+                2
+            ),
+            XGatewayBitcoinErr::NoPendingDeposit,
+        );
+
+        PendingDeposits::<Test>::insert(
+            &addr,
+            vec![
+                BtcDepositCache {
+                    txid: H256::repeat_byte(1),
+                    balance: 100000,
+                },
+                BtcDepositCache {
+                    txid: H256::repeat_byte(2),
+                    balance: 200000,
+                },
+            ],
+        );
+
+        // mainnet address of the same key is not allowed in testnet
+        assert_noop!(
+            XGatewayBitcoin::claim_pending_deposit(
+                frame_system::RawOrigin::Signed(alice()).into(),
+                b"12YyzFX4dDUNJ9xHHhdXCe2mp37NojukaA".to_vec(),
+                signature.clone(),
+                2
+            ),
+            XGatewayBitcoinErr::InvalidAddress,
+        );
+        // the signature is not for Bob
+        assert_noop!(
+            XGatewayBitcoin::claim_pending_deposit(
+                frame_system::RawOrigin::Signed(bob()).into(),
+                addr.clone(),
+                signature.clone(),
+                2
+            ),
+            XGatewayBitcoinErr::InvalidSignedMessage,
+        );
+
+        // the witness count must cover all the pending deposits
+        assert_noop!(
+            XGatewayBitcoin::claim_pending_deposit(
+                frame_system::RawOrigin::Signed(alice()).into(),
+                addr.clone(),
+                signature.clone(),
+                1
+            ),
+            XGatewayBitcoinErr::InvalidDepositCount,
+        );
+
+        assert_ok!(XGatewayBitcoin::claim_pending_deposit(
+            frame_system::RawOrigin::Signed(alice()).into(),
+            addr.clone(),
+            signature,
+            2
+        ));
+        assert!(XGatewayBitcoin::pending_deposits(&addr).is_empty());
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 300000);
+        assert_eq!(
+            xpallet_gateway_common::AddressBindingOf::<Test>::get(Chain::Bitcoin, &addr),
+            Some(alice())
+        );
+    });
+}
+
+#[test]
+fn test_expire_pending_deposits() {
+    ExtBuilder::default().build_and_execute(|| {
+        let addr = b"mqyq7PNg9NzCxnHGDjP8HXcLWz7ReASKY3".to_vec();
+        let best_height = XGatewayBitcoin::best_index().height;
+        let txid1 = H256::repeat_byte(1);
+        let txid2 = H256::repeat_byte(2);
+        PendingDeposits::<Test>::insert(
+            &addr,
+            vec![
+                BtcDepositCache {
+                    txid: txid1,
+                    balance: 100000,
+                },
+                BtcDepositCache {
+                    txid: txid2,
+                    balance: 200000,
+                },
+            ],
+        );
+        PendingDepositHeight::<Test>::insert(&txid1, best_height - 10);
+        PendingDepositHeight::<Test>::insert(&txid2, best_height - 5);
+
+        // never expire by default
+        assert_noop!(
+            XGatewayBitcoin::expire_pending_deposits(
+                frame_system::RawOrigin::Root.into(),
+                addr.clone()
+            ),
+            XGatewayBitcoinErr::NoExpiredDeposit,
+        );
+        assert_ok!(XGatewayBitcoin::set_pending_deposit_expiry(
+            frame_system::RawOrigin::Root.into(),
+            10
+        ));
+        assert_ok!(XGatewayBitcoin::expire_pending_deposits(
+            frame_system::RawOrigin::Root.into(),
+            addr.clone()
+        ));
+        assert_eq!(
+            XGatewayBitcoin::pending_deposits(&addr),
+            vec![BtcDepositCache {
+                txid: txid2,
+                balance: 200000,
+            }]
+        );
+        assert_eq!(
+            XGatewayBitcoin::expired_deposits(&addr),
+            vec![BtcDepositCache {
+                txid: txid1,
+                balance: 100000,
+            }]
+        );
+        assert_eq!(XGatewayBitcoin::pending_deposit_height(&txid1), None);
+        assert_noop!(
+            XGatewayBitcoin::expire_pending_deposits(
+                frame_system::RawOrigin::Root.into(),
+                addr.clone()
+            ),
+            XGatewayBitcoinErr::NoExpiredDeposit,
+        );
+
+        // refund output to the p2pkh address of the sender
+        let refund_tx = |value: u64| Transaction {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![TransactionOutput {
+                value,
+                script_pubkey: hex::decode("76a91472c67df1c505f4e434f98210cb1fe8e20840f8a188ac")
+                    .unwrap()
+                    .into(),
+            }],
+            lock_time: 0,
+        };
+        assert_eq!(
+            check_withdraw_tx_impl::<Test>(&refund_tx(100000), &[]),
+            Ok(vec![addr.clone()])
+        );
+        assert_eq!(
+            check_withdraw_tx_impl::<Test>(&refund_tx(300000), &[]),
+            Err(XGatewayBitcoinErr::InvalidProposal.into())
+        );
+
+        // the expired deposits could not be changed during refunding
+        ProposalRefunds::<Test>::insert(0, vec![addr.clone()]);
+        PendingDepositHeight::<Test>::insert(&txid2, best_height - 10);
+        assert_noop!(
+            XGatewayBitcoin::expire_pending_deposits(
+                frame_system::RawOrigin::Root.into(),
+                addr.clone()
+            ),
+            XGatewayBitcoinErr::RefundInProgress,
+        );
+    });
+}
+
+#[test]
+fn test_expire_legacy_and_dust_deposits() {
+    ExtBuilder::default().build_and_execute(|| {
+        let addr = b"mqyq7PNg9NzCxnHGDjP8HXcLWz7ReASKY3".to_vec();
+        let best_height = XGatewayBitcoin::best_index().height;
+        let txid = H256::repeat_byte(3);
+        // the legacy pending deposit without inclusion height
+        PendingDeposits::<Test>::insert(
+            &addr,
+            vec![BtcDepositCache {
+                txid,
+                balance: 1000,
+            }],
+        );
+        assert_ok!(XGatewayBitcoin::set_pending_deposit_expiry(
+            frame_system::RawOrigin::Root.into(),
+            10
+        ));
+        assert_noop!(
+            XGatewayBitcoin::expire_pending_deposits(
+                frame_system::RawOrigin::Root.into(),
+                addr.clone()
+            ),
+            XGatewayBitcoinErr::NoExpiredDeposit,
+        );
+
+        // the expiry of legacy deposits starts from the upgrade
+        migrate_pending_deposit_height::<Test>();
+        assert_eq!(
+            XGatewayBitcoin::pending_deposit_height(&txid),
+            Some(best_height)
+        );
+        PendingDepositHeight::<Test>::insert(&txid, best_height - 10);
+
+        // the refund could not cover the withdrawal fee
+        assert_ok!(XGatewayBitcoin::set_btc_withdrawal_fee(
+            frame_system::RawOrigin::Root.into(),
+            1000
+        ));
+        assert_noop!(
+            XGatewayBitcoin::expire_pending_deposits(
+                frame_system::RawOrigin::Root.into(),
+                addr.clone()
+            ),
+            XGatewayBitcoinErr::DustRefund,
+        );
+        assert_ok!(XGatewayBitcoin::set_btc_withdrawal_fee(
+            frame_system::RawOrigin::Root.into(),
+            500
+        ));
+        assert_ok!(XGatewayBitcoin::expire_pending_deposits(
+            frame_system::RawOrigin::Root.into(),
+            addr.clone()
+        ));
+        assert!(XGatewayBitcoin::pending_deposits(&addr).is_empty());
+        assert_eq!(XGatewayBitcoin::expired_deposits(&addr).len(), 1);
+    });
+}
+
+#[test]
+fn test_verify_taproot_signature() {
+    use crate::{
+        tx::secp256k1_verifier::{taproot::signature_hash, verify_taproot_sig},
+        types::BtcTxVerifier,
+        Verifier,
+    };
+
+    // (tx, prev_tx, BIP-341 sighash of the spending)
+    let cases = [
+        (
+            &*deposit_taproot1,
+            &*deposit_taproot1_prev,
+            "4e4b74061db8ecb149b41b0214d684e62e725569060f8b8d98356ae12dc18790",
+        ),
+        (
+            &*withdraw_taproot1,
+            &*withdraw_taproot1_prev,
+            "72f7ed5a3aaa836fc9f3e6be788e335aebc9fab8dde65dee2c2223e35b827a51",
+        ),
+        (
+            &*deposit_taproot2,
+            &*deposit_taproot2_prev,
+            "96b37172b00a418004e6632c6d73a0b867ab6986b1fc470ff1432840a361edd3",
+        ),
+        (
+            &*withdraw_taproot2,
+            &*withdraw_taproot2_prev,
+            "69d5ef6bc30514a0ff08b60ef123420740fefd5ccffd54c949b6cc37e047e8fb",
+        ),
+        (
+            &*hot_to_cold,
+            &*hot_to_cold_prev,
+            "f1a44ffaf1b5971097901ab2f2d869257a345183699f30531d0538183a541307",
+        ),
+    ];
+    ExtBuilder::default().build_and_execute(|| {
+        for (tx, prev_tx, sighash) in cases.iter() {
+            let input = &tx.inputs[0];
+            let spent = vec![prev_tx.outputs[input.previous_output.index as usize].clone()];
+
+            let witness = &input.script_witness;
+            let leaf_hash = if witness.len() == 1 {
+                None
+            } else {
+                // the tapleaf hash of the script in the witness, with the tapscript leaf version
+                let mut leaf = vec![0xc0, witness[1].len() as u8];
+                leaf.extend_from_slice(&witness[1]);
+                let tag = sp_io::hashing::sha2_256(b"TapLeaf");
+                Some(sp_io::hashing::sha2_256(
+                    &[&tag[..], &tag[..], &leaf[..]].concat(),
+                ))
+            };
+            assert_eq!(
+                hex::encode(signature_hash(tx, &spent, 0, leaf_hash.as_ref())),
+                *sighash
+            );
+
+            // the spending with another output would change the sighash
+            let mut tampered = (*tx).clone();
+            tampered.outputs[0].value += 1;
+
+            for verifier in &[BtcTxVerifier::Recover, BtcTxVerifier::RuntimeInterface] {
+                Verifier::<Test>::put(*verifier);
+                assert_ok!(verify_taproot_sig::<Test>(tx, &spent));
+                assert_noop!(
+                    verify_taproot_sig::<Test>(&tampered, &spent),
+                    XGatewayBitcoinErr::VerifySignFailed
+                );
+            }
+        }
+    });
+}
+
+#[test]
+fn test_withdraw_fee_rate() {
+    ExtBuilder::default().build_and_execute(|| {
+        let outpoint = withdraw_taproot1.inputs[0].previous_output.clone();
+        let hot_utxo = |value: u64| BtcTrusteeUtxo {
+            value,
+            addr: DEPOSIT_HOT_ADDR.as_bytes().to_vec(),
+        };
+        // fee: 100000 - 90000 = 10000, vsize: ceil((137 * 3 + 338) / 4) = 188
+        let vsize = tx_vsize(&withdraw_taproot1);
+        assert_eq!(vsize, 188);
+
+        // the input is not a trustee utxo
+        assert_noop!(
+            check_withdraw_fee_rate::<Test>(&withdraw_taproot1, vsize),
+            XGatewayBitcoinErr::UnknownTrusteeUtxo,
+        );
+        TrusteeUtxos::<Test>::insert(outpoint.txid, outpoint.index, hot_utxo(100000));
+
+        // no band by default
+        assert_ok!(check_withdraw_fee_rate::<Test>(&withdraw_taproot1, vsize));
+
+        assert_noop!(
+            XGatewayBitcoin::set_withdrawal_fee_rate(frame_system::RawOrigin::Root.into(), 60, 50),
+            XGatewayBitcoinErr::InvalidFeeRateBand,
+        );
+        assert_ok!(XGatewayBitcoin::set_withdrawal_fee_rate(
+            frame_system::RawOrigin::Root.into(),
+            50,
+            60
+        ));
+        assert_ok!(check_withdraw_fee_rate::<Test>(&withdraw_taproot1, vsize));
+
+        // fee rate is too high
+        assert_ok!(XGatewayBitcoin::set_withdrawal_fee_rate(
+            frame_system::RawOrigin::Root.into(),
+            1,
+            50
+        ));
+        assert_noop!(
+            check_withdraw_fee_rate::<Test>(&withdraw_taproot1, vsize),
+            XGatewayBitcoinErr::FeeRateOutOfBand,
+        );
+        // fee rate is too low
+        assert_ok!(XGatewayBitcoin::set_withdrawal_fee_rate(
+            frame_system::RawOrigin::Root.into(),
+            60,
+            0
+        ));
+        assert_noop!(
+            check_withdraw_fee_rate::<Test>(&withdraw_taproot1, vsize),
+            XGatewayBitcoinErr::FeeRateOutOfBand,
+        );
+
+        // the value of trustee utxos is less than the outputs
+        TrusteeUtxos::<Test>::insert(outpoint.txid, outpoint.index, hot_utxo(80000));
+        assert_noop!(
+            check_withdraw_fee_rate::<Test>(&withdraw_taproot1, vsize),
+            XGatewayBitcoinErr::InvalidSpentOutputs,
+        );
+    });
+}
+
+#[test]
+fn test_fee_bump_tx() {
+    ExtBuilder::default().build_and_execute(|| {
+        // outputs: [payee, change to hot trustee address]
+        let original: Transaction = withdraw_taproot1.clone();
+
+        // reduce the change for a higher fee
+        let mut bumped = original.clone();
+        bumped.outputs[1].value -= 1000;
+        assert_ok!(check_fee_bump_tx::<Test>(&original, &bumped));
+
+        // the fee is not bumped
+        assert_noop!(
+            check_fee_bump_tx::<Test>(&original, &original),
+            XGatewayBitcoinErr::InvalidFeeBump
+        );
+        // the change is increased
+        let mut tx = original.clone();
+        tx.outputs[1].value += 1000;
+        assert_noop!(
+            check_fee_bump_tx::<Test>(&original, &tx),
+            XGatewayBitcoinErr::InvalidFeeBump
+        );
+        // the payee output is changed
+        let mut tx = bumped.clone();
+        tx.outputs[0].value -= 1000;
+        assert_noop!(
+            check_fee_bump_tx::<Test>(&original, &tx),
+            XGatewayBitcoinErr::InvalidFeeBump
+        );
+        // spend different inputs
+        let mut tx = bumped.clone();
+        tx.inputs[0].previous_output.index += 1;
+        assert_noop!(
+            check_fee_bump_tx::<Test>(&original, &tx),
+            XGatewayBitcoinErr::InvalidFeeBump
+        );
+        // drop an output
+        let mut tx = bumped.clone();
+        tx.outputs.pop();
+        assert_noop!(
+            check_fee_bump_tx::<Test>(&original, &tx),
+            XGatewayBitcoinErr::InvalidFeeBump
+        );
+        // the original tx does not signal replaceability
+        let mut final_tx = original;
+        final_tx.inputs[0].sequence = 0xffff_fffe;
+        assert_noop!(
+            check_fee_bump_tx::<Test>(&final_tx, &bumped),
+            XGatewayBitcoinErr::NotReplaceable
+        );
+    });
+}
+
+#[test]
+fn test_withdraw_fee_bump_tx() {
+    ExtBuilder::default().build_and_execute(|| {
+        let mut bumped: Transaction = withdraw_taproot1.clone();
+        bumped.outputs[1].value -= 1000;
+
+        let proposal = BtcWithdrawalProposal::new(
+            VoteResult::Finish,
+            vec![],
+            withdraw_taproot1.clone(),
+            vec![],
+        );
+        let fee_bump = BtcWithdrawalProposal::new(VoteResult::Finish, vec![], bumped, vec![]);
+        WithdrawalProposals::<Test>::insert(0, proposal);
+        FeeBumpProposals::<Test>::insert(0, fee_bump.clone());
+
+        // bump the fee again is not allowed
+        assert_noop!(
+            XGatewayBitcoin::apply_bump_withdrawal_fee(
+                alice(),
+                0,
+                fee_bump.tx.clone(),
+                vec![withdraw_taproot1_prev.outputs[0].clone()]
+            ),
+            XGatewayBitcoinErr::FeeBumpInProgress
+        );
+
+        // the fee bump tx is observed on-chain
+        assert_eq!(
+            crate::tx::withdraw::<Test>(fee_bump.tx),
+            BtcTxResult::Success
+        );
+        assert_eq!(XGatewayBitcoin::withdrawal_proposal(0), None);
+        assert_eq!(XGatewayBitcoin::fee_bump_proposal(0), None);
+    });
+}
+
+#[test]
+fn test_concurrent_withdrawal_proposals() {
+    ExtBuilder::default().build_and_execute(|| {
+        let proposal = |withdrawal_id_list: Vec<u32>, tx: &Transaction| {
+            BtcWithdrawalProposal::new(VoteResult::Finish, withdrawal_id_list, tx.clone(), vec![])
+        };
+        let empty_tx = Transaction {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![],
+            lock_time: 0,
+        };
+        WithdrawalProposals::<Test>::insert(0, proposal(vec![1], &withdraw_taproot1));
+        WithdrawalProposals::<Test>::insert(1, proposal(vec![2], &withdraw_taproot2));
+
+        assert_eq!(check_withdraw_tx::<Test>(&empty_tx, &[]), Ok(vec![]));
+        // the withdrawal application is in other proposal
+        assert_noop!(
+            check_withdraw_tx::<Test>(&empty_tx, &[2]),
+            XGatewayBitcoinErr::OverlappingWithdrawals
+        );
+        // the inputs are spent by other proposal
+        assert_noop!(
+            check_withdraw_tx::<Test>(&withdraw_taproot1, &[]),
+            XGatewayBitcoinErr::ConflictingInputs
+        );
+
+        // match the withdrawal tx to the correct proposal
+        assert_eq!(
+            crate::tx::withdraw::<Test>(withdraw_taproot2.clone()),
+            BtcTxResult::Success
+        );
+        assert_eq!(XGatewayBitcoin::withdrawal_proposal(1), None);
+        assert_eq!(
+            XGatewayBitcoin::withdrawal_proposal(0),
+            Some(proposal(vec![1], &withdraw_taproot1))
+        );
+        // no proposal matches the withdrawal tx
+        assert_eq!(
+            crate::tx::withdraw::<Test>(withdraw_taproot2.clone()),
+            BtcTxResult::Failure
+        );
+
+        // the count of proposals in flight is limited
+        for id in 1..crate::MAX_WITHDRAWAL_PROPOSALS {
+            WithdrawalProposals::<Test>::insert(id, proposal(vec![], &empty_tx));
+        }
+        assert_noop!(
+            check_withdraw_tx::<Test>(&empty_tx, &[]),
+            XGatewayBitcoinErr::TooManyProposals
+        );
+    });
+}
+
+#[test]
+fn test_trustee_utxos() {
+    ExtBuilder::default().build_and_execute(|| {
+        let hot_utxo = |value: u64| BtcTrusteeUtxo {
+            value,
+            addr: DEPOSIT_HOT_ADDR.as_bytes().to_vec(),
+        };
+        // the trustee utxo set of a new chain is initialized
+        assert!(XGatewayBitcoin::trustee_utxos_initialized());
+        // the input of withdrawal tx is not a trustee utxo
+        assert_noop!(
+            check_withdraw_tx::<Test>(&withdraw_taproot1, &[]),
+            XGatewayBitcoinErr::UnknownTrusteeUtxo
+        );
+        // the utxo check is skipped until the set is initialized on an upgraded chain
+        assert_ok!(XGatewayBitcoin::set_trustee_utxos_initialized(
+            frame_system::RawOrigin::Root.into(),
+            false
+        ));
+        assert_noop!(
+            check_withdraw_tx::<Test>(&withdraw_taproot1, &[]),
+            XGatewayBitcoinErr::InvalidProposal
+        );
+        assert_ok!(XGatewayBitcoin::set_trustee_utxos_initialized(
+            frame_system::RawOrigin::Root.into(),
+            true
+        ));
+
+        // deposit to the hot address
+        let r = mock_process_tx::<Test>(
+            deposit_taproot1.clone(),
+            vec![deposit_taproot1_prev.clone()],
+        );
+        assert_eq!(r.result, BtcTxResult::Success);
+        assert_eq!(
+            XGatewayBitcoin::trustee_utxos(),
+            vec![(deposit_taproot1.hash(), 0, hot_utxo(100000))]
+        );
+        // pass the utxo check, but the output does not match any withdrawal application
+        assert_noop!(
+            check_withdraw_tx::<Test>(&withdraw_taproot1, &[]),
+            XGatewayBitcoinErr::InvalidProposal
+        );
+
+        // withdraw spends the deposit, and the change is back to the hot address
+        WithdrawalProposals::<Test>::insert(
+            0,
+            BtcWithdrawalProposal::new(
+                VoteResult::Finish,
+                vec![],
+                withdraw_taproot1.clone(),
+                vec![],
+            ),
+        );
+        let r = mock_process_tx::<Test>(
+            withdraw_taproot1.clone(),
+            vec![withdraw_taproot1_prev.clone()],
+        );
+        assert_eq!(r.result, BtcTxResult::Success);
+        assert_eq!(
+            XGatewayBitcoin::trustee_utxos(),
+            vec![(withdraw_taproot1.hash(), 1, hot_utxo(40000))]
+        );
+
+        // root could fix the trustee utxo set
+        assert_ok!(XGatewayBitcoin::set_trustee_utxo(
+            frame_system::RawOrigin::Root.into(),
+            withdraw_taproot1.hash(),
+            1,
+            None
+        ));
+        assert_eq!(XGatewayBitcoin::trustee_utxos(), vec![]);
+    });
+}
+
+#[test]
+fn test_proof_of_reserves() {
+    ExtBuilder::default().build_and_execute(|| {
+        assert_eq!(
+            XGatewayBitcoin::proof_of_reserves(),
+            BtcProofOfReserves {
+                trustee_utxos_initialized: true,
+                ..Default::default()
+            }
+        );
+
+        // the pending deposit is held by the hot address
+        let r = mock_process_tx::<Test>(
+            deposit_taproot1.clone(),
+            vec![deposit_taproot1_prev.clone()],
+        );
+        assert_eq!(r.result, BtcTxResult::Success);
+        assert_eq!(
+            XGatewayBitcoin::proof_of_reserves(),
+            BtcProofOfReserves {
+                total_issuance: 0,
+                trustee_balance: 100000,
+                pending_deposits: 100000,
+                pending_withdrawals: 0,
+                trustee_utxos_initialized: true,
+                surplus: 0,
+                deficit: 0,
+            }
+        );
+
+        // X-BTC issued without BTC held by trustees
+        assert_ok!(XGatewayRecords::deposit(&alice(), X_BTC, 50000));
+        assert_ok!(XGatewayRecords::withdraw(
+            &alice(),
+            X_BTC,
+            20000,
+            b"tb1pexff2s7l58sthpyfrtx500ax234stcnt0gz2lr4kwe0ue95a2e0srxsc68".to_vec(),
+            b"".to_vec().into()
+        ));
+        assert_eq!(
+            XGatewayBitcoin::proof_of_reserves(),
+            BtcProofOfReserves {
+                total_issuance: 50000,
+                trustee_balance: 100000,
+                pending_deposits: 100000,
+                pending_withdrawals: 20000,
+                trustee_utxos_initialized: true,
+                surplus: 0,
+                deficit: 50000,
+            }
+        );
+
+        // more BTC held by trustees than the liabilities
+        assert_ok!(XGatewayBitcoin::set_trustee_utxo(
+            frame_system::RawOrigin::Root.into(),
+            H256::repeat_byte(1),
+            0,
+            Some(BtcTrusteeUtxo {
+                value: 80000,
+                addr: DEPOSIT_HOT_ADDR.as_bytes().to_vec(),
+            })
+        ));
+        assert_eq!(XGatewayBitcoin::proof_of_reserves().surplus, 30000);
+        assert_eq!(XGatewayBitcoin::proof_of_reserves().deficit, 0);
+
+        // no surplus or deficit before the trustee utxo set is initialized
+        assert_ok!(XGatewayBitcoin::set_trustee_utxos_initialized(
+            frame_system::RawOrigin::Root.into(),
+            false
+        ));
+        let reserves = XGatewayBitcoin::proof_of_reserves();
+        assert!(!reserves.trustee_utxos_initialized);
+        assert_eq!(reserves.trustee_balance, 180000);
+        assert_eq!((reserves.surplus, reserves.deficit), (0, 0));
+    });
+}
+
+#[test]
+fn test_deposit_action() {
+    use codec::Encode;
+    use frame_support::sp_io;
+    use xp_gateway_bitcoin::DepositAction;
+
+    ExtBuilder::default().build_and_execute(|| {
+        assert_ok!(XGatewayRecords::deposit(&alice(), X_BTC, 50000));
+        let txid = H256::repeat_byte(1);
+
+        // the action is performed
+        let buy_pcx = DepositAction::BuyPcx {
+            pair_id: 0,
+            amount: 50000,
+        };
+        deposit_action::<Test>(txid, &alice(), 50000, buy_pcx.clone());
+        assert_eq!(
+            sp_io::storage::get(DEPOSIT_ACTION_KEY),
+            Some(buy_pcx.encode())
+        );
+        sp_io::storage::clear(DEPOSIT_ACTION_KEY);
+
+        // the changes of a failed action are reverted, but the deposit is kept
+        let overpaid = DepositAction::BuyPcx {
+            pair_id: 0,
+            amount: 50001,
+        };
+        deposit_action::<Test>(txid, &alice(), 50000, overpaid);
+        assert_eq!(sp_io::storage::get(DEPOSIT_ACTION_KEY), None);
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 50000);
+
+        // the overweight action is never performed
+        let overweight = DepositAction::BuyPcx {
+            pair_id: 1,
+            amount: 100,
+        };
+        deposit_action::<Test>(txid, &alice(), 50000, overweight);
+        assert_eq!(sp_io::storage::get(DEPOSIT_ACTION_KEY), None);
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 50000);
+    });
+}
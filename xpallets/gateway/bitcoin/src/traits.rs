@@ -0,0 +1,110 @@
+// Copyright 2019-2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+use sp_runtime::{
+    traits::{Saturating, Zero},
+    SaturatedConversion,
+};
+use sp_std::marker::PhantomData;
+
+use frame_support::{
+    dispatch::{DispatchError, DispatchResult},
+    ensure,
+    weights::Weight,
+};
+use frame_system::RawOrigin;
+
+use light_bitcoin::primitives::H256;
+use xp_gateway_bitcoin::DepositAction;
+use xp_protocol::{PCX, X_BTC};
+use xpallet_assets::{BalanceOf, Pallet as XAssets};
+use xpallet_dex_spot::{OrderStatus, OrderType, Pallet as XSpot, Side, WeightInfo as _};
+
+/// Hooks for performing the follow-up action of a deposit after X-BTC has been minted.
+pub trait OnBtcDeposited<AccountId, Balance> {
+    /// Returns the weight of performing the `action`.
+    ///
+    /// The action would be rejected if the weight exceeds `MaxDepositActionWeight`.
+    fn weight(_action: &DepositAction) -> Weight {
+        0
+    }
+
+    /// Triggered after `who` got `value` X-BTC from the deposit transaction `txid`.
+    ///
+    /// All the changes would be reverted if it returns an error, but the minted X-BTC is kept.
+    fn on_deposited(
+        _txid: H256,
+        _who: &AccountId,
+        _value: Balance,
+        _action: &DepositAction,
+    ) -> DispatchResult {
+        Err(DispatchError::Other("Unsupported deposit action"))
+    }
+}
+
+impl<AccountId, Balance> OnBtcDeposited<AccountId, Balance> for () {}
+
+/// Performs the follow-up action carried by the memo of X-BTC deposit, e.g. buying some PCX for
+/// the transaction fees in the DEX. The action could only spend the deposited X-BTC, since anyone
+/// could deposit to any account with a memo.
+pub struct DepositActionHandler<T>(PhantomData<T>);
+
+impl<T> OnBtcDeposited<T::AccountId, BalanceOf<T>> for DepositActionHandler<T>
+where
+    T: crate::Config + xpallet_dex_spot::Config,
+{
+    fn weight(action: &DepositAction) -> Weight {
+        match action {
+            DepositAction::BuyPcx { .. } => {
+                <T as xpallet_dex_spot::Config>::WeightInfo::put_order()
+                    .saturating_add(<T as xpallet_dex_spot::Config>::WeightInfo::cancel_order())
+            }
+        }
+    }
+
+    fn on_deposited(
+        _txid: H256,
+        who: &T::AccountId,
+        value: BalanceOf<T>,
+        action: &DepositAction,
+    ) -> DispatchResult {
+        match *action {
+            DepositAction::BuyPcx { pair_id, amount } => {
+                let pair = XSpot::<T>::trading_pair_of(pair_id)
+                    .ok_or("Invalid trading pair of deposit")?;
+                ensure!(
+                    pair.base() == PCX && pair.quote() == X_BTC,
+                    "Deposit could only buy PCX with X-BTC"
+                );
+                // buy at the lowest ask like a market order
+                let price = XSpot::<T>::handicap_of(pair_id).lowest_ask;
+                ensure!(!price.is_zero(), "No ask of PCX");
+
+                let before = XAssets::<T>::usable_balance(who, &X_BTC);
+                let order_id = XSpot::<T>::order_count_of(who);
+                XSpot::<T>::put_order(
+                    RawOrigin::Signed(who.clone()).into(),
+                    pair_id,
+                    OrderType::Limit,
+                    Side::Buy,
+                    amount.saturated_into(),
+                    price,
+                )?;
+                // immediate or cancel, the remainder which is not filled at the lowest ask is
+                // canceled rather than resting in the order book
+                let resting = XSpot::<T>::order_info_of(who, order_id).map_or(false, |order| {
+                    order.status == OrderStatus::Created || order.status == OrderStatus::PartialFill
+                });
+                if resting {
+                    XSpot::<T>::cancel_order(
+                        RawOrigin::Signed(who.clone()).into(),
+                        pair_id,
+                        order_id,
+                    )?;
+                }
+                let paid = before.saturating_sub(XAssets::<T>::usable_balance(who, &X_BTC));
+                ensure!(paid <= value, "Deposit is insufficient to buy PCX");
+                Ok(())
+            }
+        }
+    }
+}
@@ -5,8 +5,11 @@ use alloc::string::ToString;
 use frame_support::{
     dispatch::{DispatchError, DispatchResult},
     ensure,
+    storage::migration::take_storage_value,
+    traits::{Get, PalletInfoAccess},
+    weights::Weight,
 };
-use sp_runtime::SaturatedConversion;
+use sp_runtime::{traits::Saturating, SaturatedConversion};
 use sp_std::{
     cmp::max,
     convert::{TryFrom, TryInto},
@@ -16,10 +19,11 @@ use sp_std::{
 use light_bitcoin::{
     chain::{Transaction, TransactionOutput},
     crypto::dhash160,
-    keys::{Address, AddressTypes, Public, Type},
+    keys::{Address, AddressTypes, Network, Public, Type},
     mast::{compute_min_threshold, Mast},
-    primitives::Bytes,
+    primitives::{hash_rev, Bytes},
     script::{Builder, Opcode, Script},
+    serialization::{serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS},
 };
 
 use xp_gateway_bitcoin::extract_output_addr;
@@ -27,16 +31,18 @@ use xpallet_assets::Chain;
 use xpallet_gateway_common::{
     traits::{TrusteeForChain, TrusteeSession},
     trustees::bitcoin::{BtcTrusteeAddrInfo, BtcTrusteeType},
-    types::{TrusteeInfoConfig, TrusteeIntentionProps, TrusteeSessionInfo},
+    types::{TrusteeInfoConfig, TrusteeIntentionProps, TrusteeOffence, TrusteeSessionInfo},
     utils::two_thirds_unsafe,
 };
 
 use crate::tx::validator::parse_check_taproot_tx;
 use crate::{
     log,
-    tx::{ensure_identical, validator::parse_and_check_signed_tx},
-    types::{BtcWithdrawalProposal, VoteResult},
-    Config, Error, Event, Pallet, WithdrawalProposal,
+    tx::{ensure_identical, secp256k1_verifier::verify_sig, validator::parse_and_check_signed_tx},
+    types::{BtcAddress, BtcWithdrawalProposal, VoteResult},
+    Config, Error, Event, ExpiredDeposits, FeeBumpProposals, NextProposalId, Pallet,
+    ProposalRefunds, ProposalSessions, ProposalVoteDeadlines, StalledProposals, TrusteeUtxos,
+    WithdrawalProposals, MAX_WITHDRAWAL_PROPOSALS, PROPOSAL_VOTE_TIMEOUT,
 };
 
 pub fn current_trustee_session<T: Config>(
@@ -208,7 +214,7 @@ impl<T: Config> TrusteeForChain<T::AccountId, BtcTrusteeType, BtcTrusteeAddrInfo
 
         let threshold_addr: Address = Mast::new(pks, sig_num as usize)
             .map_err(|_| Error::<T>::InvalidAddress)?
-            .generate_address(&Pallet::<T>::network_id().to_string())
+            .generate_address(&Network::from(Pallet::<T>::network_id()).to_string())
             .map_err(|_| Error::<T>::InvalidAddress)?
             .parse()
             .map_err(|_| Error::<T>::InvalidAddress)?;
@@ -280,13 +286,17 @@ impl<T: Config> Pallet<T> {
         withdrawal_id_list.sort_unstable();
         withdrawal_id_list.dedup();
 
-        check_withdraw_tx::<T>(&tx, &withdrawal_id_list)?;
+        let refunds = check_withdraw_tx::<T>(&tx, &withdrawal_id_list)?;
         log!(
             info,
             "[apply_create_withdraw] Create new withdraw, id_list:{:?}",
             withdrawal_id_list
         );
 
+        // the tx is not fully signed yet, estimate the size after all trustees signed
+        let vsize = estimate_multisig_tx_vsize::<T>(&tx)?;
+        check_withdraw_fee_rate::<T>(&tx, vsize)?;
+
         // check sig
         let sigs_count = parse_and_check_signed_tx::<T>(&tx)?;
         let apply_sig = if sigs_count == 0 {
@@ -319,7 +329,9 @@ impl<T: Config> Pallet<T> {
             "[apply_create_withdraw] Pass the legality check of withdrawal"
         );
 
+        let proposal_id = Self::next_proposal_id();
         Self::deposit_event(Event::<T>::WithdrawalProposalCreated(
+            proposal_id,
             who.clone(),
             withdrawal_id_list,
         ));
@@ -331,14 +343,16 @@ impl<T: Config> Pallet<T> {
             );
             // due to `SignWithdrawalProposal` event should after `WithdrawalProposalCreated`, thus this function should after proposal
             // but this function would have an error return, this error return should not meet.
-            if insert_trustee_vote_state::<T>(true, &who, &mut proposal.trustee_list).is_err() {
+            if insert_trustee_vote_state::<T>(proposal_id, true, &who, &mut proposal.trustee_list)
+                .is_err()
+            {
                 // should not be error in this function, if hit this branch, panic to clear all modification
                 // TODO change to revoke in future
                 panic!("insert_trustee_vote_state should not be error")
             }
         }
 
-        WithdrawalProposal::<T>::put(proposal);
+        insert_proposal::<T>(proposal, refunds);
 
         Ok(())
     }
@@ -363,7 +377,7 @@ impl<T: Config> Pallet<T> {
         withdrawal_id_list.sort_unstable();
         withdrawal_id_list.dedup();
 
-        check_withdraw_tx::<T>(&tx, &withdrawal_id_list)?;
+        let refunds = check_withdraw_tx::<T>(&tx, &withdrawal_id_list)?;
         log!(
             info,
             "[apply_create_withdraw] Create new withdraw, id_list:{:?}",
@@ -375,6 +389,9 @@ impl<T: Config> Pallet<T> {
             return Err(Error::<T>::VerifySignFailed.into());
         };
 
+        // the tx is fully signed, and the signatures commit to the amounts of spent outputs
+        check_withdraw_fee_rate::<T>(&tx, tx_vsize(&tx))?;
+
         xpallet_gateway_records::Pallet::<T>::process_withdrawals(
             &withdrawal_id_list,
             Chain::Bitcoin,
@@ -393,138 +410,171 @@ impl<T: Config> Pallet<T> {
         );
 
         Self::deposit_event(Event::<T>::WithdrawalProposalCreated(
+            Self::next_proposal_id(),
             who,
             withdrawal_id_list,
         ));
 
-        WithdrawalProposal::<T>::put(proposal);
+        insert_proposal::<T>(proposal, refunds);
 
         Ok(())
     }
 
-    pub fn apply_sig_withdraw(who: T::AccountId, tx: Option<Transaction>) -> DispatchResult {
+    pub fn apply_sig_withdraw(
+        who: T::AccountId,
+        proposal_id: u32,
+        tx: Option<Transaction>,
+    ) -> DispatchResult {
         let mut proposal: BtcWithdrawalProposal<T::AccountId> =
-            Self::withdrawal_proposal().ok_or(Error::<T>::NoProposal)?;
+            Self::withdrawal_proposal(proposal_id).ok_or(Error::<T>::NoProposal)?;
 
-        if proposal.sig_state == VoteResult::Finish {
-            log!(error, "[apply_sig_withdraw] Proposal is on FINISH state, can't sign for this proposal:{:?}", proposal);
-            return Err(Error::<T>::RejectSig.into());
+        if let Some(reject_count) = vote_proposal::<T>(proposal_id, false, &who, tx, &mut proposal)?
+        {
+            // release withdrawal for applications
+            for id in proposal.withdrawal_id_list.iter() {
+                let _ =
+                    xpallet_gateway_records::Pallet::<T>::recover_withdrawal(*id, Chain::Bitcoin);
+            }
+
+            record_proposal_votes::<T>(proposal_id, false, &proposal.trustee_list, false);
+            WithdrawalProposals::<T>::remove(proposal_id);
+            // the expired deposits would be refunded in other proposal
+            ProposalRefunds::<T>::remove(proposal_id);
+            ProposalSessions::<T>::remove(proposal_id);
+
+            let (sig_num, _) = get_sig_num::<T>();
+            Self::deposit_event(Event::<T>::WithdrawalProposalDropped(
+                proposal_id,
+                reject_count,
+                sig_num,
+                proposal.withdrawal_id_list,
+            ));
+            return Ok(());
         }
 
-        let (sig_num, total) = get_sig_num::<T>();
-        match tx {
-            Some(tx) => {
-                // check this tx is same to proposal, just check input and output, not include sigs
-                ensure_identical::<T>(&tx, &proposal.tx)?;
+        WithdrawalProposals::<T>::insert(proposal_id, proposal);
+        Ok(())
+    }
 
-                // sign
-                // check first and get signatures from commit transaction
-                let sigs_count = parse_and_check_signed_tx::<T>(&tx)?;
-                if sigs_count == 0 {
-                    log!(error, "[apply_sig_withdraw] Tx sig should not be zero, zero is the source tx without any sig, tx{:?}", tx);
-                    return Err(Error::<T>::InvalidSignCount.into());
-                }
+    /// Create a proposal to replace the finished withdrawal proposal transaction with a higher
+    /// fee, the original proposal is kept until one of them is observed on-chain.
+    pub fn apply_bump_withdrawal_fee(
+        who: T::AccountId,
+        proposal_id: u32,
+        tx: Transaction,
+        spent_outputs: Vec<TransactionOutput>,
+    ) -> DispatchResult {
+        let proposal: BtcWithdrawalProposal<T::AccountId> =
+            Self::withdrawal_proposal(proposal_id).ok_or(Error::<T>::NoProposal)?;
+        ensure!(
+            proposal.sig_state == VoteResult::Finish,
+            Error::<T>::UnfinishedProposal
+        );
+        ensure!(
+            Self::fee_bump_proposal(proposal_id).is_none(),
+            Error::<T>::FeeBumpInProgress
+        );
 
-                let confirmed_count = proposal
-                    .trustee_list
-                    .iter()
-                    .filter(|(_, vote)| *vote)
-                    .count() as u32;
-
-                if sigs_count != confirmed_count + 1 {
-                    log!(
-                        error,
-                        "[apply_sig_withdraw] Need to sign on the latest signature results, sigs count:{}, confirmed count:{}",
-                        sigs_count, confirmed_count
-                    );
-                    return Err(Error::<T>::InvalidSignCount.into());
-                }
+        check_fee_bump_tx::<T>(&proposal.tx, &tx)?;
+
+        let hot_trustee_address = get_hot_trustee_address::<T>()?;
+        let fee_bump = if hot_trustee_address.kind == Type::P2TR {
+            // the threshold signature of taproot commits to the amounts of spent outputs
+            if parse_check_taproot_tx::<T>(&tx, &spent_outputs).is_err() {
+                return Err(Error::<T>::VerifySignFailed.into());
+            };
+            check_withdraw_fee_rate::<T>(&tx, tx_vsize(&tx))?;
+            BtcWithdrawalProposal::new(
+                VoteResult::Finish,
+                proposal.withdrawal_id_list,
+                tx.clone(),
+                Vec::new(),
+            )
+        } else {
+            let vsize = estimate_multisig_tx_vsize::<T>(&tx)?;
+            check_withdraw_fee_rate::<T>(&tx, vsize)?;
+            // trustees must sign the new transaction again
+            let sigs_count = parse_and_check_signed_tx::<T>(&tx)?;
+            ensure!(sigs_count == 0, Error::<T>::InvalidSignCount);
+            BtcWithdrawalProposal::new(
+                VoteResult::Unfinish,
+                proposal.withdrawal_id_list,
+                tx.clone(),
+                Vec::new(),
+            )
+        };
 
-                insert_trustee_vote_state::<T>(true, &who, &mut proposal.trustee_list)?;
-                // check required count
-                // required count should be equal or more than (2/3)*total
-                // e.g. total=6 => required=2*6/3=4, thus equal to 4 should mark as finish
-                if sigs_count == sig_num {
-                    // mark as finish, can't do anything for this proposal
-                    log!(
-                        info,
-                        "[apply_sig_withdraw] Signature completed:{}",
-                        sigs_count
-                    );
-                    proposal.sig_state = VoteResult::Finish;
+        log!(
+            info,
+            "[apply_bump_withdrawal_fee] Create fee bump, original tx:{:?}, new tx:{:?}",
+            proposal.tx.hash(),
+            tx.hash()
+        );
+        Self::deposit_event(Event::<T>::WithdrawalFeeBumpCreated(
+            proposal_id,
+            who,
+            proposal.tx.hash(),
+            tx.hash(),
+        ));
+        if fee_bump.sig_state == VoteResult::Finish {
+            Self::deposit_event(Event::<T>::WithdrawalProposalCompleted(
+                proposal_id,
+                tx.hash(),
+            ));
+        }
 
-                    Self::deposit_event(Event::<T>::WithdrawalProposalCompleted(tx.hash()))
-                } else {
-                    proposal.sig_state = VoteResult::Unfinish;
-                }
-                // update tx
-                proposal.tx = tx;
-            }
-            None => {
-                // reject
-                insert_trustee_vote_state::<T>(false, &who, &mut proposal.trustee_list)?;
+        if fee_bump.sig_state != VoteResult::Finish {
+            schedule_vote_deadline::<T>(proposal_id, true);
+        }
+        FeeBumpProposals::<T>::insert(proposal_id, fee_bump);
+        Ok(())
+    }
 
-                let reject_count = proposal
-                    .trustee_list
-                    .iter()
-                    .filter(|(_, vote)| !(*vote))
-                    .count() as u32;
-
-                // reject count just need  < (total-required) / total
-                // e.g. total=6 => required=2*6/3=4, thus, reject should more than (6-4) = 2
-                // > 2 equal to total - required + 1 = 6-4+1 = 3
-                let need_reject = total - sig_num + 1;
-                if reject_count == need_reject {
-                    log!(
-                        info,
-                        "[apply_sig_withdraw] {}/{} opposition, clear withdrawal proposal",
-                        reject_count,
-                        total
-                    );
+    pub fn apply_sig_fee_bump(
+        who: T::AccountId,
+        proposal_id: u32,
+        tx: Option<Transaction>,
+    ) -> DispatchResult {
+        let mut fee_bump: BtcWithdrawalProposal<T::AccountId> =
+            Self::fee_bump_proposal(proposal_id).ok_or(Error::<T>::NoProposal)?;
 
-                    // release withdrawal for applications
-                    for id in proposal.withdrawal_id_list.iter() {
-                        let _ = xpallet_gateway_records::Pallet::<T>::recover_withdrawal(
-                            *id,
-                            Chain::Bitcoin,
-                        );
-                    }
-
-                    WithdrawalProposal::<T>::kill();
-
-                    Self::deposit_event(Event::<T>::WithdrawalProposalDropped(
-                        reject_count as u32,
-                        sig_num as u32,
-                        proposal.withdrawal_id_list,
-                    ));
-                    return Ok(());
-                }
-            }
+        if let Some(reject_count) = vote_proposal::<T>(proposal_id, true, &who, tx, &mut fee_bump)?
+        {
+            // just drop the fee bump, the original proposal is still valid
+            record_proposal_votes::<T>(proposal_id, true, &fee_bump.trustee_list, false);
+            FeeBumpProposals::<T>::remove(proposal_id);
+
+            let (sig_num, _) = get_sig_num::<T>();
+            Self::deposit_event(Event::<T>::WithdrawalFeeBumpDropped(
+                proposal_id,
+                reject_count,
+                sig_num,
+            ));
+            return Ok(());
         }
 
-        log!(
-            info,
-            "[apply_sig_withdraw] Current sig state:{:?}, trustee vote:{:?}",
-            proposal.sig_state,
-            proposal.trustee_list
-        );
-
-        WithdrawalProposal::<T>::put(proposal);
+        FeeBumpProposals::<T>::insert(proposal_id, fee_bump);
         Ok(())
     }
 
-    pub fn force_replace_withdraw_tx(tx: Transaction) -> DispatchResult {
+    pub fn force_replace_withdraw_tx(proposal_id: u32, tx: Transaction) -> DispatchResult {
         let mut proposal: BtcWithdrawalProposal<T::AccountId> =
-            Self::withdrawal_proposal().ok_or(Error::<T>::NoProposal)?;
+            Self::withdrawal_proposal(proposal_id).ok_or(Error::<T>::NoProposal)?;
 
         ensure!(
             proposal.sig_state == VoteResult::Finish,
             "Only allow force change finished vote"
         );
 
-        // make sure withdrawal list is same as current proposal
+        // make sure withdrawal list and refunds are same as current proposal
         let current_withdrawal_list = &proposal.withdrawal_id_list;
-        check_withdraw_tx_impl::<T>(&tx, current_withdrawal_list)?;
+        let mut refunds = check_withdraw_tx_impl::<T>(&tx, current_withdrawal_list)?;
+        let mut current_refunds = Self::proposal_refunds(proposal_id);
+        refunds.sort();
+        current_refunds.sort();
+        ensure!(refunds == current_refunds, Error::<T>::InvalidProposal);
+        ensure_disjoint_inputs::<T>(&tx, Some(proposal_id))?;
+        ensure_trustee_utxos::<T>(&tx)?;
 
         // sign
         // check first and get signatures from commit transaction
@@ -534,14 +584,150 @@ impl<T: Config> Pallet<T> {
             Error::<T>::InvalidSignCount
         );
 
-        // replace old transaction
+        // replace old transaction, the fee bump of old transaction is useless
         proposal.tx = tx;
 
-        WithdrawalProposal::<T>::put(proposal);
+        WithdrawalProposals::<T>::insert(proposal_id, proposal);
+        if let Some(fee_bump) = FeeBumpProposals::<T>::take(proposal_id) {
+            record_removed_proposal_votes::<T>(proposal_id, true, &fee_bump);
+        }
         Ok(())
     }
 }
 
+/// The trustees of `session` who signed the input `index` of `tx` spending the trustee address
+/// `addr`. The signers of a multisig address are recovered from the signatures of the script sig,
+/// the key path spending of the taproot address is signed by all the trustees, and the script
+/// path spending is signed by the trustees aggregated into the revealed leaf.
+pub(crate) fn input_signers<T: Config>(
+    tx: &Transaction,
+    index: usize,
+    addr: &Address,
+    addr_info: &BtcTrusteeAddrInfo,
+    session: &TrusteeSessionInfo<T::AccountId, BtcTrusteeAddrInfo>,
+) -> Vec<T::AccountId> {
+    match addr.kind {
+        Type::P2SH => multisig_signers::<T>(tx, index, addr_info, &session.trustee_list),
+        Type::P2TR => {
+            let witness = &tx.inputs[index].script_witness;
+            match witness.len() {
+                // key path: [signature]
+                1 => session.trustee_list.clone(),
+                // script path: [signature, script, control block], the script of the leaf is
+                // OP_PUSHBYTES_32 + <32 bytes x-only public key> + OP_CHECKSIG
+                3 if witness[1].len() == 34 => {
+                    taproot_signers::<T>(&witness[1][1..33], addr_info, session)
+                }
+                _ => Vec::new(),
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The signatures of OP_CHECKMULTISIG are in the order of the public keys in the redeem script,
+/// which are in the order of the trustees.
+fn multisig_signers<T: Config>(
+    tx: &Transaction,
+    index: usize,
+    addr_info: &BtcTrusteeAddrInfo,
+    trustees: &[T::AccountId],
+) -> Vec<T::AccountId> {
+    let redeem_script: Script = addr_info.redeem_script.clone().into();
+    let pubkeys = match redeem_script.parse_redeem_script() {
+        Some((pubkeys, _, _)) => pubkeys,
+        None => return Vec::new(),
+    };
+    let script_sig: Script = tx.inputs[index].script_sig.clone().into();
+    let sigs = match script_sig.extract_multi_scriptsig() {
+        Ok((sigs, _)) => sigs,
+        Err(_) => return Vec::new(),
+    };
+    let bytes_redeem_script = redeem_script.to_bytes();
+
+    let mut keys = pubkeys.iter().zip(trustees.iter());
+    let mut signers = Vec::with_capacity(sigs.len());
+    for sig in sigs.iter() {
+        if let Some((_, who)) = keys.by_ref().find(|(pubkey, _)| {
+            verify_sig::<T>(sig, pubkey, tx, &bytes_redeem_script, index).is_ok()
+        }) {
+            signers.push(who.clone());
+        }
+    }
+    signers
+}
+
+/// Each leaf of the trustee MAST is the aggregated key of a `threshold` combination of the
+/// trustees, find the combination aggregated into `leaf_key`. The hot keys of the trustees must
+/// still generate the address of the session, since the trustees could change their keys.
+fn taproot_signers<T: Config>(
+    leaf_key: &[u8],
+    addr_info: &BtcTrusteeAddrInfo,
+    session: &TrusteeSessionInfo<T::AccountId, BtcTrusteeAddrInfo>,
+) -> Vec<T::AccountId> {
+    let keys = match session
+        .trustee_list
+        .iter()
+        .map(|who| {
+            T::TrusteeSessionProvider::trustee_hot_entity(who)
+                .and_then(|entity| Public::from_slice(&entity).ok())
+                .and_then(|public| public.try_into().ok())
+        })
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(keys) => keys,
+        None => return Vec::new(),
+    };
+    let threshold = session.threshold as usize;
+    if threshold == 0 || threshold > keys.len() {
+        return Vec::new();
+    }
+    let network = Network::from(Pallet::<T>::network_id()).to_string();
+    let session_addr = Mast::new(keys.clone(), threshold)
+        .and_then(|mast| mast.generate_address(&network))
+        .ok();
+    if session_addr.as_deref().map(str::as_bytes) != Some(&addr_info.addr[..]) {
+        log!(
+            warn,
+            "[taproot_signers] The hot keys of trustees do not match the address:{:?}",
+            addr_info
+        );
+        return Vec::new();
+    }
+
+    let mut combination = (0..threshold).collect::<Vec<_>>();
+    loop {
+        let combined_keys = combination.iter().map(|i| keys[*i].clone()).collect();
+        if let Ok(mast) = Mast::new(combined_keys, threshold) {
+            if mast.inner_pubkey.x_coor()[..] == *leaf_key {
+                return combination
+                    .iter()
+                    .map(|i| session.trustee_list[*i].clone())
+                    .collect();
+            }
+        }
+        if !next_combination(&mut combination, keys.len()) {
+            return Vec::new();
+        }
+    }
+}
+
+/// Move to the next combination of indexes in `0..n` in the lexicographic order, return false if
+/// it's the last one.
+fn next_combination(indexes: &mut [usize], n: usize) -> bool {
+    let k = indexes.len();
+    for i in (0..k).rev() {
+        if indexes[i] < n - k + i {
+            let first = indexes[i] + 1;
+            for (offset, index) in indexes[i..].iter_mut().enumerate() {
+                *index = first + offset;
+            }
+            return true;
+        }
+    }
+    false
+}
+
 /// Get the required number of signatures
 /// sig_num: Number of signatures required
 /// trustee_num: Total number of multiple signatures
@@ -591,7 +777,7 @@ pub(crate) fn create_multi_address<T: Config>(
 
     let addr = Address {
         kind: Type::P2SH,
-        network: Pallet::<T>::network_id(),
+        network: Pallet::<T>::network_id().into(),
         hash: AddressTypes::Legacy(dhash160(&redeem_script)),
     };
     let script_bytes: Bytes = redeem_script.into();
@@ -601,10 +787,120 @@ pub(crate) fn create_multi_address<T: Config>(
     })
 }
 
+/// Apply the vote of trustee to the proposal, `tx` is None means reject the proposal.
+/// Return the reject count if the proposal should be dropped.
+fn vote_proposal<T: Config>(
+    proposal_id: u32,
+    fee_bump: bool,
+    who: &T::AccountId,
+    tx: Option<Transaction>,
+    proposal: &mut BtcWithdrawalProposal<T::AccountId>,
+) -> Result<Option<u32>, DispatchError> {
+    if proposal.sig_state == VoteResult::Finish {
+        log!(
+            error,
+            "[vote_proposal] Proposal is on FINISH state, can't sign for this proposal:{:?}",
+            proposal
+        );
+        return Err(Error::<T>::RejectSig.into());
+    }
+
+    let (sig_num, total) = get_sig_num::<T>();
+    match tx {
+        Some(tx) => {
+            // check this tx is same to proposal, just check input and output, not include sigs
+            ensure_identical::<T>(&tx, &proposal.tx)?;
+
+            // sign
+            // check first and get signatures from commit transaction
+            let sigs_count = parse_and_check_signed_tx::<T>(&tx)?;
+            if sigs_count == 0 {
+                log!(error, "[vote_proposal] Tx sig should not be zero, zero is the source tx without any sig, tx{:?}", tx);
+                return Err(Error::<T>::InvalidSignCount.into());
+            }
+
+            let confirmed_count = proposal
+                .trustee_list
+                .iter()
+                .filter(|(_, vote)| *vote)
+                .count() as u32;
+
+            if sigs_count != confirmed_count + 1 {
+                log!(
+                    error,
+                    "[vote_proposal] Need to sign on the latest signature results, sigs count:{}, confirmed count:{}",
+                    sigs_count, confirmed_count
+                );
+                return Err(Error::<T>::InvalidSignCount.into());
+            }
+
+            insert_trustee_vote_state::<T>(proposal_id, true, who, &mut proposal.trustee_list)?;
+            // check required count
+            // required count should be equal or more than (2/3)*total
+            // e.g. total=6 => required=2*6/3=4, thus equal to 4 should mark as finish
+            if sigs_count == sig_num {
+                // mark as finish, can't do anything for this proposal
+                log!(info, "[vote_proposal] Signature completed:{}", sigs_count);
+                proposal.sig_state = VoteResult::Finish;
+                record_proposal_votes::<T>(proposal_id, fee_bump, &proposal.trustee_list, true);
+                // the proposal has been approved, rejecting it is considered as misbehavior
+                for (trustee, _) in proposal.trustee_list.iter().filter(|(_, vote)| !*vote) {
+                    T::TrusteeSessionProvider::report_offence(
+                        trustee,
+                        TrusteeOffence::RejectValidProposal,
+                    );
+                }
+
+                Pallet::<T>::deposit_event(Event::<T>::WithdrawalProposalCompleted(
+                    proposal_id,
+                    tx.hash(),
+                ))
+            } else {
+                proposal.sig_state = VoteResult::Unfinish;
+            }
+            // update tx
+            proposal.tx = tx;
+        }
+        None => {
+            // reject
+            insert_trustee_vote_state::<T>(proposal_id, false, who, &mut proposal.trustee_list)?;
+
+            let reject_count = proposal
+                .trustee_list
+                .iter()
+                .filter(|(_, vote)| !(*vote))
+                .count() as u32;
+
+            // reject count just need  < (total-required) / total
+            // e.g. total=6 => required=2*6/3=4, thus, reject should more than (6-4) = 2
+            // > 2 equal to total - required + 1 = 6-4+1 = 3
+            let need_reject = total - sig_num + 1;
+            if reject_count == need_reject {
+                log!(
+                    info,
+                    "[vote_proposal] {}/{} opposition, drop the proposal",
+                    reject_count,
+                    total
+                );
+                return Ok(Some(reject_count));
+            }
+        }
+    }
+
+    log!(
+        info,
+        "[vote_proposal] Current sig state:{:?}, trustee vote:{:?}",
+        proposal.sig_state,
+        proposal.trustee_list
+    );
+    Ok(None)
+}
+
 /// Update the signature status of trustee
 /// state: false -> Veto signature, true -> Consent signature
 /// only allow inseRelayedTx once
 fn insert_trustee_vote_state<T: Config>(
+    proposal_id: u32,
     state: bool,
     who: &T::AccountId,
     trustee_list: &mut Vec<(T::AccountId, bool)>,
@@ -625,22 +921,386 @@ fn insert_trustee_vote_state<T: Config>(
             );
         }
     }
-    Pallet::<T>::deposit_event(Event::<T>::WithdrawalProposalVoted(who.clone(), state));
+    Pallet::<T>::deposit_event(Event::<T>::WithdrawalProposalVoted(
+        proposal_id,
+        who.clone(),
+        state,
+    ));
+    Ok(())
+}
+
+/// Check that the cash withdrawal transaction is correct, and it does not conflict with the
+/// withdrawal proposals in flight.
+pub(crate) fn check_withdraw_tx<T: Config>(
+    tx: &Transaction,
+    withdrawal_id_list: &[u32],
+) -> Result<Vec<BtcAddress>, DispatchError> {
+    ensure!(
+        (WithdrawalProposals::<T>::iter_keys().count() as u32) < MAX_WITHDRAWAL_PROPOSALS,
+        Error::<T>::TooManyProposals
+    );
+    for proposal in WithdrawalProposals::<T>::iter_values() {
+        if proposal
+            .withdrawal_id_list
+            .iter()
+            .any(|id| withdrawal_id_list.contains(id))
+        {
+            log!(
+                error,
+                "[check_withdraw_tx] Withdrawal applications are in other proposal, id_list:{:?}, other:{:?}",
+                withdrawal_id_list,
+                proposal.withdrawal_id_list
+            );
+            return Err(Error::<T>::OverlappingWithdrawals.into());
+        }
+    }
+    ensure_disjoint_inputs::<T>(tx, None)?;
+    ensure_trustee_utxos::<T>(tx)?;
+
+    let refunds = check_withdraw_tx_impl::<T>(tx, withdrawal_id_list)?;
+    // the expired deposits of an address could only be refunded in one proposal
+    for other in ProposalRefunds::<T>::iter_values() {
+        ensure!(
+            !refunds.iter().any(|addr| other.contains(addr)),
+            Error::<T>::RefundInProgress
+        );
+    }
+    Ok(refunds)
+}
+
+/// Ensure the inputs of `tx` are not spent by other withdrawal proposals (and their fee bumps),
+/// except the proposal `except`.
+fn ensure_disjoint_inputs<T: Config>(tx: &Transaction, except: Option<u32>) -> DispatchResult {
+    for (proposal_id, proposal) in WithdrawalProposals::<T>::iter() {
+        if Some(proposal_id) == except {
+            continue;
+        }
+        let spent = |other: &Transaction| {
+            other.inputs.iter().any(|other_input| {
+                tx.inputs
+                    .iter()
+                    .any(|input| input.previous_output == other_input.previous_output)
+            })
+        };
+        let fee_bump_spent = Pallet::<T>::fee_bump_proposal(proposal_id)
+            .map(|fee_bump| spent(&fee_bump.tx))
+            .unwrap_or(false);
+        if spent(&proposal.tx) || fee_bump_spent {
+            log!(
+                error,
+                "[ensure_disjoint_inputs] Inputs are spent by other proposal:{}, tx:{:?}",
+                proposal_id,
+                tx
+            );
+            return Err(Error::<T>::ConflictingInputs.into());
+        }
+    }
     Ok(())
 }
 
-/// Check that the cash withdrawal transaction is correct
-fn check_withdraw_tx<T: Config>(tx: &Transaction, withdrawal_id_list: &[u32]) -> DispatchResult {
-    match Pallet::<T>::withdrawal_proposal() {
-        Some(_) => Err(Error::<T>::NotFinishProposal.into()),
-        None => check_withdraw_tx_impl::<T>(tx, withdrawal_id_list),
+/// Ensure the inputs of `tx` only spend the unspent outputs of trustee addresses,
+/// it's skipped until the trustee utxo set is initialized.
+fn ensure_trustee_utxos<T: Config>(tx: &Transaction) -> DispatchResult {
+    if !Pallet::<T>::trustee_utxos_initialized() {
+        return Ok(());
+    }
+    for input in tx.inputs.iter() {
+        let outpoint = &input.previous_output;
+        if !TrusteeUtxos::<T>::contains_key(&outpoint.txid, outpoint.index) {
+            log!(
+                error,
+                "[ensure_trustee_utxos] Input ({:?}:{}) is not a trustee utxo",
+                hash_rev(outpoint.txid),
+                outpoint.index
+            );
+            return Err(Error::<T>::UnknownTrusteeUtxo.into());
+        }
     }
+    Ok(())
 }
 
-fn check_withdraw_tx_impl<T: Config>(
+/// Insert the new withdrawal proposal with the next proposal id.
+fn insert_proposal<T: Config>(
+    proposal: BtcWithdrawalProposal<T::AccountId>,
+    refunds: Vec<BtcAddress>,
+) {
+    let proposal_id = NextProposalId::<T>::mutate(|id| {
+        let current = *id;
+        *id = id.wrapping_add(1);
+        current
+    });
+    if proposal.sig_state != VoteResult::Finish {
+        schedule_vote_deadline::<T>(proposal_id, false);
+    }
+    WithdrawalProposals::<T>::insert(proposal_id, proposal);
+    if !refunds.is_empty() {
+        ProposalRefunds::<T>::insert(proposal_id, refunds);
+    }
+    if let Some(session_number) = T::TrusteeSessionProvider::current_session_number() {
+        ProposalSessions::<T>::insert(proposal_id, session_number);
+    }
+}
+
+/// Record the votes of the proposal into the statistics of the trustee session which the proposal
+/// is created in, the proposals created before tracking the session belong to current session.
+/// The votes of a proposal recorded since it's stalled are not recorded again.
+pub(crate) fn record_proposal_votes<T: Config>(
+    proposal_id: u32,
+    fee_bump: bool,
+    votes: &[(T::AccountId, bool)],
+    completed: bool,
+) {
+    ProposalVoteDeadlines::<T>::remove((proposal_id, fee_bump));
+    if StalledProposals::<T>::take((proposal_id, fee_bump)).is_some() {
+        return;
+    }
+    if let Some(session_number) = Pallet::<T>::proposal_session(proposal_id)
+        .or_else(T::TrusteeSessionProvider::current_session_number)
+    {
+        T::TrusteeSessionProvider::record_trustee_votes(session_number, votes, completed);
+    }
+}
+
+/// Record the votes of the removed withdrawal proposal or fee bump if it's unfinished, e.g. it's
+/// replaced by the other transaction observed on-chain, the trustees not voting are counted as
+/// missed.
+pub(crate) fn record_removed_proposal_votes<T: Config>(
+    proposal_id: u32,
+    fee_bump: bool,
+    proposal: &BtcWithdrawalProposal<T::AccountId>,
+) {
+    // the votes of the finished one are recorded when it's finished
+    if proposal.sig_state != VoteResult::Finish {
+        record_proposal_votes::<T>(proposal_id, fee_bump, &proposal.trustee_list, false);
+    }
+}
+
+/// The trustees should vote on the unfinished withdrawal proposal or fee bump in
+/// `PROPOSAL_VOTE_TIMEOUT` blocks.
+fn schedule_vote_deadline<T: Config>(proposal_id: u32, fee_bump: bool) {
+    let deadline =
+        frame_system::Pallet::<T>::block_number().saturating_add(PROPOSAL_VOTE_TIMEOUT.into());
+    ProposalVoteDeadlines::<T>::insert((proposal_id, fee_bump), deadline);
+}
+
+/// Record the votes of the unfinished withdrawal proposals and fee bumps which are stalled until
+/// the deadline, the trustees not voting are counted as missed. The stalled ones are kept for the
+/// trustees to go on voting.
+pub(crate) fn record_stalled_proposals<T: Config>(now: T::BlockNumber) -> Weight {
+    let stalled = ProposalVoteDeadlines::<T>::iter()
+        .filter(|(_, deadline)| *deadline <= now)
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    // the deadlines are at most two for each proposal in flight
+    let mut weight = T::DbWeight::get().reads(2 * MAX_WITHDRAWAL_PROPOSALS as u64);
+    for (proposal_id, fee_bump) in stalled {
+        let proposal = if fee_bump {
+            FeeBumpProposals::<T>::get(proposal_id)
+        } else {
+            WithdrawalProposals::<T>::get(proposal_id)
+        };
+        match proposal {
+            Some(proposal) if proposal.sig_state != VoteResult::Finish => {
+                log!(
+                    info,
+                    "[record_stalled_proposals] Proposal {} is stalled, fee bump:{}, votes:{:?}",
+                    proposal_id,
+                    fee_bump,
+                    proposal.trustee_list
+                );
+                record_proposal_votes::<T>(proposal_id, fee_bump, &proposal.trustee_list, false);
+                StalledProposals::<T>::insert((proposal_id, fee_bump), ());
+            }
+            _ => ProposalVoteDeadlines::<T>::remove((proposal_id, fee_bump)),
+        }
+        // the proposal, the session and the stats of at most 15 trustees
+        weight = weight.saturating_add(T::DbWeight::get().reads_writes(18, 17));
+    }
+    weight
+}
+
+/// Move the legacy single `WithdrawalProposal` into `WithdrawalProposals`.
+pub(crate) fn migrate_withdrawal_proposal<T: Config>() -> Weight {
+    let pallet_name = <Pallet<T> as PalletInfoAccess>::name();
+    match take_storage_value::<BtcWithdrawalProposal<T::AccountId>>(
+        pallet_name.as_bytes(),
+        b"WithdrawalProposal",
+        &[],
+    ) {
+        Some(proposal) => {
+            log!(
+                info,
+                "[migrate_withdrawal_proposal] Migrate the withdrawal proposal:{:?}",
+                proposal
+            );
+            insert_proposal::<T>(proposal, Vec::new());
+            T::DbWeight::get().reads_writes(2, 3)
+        }
+        None => T::DbWeight::get().reads(1),
+    }
+}
+
+/// The max size of a DER encoded signature with the sighash type.
+const MAX_SIG_SIZE: usize = 73;
+
+/// The size of the compact size prefix for the length `len`.
+fn compact_size_len(len: usize) -> usize {
+    if len < 0xfd {
+        1
+    } else if len <= 0xffff {
+        3
+    } else {
+        5
+    }
+}
+
+/// Calculate the virtual size of transaction, `vsize = ceil((base_size * 3 + total_size) / 4)`.
+pub(crate) fn tx_vsize(tx: &Transaction) -> u64 {
+    let base_size = serialize(tx).len() as u64;
+    let total_size = serialize_with_flags(tx, SERIALIZE_TRANSACTION_WITNESS).len() as u64;
+    (base_size * 3 + total_size + 3) / 4
+}
+
+/// Estimate the virtual size of the multisig withdrawal transaction after all trustees signed,
+/// the scriptSig of every input would be `OP_0 <sig>... <redeem script>`.
+pub(crate) fn estimate_multisig_tx_vsize<T: Config>(
     tx: &Transaction,
-    withdrawal_id_list: &[u32],
+) -> Result<u64, DispatchError> {
+    let redeem_script_len = get_hot_trustee_redeem_script::<T>()?.len();
+    let (sig_num, _) = get_sig_num::<T>();
+    let push_redeem_script_len = match redeem_script_len {
+        0..=75 => 1,
+        76..=255 => 2,
+        _ => 3,
+    };
+    let script_sig_len =
+        1 + sig_num as usize * (1 + MAX_SIG_SIZE) + push_redeem_script_len + redeem_script_len;
+
+    let mut unsigned_tx = tx.clone();
+    for input in unsigned_tx.inputs.iter_mut() {
+        input.script_sig = Bytes::new();
+    }
+    // the empty scriptSig has taken 1 byte for its length
+    let extra_size = compact_size_len(script_sig_len) + script_sig_len - 1;
+    Ok(tx_vsize(&unsigned_tx) + (extra_size * tx.inputs.len()) as u64)
+}
+
+/// Check the fee rate of withdrawal transaction is in the band of `WithdrawalFeeRate`,
+/// the fee is the value of spent trustee utxos minus the value of tx outputs.
+/// The value of inputs is unknown until the trustee utxo set is initialized, then it's skipped.
+pub(crate) fn check_withdraw_fee_rate<T: Config>(tx: &Transaction, vsize: u64) -> DispatchResult {
+    if !Pallet::<T>::trustee_utxos_initialized() {
+        log!(
+            warn,
+            "[check_withdraw_fee_rate] Trustee utxos are not initialized, skip the fee rate check"
+        );
+        return Ok(());
+    }
+    let mut input_value = 0u64;
+    for input in tx.inputs.iter() {
+        let outpoint = &input.previous_output;
+        let utxo = TrusteeUtxos::<T>::get(&outpoint.txid, outpoint.index)
+            .ok_or(Error::<T>::UnknownTrusteeUtxo)?;
+        input_value = input_value.saturating_add(utxo.value);
+    }
+    let output_value = tx
+        .outputs
+        .iter()
+        .fold(0u64, |acc, output| acc.saturating_add(output.value));
+    let fee = input_value
+        .checked_sub(output_value)
+        .ok_or(Error::<T>::InvalidSpentOutputs)?;
+
+    let (min, max) = Pallet::<T>::withdrawal_fee_rate();
+    if fee < min.saturating_mul(vsize) || (max != 0 && fee > max.saturating_mul(vsize)) {
+        log!(
+            error,
+            "[check_withdraw_fee_rate] Fee rate out of band, fee:{}, vsize:{}, band:{:?}",
+            fee,
+            vsize,
+            (min, max)
+        );
+        return Err(Error::<T>::FeeRateOutOfBand.into());
+    }
+    Ok(())
+}
+
+/// Check the `new_tx` is a fee bump of `original_tx`: it spends the same inputs and pays the same
+/// outputs, except the change to hot trustee address which is reduced for a higher fee.
+/// The `original_tx` must signal replaceability (BIP125), otherwise the bitcoin nodes would not
+/// relay the replacement.
+pub(crate) fn check_fee_bump_tx<T: Config>(
+    original_tx: &Transaction,
+    new_tx: &Transaction,
 ) -> DispatchResult {
+    // BIP125: any input with a sequence less than 0xfffffffe
+    let replaceable = original_tx
+        .inputs
+        .iter()
+        .any(|input| input.sequence < 0xffff_fffe);
+    if !replaceable {
+        log!(
+            error,
+            "[check_fee_bump_tx] The original tx does not signal replaceability:{:?}",
+            original_tx
+        );
+        return Err(Error::<T>::NotReplaceable.into());
+    }
+
+    let same_inputs = original_tx.inputs.len() == new_tx.inputs.len()
+        && original_tx
+            .inputs
+            .iter()
+            .zip(new_tx.inputs.iter())
+            .all(|(a, b)| a.previous_output == b.previous_output);
+    if original_tx.version != new_tx.version
+        || original_tx.lock_time != new_tx.lock_time
+        || !same_inputs
+        || original_tx.outputs.len() != new_tx.outputs.len()
+    {
+        log!(
+            error,
+            "[check_fee_bump_tx] The new tx does not spend the same inputs, original:{:?}, new:{:?}",
+            original_tx,
+            new_tx
+        );
+        return Err(Error::<T>::InvalidFeeBump.into());
+    }
+
+    let hot_trustee_address = get_hot_trustee_address::<T>()?;
+    let btc_network = Network::from(Pallet::<T>::network_id());
+    let mut bumped = false;
+    for (original, new) in original_tx.outputs.iter().zip(new_tx.outputs.iter()) {
+        if original == new {
+            continue;
+        }
+        // only the value of change could be reduced
+        let is_change = original.script_pubkey == new.script_pubkey
+            && extract_output_addr(original, btc_network)
+                .map(|addr| addr.hash == hot_trustee_address.hash)
+                .unwrap_or(false);
+        if !is_change || new.value > original.value {
+            log!(
+                error,
+                "[check_fee_bump_tx] The payee outputs must be identical, original:{:?}, new:{:?}",
+                original,
+                new
+            );
+            return Err(Error::<T>::InvalidFeeBump.into());
+        }
+        bumped = true;
+    }
+    ensure!(bumped, Error::<T>::InvalidFeeBump);
+    Ok(())
+}
+
+/// Check the outputs of withdrawal transaction, every withdrawal application must match an output,
+/// and the rest outputs (except the change to hot trustee address) must be refunds of expired
+/// pending deposits. Return the refunded addresses.
+pub(crate) fn check_withdraw_tx_impl<T: Config>(
+    tx: &Transaction,
+    withdrawal_id_list: &[u32],
+) -> Result<Vec<BtcAddress>, DispatchError> {
     // withdrawal addr list for account withdrawal application
     let mut appl_withdrawal_list: Vec<(Address, u64)> = Vec::new();
     for withdraw_index in withdrawal_id_list.iter() {
@@ -656,7 +1316,7 @@ fn check_withdraw_tx_impl<T: Config>(
     let hot_trustee_address: Address = get_hot_trustee_address::<T>()?;
     // withdrawal addr list for tx outputs
     let btc_withdrawal_fee = Pallet::<T>::btc_withdrawal_fee();
-    let btc_network = Pallet::<T>::network_id();
+    let btc_network = Network::from(Pallet::<T>::network_id());
     let mut tx_withdraw_list = Vec::new();
     for output in &tx.outputs {
         let addr = extract_output_addr(output, btc_network).ok_or("not found addr in this out")?;
@@ -669,45 +1329,43 @@ fn check_withdraw_tx_impl<T: Config>(
     tx_withdraw_list.sort();
     appl_withdrawal_list.sort();
 
-    // appl_withdrawal_list must match to tx_withdraw_list
-    if appl_withdrawal_list.len() != tx_withdraw_list.len() {
-        log!(
-            error,
-            "Withdrawal tx's outputs (len:{}) != withdrawal application list (len:{}), \
-            withdrawal tx's outputs:{:?}, withdrawal application list:{:?}",
-            tx_withdraw_list.len(),
-            appl_withdrawal_list.len(),
-            tx_withdraw_list,
-            withdrawal_id_list
-                .iter()
-                .zip(appl_withdrawal_list)
-                .collect::<Vec<_>>()
-        );
-        return Err(Error::<T>::InvalidProposal.into());
-    }
-
-    let count = appl_withdrawal_list
-        .iter()
-        .zip(tx_withdraw_list)
-        .filter(|(a, b)| {
-            if a.0 == b.0 && a.1 == b.1 {
-                true
-            } else {
+    // appl_withdrawal_list must be contained in tx_withdraw_list
+    for appl in appl_withdrawal_list.iter() {
+        match tx_withdraw_list.iter().position(|out| out == appl) {
+            Some(index) => {
+                tx_withdraw_list.remove(index);
+            }
+            None => {
                 log!(
                     error,
-                    "Withdrawal tx's output not match to withdrawal application. \
-                    withdrawal application:{:?}, tx withdrawal output:{:?}",
-                    a,
-                    b
+                    "Withdrawal application not match to any withdrawal tx's output. \
+                    withdrawal application:{:?}, withdrawal tx's outputs:{:?}",
+                    appl,
+                    tx_withdraw_list
                 );
-                false
+                return Err(Error::<T>::InvalidProposal.into());
             }
-        })
-        .count();
+        }
+    }
 
-    if count != appl_withdrawal_list.len() {
-        return Err(Error::<T>::InvalidProposal.into());
+    // the rest outputs must refund all expired deposits of an address
+    let mut refunds: Vec<BtcAddress> = Vec::new();
+    for (addr, value) in tx_withdraw_list {
+        let addr_bytes = addr.to_string().into_bytes();
+        let expired = ExpiredDeposits::<T>::get(&addr_bytes);
+        let refund = expired.iter().map(|record| record.balance).sum::<u64>();
+        if expired.is_empty() || refund != value || refunds.contains(&addr_bytes) {
+            log!(
+                error,
+                "Withdrawal tx's output not match to withdrawal application or expired deposits. \
+                tx withdrawal output:{:?}, expired deposits:{:?}",
+                (addr, value),
+                expired
+            );
+            return Err(Error::<T>::InvalidProposal.into());
+        }
+        refunds.push(addr_bytes);
     }
 
-    Ok(())
+    Ok(refunds)
 }
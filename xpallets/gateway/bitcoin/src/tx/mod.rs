@@ -3,16 +3,24 @@
 extern crate alloc;
 use alloc::string::ToString;
 
-mod secp256k1_verifier;
+pub(crate) mod secp256k1_verifier;
 pub mod validator;
 
 use frame_support::{
     dispatch::DispatchResult,
+    ensure,
     log::{self, debug, error, info, warn},
+    traits::Get,
+    weights::Weight,
+};
+use sp_runtime::{
+    traits::{Saturating, Zero},
+    SaturatedConversion,
 };
-use sp_runtime::{traits::Zero, SaturatedConversion};
 use sp_std::prelude::*;
 
+use orml_utilities::with_transaction_result;
+
 use light_bitcoin::{
     chain::Transaction,
     keys::{Address, Network},
@@ -20,40 +28,61 @@ use light_bitcoin::{
 };
 
 use chainx_primitives::AssetId;
-use xp_gateway_bitcoin::{BtcDepositInfo, BtcTxMetaType, BtcTxTypeDetector};
+use xp_gateway_bitcoin::{
+    extract_output_addr, is_trustee_addr, BtcDepositInfo, BtcSpentOutput, BtcTxMetaType, BtcTxType,
+    BtcTxTypeDetector, DepositAction,
+};
 use xp_gateway_common::AccountExtractor;
-use xpallet_assets::ChainT;
-use xpallet_gateway_common::traits::{AddressBinding, ReferralBinding};
+use xpallet_assets::{AssetType, ChainT};
+use xpallet_gateway_common::traits::{AddressBinding, ReferralBinding, TrusteeSession};
 use xpallet_support::try_str;
 
 pub use self::validator::validate_transaction;
 use crate::{
-    types::{AccountInfo, BtcAddress, BtcDepositCache, BtcTxResult, BtcTxState},
-    BalanceOf, Config, Error, Event, Pallet, PendingDeposits, WithdrawalProposal,
+    trustee,
+    types::{
+        AccountInfo, BtcAddress, BtcDepositCache, BtcReorgedDeposit, BtcTrusteeUtxo, BtcTxResult,
+        BtcTxState,
+    },
+    BalanceOf, BlockTxs, Config, Error, Event, ExpiredDeposits, FeeBumpProposals, Pallet,
+    PendingDepositAddrs, PendingDepositHeight, PendingDeposits, ProposalRefunds, ProposalSessions,
+    ReorgedDeposits, ReorgedTrusteeTxs, TrusteeUtxos, TxDepositOf, TxSpentTrusteeUtxos, TxState,
+    WithdrawalProposals,
 };
 
 pub fn process_tx<T: Config>(
     tx: Transaction,
-    prev_tx: Option<Transaction>,
+    prev_txs: Vec<Transaction>,
+    height: u32,
     network: Network,
     min_deposit: u64,
     current_trustee_pair: (Address, Address),
     last_trustee_pair: Option<(Address, Address)>,
 ) -> BtcTxState {
+    // the tx is confirmed in the main chain, so the trustee utxo set is updated whatever the
+    // tx type and process result are
+    apply_trustee_utxos::<T>(
+        &tx,
+        network,
+        current_trustee_pair.clone(),
+        last_trustee_pair.clone(),
+    );
+
     let btc_tx_detector = BtcTxTypeDetector::new(network, min_deposit);
     let meta_type = btc_tx_detector.detect_transaction_type::<T::AccountId, _>(
         &tx,
-        prev_tx.as_ref(),
+        &prev_txs,
         T::AccountExtractor::extract_account,
         current_trustee_pair,
-        last_trustee_pair,
+        last_trustee_pair.clone(),
     );
 
     let tx_type = meta_type.ref_into();
     let result = match meta_type {
-        BtcTxMetaType::<_>::Deposit(deposit_info) => deposit::<T>(tx.hash(), deposit_info),
+        BtcTxMetaType::<_>::Deposit(deposit_info) => deposit::<T>(tx.hash(), height, deposit_info),
         BtcTxMetaType::<_>::Withdrawal => withdraw::<T>(tx),
-        BtcTxMetaType::HotAndCold | BtcTxMetaType::TrusteeTransition => BtcTxResult::Success,
+        BtcTxMetaType::HotAndCold => BtcTxResult::Success,
+        BtcTxMetaType::TrusteeTransition => trustee_transition::<T>(tx.hash(), last_trustee_pair),
         // mark `Irrelevance` be `Failure` so that it could be replayed in the future
         BtcTxMetaType::<_>::Irrelevance => BtcTxResult::Failure,
     };
@@ -61,7 +90,144 @@ pub fn process_tx<T: Config>(
     BtcTxState { tx_type, result }
 }
 
-fn deposit<T: Config>(txid: H256, deposit_info: BtcDepositInfo<T::AccountId>) -> BtcTxResult {
+/// Update the trustee utxo set by the tx with the current and last trustee address pairs.
+pub(crate) fn apply_trustee_utxos<T: Config>(
+    tx: &Transaction,
+    network: Network,
+    current_trustee_pair: (Address, Address),
+    last_trustee_pair: Option<(Address, Address)>,
+) {
+    let mut trustee_pairs = vec![current_trustee_pair];
+    trustee_pairs.extend(last_trustee_pair);
+    update_trustee_utxos::<T>(tx, network, &trustee_pairs);
+}
+
+/// Remove the trustee outputs spent by the tx, and add the outputs of the tx which belong to
+/// the trustee hot and cold addresses into the trustee utxo set.
+///
+/// The spent trustee utxos are kept by the tx until its block could not be reorged any more.
+fn update_trustee_utxos<T: Config>(
+    tx: &Transaction,
+    network: Network,
+    trustee_pairs: &[(Address, Address)],
+) {
+    let txid = tx.hash();
+    for input in tx.inputs.iter() {
+        let outpoint = &input.previous_output;
+        if let Some(utxo) = TrusteeUtxos::<T>::take(&outpoint.txid, outpoint.index) {
+            debug!(
+                target: "runtime::bitcoin",
+                "[update_trustee_utxos] Trustee utxo ({:?}:{}) is spent",
+                hash_rev(outpoint.txid),
+                outpoint.index
+            );
+            TxSpentTrusteeUtxos::<T>::append(&txid, (outpoint.txid, outpoint.index, utxo));
+        }
+    }
+
+    for (index, output) in tx.outputs.iter().enumerate() {
+        let addr = match extract_output_addr(output, network) {
+            Some(addr) => addr,
+            None => continue,
+        };
+        if trustee_pairs
+            .iter()
+            .any(|pair| is_trustee_addr(addr.clone(), pair.clone()))
+        {
+            debug!(
+                target: "runtime::bitcoin",
+                "[update_trustee_utxos] Add trustee utxo ({:?}:{}), value:{}",
+                hash_rev(txid),
+                index,
+                output.value
+            );
+            TrusteeUtxos::<T>::insert(
+                txid,
+                index as u32,
+                BtcTrusteeUtxo {
+                    value: output.value,
+                    addr: addr.to_string().into_bytes(),
+                },
+            );
+        }
+    }
+}
+
+/// Whether the tx has updated the trustee utxo set.
+pub(crate) fn updates_trustee_utxos<T: Config>(txid: &H256) -> bool {
+    TxSpentTrusteeUtxos::<T>::contains_key(txid)
+        || TrusteeUtxos::<T>::iter_prefix(txid).next().is_some()
+}
+
+/// Revert the trustee utxo updates of a tx whose block is flipped out of the main chain, the
+/// trustee outputs of the tx are removed and the trustee utxos spent by it are restored.
+/// Return whether the trustee utxo set is changed.
+fn revert_trustee_utxos<T: Config>(txid: H256) -> bool {
+    let added = TrusteeUtxos::<T>::iter_prefix(&txid).count();
+    let _ = TrusteeUtxos::<T>::remove_prefix(&txid, None);
+    let spent = TxSpentTrusteeUtxos::<T>::take(&txid);
+    for (prev_txid, index, utxo) in spent.iter() {
+        TrusteeUtxos::<T>::insert(prev_txid, index, utxo);
+    }
+    if added == 0 && spent.is_empty() {
+        return false;
+    }
+    warn!(
+        target: "runtime::bitcoin",
+        "[revert_trustee_utxos] Trustee utxo updates of tx ({:?}) are reverted, removed:{}, restored:{}",
+        hash_rev(txid),
+        added,
+        spent.len()
+    );
+    true
+}
+
+/// Track the balance of previous trustee addresses which is being drained by the trustee
+/// transition tx, and finish draining once all the balance is moved to the current trustees.
+/// The balance is unknown before the trustee utxo set is initialized, then the draining could
+/// only be finished by root.
+pub(crate) fn trustee_transition<T: Config>(
+    txid: H256,
+    last_trustee_pair: Option<(Address, Address)>,
+) -> BtcTxResult {
+    let (session_number, last_trustee_pair) = match (
+        T::TrusteeSessionProvider::draining_session(),
+        last_trustee_pair,
+    ) {
+        (Some(number), Some(pair)) => (number, pair),
+        _ => return BtcTxResult::Success,
+    };
+
+    let remaining = TrusteeUtxos::<T>::iter_values()
+        .filter(|utxo| {
+            Pallet::<T>::verify_btc_address(&utxo.addr)
+                .map(|addr| is_trustee_addr(addr, last_trustee_pair.clone()))
+                .unwrap_or(false)
+        })
+        .fold(0u64, |acc, utxo| acc.saturating_add(utxo.value));
+    info!(
+        target: "runtime::bitcoin",
+        "[trustee_transition] Trustee transition tx ({:?}) processed, draining session:{}, remaining:{}",
+        hash_rev(txid),
+        session_number,
+        remaining
+    );
+    Pallet::<T>::deposit_event(Event::<T>::TrusteeTransitionProcessed(
+        txid,
+        session_number,
+        remaining,
+    ));
+    if remaining == 0 && Pallet::<T>::trustee_utxos_initialized() {
+        T::TrusteeSessionProvider::finish_draining();
+    }
+    BtcTxResult::Success
+}
+
+fn deposit<T: Config>(
+    txid: H256,
+    height: u32,
+    deposit_info: BtcDepositInfo<T::AccountId>,
+) -> BtcTxResult {
     let account_info = match (deposit_info.op_return, deposit_info.input_addr) {
         (Some((account, referral)), Some(input_addr)) => {
             let input_addr = input_addr.to_string().into_bytes();
@@ -81,21 +247,35 @@ fn deposit<T: Config>(txid: H256, deposit_info: BtcDepositInfo<T::AccountId>) ->
             );
             AccountInfo::<T::AccountId>::Account((account, referral))
         }
-        (None, Some(input_addr)) => {
-            // no opreturn but have input addr, use input addr to get accountid
-            let addr_bytes = input_addr.to_string().into_bytes();
-            match T::AddressBinding::address(Pallet::<T>::chain(), addr_bytes) {
-                Some(account) => AccountInfo::Account((account, None)),
-                None => AccountInfo::Address(input_addr),
+        (None, _) => {
+            // no opreturn, use input addrs to get accountid
+            if deposit_info.inputs.contains(&BtcSpentOutput::Missing) {
+                // mark it `Failure` so that it could be replayed with all the previous txs
+                warn!(
+                    target: "runtime::bitcoin",
+                    "[deposit] Process deposit tx ({:?}) but missing the previous txs of some inputs",
+                    hash_rev(txid)
+                );
+                return BtcTxResult::Failure;
             }
-        }
-        (None, None) => {
-            warn!(
-                target: "runtime::bitcoin",
-                "[deposit] Process deposit tx ({:?}) but missing valid opreturn and input addr",
-                hash_rev(txid)
-            );
-            return BtcTxResult::Failure;
+            // the inputs whose address could not be extracted are ineligible for the deposit
+            let inputs = deposit_info
+                .inputs
+                .into_iter()
+                .filter_map(|input| match input {
+                    BtcSpentOutput::Address(addr, value) => Some((addr, value)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if inputs.is_empty() {
+                warn!(
+                    target: "runtime::bitcoin",
+                    "[deposit] Process deposit tx ({:?}) but missing valid opreturn and no input addr could be extracted",
+                    hash_rev(txid)
+                );
+                return BtcTxResult::Failure;
+            }
+            attribute_deposit::<T>(deposit_info.deposit_value, inputs)
         }
     };
 
@@ -115,31 +295,90 @@ fn deposit<T: Config>(txid: H256, deposit_info: BtcDepositInfo<T::AccountId>) ->
                         account,
                         deposit_info.deposit_value
                     );
+                    if let Some(action) = deposit_info.action {
+                        deposit_action::<T>(txid, &account, deposit_info.deposit_value, action);
+                    }
                     BtcTxResult::Success
                 }
                 Err(_) => BtcTxResult::Failure,
             }
         }
-        AccountInfo::<_>::Address(input_addr) => {
-            insert_pending_deposit::<T>(&input_addr, txid, deposit_info.deposit_value);
-            info!(
-                target: "runtime::bitcoin",
-                "[deposit] Deposit tx ({:?}) into pending, addr:{:?}, balance:{}",
-                hash_rev(txid),
-                try_str(input_addr.to_string().into_bytes()),
-                deposit_info.deposit_value
-            );
+        AccountInfo::<_>::Addresses(shares) => {
+            for (input_addr, balance) in shares {
+                insert_pending_deposit::<T>(&input_addr, txid, height, balance);
+                info!(
+                    target: "runtime::bitcoin",
+                    "[deposit] Deposit tx ({:?}) into pending, addr:{:?}, balance:{}",
+                    hash_rev(txid),
+                    try_str(input_addr.to_string().into_bytes()),
+                    balance
+                );
+            }
             BtcTxResult::Success
         }
     }
 }
 
+/// Attribute a deposit without opreturn by the addresses spent by its inputs.
+///
+/// The deposit goes to the bound account only when all the input addresses are bound to the
+/// same account, otherwise it's parked in the pending deposits of each input address, split in
+/// proportion to the value spent from the address.
+fn attribute_deposit<T: Config>(
+    deposit_value: u64,
+    inputs: Vec<(Address, u64)>,
+) -> AccountInfo<T::AccountId> {
+    let mut accounts = inputs.iter().map(|(addr, _)| {
+        T::AddressBinding::address(Pallet::<T>::chain(), addr.to_string().into_bytes())
+    });
+    if let Some(Some(account)) = accounts.next() {
+        if accounts.all(|other| other.as_ref() == Some(&account)) {
+            return AccountInfo::Account((account, None));
+        }
+    }
+    AccountInfo::Addresses(split_deposit(deposit_value, inputs))
+}
+
+/// Split the deposit value among the input addresses in proportion to the value spent from
+/// each address, the remainder of the division goes to the address of the first input.
+fn split_deposit(deposit_value: u64, inputs: Vec<(Address, u64)>) -> Vec<(Address, u64)> {
+    let mut spent: Vec<(Address, u64)> = Vec::new();
+    for (addr, value) in inputs {
+        match spent.iter_mut().find(|(spent_addr, _)| *spent_addr == addr) {
+            Some((_, total)) => *total = total.saturating_add(value),
+            None => spent.push((addr, value)),
+        }
+    }
+    let total_spent = spent
+        .iter()
+        .fold(0u128, |acc, (_, value)| acc + u128::from(*value));
+    let mut shares = spent
+        .into_iter()
+        .map(|(addr, value)| {
+            let share = if total_spent == 0 {
+                0
+            } else {
+                (u128::from(deposit_value) * u128::from(value) / total_spent) as u64
+            };
+            (addr, share)
+        })
+        .collect::<Vec<_>>();
+    let assigned = shares.iter().fold(0u64, |acc, (_, share)| acc + share);
+    if let Some((_, share)) = shares.first_mut() {
+        *share += deposit_value - assigned;
+    }
+    shares.retain(|(_, share)| *share > 0);
+    shares
+}
+
 fn deposit_token<T: Config>(txid: H256, who: &T::AccountId, balance: u64) -> DispatchResult {
     let id: AssetId = <Pallet<T> as ChainT<_>>::ASSET_ID;
 
     let value: BalanceOf<T> = balance.saturated_into();
     match <xpallet_gateway_records::Pallet<T>>::deposit(who, id, value) {
         Ok(()) => {
+            // record the credit, so that it could be frozen if the block is reorged
+            TxDepositOf::<T>::insert(txid, (who.clone(), value));
             Pallet::<T>::deposit_event(Event::<T>::Deposited(txid, who.clone(), value));
             Ok(())
         }
@@ -154,10 +393,64 @@ fn deposit_token<T: Config>(txid: H256, who: &T::AccountId, balance: u64) -> Dis
     }
 }
 
+/// Perform the follow-up action of a deposit after X-BTC has been minted.
+///
+/// The action runs in its own storage layer, a failed action never reverts the deposit.
+pub(crate) fn deposit_action<T: Config>(
+    txid: H256,
+    who: &T::AccountId,
+    balance: u64,
+    action: DepositAction,
+) {
+    let weight = T::OnBtcDeposited::weight(&action);
+    let max_weight = T::MaxDepositActionWeight::get();
+    let result = if weight > max_weight {
+        Err(Error::<T>::DepositActionOverweight.into())
+    } else {
+        let value: BalanceOf<T> = balance.saturated_into();
+        with_transaction_result(|| T::OnBtcDeposited::on_deposited(txid, who, value, &action))
+    };
+    match result {
+        Ok(_) => {
+            info!(
+                target: "runtime::bitcoin",
+                "[deposit_action] Deposit tx ({:?}) action success, who:{:?}, action:{:?}",
+                hash_rev(txid),
+                who,
+                action
+            );
+            Pallet::<T>::deposit_event(Event::<T>::DepositActionExecuted(
+                txid,
+                who.clone(),
+                action,
+            ));
+        }
+        Err(err) => {
+            warn!(
+                target: "runtime::bitcoin",
+                "[deposit_action] Deposit tx ({:?}) action failed, who:{:?}, action:{:?}, weight:{}, max_weight:{}, err:{:?}",
+                hash_rev(txid),
+                who,
+                action,
+                weight,
+                max_weight,
+                err
+            );
+            Pallet::<T>::deposit_event(Event::<T>::DepositActionFailed(
+                txid,
+                who.clone(),
+                action,
+                err,
+            ));
+        }
+    }
+}
+
 pub fn remove_pending_deposit<T: Config>(input_address: &BtcAddress, who: &T::AccountId) {
     // notice this would delete this cache
     let records = PendingDeposits::<T>::take(input_address);
     for record in records {
+        release_pending_deposit::<T>(&record.txid, input_address);
         // ignore error
         let _ = deposit_token::<T>(record.txid, who, record.balance);
         info!(
@@ -175,7 +468,234 @@ pub fn remove_pending_deposit<T: Config>(input_address: &BtcAddress, who: &T::Ac
     }
 }
 
-fn insert_pending_deposit<T: Config>(input_addr: &Address, txid: H256, balance: u64) {
+/// Move the pending deposits of an address which are expired into the refund list.
+pub fn expire_pending_deposits<T: Config>(input_address: &BtcAddress) -> DispatchResult {
+    let expiry = Pallet::<T>::pending_deposit_expiry();
+    ensure!(expiry > 0, Error::<T>::NoExpiredDeposit);
+    // the expired deposits of this address could not be changed during refunding
+    ensure!(
+        !ProposalRefunds::<T>::iter_values().any(|refunds| refunds.contains(input_address)),
+        Error::<T>::RefundInProgress
+    );
+
+    let best_height = Pallet::<T>::best_index().height;
+    let (expired, pending): (Vec<_>, Vec<_>) = PendingDeposits::<T>::get(input_address)
+        .into_iter()
+        .partition(|record| {
+            // the inclusion height of legacy deposits is seeded by migration, a deposit without
+            // the height is treated as just included
+            let height = PendingDepositHeight::<T>::get(&record.txid).unwrap_or(best_height);
+            best_height.saturating_sub(height) >= expiry
+        });
+    ensure!(!expired.is_empty(), Error::<T>::NoExpiredDeposit);
+    // the refund pays the withdrawal fee as well, the deposits which could not cover the fee
+    // are kept pending until they are claimed or more deposits of the address expire
+    let refund = ExpiredDeposits::<T>::get(input_address)
+        .iter()
+        .chain(expired.iter())
+        .map(|record| record.balance)
+        .sum::<u64>();
+    ensure!(
+        refund > Pallet::<T>::btc_withdrawal_fee(),
+        Error::<T>::DustRefund
+    );
+
+    for record in expired.iter() {
+        release_pending_deposit::<T>(&record.txid, input_address);
+        info!(
+            target: "runtime::bitcoin",
+            "[expire_pending_deposits] Pending deposit expired, addr:{:?}, balance:{}, cached_tx:{:?}",
+            try_str(input_address), record.balance, record.txid,
+        );
+        Pallet::<T>::deposit_event(Event::<T>::PendingDepositExpired(
+            record.txid,
+            input_address.clone(),
+            record.balance.saturated_into(),
+        ));
+    }
+    ExpiredDeposits::<T>::mutate(input_address, |list| list.extend(expired));
+    if pending.is_empty() {
+        PendingDeposits::<T>::remove(input_address);
+    } else {
+        PendingDeposits::<T>::insert(input_address, pending);
+    }
+    Ok(())
+}
+
+/// Seed the inclusion height of the pending deposits recorded before the height is tracked,
+/// the expiry of them starts from the best height at the time of upgrade.
+pub(crate) fn migrate_pending_deposit_height<T: Config>() -> Weight {
+    let best_height = Pallet::<T>::best_index().height;
+    let mut reads = 1;
+    let mut writes = 0;
+    for (_, records) in PendingDeposits::<T>::iter() {
+        for record in records {
+            reads += 1;
+            if !PendingDepositHeight::<T>::contains_key(&record.txid) {
+                PendingDepositHeight::<T>::insert(&record.txid, best_height);
+                writes += 1;
+            }
+        }
+    }
+    info!(
+        target: "runtime::bitcoin",
+        "[migrate_pending_deposit_height] Seed the height of {} pending deposits with {}",
+        writes,
+        best_height
+    );
+    T::DbWeight::get().reads_writes(reads, writes)
+}
+
+/// Freeze the deposits applied from a block which is flipped out of the main chain, and queue
+/// them for governance resolution. The unclaimed pending deposits of the block are dropped,
+/// and the trustee utxo updates of the txs in the block are reverted.
+///
+/// The txs could be pushed again once they are included by the new main chain.
+pub fn freeze_reorged_deposits<T: Config>(block_hash: H256) {
+    let id: AssetId = <Pallet<T> as ChainT<_>>::ASSET_ID;
+    // revert in the reverse order of processing, a tx may spend the trustee outputs of a
+    // previous tx in the same block
+    for txid in BlockTxs::<T>::take(&block_hash).into_iter().rev() {
+        let reverted = revert_trustee_utxos::<T>(txid);
+        match TxState::<T>::get(&txid) {
+            Some(state) if state.tx_type == BtcTxType::Deposit => TxState::<T>::remove(&txid),
+            // the other processed txs would not be processed twice, only their trustee utxo
+            // updates are applied again once they are included by the new main chain
+            Some(state) if reverted && state.result == BtcTxResult::Success => {
+                ReorgedTrusteeTxs::<T>::insert(&txid, block_hash)
+            }
+            _ => {}
+        }
+        drop_reorged_pending_deposit::<T>(txid, block_hash);
+        let (who, credited) = match TxDepositOf::<T>::take(&txid) {
+            Some(deposit) => deposit,
+            None => continue,
+        };
+        // the depositor may have spent some of the credited balance
+        let usable = xpallet_assets::Pallet::<T>::usable_balance(&who, &id);
+        let mut frozen = usable.min(credited);
+        if let Err(err) = xpallet_assets::Pallet::<T>::move_balance(
+            &id,
+            &who,
+            AssetType::Usable,
+            &who,
+            AssetType::Locked,
+            frozen,
+        ) {
+            error!(
+                target: "runtime::bitcoin",
+                "[freeze_reorged_deposits] Freeze deposit ({:?}) error:{:?}, must use root to fix it",
+                hash_rev(txid),
+                err
+            );
+            frozen = Zero::zero();
+        }
+        warn!(
+            target: "runtime::bitcoin",
+            "[freeze_reorged_deposits] Deposit tx ({:?}) in block ({:?}) is reorged, who:{:?}, credited:{:?}, frozen:{:?}",
+            hash_rev(txid),
+            hash_rev(block_hash),
+            who,
+            credited,
+            frozen
+        );
+
+        ReorgedDeposits::<T>::insert(
+            &txid,
+            BtcReorgedDeposit {
+                block_hash,
+                who: who.clone(),
+                credited,
+                frozen,
+            },
+        );
+        Pallet::<T>::deposit_event(Event::<T>::DepositReorged(txid, block_hash, who, frozen));
+    }
+}
+
+/// Remove the shares of a reorged deposit which are still pending in the input addresses.
+fn drop_reorged_pending_deposit<T: Config>(txid: H256, block_hash: H256) {
+    let addrs = PendingDepositAddrs::<T>::take(&txid);
+    if addrs.is_empty() {
+        return;
+    }
+    PendingDepositHeight::<T>::remove(&txid);
+    for addr in addrs {
+        PendingDeposits::<T>::mutate_exists(&addr, |maybe_list| {
+            if let Some(list) = maybe_list {
+                list.retain(|record| record.txid != txid);
+                if list.is_empty() {
+                    *maybe_list = None;
+                }
+            }
+        });
+        warn!(
+            target: "runtime::bitcoin",
+            "[freeze_reorged_deposits] Pending deposit tx ({:?}) in block ({:?}) is reorged, addr:{:?}",
+            hash_rev(txid),
+            hash_rev(block_hash),
+            try_str(&addr)
+        );
+    }
+}
+
+/// Restore a reorged deposit which is included by the new main chain, the frozen balance is
+/// released to the depositor.
+pub fn restore_reorged_deposit<T: Config>(
+    txid: H256,
+    deposit: BtcReorgedDeposit<T::AccountId, BalanceOf<T>>,
+) -> DispatchResult {
+    info!(
+        target: "runtime::bitcoin",
+        "[restore_reorged_deposit] Deposit tx ({:?}) is included again, who:{:?}, frozen:{:?}",
+        hash_rev(txid),
+        deposit.who,
+        deposit.frozen
+    );
+    let (who, credited) = (deposit.who.clone(), deposit.credited);
+    resolve_reorged_deposit::<T>(deposit, true)?;
+    TxDepositOf::<T>::insert(txid, (who, credited));
+    Ok(())
+}
+
+/// Release the frozen balance of a reorged deposit to the depositor, or burn it.
+pub fn resolve_reorged_deposit<T: Config>(
+    deposit: BtcReorgedDeposit<T::AccountId, BalanceOf<T>>,
+    release: bool,
+) -> DispatchResult {
+    let id: AssetId = <Pallet<T> as ChainT<_>>::ASSET_ID;
+    if release {
+        xpallet_assets::Pallet::<T>::move_balance(
+            &id,
+            &deposit.who,
+            AssetType::Locked,
+            &deposit.who,
+            AssetType::Usable,
+            deposit.frozen,
+        )
+        .map_err::<xpallet_assets::Error<T>, _>(Into::into)?;
+    } else {
+        let locked = xpallet_assets::Pallet::<T>::locked_balance(&deposit.who, &id);
+        let mut balances = sp_std::collections::btree_map::BTreeMap::new();
+        balances.insert(AssetType::Locked, locked.saturating_sub(deposit.frozen));
+        xpallet_assets::Pallet::<T>::set_balance_impl(&deposit.who, &id, balances)?;
+    }
+    Ok(())
+}
+
+/// Release the inclusion height of a pending deposit, which is kept until all the shares of a
+/// split deposit are released.
+pub(crate) fn release_pending_deposit<T: Config>(txid: &H256, input_address: &BtcAddress) {
+    let mut addrs = PendingDepositAddrs::<T>::take(txid);
+    addrs.retain(|addr| addr != input_address);
+    if addrs.is_empty() {
+        PendingDepositHeight::<T>::remove(txid);
+    } else {
+        PendingDepositAddrs::<T>::insert(txid, addrs);
+    }
+}
+
+fn insert_pending_deposit<T: Config>(input_addr: &Address, txid: H256, height: u32, balance: u64) {
     let addr_bytes = input_addr.to_string().into_bytes();
 
     let cache = BtcDepositCache { txid, balance };
@@ -190,80 +710,101 @@ fn insert_pending_deposit<T: Config>(input_addr: &Address, txid: H256, balance:
                 balance
             );
             list.push(cache);
+            PendingDepositHeight::<T>::insert(&txid, height);
+            PendingDepositAddrs::<T>::append(&txid, addr_bytes.clone());
 
             Pallet::<T>::deposit_event(Event::<T>::UnclaimedDeposit(txid, addr_bytes.clone()));
         }
     });
 }
 
-fn withdraw<T: Config>(tx: Transaction) -> BtcTxResult {
-    if let Some(proposal) = WithdrawalProposal::<T>::take() {
+pub(crate) fn withdraw<T: Config>(tx: Transaction) -> BtcTxResult {
+    let tx_hash = tx.hash();
+    // the fee bump of proposal could be observed on-chain instead of the original one
+    let matched = WithdrawalProposals::<T>::iter().find(|(proposal_id, proposal)| {
+        proposal.tx.hash() == tx_hash
+            || FeeBumpProposals::<T>::get(proposal_id).map(|fee_bump| fee_bump.tx.hash())
+                == Some(tx_hash)
+    });
+    if let Some((proposal_id, proposal)) = matched {
         log::debug!(
             target: "runtime::bitcoin",
-            "[withdraw] Withdraw tx {:?}, proposal:{:?}",
-            proposal,
-            tx
+            "[withdraw] Withdraw tx {:?}, proposal_id:{}, proposal:{:?}",
+            tx,
+            proposal_id,
+            proposal
         );
-        let proposal_hash = proposal.tx.hash();
-        let tx_hash = tx.hash();
-
-        if proposal_hash == tx_hash {
-            let mut total = BalanceOf::<T>::zero();
-            for number in proposal.withdrawal_id_list.iter() {
-                // just for event record
-                let withdraw_balance =
-                    xpallet_gateway_records::Pallet::<T>::pending_withdrawals(number)
-                        .map(|record| record.balance())
-                        .unwrap_or_else(BalanceOf::<T>::zero);
-                total += withdraw_balance;
-
-                match xpallet_gateway_records::Pallet::<T>::finish_withdrawal(*number, None) {
-                    Ok(_) => {
-                        info!(target: "runtime::bitcoin", "[withdraw] Withdrawal ({}) completion", *number);
-                    }
-                    Err(err) => {
-                        error!(
-                            target: "runtime::bitcoin",
-                            "[withdraw] Withdrawal ({}) error:{:?}, must use root to fix it",
-                            *number, err
-                        );
-                    }
+        // the unfinished one of the proposal and its fee bump is replaced by the observed tx
+        WithdrawalProposals::<T>::remove(proposal_id);
+        trustee::record_removed_proposal_votes::<T>(proposal_id, false, &proposal);
+        if let Some(fee_bump) = FeeBumpProposals::<T>::take(proposal_id) {
+            trustee::record_removed_proposal_votes::<T>(proposal_id, true, &fee_bump);
+        }
+        ProposalSessions::<T>::remove(proposal_id);
+
+        let mut total = BalanceOf::<T>::zero();
+        for number in proposal.withdrawal_id_list.iter() {
+            // just for event record
+            let withdraw_balance =
+                xpallet_gateway_records::Pallet::<T>::pending_withdrawals(number)
+                    .map(|record| record.balance())
+                    .unwrap_or_else(BalanceOf::<T>::zero);
+            total += withdraw_balance;
+
+            // archive the txid in the display byte order used by the block explorers
+            match xpallet_gateway_records::Pallet::<T>::finish_withdrawal_with_tx(
+                *number,
+                None,
+                Some(hash_rev(tx_hash).as_bytes().to_vec()),
+            ) {
+                Ok(_) => {
+                    info!(target: "runtime::bitcoin", "[withdraw] Withdrawal ({}) completion", *number);
+                }
+                Err(err) => {
+                    error!(
+                        target: "runtime::bitcoin",
+                        "[withdraw] Withdrawal ({}) error:{:?}, must use root to fix it",
+                        *number, err
+                    );
                 }
             }
+        }
 
-            let btc_withdrawal_fee = Pallet::<T>::btc_withdrawal_fee();
-            // real withdraw value would reduce withdraw_fee
-            total -=
-                (proposal.withdrawal_id_list.len() as u64 * btc_withdrawal_fee).saturated_into();
-            Pallet::<T>::deposit_event(Event::<T>::Withdrawn(
-                tx_hash,
-                proposal.withdrawal_id_list,
-                total,
-            ));
-            BtcTxResult::Success
-        } else {
-            error!(
+        let btc_withdrawal_fee = Pallet::<T>::btc_withdrawal_fee();
+        for addr in ProposalRefunds::<T>::take(proposal_id) {
+            let refund = ExpiredDeposits::<T>::take(&addr)
+                .iter()
+                .map(|record| record.balance)
+                .sum::<u64>()
+                .saturating_sub(btc_withdrawal_fee);
+            info!(
                 target: "runtime::bitcoin",
-                "[withdraw] Withdraw error: mismatch (tx_hash:{:?}, proposal_hash:{:?}), id_list:{:?}, must use root to fix it",
-                tx_hash, proposal_hash, proposal.withdrawal_id_list
+                "[withdraw] Refund expired deposits, addr:{:?}, refund:{}",
+                try_str(&addr), refund
             );
-            // re-store proposal into storage.
-            WithdrawalProposal::<T>::put(proposal);
-
-            Pallet::<T>::deposit_event(Event::<T>::WithdrawalFatalErr(proposal_hash, tx_hash));
-            BtcTxResult::Failure
+            Pallet::<T>::deposit_event(Event::<T>::ExpiredDepositRefunded(
+                tx_hash,
+                addr,
+                refund.saturated_into(),
+            ));
         }
+
+        // real withdraw value would reduce withdraw_fee
+        total -= (proposal.withdrawal_id_list.len() as u64 * btc_withdrawal_fee).saturated_into();
+        Pallet::<T>::deposit_event(Event::<T>::Withdrawn(
+            tx_hash,
+            proposal.withdrawal_id_list,
+            total,
+        ));
+        BtcTxResult::Success
     } else {
         error!(
             target: "runtime::bitcoin",
-            "[withdraw] Withdrawal error: proposal is EMPTY (tx_hash:{:?}), but receive a withdrawal tx, must use root to fix it",
-            tx.hash()
+            "[withdraw] Withdrawal error: no proposal matches the withdrawal tx (tx_hash:{:?}), must use root to fix it",
+            tx_hash
         );
         // no proposal, but find a withdraw tx, it's a fatal error in withdrawal
-        Pallet::<T>::deposit_event(Event::<T>::WithdrawalFatalErr(
-            tx.hash(),
-            Default::default(),
-        ));
+        Pallet::<T>::deposit_event(Event::<T>::WithdrawalFatalErr(tx_hash, Default::default()));
 
         BtcTxResult::Failure
     }
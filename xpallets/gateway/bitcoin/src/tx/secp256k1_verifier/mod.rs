@@ -2,22 +2,47 @@
 
 use frame_support::dispatch::DispatchResult;
 
-use light_bitcoin::{chain::Transaction, primitives::Bytes};
+use light_bitcoin::{
+    chain::{Transaction, TransactionOutput},
+    primitives::Bytes,
+    script::check_taproot_tx,
+};
 
 use crate::types::BtcTxVerifier;
 use crate::{Config, Error, Pallet};
 
 mod recover_verifier;
+pub(crate) mod taproot;
 mod runtime_interface {
-    use super::*;
+    use sp_std::convert::TryFrom;
+
+    use light_bitcoin::script::{Script, SignatureVersion, TransactionInputSigner};
+
+    use super::{recover_verifier::Signature, *};
+
+    /// Verify the signature by the native secp256k1 through the host function of `xp-io`.
     pub fn verify_sig_impl<T: Config>(
-        _sig: &Bytes,
-        _pubkey: &Bytes,
-        _tx: &Transaction,
-        _script_pubkey: &Bytes,
-        _index: usize,
+        sig: &Bytes,
+        pubkey: &Bytes,
+        tx: &Transaction,
+        script_pubkey: &Bytes,
+        index: usize,
     ) -> DispatchResult {
-        Err(Error::<T>::VerifySignFailed.into())
+        let tx_signer: TransactionInputSigner = tx.clone().into();
+        let script_code: Script = script_pubkey.clone().into();
+        // Sighash all, same to the recover verifier
+        let sighashtype = 1;
+        let hash =
+            tx_signer.signature_hash(index, 0, &script_code, SignatureVersion::Base, sighashtype);
+
+        let signature = Signature::parse_der_lax(sig).map_err(|_| Error::<T>::ConstructBadSign)?;
+        let pubkey =
+            <[u8; 33]>::try_from(pubkey.as_slice()).map_err(|_| Error::<T>::InvalidPublicKey)?;
+        if xp_io::btc_crypto::ecdsa_verify(&signature.serialize(), hash.as_fixed_bytes(), &pubkey) {
+            Ok(())
+        } else {
+            Err(Error::<T>::VerifySignFailed.into())
+        }
     }
 }
 
@@ -39,3 +64,17 @@ pub fn verify_sig<T: Config>(
         BtcTxVerifier::Test => Ok(()),
     }
 }
+
+pub fn verify_taproot_sig<T: Config>(
+    tx: &Transaction,
+    spent_outputs: &[TransactionOutput],
+) -> DispatchResult {
+    match Pallet::<T>::verifier() {
+        BtcTxVerifier::Recover => {
+            check_taproot_tx(tx, spent_outputs).map_err(|_| Error::<T>::VerifySignFailed.into())
+        }
+        BtcTxVerifier::RuntimeInterface => taproot::verify_taproot_impl::<T>(tx, spent_outputs),
+        #[cfg(any(feature = "runtime-benchmarks", test))]
+        BtcTxVerifier::Test => Ok(()),
+    }
+}
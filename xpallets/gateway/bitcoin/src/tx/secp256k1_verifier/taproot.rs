@@ -0,0 +1,194 @@
+// Copyright 2019-2021 ChainX Project Authors. Licensed under GPL-3.0.
+
+//! Verify the taproot spending by the BIP-341 signature hash and the host functions of `xp-io`.
+
+use frame_support::{dispatch::DispatchResult, ensure};
+use sp_io::hashing::sha2_256;
+use sp_std::prelude::*;
+
+use light_bitcoin::chain::{Transaction, TransactionOutput};
+
+use crate::{Config, Error};
+
+/// The leaf version of tapscript.
+const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+/// The size of control block without the merkle path, i.e. leaf version + internal key.
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+/// hash = sha256(sha256(tag) + sha256(tag) + data)
+fn tagged_hash(tag: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = sha2_256(tag);
+    let mut input = Vec::with_capacity(64 + data.iter().map(|d| d.len()).sum::<usize>());
+    input.extend_from_slice(&tag_hash);
+    input.extend_from_slice(&tag_hash);
+    for d in data {
+        input.extend_from_slice(d);
+    }
+    sha2_256(&input)
+}
+
+fn write_compact_size(buf: &mut Vec<u8>, len: usize) {
+    if len < 0xfd {
+        buf.push(len as u8);
+    } else if len <= 0xffff {
+        buf.push(0xfd);
+        buf.extend_from_slice(&(len as u16).to_le_bytes());
+    } else {
+        buf.push(0xfe);
+        buf.extend_from_slice(&(len as u32).to_le_bytes());
+    }
+}
+
+fn write_output(buf: &mut Vec<u8>, output: &TransactionOutput) {
+    buf.extend_from_slice(&output.value.to_le_bytes());
+    write_compact_size(buf, output.script_pubkey.len());
+    buf.extend_from_slice(&output.script_pubkey[..]);
+}
+
+fn to_bytes32(data: &[u8]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(data);
+    bytes
+}
+
+/// The BIP-341 signature hash of the input with `SIGHASH_DEFAULT` and without annex,
+/// `leaf_hash` is the tapleaf hash of the script path spending.
+pub fn signature_hash(
+    tx: &Transaction,
+    spent_outputs: &[TransactionOutput],
+    index: usize,
+    leaf_hash: Option<&[u8; 32]>,
+) -> [u8; 32] {
+    let mut prevouts = Vec::with_capacity(tx.inputs.len() * 36);
+    let mut sequences = Vec::with_capacity(tx.inputs.len() * 4);
+    for input in &tx.inputs {
+        prevouts.extend_from_slice(input.previous_output.txid.as_bytes());
+        prevouts.extend_from_slice(&input.previous_output.index.to_le_bytes());
+        sequences.extend_from_slice(&input.sequence.to_le_bytes());
+    }
+    let mut amounts = Vec::with_capacity(spent_outputs.len() * 8);
+    let mut script_pubkeys = Vec::new();
+    for output in spent_outputs {
+        amounts.extend_from_slice(&output.value.to_le_bytes());
+        write_compact_size(&mut script_pubkeys, output.script_pubkey.len());
+        script_pubkeys.extend_from_slice(&output.script_pubkey[..]);
+    }
+    let mut outputs = Vec::new();
+    for output in &tx.outputs {
+        write_output(&mut outputs, output);
+    }
+
+    let mut msg = Vec::with_capacity(247);
+    // epoch and hash type
+    msg.extend_from_slice(&[0x00, 0x00]);
+    msg.extend_from_slice(&tx.version.to_le_bytes());
+    msg.extend_from_slice(&tx.lock_time.to_le_bytes());
+    msg.extend_from_slice(&sha2_256(&prevouts));
+    msg.extend_from_slice(&sha2_256(&amounts));
+    msg.extend_from_slice(&sha2_256(&script_pubkeys));
+    msg.extend_from_slice(&sha2_256(&sequences));
+    msg.extend_from_slice(&sha2_256(&outputs));
+    // spend type, `ext_flag * 2 + annex_present`
+    msg.push(if leaf_hash.is_some() { 2 } else { 0 });
+    msg.extend_from_slice(&(index as u32).to_le_bytes());
+    if let Some(leaf_hash) = leaf_hash {
+        msg.extend_from_slice(leaf_hash);
+        // key version, and no OP_CODESEPARATOR is executed
+        msg.push(0x00);
+        msg.extend_from_slice(&u32::MAX.to_le_bytes());
+    }
+    tagged_hash(b"TapSighash", &[&msg])
+}
+
+/// The merkle root of the script tree, which is computed by the leaf and the merkle path in the
+/// control block.
+fn merkle_root(leaf_hash: [u8; 32], path: &[u8]) -> [u8; 32] {
+    path.chunks(TAPROOT_CONTROL_NODE_SIZE)
+        .fold(leaf_hash, |node, branch| {
+            if node[..] <= branch[..] {
+                tagged_hash(b"TapBranch", &[&node, branch])
+            } else {
+                tagged_hash(b"TapBranch", &[branch, &node])
+            }
+        })
+}
+
+/// Verify the taproot spending of every input with the host functions.
+///
+/// Only the spending with `SIGHASH_DEFAULT` and without annex is supported, that is the key path
+/// spending, or the script path spending of a single key leaf (`<pubkey> OP_CHECKSIG`) like the
+/// leaves of the trustee MAST.
+pub fn verify_taproot_impl<T: Config>(
+    tx: &Transaction,
+    spent_outputs: &[TransactionOutput],
+) -> DispatchResult {
+    ensure!(
+        spent_outputs.len() == tx.inputs.len(),
+        Error::<T>::InvalidSpentOutputs
+    );
+    for (index, (input, spent)) in tx.inputs.iter().zip(spent_outputs).enumerate() {
+        let script_pubkey = &spent.script_pubkey[..];
+        // OP_1 + OP_PUSHBYTES_32 + <32 bytes output key>
+        ensure!(
+            script_pubkey.len() == 34 && script_pubkey[0] == 0x51 && script_pubkey[1] == 0x20,
+            Error::<T>::InvalidPublicKey
+        );
+        let output_key = to_bytes32(&script_pubkey[2..]);
+
+        let witness = &input.script_witness;
+        ensure!(
+            !witness.is_empty() && witness[0].len() == 64,
+            Error::<T>::ConstructBadSign
+        );
+        let mut sig = [0u8; 64];
+        sig.copy_from_slice(&witness[0][..]);
+
+        let verified = match witness.len() {
+            // key path: [signature]
+            1 => {
+                let msg = signature_hash(tx, spent_outputs, index, None);
+                xp_io::btc_crypto::schnorr_verify(&sig, &msg, &output_key)
+            }
+            // script path: [signature, script, control block]
+            3 => {
+                let script = &witness[1][..];
+                let control = &witness[2][..];
+                // OP_PUSHBYTES_32 + <32 bytes x-only public key> + OP_CHECKSIG
+                ensure!(
+                    script.len() == 34 && script[0] == 0x20 && script[33] == 0xac,
+                    Error::<T>::InvalidPublicKey
+                );
+                let path_len = control.len().saturating_sub(TAPROOT_CONTROL_BASE_SIZE);
+                ensure!(
+                    control.len() >= TAPROOT_CONTROL_BASE_SIZE
+                        && path_len % TAPROOT_CONTROL_NODE_SIZE == 0
+                        && path_len / TAPROOT_CONTROL_NODE_SIZE <= TAPROOT_CONTROL_MAX_NODE_COUNT
+                        && control[0] & 0xfe == TAPROOT_LEAF_TAPSCRIPT,
+                    Error::<T>::VerifySignFailed
+                );
+
+                let mut leaf = vec![TAPROOT_LEAF_TAPSCRIPT];
+                write_compact_size(&mut leaf, script.len());
+                leaf.extend_from_slice(script);
+                let leaf_hash = tagged_hash(b"TapLeaf", &[&leaf]);
+                let internal_key = to_bytes32(&control[1..TAPROOT_CONTROL_BASE_SIZE]);
+                let root = merkle_root(leaf_hash, &control[TAPROOT_CONTROL_BASE_SIZE..]);
+                let tweak = tagged_hash(b"TapTweak", &[&internal_key, &root]);
+
+                let msg = signature_hash(tx, spent_outputs, index, Some(&leaf_hash));
+                xp_io::btc_crypto::taproot_tweak_verify(
+                    &internal_key,
+                    &tweak,
+                    &output_key,
+                    control[0] & 1 == 1,
+                ) && xp_io::btc_crypto::schnorr_verify(&sig, &msg, &to_bytes32(&script[1..33]))
+            }
+            // the annex and other scripts are not supported
+            _ => false,
+        };
+        ensure!(verified, Error::<T>::VerifySignFailed);
+    }
+    Ok(())
+}
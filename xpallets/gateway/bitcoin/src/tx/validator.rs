@@ -11,7 +11,7 @@ use light_bitcoin::{
     chain::{Transaction, TransactionOutput},
     keys::{AddressTypes, XOnly},
     primitives::H256,
-    script::{check_taproot_tx, Script},
+    script::Script,
 };
 
 use crate::{
@@ -23,7 +23,7 @@ use crate::{
 pub fn validate_transaction<T: Config>(
     tx: &BtcRelayedTx,
     merkle_root: H256,
-    prev_tx: Option<&Transaction>,
+    prev_txs: &[Transaction],
 ) -> DispatchResult {
     let tx_hash = tx.raw.hash();
     debug!(
@@ -55,19 +55,33 @@ pub fn validate_transaction<T: Config>(
         return Err(Error::<T>::BadMerkleProof.into());
     }
 
-    if let Some(prev) = prev_tx {
-        // verify prev tx for input
-        // only check the first(0) input in transaction
+    // verify prev txs for inputs, each of them must be spent by an input of the transaction,
+    // the prev txs are verified by txid without merkle proof
+    let mut previous_txids = Vec::with_capacity(prev_txs.len());
+    for prev in prev_txs {
         let previous_txid = prev.hash();
-        let expected_id = tx.raw.inputs[0].previous_output.txid;
-        if previous_txid != expected_id {
+        if previous_txids.contains(&previous_txid) {
             error!(
                 target: "runtime::bitcoin",
-                "[validate_transaction] Relay previous tx's hash not equal to relay tx first input, expected_id:{:?}, prev:{:?}",
-                expected_id, previous_txid
+                "[validate_transaction] Relay duplicated previous tx:{:?}",
+                previous_txid
             );
             return Err(Error::<T>::InvalidPrevTx.into());
         }
+        if !tx
+            .raw
+            .inputs
+            .iter()
+            .any(|input| input.previous_output.txid == previous_txid)
+        {
+            error!(
+                target: "runtime::bitcoin",
+                "[validate_transaction] Relay previous tx's hash not spent by any input of relay tx, prev:{:?}",
+                previous_txid
+            );
+            return Err(Error::<T>::InvalidPrevTx.into());
+        }
+        previous_txids.push(previous_txid);
     }
     Ok(())
 }
@@ -105,11 +119,8 @@ pub fn parse_check_taproot_tx<T: Config>(
     if AddressTypes::WitnessV1Taproot(tweak_pubkey) != hot_addr.hash {
         return Err(Error::<T>::InvalidPublicKey.into());
     }
-    if check_taproot_tx(tx, spent_outputs).is_err() {
-        Err(Error::<T>::VerifySignFailed.into())
-    } else {
-        Ok(true)
-    }
+    super::secp256k1_verifier::verify_taproot_sig::<T>(tx, spent_outputs)?;
+    Ok(true)
 }
 
 /// for test convenient
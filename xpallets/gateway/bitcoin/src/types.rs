@@ -10,7 +10,7 @@ use sp_std::prelude::*;
 
 use light_bitcoin::{
     chain::{BlockHeader as BtcHeader, Transaction as BtcTransaction},
-    keys::Address,
+    keys::{Address, Network},
     merkle::PartialMerkleTree,
     primitives::{Compact, H256},
 };
@@ -59,13 +59,26 @@ pub struct BtcHeaderIndex {
     pub height: u32,
 }
 
+/// The last retarget boundary header reached by the pruning, which is kept to calculate the
+/// work of the next retarget.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct BtcHeaderCheckpoint {
+    pub height: u32,
+    pub hash: H256,
+    pub time: u32,
+    pub bits: Compact,
+}
+
 #[derive(PartialEq, Clone, Copy, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
 pub struct BtcTxState {
     pub tx_type: BtcTxType,
     pub result: BtcTxResult,
 }
 
 #[derive(PartialEq, Clone, Copy, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum BtcTxResult {
     Success,
     Failure,
@@ -74,8 +87,8 @@ pub enum BtcTxResult {
 pub enum AccountInfo<AccountId> {
     /// A value of type `L`.
     Account((AccountId, Option<ReferralId>)),
-    /// A value of type `R`.
-    Address(Address),
+    /// The deposit value parked for each input address.
+    Addresses(Vec<(Address, u64)>),
 }
 
 #[derive(PartialEq, Clone, Encode, Decode, Default, RuntimeDebug, TypeInfo)]
@@ -84,6 +97,44 @@ pub struct BtcDepositCache {
     pub balance: u64,
 }
 
+/// A deposit which was applied from a block that is flipped out of the main chain later.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct BtcReorgedDeposit<AccountId, Balance> {
+    pub block_hash: H256,
+    pub who: AccountId,
+    /// the balance credited by this deposit
+    pub credited: Balance,
+    /// the balance moved from `Usable` to `Locked`, could be less than `credited`
+    pub frozen: Balance,
+}
+
+/// An unspent output of the trustee hot or cold address.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct BtcTrusteeUtxo {
+    pub value: u64,
+    pub addr: BtcAddress,
+}
+
+/// The BTC held by trustee addresses against the liabilities of X-BTC.
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct BtcProofOfReserves<Balance> {
+    /// total X-BTC issued, of all asset types
+    pub total_issuance: Balance,
+    /// BTC held by current and previous trustee addresses
+    pub trustee_balance: u64,
+    /// BTC deposited but not credited yet, including the expired pending deposits
+    pub pending_deposits: u64,
+    /// X-BTC of withdrawal applications in flight, which is a part of `total_issuance`
+    pub pending_withdrawals: Balance,
+    /// whether the trustee utxo set is initialized, otherwise `trustee_balance` is incomplete
+    /// and both `surplus` and `deficit` are zero
+    pub trustee_utxos_initialized: bool,
+    /// `trustee_balance - total_issuance - pending_deposits`, if positive
+    pub surplus: Balance,
+    /// `total_issuance + pending_deposits - trustee_balance`, if positive
+    pub deficit: Balance,
+}
+
 #[derive(PartialEq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
 pub struct BtcWithdrawalProposal<AccountId> {
     pub sig_state: VoteResult,
@@ -115,6 +166,52 @@ pub enum VoteResult {
     Finish,
 }
 
+/// The bitcoin network tracked by the light client.
+///
+/// The network decides the consensus rules of the headers, the address format of regtest and
+/// signet is the same as testnet.
+///
+/// NOTE: `BtcNetwork` used to be the re-export of `light_bitcoin::keys::Network`, the encoding
+/// of `Mainnet` and `Testnet` is unchanged, use `Network::from` for the address network.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum BtcNetwork {
+    Mainnet,
+    Testnet,
+    /// No retargeting and the trivial proof of work.
+    Regtest,
+    /// The blocks are signed by the signet challenge, which is committed in the coinbase.
+    Signet,
+}
+
+impl Default for BtcNetwork {
+    fn default() -> Self {
+        Self::Mainnet
+    }
+}
+
+impl BtcNetwork {
+    /// Whether a block could use the minimum difficulty when it is mined more than twice the
+    /// target spacing (20 minutes) after its parent.
+    pub fn allow_min_difficulty_blocks(&self) -> bool {
+        matches!(self, Self::Testnet | Self::Regtest)
+    }
+
+    /// Whether the difficulty is kept unchanged at the retarget heights.
+    pub fn no_retargeting(&self) -> bool {
+        matches!(self, Self::Regtest)
+    }
+}
+
+impl From<BtcNetwork> for Network {
+    fn from(network: BtcNetwork) -> Self {
+        match network {
+            BtcNetwork::Mainnet => Network::Mainnet,
+            BtcNetwork::Testnet | BtcNetwork::Regtest | BtcNetwork::Signet => Network::Testnet,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Default, RuntimeDebug, TypeInfo)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
@@ -153,6 +250,22 @@ impl BtcParams {
         }
     }
 
+    /// The default params of the network, the max bits is the proof of work limit.
+    pub fn from_network(network: BtcNetwork) -> BtcParams {
+        let max_bits = match network {
+            BtcNetwork::Mainnet | BtcNetwork::Testnet => 0x1d00ffff,
+            BtcNetwork::Regtest => 0x207fffff,
+            BtcNetwork::Signet => 0x1e0377ae,
+        };
+        Self::new(
+            max_bits,
+            2 * 60 * 60,          // block_max_future
+            2 * 7 * 24 * 60 * 60, // target_timespan_seconds
+            10 * 60,              // target_spacing_seconds
+            4,                    // retargeting_factor
+        )
+    }
+
     pub fn max_bits(&self) -> Compact {
         Compact::new(self.max_bits)
     }
@@ -162,6 +275,9 @@ impl BtcParams {
     pub fn target_timespan_seconds(&self) -> u32 {
         self.target_timespan_seconds
     }
+    pub fn target_spacing_seconds(&self) -> u32 {
+        self.target_spacing_seconds
+    }
 
     pub fn retargeting_interval(&self) -> u32 {
         self.retargeting_interval
@@ -179,6 +295,8 @@ impl BtcParams {
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum BtcTxVerifier {
     Recover,
+    /// Verify by the `xp_io::btc_crypto` host functions, which only exist on the upgraded node,
+    /// so switch to it only after the validators have upgraded their nodes.
     RuntimeInterface,
     #[cfg(any(feature = "runtime-benchmarks", test))]
     /// Test would ignore sign check and always return true
@@ -31,16 +31,29 @@ use sp_std::marker::PhantomData;
 /// Weight functions needed for xpallet_gateway_bitcoin.
 pub trait WeightInfo {
     fn push_header() -> Weight;
+    fn push_headers(n: u32) -> Weight;
     fn push_transaction() -> Weight;
     fn create_withdraw_tx() -> Weight;
     fn sign_withdraw_tx() -> Weight;
     fn set_best_index() -> Weight;
     fn set_confirmed_index() -> Weight;
     fn remove_pending() -> Weight;
+    fn claim_pending_deposit(n: u32) -> Weight;
     fn remove_proposal() -> Weight;
     fn force_replace_proposal_tx() -> Weight;
     fn set_btc_withdrawal_fee() -> Weight;
     fn set_btc_deposit_limit() -> Weight;
+    fn set_header_retention_depth() -> Weight;
+    fn prune_header() -> Weight;
+    fn set_pending_deposit_expiry() -> Weight;
+    fn expire_pending_deposits() -> Weight;
+    fn verify_sig_recover() -> Weight;
+    fn verify_sig_runtime_interface() -> Weight;
+    fn set_withdrawal_fee_rate() -> Weight;
+    fn bump_withdrawal_fee() -> Weight;
+    fn resolve_reorged_deposit() -> Weight;
+    fn set_trustee_utxo() -> Weight;
+    fn report_unauthorized_spend(n: u32) -> Weight;
 }
 
 /// Weights for xpallet_gateway_bitcoin using the Substrate node and recommended hardware.
@@ -51,6 +64,16 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
             .saturating_add(T::DbWeight::get().reads(10_u64))
             .saturating_add(T::DbWeight::get().writes(5_u64))
     }
+    // TODO: regenerate with the `push_headers` benchmark, which covers the batches up to
+    // `MAX_HEADERS_PER_BATCH`, the per header component is taken from `push_header`.
+    fn push_headers(n: u32) -> Weight {
+        (31_052_000_u64)
+            .saturating_add((172_185_000_u64).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(4_u64))
+            .saturating_add(T::DbWeight::get().reads((8_u64).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(2_u64))
+            .saturating_add(T::DbWeight::get().writes((4_u64).saturating_mul(n as Weight)))
+    }
     fn push_transaction() -> Weight {
         (821_219_000_u64)
             .saturating_add(T::DbWeight::get().reads(21_u64))
@@ -77,6 +100,16 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
             .saturating_add(T::DbWeight::get().reads(9_u64))
             .saturating_add(T::DbWeight::get().writes(5_u64))
     }
+    // TODO: regenerate with the `claim_pending_deposit` benchmark over the count of deposits,
+    // the per deposit component is split from the result of one pending deposit by hand.
+    fn claim_pending_deposit(n: u32) -> Weight {
+        (496_130_000_u64)
+            .saturating_add((116_277_000_u64).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(7_u64))
+            .saturating_add(T::DbWeight::get().reads((4_u64).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(3_u64))
+            .saturating_add(T::DbWeight::get().writes((4_u64).saturating_mul(n as Weight)))
+    }
     fn remove_proposal() -> Weight {
         (4_976_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
     }
@@ -91,6 +124,53 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
     fn set_btc_deposit_limit() -> Weight {
         (4_570_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
     }
+    fn set_header_retention_depth() -> Weight {
+        (4_622_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    fn prune_header() -> Weight {
+        (21_736_000_u64)
+            .saturating_add(T::DbWeight::get().reads(4_u64))
+            .saturating_add(T::DbWeight::get().writes(6_u64))
+    }
+    fn set_pending_deposit_expiry() -> Weight {
+        (2_724_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    fn expire_pending_deposits() -> Weight {
+        (68_352_000_u64)
+            .saturating_add(T::DbWeight::get().reads(6_u64))
+            .saturating_add(T::DbWeight::get().writes(5_u64))
+    }
+    fn verify_sig_recover() -> Weight {
+        (1_473_612_000_u64).saturating_add(T::DbWeight::get().reads(1_u64))
+    }
+    fn verify_sig_runtime_interface() -> Weight {
+        (326_905_000_u64).saturating_add(T::DbWeight::get().reads(1_u64))
+    }
+    fn set_withdrawal_fee_rate() -> Weight {
+        (2_174_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    fn bump_withdrawal_fee() -> Weight {
+        (321_483_000_u64)
+            .saturating_add(T::DbWeight::get().reads(8_u64))
+            .saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    fn resolve_reorged_deposit() -> Weight {
+        (63_208_000_u64)
+            .saturating_add(T::DbWeight::get().reads(5_u64))
+            .saturating_add(T::DbWeight::get().writes(4_u64))
+    }
+    fn set_trustee_utxo() -> Weight {
+        (2_236_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    // TODO: regenerate with the `report_unauthorized_spend` benchmark, it is estimated by
+    // `push_transaction` with a prev tx per input and the slash of at most 15 trustees of each of
+    // the two sessions.
+    fn report_unauthorized_spend(n: u32) -> Weight {
+        (821_219_000_u64)
+            .saturating_add((52_433_000_u64).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(102_u64))
+            .saturating_add(T::DbWeight::get().writes(91_u64))
+    }
 }
 
 // For backwards compatibility and tests
@@ -100,6 +180,16 @@ impl WeightInfo for () {
             .saturating_add(RocksDbWeight::get().reads(10_u64))
             .saturating_add(RocksDbWeight::get().writes(5_u64))
     }
+    // TODO: regenerate with the `push_headers` benchmark, which covers the batches up to
+    // `MAX_HEADERS_PER_BATCH`, the per header component is taken from `push_header`.
+    fn push_headers(n: u32) -> Weight {
+        (31_052_000_u64)
+            .saturating_add((172_185_000_u64).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(4_u64))
+            .saturating_add(RocksDbWeight::get().reads((8_u64).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(2_u64))
+            .saturating_add(RocksDbWeight::get().writes((4_u64).saturating_mul(n as Weight)))
+    }
     fn push_transaction() -> Weight {
         (821_219_000_u64)
             .saturating_add(RocksDbWeight::get().reads(21_u64))
@@ -126,6 +216,16 @@ impl WeightInfo for () {
             .saturating_add(RocksDbWeight::get().reads(9_u64))
             .saturating_add(RocksDbWeight::get().writes(5_u64))
     }
+    // TODO: regenerate with the `claim_pending_deposit` benchmark over the count of deposits,
+    // the per deposit component is split from the result of one pending deposit by hand.
+    fn claim_pending_deposit(n: u32) -> Weight {
+        (496_130_000_u64)
+            .saturating_add((116_277_000_u64).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(7_u64))
+            .saturating_add(RocksDbWeight::get().reads((4_u64).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(3_u64))
+            .saturating_add(RocksDbWeight::get().writes((4_u64).saturating_mul(n as Weight)))
+    }
     fn remove_proposal() -> Weight {
         (4_976_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
     }
@@ -140,4 +240,51 @@ impl WeightInfo for () {
     fn set_btc_deposit_limit() -> Weight {
         (4_570_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
     }
+    fn set_header_retention_depth() -> Weight {
+        (4_622_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    fn prune_header() -> Weight {
+        (21_736_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(4_u64))
+            .saturating_add(RocksDbWeight::get().writes(6_u64))
+    }
+    fn set_pending_deposit_expiry() -> Weight {
+        (2_724_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    fn expire_pending_deposits() -> Weight {
+        (68_352_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(6_u64))
+            .saturating_add(RocksDbWeight::get().writes(5_u64))
+    }
+    fn verify_sig_recover() -> Weight {
+        (1_473_612_000_u64).saturating_add(RocksDbWeight::get().reads(1_u64))
+    }
+    fn verify_sig_runtime_interface() -> Weight {
+        (326_905_000_u64).saturating_add(RocksDbWeight::get().reads(1_u64))
+    }
+    fn set_withdrawal_fee_rate() -> Weight {
+        (2_174_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    fn bump_withdrawal_fee() -> Weight {
+        (321_483_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(8_u64))
+            .saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    fn resolve_reorged_deposit() -> Weight {
+        (63_208_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(5_u64))
+            .saturating_add(RocksDbWeight::get().writes(4_u64))
+    }
+    fn set_trustee_utxo() -> Weight {
+        (2_236_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    // TODO: regenerate with the `report_unauthorized_spend` benchmark, it is estimated by
+    // `push_transaction` with a prev tx per input and the slash of at most 15 trustees of each of
+    // the two sessions.
+    fn report_unauthorized_spend(n: u32) -> Weight {
+        (821_219_000_u64)
+            .saturating_add((52_433_000_u64).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(102_u64))
+            .saturating_add(RocksDbWeight::get().writes(91_u64))
+    }
 }
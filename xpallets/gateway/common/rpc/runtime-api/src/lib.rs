@@ -15,7 +15,7 @@ pub use xp_runtime::Memo;
 pub use xpallet_assets::{Chain, WithdrawalLimit};
 pub use xpallet_gateway_common::{
     trustees,
-    types::{GenericTrusteeIntentionProps, GenericTrusteeSessionInfo},
+    types::{GenericTrusteeIntentionProps, GenericTrusteeSessionInfo, TrusteeStats},
 };
 
 sp_api::decl_runtime_apis! {
@@ -39,5 +39,8 @@ sp_api::decl_runtime_apis! {
         fn trustee_session_info(chain: Chain) -> Option<GenericTrusteeSessionInfo<AccountId>>;
 
         fn generate_trustee_session_info(chain: Chain, Vec<AccountId>) -> Result<GenericTrusteeSessionInfo<AccountId>, DispatchError>;
+
+        /// Get the signing statistics of the trustees in a session, `None` for the current session.
+        fn trustee_stats(chain: Chain, session_number: Option<u32>) -> Vec<(AccountId, TrusteeStats)>;
     }
 }
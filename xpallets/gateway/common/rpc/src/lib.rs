@@ -24,8 +24,8 @@ use xpallet_gateway_common_rpc_runtime_api::trustees::bitcoin::{
     BtcTrusteeIntentionProps, BtcTrusteeSessionInfo,
 };
 use xpallet_gateway_common_rpc_runtime_api::{
-    AssetId, Chain, GenericTrusteeIntentionProps, GenericTrusteeSessionInfo, WithdrawalLimit,
-    XGatewayCommonApi as XGatewayCommonRuntimeApi,
+    AssetId, Chain, GenericTrusteeIntentionProps, GenericTrusteeSessionInfo, TrusteeStats,
+    WithdrawalLimit, XGatewayCommonApi as XGatewayCommonRuntimeApi,
 };
 
 /// XGatewayCommon RPC methods.
@@ -87,6 +87,14 @@ where
         candidates: Vec<AccountId>,
         at: Option<BlockHash>,
     ) -> Result<BtcTrusteeSessionInfo<AccountId>>;
+
+    /// Return the signing statistics (proposals seen/signed/rejected/missed) of bitcoin trustees in a session, default to the current session
+    #[rpc(name = "xgatewaycommon_bitcoinTrusteeStats")]
+    fn btc_trustee_stats(
+        &self,
+        session_number: Option<u32>,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(AccountId, TrusteeStats)>>;
 }
 
 /// A struct that implements the [`XStakingApi`].
@@ -290,4 +298,19 @@ where
         let info = self.generate_generic_trustee_session_info(Chain::Bitcoin, candidates, at)?;
         BtcTrusteeSessionInfo::<_>::try_from(info).map_err(trustee_decode_error_into_rpc_err)
     }
+
+    fn btc_trustee_stats(
+        &self,
+        session_number: Option<u32>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(AccountId, TrusteeStats)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        let result = api
+            .trustee_stats(&at, Chain::Bitcoin, session_number)
+            .map_err(runtime_error_into_rpc_err)?;
+
+        Ok(result)
+    }
 }
@@ -2,6 +2,7 @@
 
 use codec::{Decode, Encode};
 use frame_benchmarking::benchmarks;
+use frame_support::traits::{Currency, Get, ReservableCurrency};
 use frame_system::RawOrigin;
 use sp_core::crypto::AccountId32;
 use sp_runtime::traits::StaticLookup;
@@ -11,7 +12,10 @@ use chainx_primitives::AssetId;
 use xpallet_assets::{BalanceOf, Chain};
 use xpallet_gateway_records::{Pallet as XGatewayRecords, WithdrawalRecordId, WithdrawalState};
 
-use crate::{types::*, Call, Config, Pallet, TrusteeMultiSigAddr};
+use crate::{
+    types::*, Call, Config, DrainingSessionOf, Pallet, TrusteeBondOf, TrusteeMultiSigAddr,
+    WithdrawalRateLimitOf,
+};
 
 const ASSET_ID: AssetId = xp_protocol::X_BTC;
 
@@ -34,6 +38,35 @@ fn charlie<T: Config>() -> T::AccountId {
     // sr25519 Charlie
     account::<T>("90b5ab205c6974c9ea841be688864633dc9ca8a357843eeacf2314649965fe22")
 }
+fn fund_trustee_bond<T: Config>(who: &T::AccountId) {
+    let amount = T::TrusteeBond::get() + <T as xpallet_assets::Config>::Currency::minimum_balance();
+    <T as xpallet_assets::Config>::Currency::make_free_balance_be(who, amount);
+}
+fn queue_withdrawal<T: Config>(caller: &T::AccountId) {
+    let amount: BalanceOf<T> = 1_000_000_000u32.into();
+    XGatewayRecords::<T>::deposit(caller, ASSET_ID, amount).unwrap();
+    WithdrawalRateLimitOf::<T>::insert(
+        ASSET_ID,
+        WithdrawalRateLimit {
+            window: 100u32.into(),
+            asset_cap: 0u32.into(),
+            account_cap: 0u32.into(),
+            delay: 10u32.into(),
+            breaker_threshold: amount,
+        },
+    );
+    let addr = b"3PgYgJA6h5xPEc3HbnZrUZWkpRxuCZVyEP".to_vec();
+    let memo = b"".to_vec().into();
+    Pallet::<T>::withdraw(
+        RawOrigin::Signed(caller.clone()).into(),
+        ASSET_ID,
+        amount,
+        addr,
+        memo,
+    )
+    .unwrap();
+    assert!(Pallet::<T>::queued_withdrawals(0).is_some());
+}
 fn new_trustees<T: Config>() -> Vec<(T::AccountId, Vec<u8>, Vec<u8>, Vec<u8>)> {
     vec![
         (
@@ -109,6 +142,7 @@ benchmarks! {
 
     setup_trustee {
         let caller: T::AccountId = alice::<T>();
+        fund_trustee_bond::<T>(&caller);
         let hot = hex::decode("02df92e88c4380778c9c48268460a124a8f4e7da883f80477deaa644ced486efc6")
                 .unwrap();
         let cold = hex::decode("0386b58f51da9b37e59c40262153173bdb59d7e4e45b73994b99eec4d964ee7e88")
@@ -126,6 +160,7 @@ benchmarks! {
 
         let mut candidators = vec![];
         for (account, about, hot, cold) in new_trustees::<T>() {
+            fund_trustee_bond::<T>(&account);
             Pallet::<T>::setup_trustee_impl(account.clone(), Chain::Bitcoin, about, hot, cold).unwrap();
             candidators.push(account);
         }
@@ -182,6 +217,64 @@ benchmarks! {
     verify {
         assert_eq!(Pallet::<T>::referral_binding_of(&who, Chain::Bitcoin), Some(who));
     }
+
+    force_finish_draining {
+        DrainingSessionOf::<T>::insert(Chain::Bitcoin, 0);
+    }: _(RawOrigin::Root, Chain::Bitcoin)
+    verify {
+        assert!(Pallet::<T>::draining_session_of(Chain::Bitcoin).is_none());
+    }
+
+    set_max_missed_proposals {
+    }: _(RawOrigin::Root, Chain::Bitcoin, Some(10))
+    verify {
+        assert_eq!(Pallet::<T>::max_missed_proposals_of(Chain::Bitcoin), Some(10));
+    }
+
+    withdraw_trustee_bond {
+        let caller: T::AccountId = alice::<T>();
+        let bond: BalanceOf<T> = 100u32.into();
+        <T as xpallet_assets::Config>::Currency::make_free_balance_be(&caller, bond + bond);
+        <T as xpallet_assets::Config>::Currency::reserve(&caller, bond).unwrap();
+        TrusteeBondOf::<T>::insert(&caller, Chain::Bitcoin, bond);
+    }: _(RawOrigin::Signed(caller.clone()), Chain::Bitcoin)
+    verify {
+        assert_eq!(Pallet::<T>::trustee_bond_of(&caller, Chain::Bitcoin), 0u32.into());
+    }
+
+    set_withdrawal_rate_limit {
+        let limit = WithdrawalRateLimit {
+            window: 100u32.into(),
+            asset_cap: 1_000_000u32.into(),
+            account_cap: 100_000u32.into(),
+            delay: 10u32.into(),
+            breaker_threshold: 2_000_000u32.into(),
+        };
+    }: _(RawOrigin::Root, ASSET_ID, Some(limit.clone()))
+    verify {
+        assert_eq!(Pallet::<T>::withdrawal_rate_limit_of(ASSET_ID), Some(limit));
+    }
+
+    cancel_queued_withdrawal {
+        let caller: T::AccountId = alice::<T>();
+        queue_withdrawal::<T>(&caller);
+    }: _(RawOrigin::Signed(caller.clone()), 0)
+    verify {
+        assert!(Pallet::<T>::queued_withdrawals(0).is_none());
+    }
+
+    release_queued_withdrawal {
+        let caller: T::AccountId = alice::<T>();
+        queue_withdrawal::<T>(&caller);
+        let unlock_at = Pallet::<T>::queued_withdrawals(0).unwrap().unlock_at;
+        frame_system::Pallet::<T>::set_block_number(unlock_at);
+    }: {
+        Pallet::<T>::release_queued_withdrawals(unlock_at);
+    }
+    verify {
+        assert!(Pallet::<T>::queued_withdrawals(0).is_none());
+        assert!(XGatewayRecords::<T>::pending_withdrawals(0).is_some());
+    }
 }
 
 #[cfg(test)]
@@ -200,6 +293,12 @@ mod tests {
             assert_ok!(Pallet::<Test>::test_benchmark_set_withdrawal_state());
             assert_ok!(Pallet::<Test>::test_benchmark_set_trustee_info_config());
             assert_ok!(Pallet::<Test>::test_benchmark_force_set_referral_binding());
+            assert_ok!(Pallet::<Test>::test_benchmark_force_finish_draining());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_max_missed_proposals());
+            assert_ok!(Pallet::<Test>::test_benchmark_withdraw_trustee_bond());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_withdrawal_rate_limit());
+            assert_ok!(Pallet::<Test>::test_benchmark_cancel_queued_withdrawal());
+            assert_ok!(Pallet::<Test>::test_benchmark_release_queued_withdrawal());
         });
     }
 }
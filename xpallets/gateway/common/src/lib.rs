@@ -24,26 +24,35 @@ pub mod weights;
 use frame_support::{
     dispatch::{DispatchError, DispatchResult},
     ensure,
-    log::{error, info},
+    log::{error, info, warn},
+    storage::with_transaction,
+    traits::{Currency, Get, Imbalance, ReservableCurrency, StorageVersion},
+    transactional,
 };
 use frame_system::{ensure_root, ensure_signed};
-use sp_runtime::traits::StaticLookup;
+use sp_runtime::{
+    traits::{One, Saturating, StaticLookup, Zero},
+    Perbill, TransactionOutcome,
+};
 use sp_std::{collections::btree_map::BTreeMap, convert::TryFrom, prelude::*};
 
 use chainx_primitives::{AddrStr, AssetId, ChainAddress, Text};
 use xp_runtime::Memo;
-use xpallet_assets::{AssetRestrictions, BalanceOf, Chain, ChainT, WithdrawalLimit};
+use xpallet_assets::{AssetRestrictions, AssetType, BalanceOf, Chain, ChainT, WithdrawalLimit};
 use xpallet_gateway_records::{WithdrawalRecordId, WithdrawalState};
-use xpallet_support::traits::{MultisigAddressFor, Validator};
+use xpallet_support::traits::{MultisigAddressFor, TreasuryAccount, Validator};
 
 use self::traits::TrusteeForChain;
 use self::types::{
-    GenericTrusteeIntentionProps, GenericTrusteeSessionInfo, TrusteeInfoConfig,
-    TrusteeIntentionProps,
+    GenericTrusteeIntentionProps, GenericTrusteeSessionInfo, QueuedWithdrawal, TrusteeInfoConfig,
+    TrusteeIntentionProps, TrusteeOffence, TrusteeStats, WithdrawalRateLimit,
 };
 pub use self::weights::WeightInfo;
 pub use pallet::*;
 
+/// The storage version 1 records the bonds of the trustees in `TrusteeBondOf`.
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 #[frame_support::pallet]
 pub mod pallet {
     use super::*;
@@ -66,13 +75,47 @@ pub mod pallet {
             trustees::bitcoin::BtcTrusteeAddrInfo,
         >;
 
+        /// The amount of PCX reserved from a trustee when setting up the trustee.
+        #[pallet::constant]
+        type TrusteeBond: Get<BalanceOf<Self>>;
+
+        /// The maximum number of approved proposals a trustee could reject before being slashed.
+        #[pallet::constant]
+        type MaxRejectedValidProposals: Get<u32>;
+
+        /// The ratio of the trustee bond to slash for rejecting too many approved proposals.
+        #[pallet::constant]
+        type RejectionSlash: Get<Perbill>;
+
+        /// The maximum number of queued withdrawals to release in a block, the rest are
+        /// postponed to the next block.
+        #[pallet::constant]
+        type MaxQueuedWithdrawalReleases: Get<u32>;
+
         type WeightInfo: WeightInfo;
     }
 
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(PhantomData<T>);
 
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            Self::release_queued_withdrawals(now)
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            if StorageVersion::get::<Pallet<T>>() >= STORAGE_VERSION {
+                return T::DbWeight::get().reads(1);
+            }
+            let weight = Self::migrate_trustee_bonds();
+            STORAGE_VERSION.put::<Pallet<T>>();
+            weight.saturating_add(T::DbWeight::get().reads_writes(1, 1))
+        }
+    }
+
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         /// Create a withdrawal.
@@ -80,8 +123,12 @@ pub mod pallet {
         ///
         /// WithdrawalRecord State: `Applying`
         ///
+        /// The withdrawal exceeding the rate limit of the asset is queued and released after
+        /// the delay.
+        ///
         /// NOTE: `ext` is for the compatibility purpose, e.g., EOS requires a memo when doing the transfer.
         #[pallet::weight(<T as Config>::WeightInfo::withdraw())]
+        #[transactional]
         pub fn withdraw(
             origin: OriginFor<T>,
             #[pallet::compact] asset_id: AssetId,
@@ -97,7 +144,12 @@ pub mod pallet {
             );
             Self::verify_withdrawal(asset_id, value, &addr, &ext)?;
 
-            xpallet_gateway_records::Pallet::<T>::withdraw(&who, asset_id, value, addr, ext)?;
+            if Self::check_withdrawal_rate(&who, asset_id, value, false) {
+                xpallet_gateway_records::Pallet::<T>::withdraw(&who, asset_id, value, addr, ext)?;
+                Self::note_withdrawal(&who, asset_id, value);
+            } else {
+                Self::queue_withdrawal(who, asset_id, value, addr, ext)?;
+            }
             Ok(())
         }
 
@@ -148,7 +200,7 @@ pub mod pallet {
                 chain,
                 new_trustees
             );
-            Self::transition_trustee_session_impl(chain, new_trustees)
+            Self::transition_trustee_session_impl(chain, new_trustees, true)
         }
 
         /// Set the state of withdraw record by the trustees.
@@ -199,6 +251,87 @@ pub mod pallet {
             Self::set_referral_binding(chain, who, referral);
             Ok(())
         }
+
+        /// Finish draining the previous trustee session, so that the trustee session could be
+        /// transitioned again.
+        ///
+        /// This is a root-only operation.
+        #[pallet::weight(<T as Config>::WeightInfo::force_finish_draining())]
+        pub fn force_finish_draining(origin: OriginFor<T>, chain: Chain) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                Self::draining_session_of(chain).is_some(),
+                Error::<T>::NoDrainingSession
+            );
+            Self::finish_draining_impl(chain);
+            Ok(())
+        }
+
+        /// Set the maximum number of missed proposals of a trustee in a session, the trustees
+        /// exceeding it are removed from the candidates at the next trustee session transition.
+        /// `None` disables the removal.
+        ///
+        /// This is a root-only operation.
+        #[pallet::weight(<T as Config>::WeightInfo::set_max_missed_proposals())]
+        pub fn set_max_missed_proposals(
+            origin: OriginFor<T>,
+            chain: Chain,
+            max_missed: Option<u32>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            MaxMissedProposalsOf::<T>::mutate_exists(chain, |max| *max = max_missed);
+            Ok(())
+        }
+
+        /// Withdraw the bond of a trustee which is no longer in the current or draining trustee
+        /// session.
+        #[pallet::weight(<T as Config>::WeightInfo::withdraw_trustee_bond())]
+        pub fn withdraw_trustee_bond(origin: OriginFor<T>, chain: Chain) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::withdraw_trustee_bond_impl(who, chain)
+        }
+
+        /// Set or remove the withdrawal rate limit of the asset.
+        ///
+        /// This is a root-only operation.
+        #[pallet::weight(<T as Config>::WeightInfo::set_withdrawal_rate_limit())]
+        pub fn set_withdrawal_rate_limit(
+            origin: OriginFor<T>,
+            #[pallet::compact] asset_id: AssetId,
+            limit: Option<WithdrawalRateLimit<BalanceOf<T>, T::BlockNumber>>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            if limit.is_none() {
+                WithdrawalWindowOf::<T>::remove(asset_id);
+                let _ = AccountWithdrawalWindowOf::<T>::remove_prefix(asset_id, None);
+            }
+            WithdrawalRateLimitOf::<T>::mutate_exists(asset_id, |l| *l = limit);
+            Ok(())
+        }
+
+        /// Cancel the queued withdrawal and unlock the balance, by the applicant or root.
+        #[pallet::weight(<T as Config>::WeightInfo::cancel_queued_withdrawal())]
+        pub fn cancel_queued_withdrawal(origin: OriginFor<T>, id: u32) -> DispatchResult {
+            let maybe_who = match ensure_signed(origin.clone()) {
+                Ok(who) => Some(who),
+                Err(_) => {
+                    ensure_root(origin)?;
+                    None
+                }
+            };
+            let queued = Self::queued_withdrawals(id).ok_or(Error::<T>::NoQueuedWithdrawal)?;
+            if let Some(who) = maybe_who {
+                ensure!(queued.applicant == who, Error::<T>::NotApplicant);
+            }
+            Self::move_queued_balance(
+                &queued,
+                AssetType::ReservedQueuedWithdrawal,
+                AssetType::Usable,
+            )?;
+            QueuedWithdrawals::<T>::remove(id);
+            Self::deposit_event(Event::<T>::QueuedWithdrawalCancelled(id));
+            Ok(())
+        }
     }
 
     #[pallet::event]
@@ -210,6 +343,31 @@ pub mod pallet {
         ReferralBinded(T::AccountId, Chain, T::AccountId),
         /// The trustee set of a chain was changed. [chain, session_number, session_info]
         TrusteeSetChanged(Chain, u32, GenericTrusteeSessionInfo<T::AccountId>),
+        /// The funds of a previous trustee session are being moved to the current trustees. [chain, session_number]
+        TrusteeSessionDraining(Chain, u32),
+        /// The funds of a previous trustee session were all moved to the current trustees. [chain, session_number]
+        TrusteeSessionDrained(Chain, u32),
+        /// The inactive trustees of a session were removed from the candidates of the next session. [chain, session_number, trustees]
+        InactiveTrusteesRemoved(Chain, u32, Vec<T::AccountId>),
+        /// Some PCX was reserved as the bond of a trustee. [who, chain, amount]
+        TrusteeBonded(T::AccountId, Chain, BalanceOf<T>),
+        /// The bond of a trustee was withdrawn. [who, chain, amount]
+        TrusteeBondWithdrawn(T::AccountId, Chain, BalanceOf<T>),
+        /// The bond of a trustee was slashed to the treasury for the offence. [who, chain, offence, amount]
+        TrusteeSlashed(T::AccountId, Chain, TrusteeOffence, BalanceOf<T>),
+        /// A withdrawal exceeding the rate limit was queued. [queue_id, who, asset_id, balance, unlock_at]
+        WithdrawalQueued(u32, T::AccountId, AssetId, BalanceOf<T>, T::BlockNumber),
+        /// A queued withdrawal was applied after the delay. [queue_id]
+        QueuedWithdrawalReleased(u32),
+        /// A queued withdrawal could not be applied and was delayed again. [queue_id, unlock_at]
+        QueuedWithdrawalDelayed(u32, T::BlockNumber),
+        /// A queued withdrawal failed to apply, the balance was unlocked. [queue_id, error]
+        QueuedWithdrawalFailed(u32, DispatchError),
+        /// A queued withdrawal was cancelled, the balance was unlocked. [queue_id]
+        QueuedWithdrawalCancelled(u32),
+        /// The withdrawals of the asset in the window exceeded the threshold, WITHDRAW of the
+        /// asset was paused. [asset_id, window_total]
+        WithdrawalCircuitBreakerTripped(AssetId, BalanceOf<T>),
     }
 
     #[pallet::error]
@@ -232,6 +390,20 @@ pub mod pallet {
         NotRegistered,
         /// just allow validator to register trustee
         NotValidator,
+        /// the funds of previous trustee session are not drained yet
+        SessionDraining,
+        /// no trustee session is being drained
+        NoDrainingSession,
+        /// the free balance is not enough for the trustee bond
+        InsufficientBond,
+        /// the trustee bond could not be withdrawn while the trustee is in charge
+        TrusteeBondLocked,
+        /// no trustee bond to withdraw
+        NoTrusteeBond,
+        /// the queued withdrawal does not exist
+        NoQueuedWithdrawal,
+        /// only the applicant could cancel the queued withdrawal
+        NotApplicant,
     }
 
     #[pallet::storage]
@@ -273,6 +445,96 @@ pub mod pallet {
         GenericTrusteeSessionInfo<T::AccountId>,
     >;
 
+    /// The previous trustee session of the chain whose funds are being moved to the current
+    /// trustees, the trustee session could not be transitioned until it's drained.
+    #[pallet::storage]
+    #[pallet::getter(fn draining_session_of)]
+    pub type DrainingSessionOf<T: Config> = StorageMap<_, Twox64Concat, Chain, u32>;
+
+    /// The signing participation statistics of the trustees in the trustee session of the chain.
+    #[pallet::storage]
+    #[pallet::getter(fn trustee_stats_of)]
+    pub type TrusteeStatsOf<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        (Chain, u32),
+        Blake2_128Concat,
+        T::AccountId,
+        TrusteeStats,
+        ValueQuery,
+    >;
+
+    /// The maximum number of missed proposals of a trustee in a session before it's removed from
+    /// the candidates of the next session, the removal is disabled if it's not set.
+    #[pallet::storage]
+    #[pallet::getter(fn max_missed_proposals_of)]
+    pub type MaxMissedProposalsOf<T: Config> = StorageMap<_, Twox64Concat, Chain, u32>;
+
+    /// The PCX reserved as the bond of the trustee of the chain.
+    #[pallet::storage]
+    #[pallet::getter(fn trustee_bond_of)]
+    pub type TrusteeBondOf<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Twox64Concat,
+        Chain,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// The number of approved proposals the trustee of the chain rejected since last slashed.
+    #[pallet::storage]
+    #[pallet::getter(fn rejected_valid_proposals_of)]
+    pub type RejectedValidProposalsOf<T: Config> =
+        StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, Chain, u32, ValueQuery>;
+
+    /// The withdrawal rate limit of the asset, the withdrawals are not limited if it's not set.
+    #[pallet::storage]
+    #[pallet::getter(fn withdrawal_rate_limit_of)]
+    pub type WithdrawalRateLimitOf<T: Config> =
+        StorageMap<_, Twox64Concat, AssetId, WithdrawalRateLimit<BalanceOf<T>, T::BlockNumber>>;
+
+    /// The withdrawals of the asset in the sliding window, [(block_number, balance)].
+    #[pallet::storage]
+    #[pallet::getter(fn withdrawal_window_of)]
+    pub type WithdrawalWindowOf<T: Config> =
+        StorageMap<_, Twox64Concat, AssetId, Vec<(T::BlockNumber, BalanceOf<T>)>, ValueQuery>;
+
+    /// The withdrawals of the account for the asset in the sliding window, [(block_number, balance)].
+    #[pallet::storage]
+    #[pallet::getter(fn account_withdrawal_window_of)]
+    pub type AccountWithdrawalWindowOf<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        AssetId,
+        Blake2_128Concat,
+        T::AccountId,
+        Vec<(T::BlockNumber, BalanceOf<T>)>,
+        ValueQuery,
+    >;
+
+    /// The withdrawals delayed by the rate limit, queue_id => queued withdrawal.
+    #[pallet::storage]
+    #[pallet::getter(fn queued_withdrawals)]
+    pub type QueuedWithdrawals<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        u32,
+        QueuedWithdrawal<T::AccountId, BalanceOf<T>, T::BlockNumber>,
+    >;
+
+    /// The queued withdrawals to release at the block.
+    #[pallet::storage]
+    #[pallet::getter(fn queued_withdrawals_at)]
+    pub type QueuedWithdrawalsAt<T: Config> =
+        StorageMap<_, Twox64Concat, T::BlockNumber, Vec<u32>, ValueQuery>;
+
+    /// The id of next queued withdrawal.
+    #[pallet::storage]
+    #[pallet::getter(fn next_queued_withdrawal_id)]
+    pub type NextQueuedWithdrawalId<T: Config> = StorageValue<_, u32, ValueQuery>;
+
     /// Trustee intention properties of the corresponding account and chain.
     #[pallet::storage]
     #[pallet::getter(fn trustee_intention_props_of)]
@@ -333,14 +595,23 @@ pub mod pallet {
                 for (chain, info_config, trustee_infos) in config.trustees.iter() {
                     let mut trustees = Vec::with_capacity(trustee_infos.len());
                     for (who, about, hot, cold) in trustee_infos.iter() {
-                        Pallet::<T>::setup_trustee_impl(
-                            who.clone(),
+                        let props = Pallet::<T>::generic_trustee_props(
                             *chain,
                             about.clone(),
                             hot.clone(),
                             cold.clone(),
                         )
-                        .expect("setup trustee can not fail; qed");
+                        .expect("genesis trustee props must be valid; qed");
+                        // the genesis trustees may have no balance yet, they could bond later
+                        if Pallet::<T>::bond_trustee(who, *chain).is_err() {
+                            warn!(
+                                target: "runtime::gateway::common",
+                                "[genesis_build] Genesis trustee {:?} has not enough balance for the bond, chain:{:?}",
+                                who,
+                                chain
+                            );
+                        }
+                        TrusteeIntentionPropertiesOf::<T>::insert(who, chain, props);
                         trustees.push(who.clone());
                     }
                     TrusteeInfoConfigOf::<T>::insert(chain, info_config.clone());
@@ -390,6 +661,254 @@ impl<T: Config> Pallet<T> {
     }
 }
 
+// withdrawal rate limit
+impl<T: Config> Pallet<T> {
+    /// Check whether the withdrawal is allowed by the rate limit of the asset, returns `false`
+    /// if the withdrawal should be queued.
+    ///
+    /// The withdrawal making the executed total in the window exceed the threshold is queued,
+    /// since it could still be cancelled, the circuit breaker is tripped only if it's released.
+    /// `bypass_cap` is used by the released queued withdrawals which have been delayed.
+    fn check_withdrawal_rate(
+        who: &T::AccountId,
+        asset_id: AssetId,
+        value: BalanceOf<T>,
+        bypass_cap: bool,
+    ) -> bool {
+        let limit = match Self::withdrawal_rate_limit_of(asset_id) {
+            Some(limit) => limit,
+            None => return true,
+        };
+        let start = frame_system::Pallet::<T>::block_number().saturating_sub(limit.window);
+        let total_of = |window: Vec<(T::BlockNumber, BalanceOf<T>)>| {
+            window
+                .into_iter()
+                .filter(|(height, _)| *height > start)
+                .fold(value, |acc, (_, v)| acc.saturating_add(v))
+        };
+
+        let asset_total = total_of(Self::withdrawal_window_of(asset_id));
+        if asset_total > limit.breaker_threshold {
+            if bypass_cap {
+                Self::trip_circuit_breaker(asset_id, asset_total);
+            }
+            return false;
+        }
+        bypass_cap
+            || (asset_total <= limit.asset_cap
+                && total_of(Self::account_withdrawal_window_of(asset_id, who)) <= limit.account_cap)
+    }
+
+    /// Record the executed withdrawal in the sliding windows of the rate limit.
+    fn note_withdrawal(who: &T::AccountId, asset_id: AssetId, value: BalanceOf<T>) {
+        let limit = match Self::withdrawal_rate_limit_of(asset_id) {
+            Some(limit) => limit,
+            None => return,
+        };
+        let now = frame_system::Pallet::<T>::block_number();
+        let start = now.saturating_sub(limit.window);
+
+        WithdrawalWindowOf::<T>::mutate(asset_id, |window| {
+            window.retain(|(height, _)| *height > start);
+            window.push((now, value));
+        });
+        AccountWithdrawalWindowOf::<T>::mutate(asset_id, who, |window| {
+            window.retain(|(height, _)| *height > start);
+            window.push((now, value));
+        });
+    }
+
+    /// Pause the WITHDRAW of the asset until it's resumed by `xpallet_assets::set_asset_limit`.
+    fn trip_circuit_breaker(asset_id: AssetId, window_total: BalanceOf<T>) {
+        let restrictions = xpallet_assets::Pallet::<T>::asset_restrictions_of(&asset_id);
+        if let Err(err) = xpallet_assets::Pallet::<T>::set_asset_limit(
+            frame_system::RawOrigin::Root.into(),
+            asset_id,
+            restrictions | AssetRestrictions::WITHDRAW,
+        ) {
+            error!(
+                target: "runtime::gateway::common",
+                "[trip_circuit_breaker] Pause withdrawal of asset {} failed:{:?}",
+                asset_id,
+                err
+            );
+            return;
+        }
+        warn!(
+            target: "runtime::gateway::common",
+            "[trip_circuit_breaker] Withdrawal of asset {} is paused, window total:{:?}",
+            asset_id,
+            window_total
+        );
+        Self::deposit_event(Event::<T>::WithdrawalCircuitBreakerTripped(
+            asset_id,
+            window_total,
+        ));
+    }
+
+    fn queue_withdrawal(
+        who: T::AccountId,
+        asset_id: AssetId,
+        value: BalanceOf<T>,
+        addr: AddrStr,
+        ext: Memo,
+    ) -> DispatchResult {
+        let unlock_at =
+            frame_system::Pallet::<T>::block_number().saturating_add(Self::queue_delay(asset_id));
+        let queued = QueuedWithdrawal {
+            applicant: who.clone(),
+            asset_id,
+            balance: value,
+            addr,
+            ext,
+            unlock_at,
+        };
+        Self::move_queued_balance(
+            &queued,
+            AssetType::Usable,
+            AssetType::ReservedQueuedWithdrawal,
+        )?;
+
+        let id = Self::next_queued_withdrawal_id();
+        NextQueuedWithdrawalId::<T>::put(id.wrapping_add(1));
+        QueuedWithdrawals::<T>::insert(id, queued);
+        QueuedWithdrawalsAt::<T>::append(unlock_at, id);
+        Self::deposit_event(Event::<T>::WithdrawalQueued(
+            id, who, asset_id, value, unlock_at,
+        ));
+        Ok(())
+    }
+
+    fn move_queued_balance(
+        queued: &QueuedWithdrawal<T::AccountId, BalanceOf<T>, T::BlockNumber>,
+        from_type: AssetType,
+        to_type: AssetType,
+    ) -> DispatchResult {
+        xpallet_assets::Pallet::<T>::move_balance(
+            &queued.asset_id,
+            &queued.applicant,
+            from_type,
+            &queued.applicant,
+            to_type,
+            queued.balance,
+        )
+        .map_err::<xpallet_assets::Error<T>, _>(Into::into)?;
+        Ok(())
+    }
+
+    /// Release at most `MaxQueuedWithdrawalReleases` queued withdrawals due at `now`, the rest
+    /// are postponed to the next block.
+    ///
+    /// The withdrawals of the paused assets are postponed together by the delay of the asset.
+    pub(crate) fn release_queued_withdrawals(now: T::BlockNumber) -> Weight {
+        let mut due = QueuedWithdrawalsAt::<T>::take(now);
+        let mut weight = T::DbWeight::get().reads_writes(1, 1);
+        let max_releases = T::MaxQueuedWithdrawalReleases::get() as usize;
+        if due.len() > max_releases {
+            let next = now.saturating_add(One::one());
+            let mut postponed = due.split_off(max_releases);
+            postponed.extend(QueuedWithdrawalsAt::<T>::take(next));
+            QueuedWithdrawalsAt::<T>::insert(next, postponed);
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+        }
+
+        let mut paused = BTreeMap::<AssetId, Vec<u32>>::new();
+        let mut released: Weight = 0;
+        for id in due {
+            weight = weight.saturating_add(T::DbWeight::get().reads(1));
+            let queued = match Self::queued_withdrawals(id) {
+                Some(queued) => queued,
+                // cancelled
+                None => continue,
+            };
+            if let Some(ids) = paused.get_mut(&queued.asset_id) {
+                ids.push(id);
+                continue;
+            }
+            weight = weight.saturating_add(T::DbWeight::get().reads(1));
+            if !xpallet_assets::Pallet::<T>::can_do(&queued.asset_id, AssetRestrictions::WITHDRAW) {
+                paused.insert(queued.asset_id, vec![id]);
+                continue;
+            }
+            Self::release_queued_withdrawal(id, queued, now);
+            released = released.saturating_add(1);
+        }
+
+        for (asset_id, ids) in paused {
+            let unlock_at = now.saturating_add(Self::queue_delay(asset_id));
+            QueuedWithdrawalsAt::<T>::mutate(unlock_at, |at| at.extend(ids));
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(2, 1));
+        }
+        weight.saturating_add(
+            <T as Config>::WeightInfo::release_queued_withdrawal().saturating_mul(released),
+        )
+    }
+
+    fn queue_delay(asset_id: AssetId) -> T::BlockNumber {
+        Self::withdrawal_rate_limit_of(asset_id)
+            .map(|limit| limit.delay)
+            .unwrap_or_default()
+            .max(One::one())
+    }
+
+    /// Apply the queued withdrawal, it's delayed again if the circuit breaker is tripped.
+    fn release_queued_withdrawal(
+        id: u32,
+        mut queued: QueuedWithdrawal<T::AccountId, BalanceOf<T>, T::BlockNumber>,
+        now: T::BlockNumber,
+    ) {
+        if !Self::check_withdrawal_rate(&queued.applicant, queued.asset_id, queued.balance, true) {
+            queued.unlock_at = now.saturating_add(Self::queue_delay(queued.asset_id));
+            QueuedWithdrawalsAt::<T>::append(queued.unlock_at, id);
+            Self::deposit_event(Event::<T>::QueuedWithdrawalDelayed(id, queued.unlock_at));
+            QueuedWithdrawals::<T>::insert(id, queued);
+            return;
+        }
+
+        let result = with_transaction(|| {
+            let result = Self::move_queued_balance(
+                &queued,
+                AssetType::ReservedQueuedWithdrawal,
+                AssetType::Usable,
+            )
+            .and_then(|_| {
+                xpallet_gateway_records::Pallet::<T>::withdraw(
+                    &queued.applicant,
+                    queued.asset_id,
+                    queued.balance,
+                    queued.addr.clone(),
+                    queued.ext.clone(),
+                )
+            });
+            match result {
+                Ok(()) => TransactionOutcome::Commit(Ok(())),
+                Err(err) => TransactionOutcome::Rollback(Err(err)),
+            }
+        });
+        QueuedWithdrawals::<T>::remove(id);
+        match result {
+            Ok(()) => {
+                Self::note_withdrawal(&queued.applicant, queued.asset_id, queued.balance);
+                Self::deposit_event(Event::<T>::QueuedWithdrawalReleased(id));
+            }
+            Err(err) => {
+                error!(
+                    target: "runtime::gateway::common",
+                    "[release_queued_withdrawal] Apply queued withdrawal {} failed:{:?}",
+                    id,
+                    err
+                );
+                let _ = Self::move_queued_balance(
+                    &queued,
+                    AssetType::ReservedQueuedWithdrawal,
+                    AssetType::Usable,
+                );
+                Self::deposit_event(Event::<T>::QueuedWithdrawalFailed(id, err));
+            }
+        }
+    }
+}
+
 pub fn is_valid_about<T: Config>(about: &[u8]) -> DispatchResult {
     // TODO
     if about.len() > 128 {
@@ -408,6 +927,21 @@ impl<T: Config> Pallet<T> {
         hot_entity: Vec<u8>,
         cold_entity: Vec<u8>,
     ) -> DispatchResult {
+        let props = Self::generic_trustee_props(chain, about, hot_entity, cold_entity)?;
+
+        Self::bond_trustee(&who, chain)?;
+
+        TrusteeIntentionPropertiesOf::<T>::insert(&who, chain, props.clone());
+        Self::deposit_event(Event::<T>::SetTrusteeProps(who, chain, props));
+        Ok(())
+    }
+
+    fn generic_trustee_props(
+        chain: Chain,
+        about: Text,
+        hot_entity: Vec<u8>,
+        cold_entity: Vec<u8>,
+    ) -> Result<GenericTrusteeIntentionProps, DispatchError> {
         is_valid_about::<T>(&about)?;
 
         let (hot, cold) = match chain {
@@ -419,20 +953,148 @@ impl<T: Config> Pallet<T> {
             _ => return Err(Error::<T>::NotSupportedChain.into()),
         };
 
-        let props = GenericTrusteeIntentionProps(TrusteeIntentionProps::<Vec<u8>> {
+        Ok(GenericTrusteeIntentionProps(TrusteeIntentionProps::<
+            Vec<u8>,
+        > {
             about,
             hot_entity: hot,
             cold_entity: cold,
-        });
+        }))
+    }
 
-        TrusteeIntentionPropertiesOf::<T>::insert(&who, chain, props.clone());
-        Self::deposit_event(Event::<T>::SetTrusteeProps(who, chain, props));
+    /// Reserve the PCX of the trustee until the bond reaches `TrusteeBond`.
+    fn bond_trustee(who: &T::AccountId, chain: Chain) -> DispatchResult {
+        let bonded = Self::trustee_bond_of(who, chain);
+        let required = T::TrusteeBond::get().saturating_sub(bonded);
+        if required.is_zero() {
+            return Ok(());
+        }
+        <T as xpallet_assets::Config>::Currency::reserve(who, required)
+            .map_err(|_| Error::<T>::InsufficientBond)?;
+        TrusteeBondOf::<T>::insert(who, chain, bonded.saturating_add(required));
+        Self::deposit_event(Event::<T>::TrusteeBonded(who.clone(), chain, required));
+        Ok(())
+    }
+
+    /// Bond the trustees in charge before `TrusteeBondOf` is introduced, the trustee without
+    /// enough balance keeps in charge but has to bond before the next session.
+    pub(crate) fn migrate_trustee_bonds() -> Weight {
+        let mut weight: Weight = 0;
+        for (chain, len) in TrusteeSessionInfoLen::<T>::iter() {
+            weight = weight.saturating_add(T::DbWeight::get().reads(2));
+            let mut trustees = Vec::new();
+            let sessions = len
+                .checked_sub(1)
+                .into_iter()
+                .chain(Self::draining_session_of(chain))
+                .filter_map(|number| Self::trustee_session_info_of(chain, number));
+            for info in sessions {
+                weight = weight.saturating_add(T::DbWeight::get().reads(1));
+                for who in info.0.trustee_list {
+                    if !trustees.contains(&who) {
+                        trustees.push(who);
+                    }
+                }
+            }
+            for who in trustees.iter() {
+                weight = weight.saturating_add(T::DbWeight::get().reads_writes(2, 2));
+                if Self::bond_trustee(who, chain).is_err() {
+                    warn!(
+                        target: "runtime::gateway::common",
+                        "[migrate_trustee_bonds] Trustee {:?} has not enough balance for the bond, chain:{:?}",
+                        who,
+                        chain
+                    );
+                }
+            }
+            info!(
+                target: "runtime::gateway::common",
+                "[migrate_trustee_bonds] Bond {} trustees in charge, chain:{:?}",
+                trustees.len(),
+                chain
+            );
+        }
+        weight
+    }
+
+    fn withdraw_trustee_bond_impl(who: T::AccountId, chain: Chain) -> DispatchResult {
+        let session_number = Self::trustee_session_info_len(chain).checked_sub(1);
+        let in_charge = session_number
+            .into_iter()
+            .chain(Self::draining_session_of(chain))
+            .filter_map(|number| Self::trustee_session_info_of(chain, number))
+            .any(|info| info.0.trustee_list.contains(&who));
+        ensure!(!in_charge, Error::<T>::TrusteeBondLocked);
+
+        let bonded = TrusteeBondOf::<T>::take(&who, chain);
+        ensure!(!bonded.is_zero(), Error::<T>::NoTrusteeBond);
+        <T as xpallet_assets::Config>::Currency::unreserve(&who, bonded);
+        RejectedValidProposalsOf::<T>::remove(&who, chain);
+        Self::deposit_event(Event::<T>::TrusteeBondWithdrawn(who, chain, bonded));
         Ok(())
     }
 
+    pub(crate) fn report_offence_impl(chain: Chain, who: &T::AccountId, offence: TrusteeOffence) {
+        let ratio = match offence {
+            TrusteeOffence::UnauthorizedSpend => Perbill::one(),
+            TrusteeOffence::RejectValidProposal => {
+                let rejected = RejectedValidProposalsOf::<T>::mutate(who, chain, |rejected| {
+                    *rejected = rejected.saturating_add(1);
+                    *rejected
+                });
+                if rejected <= T::MaxRejectedValidProposals::get() {
+                    return;
+                }
+                RejectedValidProposalsOf::<T>::remove(who, chain);
+                T::RejectionSlash::get()
+            }
+        };
+        Self::slash_trustee(chain, who, offence, ratio);
+    }
+
+    /// Slash the `ratio` of the trustee bond to the treasury.
+    fn slash_trustee(chain: Chain, who: &T::AccountId, offence: TrusteeOffence, ratio: Perbill) {
+        let bonded = Self::trustee_bond_of(who, chain);
+        let (imbalance, _) =
+            <T as xpallet_assets::Config>::Currency::slash_reserved(who, ratio * bonded);
+        let slashed = imbalance.peek();
+        let treasury = <T as xpallet_assets::Config>::TreasuryAccount::treasury_account();
+        <T as xpallet_assets::Config>::Currency::resolve_creating(&treasury, imbalance);
+
+        let remaining = bonded.saturating_sub(slashed);
+        if remaining.is_zero() {
+            TrusteeBondOf::<T>::remove(who, chain);
+        } else {
+            TrusteeBondOf::<T>::insert(who, chain, remaining);
+        }
+        info!(
+            target: "runtime::gateway::common",
+            "[slash_trustee] Slash trustee {:?} for {:?}, chain:{:?}, slashed:{:?}",
+            who,
+            offence,
+            chain,
+            slashed
+        );
+        Self::deposit_event(Event::<T>::TrusteeSlashed(
+            who.clone(),
+            chain,
+            offence,
+            slashed,
+        ));
+    }
+
     pub fn try_generate_session_info(
         chain: Chain,
         new_trustees: Vec<T::AccountId>,
+    ) -> Result<GenericTrusteeSessionInfo<T::AccountId>, DispatchError> {
+        Self::generate_session_info(chain, new_trustees, true)
+    }
+
+    /// The bond is not checked for the genesis trustees, which may have no balance yet.
+    fn generate_session_info(
+        chain: Chain,
+        new_trustees: Vec<T::AccountId>,
+        check_bond: bool,
     ) -> Result<GenericTrusteeSessionInfo<T::AccountId>, DispatchError> {
         let config = Self::trustee_info_config_of(chain);
         let has_duplicate =
@@ -455,6 +1117,14 @@ impl<T: Config> Pallet<T> {
                 );
                 Error::<T>::NotRegistered
             })?;
+            if check_bond && Self::trustee_bond_of(&accountid, chain) < T::TrusteeBond::get() {
+                error!(
+                    target: "runtime::gateway::common",
+                    "[transition_trustee_session] Candidate {:?} has not enough trustee bond",
+                    accountid
+                );
+                return Err(Error::<T>::InsufficientBond.into());
+            }
             props.push((accountid, p));
         }
         let info = match chain {
@@ -481,8 +1151,19 @@ impl<T: Config> Pallet<T> {
     fn transition_trustee_session_impl(
         chain: Chain,
         new_trustees: Vec<T::AccountId>,
+        check_bond: bool,
     ) -> DispatchResult {
-        let info = Self::try_generate_session_info(chain, new_trustees)?;
+        if let Some(draining) = Self::draining_session_of(chain) {
+            error!(
+                target: "runtime::gateway::common",
+                "[transition_trustee_session] Trustee session {} is not drained yet, chain:{:?}",
+                draining,
+                chain
+            );
+            return Err(Error::<T>::SessionDraining.into());
+        }
+        let new_trustees = Self::remove_inactive_trustees(chain, new_trustees);
+        let info = Self::generate_session_info(chain, new_trustees, check_bond)?;
         let multi_addr = Self::generate_multisig_addr(chain, &info)?;
 
         let session_number = Self::trustee_session_info_len(chain);
@@ -494,9 +1175,98 @@ impl<T: Config> Pallet<T> {
         TrusteeMultiSigAddr::<T>::insert(chain, multi_addr);
 
         Self::deposit_event(Event::<T>::TrusteeSetChanged(chain, session_number, info));
+        // the funds of the previous trustees must be moved to the new trustees
+        if let Some(previous) = session_number.checked_sub(1) {
+            DrainingSessionOf::<T>::insert(chain, previous);
+            Self::deposit_event(Event::<T>::TrusteeSessionDraining(chain, previous));
+        }
         Ok(())
     }
 
+    pub(crate) fn finish_draining_impl(chain: Chain) {
+        if let Some(session_number) = DrainingSessionOf::<T>::take(chain) {
+            info!(
+                target: "runtime::gateway::common",
+                "[finish_draining] Trustee session {} is drained, chain:{:?}",
+                session_number,
+                chain
+            );
+            Self::deposit_event(Event::<T>::TrusteeSessionDrained(chain, session_number));
+        }
+    }
+
+    /// Remove the trustees that missed too many proposals in the current session from the
+    /// candidates, unless the remaining candidates are not enough.
+    fn remove_inactive_trustees(chain: Chain, candidates: Vec<T::AccountId>) -> Vec<T::AccountId> {
+        let max_missed = match Self::max_missed_proposals_of(chain) {
+            Some(max_missed) => max_missed,
+            None => return candidates,
+        };
+        let session_number = match Self::trustee_session_info_len(chain).checked_sub(1) {
+            Some(session_number) => session_number,
+            None => return candidates,
+        };
+
+        let (inactive, active): (Vec<_>, Vec<_>) = candidates.iter().cloned().partition(|who| {
+            Self::trustee_stats_of((chain, session_number), who).missed > max_missed
+        });
+        if inactive.is_empty() {
+            return candidates;
+        }
+        let min_count = Self::trustee_info_config_of(chain).min_trustee_count;
+        if (active.len() as u32) < min_count {
+            warn!(
+                target: "runtime::gateway::common",
+                "[remove_inactive_trustees] Keep inactive trustees {:?} due to insufficient candidates, chain:{:?}, min:{}",
+                inactive,
+                chain,
+                min_count
+            );
+            return candidates;
+        }
+
+        info!(
+            target: "runtime::gateway::common",
+            "[remove_inactive_trustees] Remove inactive trustees {:?}, chain:{:?}, session:{}",
+            inactive,
+            chain,
+            session_number
+        );
+        Self::deposit_event(Event::<T>::InactiveTrusteesRemoved(
+            chain,
+            session_number,
+            inactive,
+        ));
+        active
+    }
+
+    pub(crate) fn record_trustee_votes_impl(
+        chain: Chain,
+        session_number: u32,
+        votes: &[(T::AccountId, bool)],
+        completed: bool,
+    ) {
+        let trustees = match Self::trustee_session_info_of(chain, session_number) {
+            Some(info) => info.0.trustee_list,
+            None => return,
+        };
+        for trustee in trustees {
+            let vote = votes
+                .iter()
+                .find_map(|(who, vote)| if *who == trustee { Some(*vote) } else { None });
+            TrusteeStatsOf::<T>::mutate((chain, session_number), &trustee, |stats| {
+                stats.seen += 1;
+                match vote {
+                    Some(true) => stats.signed += 1,
+                    Some(false) => stats.rejected += 1,
+                    // the signatures are enough without the vote of the trustee
+                    None if completed => {}
+                    None => stats.missed += 1,
+                }
+            });
+        }
+    }
+
     pub fn generate_multisig_addr(
         chain: Chain,
         info: &GenericTrusteeSessionInfo<T::AccountId>,
@@ -525,4 +1295,29 @@ impl<T: Config> Pallet<T> {
     pub fn trustee_multisigs() -> BTreeMap<Chain, T::AccountId> {
         TrusteeMultiSigAddr::<T>::iter().collect()
     }
+
+    /// Returns the statistics of the trustees in the given session, or the current session if
+    /// `session_number` is `None`.
+    pub fn trustee_stats(
+        chain: Chain,
+        session_number: Option<u32>,
+    ) -> Vec<(T::AccountId, TrusteeStats)> {
+        let session_number =
+            match session_number.or_else(|| Self::trustee_session_info_len(chain).checked_sub(1)) {
+                Some(session_number) => session_number,
+                None => return Vec::new(),
+            };
+        Self::trustee_session_info_of(chain, session_number)
+            .map(|info| {
+                info.0
+                    .trustee_list
+                    .into_iter()
+                    .map(|who| {
+                        let stats = Self::trustee_stats_of((chain, session_number), &who);
+                        (who, stats)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 }
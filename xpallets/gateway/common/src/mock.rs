@@ -13,7 +13,7 @@ use sp_io::hashing::blake2_256;
 use sp_runtime::{
     testing::Header,
     traits::{BlakeTwo256, IdentityLookup},
-    AccountId32, DispatchError, DispatchResult,
+    AccountId32, DispatchError, DispatchResult, Perbill,
 };
 
 use chainx_primitives::AssetId;
@@ -118,8 +118,13 @@ impl xpallet_assets::Config for Test {
     type WeightInfo = ();
 }
 
+parameter_types! {
+    pub const MaxArchivedWithdrawals: u32 = 100;
+}
+
 impl xpallet_gateway_records::Config for Test {
     type Event = ();
+    type MaxArchivedWithdrawals = MaxArchivedWithdrawals;
     type WeightInfo = ();
 }
 
@@ -148,6 +153,8 @@ impl xpallet_gateway_bitcoin::Config for Test {
     type TrusteeOrigin = EnsureSignedBy<BtcTrusteeMultisig<Test>, AccountId>;
     type ReferralBinding = ();
     type AddressBinding = ();
+    type OnBtcDeposited = ();
+    type MaxDepositActionWeight = ();
     type WeightInfo = ();
 }
 
@@ -212,12 +219,23 @@ impl<T: xpallet_gateway_bitcoin::Config>
         })
     }
 }
+parameter_types! {
+    pub const TrusteeBond: Balance = 0;
+    pub const MaxRejectedValidProposals: u32 = 3;
+    pub const RejectionSlash: Perbill = Perbill::from_percent(10);
+    pub const MaxQueuedWithdrawalReleases: u32 = 2;
+}
+
 impl crate::Config for Test {
     type Event = ();
     type Validator = AlwaysValidator;
     type DetermineMultisigAddress = MultisigAddr;
     type Bitcoin = MockBitcoin<Test>;
     type BitcoinTrustee = MockBitcoin<Test>;
+    type TrusteeBond = TrusteeBond;
+    type MaxRejectedValidProposals = MaxRejectedValidProposals;
+    type RejectionSlash = RejectionSlash;
+    type MaxQueuedWithdrawalReleases = MaxQueuedWithdrawalReleases;
     type WeightInfo = ();
 }
 
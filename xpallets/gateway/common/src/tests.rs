@@ -1,8 +1,192 @@
 // Copyright 2019-2020 ChainX Project Authors. Licensed under GPL-3.0.
 
-use crate::mock::ExtBuilder;
+use frame_support::{assert_noop, assert_ok, traits::Hooks};
+use frame_system::RawOrigin;
+use sp_core::{crypto::UncheckedInto, H256};
+
+use xpallet_assets::{AssetRestrictions, AssetType};
+
+use crate::mock::{
+    AccountId, Balance, ExtBuilder, System, Test, XAssets, XGatewayCommon, XGatewayRecords, X_BTC,
+};
+use crate::types::WithdrawalRateLimit;
+
+fn alice() -> AccountId {
+    H256::repeat_byte(11).unchecked_into()
+}
+
+fn bob() -> AccountId {
+    H256::repeat_byte(12).unchecked_into()
+}
+
+fn withdraw(who: AccountId, value: Balance) -> frame_support::dispatch::DispatchResult {
+    XGatewayCommon::withdraw(
+        RawOrigin::Signed(who).into(),
+        X_BTC,
+        value,
+        b"mqVznxoxdeSNYgDCg6ZVE5pc6476BY6zHK".to_vec(),
+        b"".to_vec().into(),
+    )
+}
+
+fn queued_balance(who: &AccountId) -> Balance {
+    XAssets::asset_balance_of(who, &X_BTC, AssetType::ReservedQueuedWithdrawal)
+}
 
 #[test]
 fn base() {
     ExtBuilder::default().build().execute_with(|| {})
 }
+
+#[test]
+fn test_withdrawal_rate_limit() {
+    ExtBuilder::default().build().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(XGatewayRecords::deposit(&alice(), X_BTC, 100000));
+        assert_ok!(XGatewayRecords::deposit(&bob(), X_BTC, 100000));
+        assert_ok!(XGatewayCommon::set_withdrawal_rate_limit(
+            RawOrigin::Root.into(),
+            X_BTC,
+            Some(WithdrawalRateLimit {
+                window: 10,
+                asset_cap: 50000,
+                account_cap: 30000,
+                delay: 5,
+                breaker_threshold: 60000,
+            })
+        ));
+
+        // within the cap
+        assert_ok!(withdraw(alice(), 20000));
+        assert!(XGatewayRecords::pending_withdrawals(0).is_some());
+
+        // over the account cap, the withdrawal is queued and the balance is reserved
+        assert_ok!(withdraw(alice(), 20000));
+        assert!(XGatewayRecords::pending_withdrawals(1).is_none());
+        assert_eq!(XGatewayCommon::queued_withdrawals(0).unwrap().unlock_at, 6);
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 60000);
+        assert_eq!(XAssets::locked_balance(&alice(), &X_BTC), 0);
+        assert_eq!(queued_balance(&alice()), 20000);
+
+        // released after the delay
+        System::set_block_number(6);
+        XGatewayCommon::on_initialize(6);
+        assert!(XGatewayCommon::queued_withdrawals(0).is_none());
+        assert!(XGatewayRecords::pending_withdrawals(1).is_some());
+        assert_eq!(queued_balance(&alice()), 0);
+
+        // the withdrawal exceeding the threshold is queued without tripping the circuit breaker
+        assert_ok!(withdraw(bob(), 50000));
+        assert!(XGatewayRecords::pending_withdrawals(2).is_none());
+        assert!(XAssets::can_do(&X_BTC, AssetRestrictions::WITHDRAW));
+
+        // the executed total exceeds the threshold if it's released, the circuit breaker is
+        // tripped and the withdrawal is delayed
+        System::set_block_number(11);
+        XGatewayCommon::on_initialize(11);
+        assert!(XGatewayRecords::pending_withdrawals(2).is_none());
+        assert!(!XAssets::can_do(&X_BTC, AssetRestrictions::WITHDRAW));
+        assert_noop!(
+            withdraw(bob(), 10000),
+            xpallet_assets::Error::<Test>::ActionNotAllowed
+        );
+        assert_eq!(XGatewayCommon::queued_withdrawals_at(16), vec![1]);
+
+        // the queued withdrawals are postponed by the delay while the withdrawal is paused
+        System::set_block_number(16);
+        XGatewayCommon::on_initialize(16);
+        assert!(XGatewayCommon::queued_withdrawals(1).is_some());
+        assert_eq!(XGatewayCommon::queued_withdrawals_at(21), vec![1]);
+
+        // cancelled by the applicant
+        assert_noop!(
+            XGatewayCommon::cancel_queued_withdrawal(RawOrigin::Signed(alice()).into(), 1),
+            crate::Error::<Test>::NotApplicant
+        );
+        assert_ok!(XGatewayCommon::cancel_queued_withdrawal(
+            RawOrigin::Signed(bob()).into(),
+            1
+        ));
+        assert_eq!(XAssets::usable_balance(&bob(), &X_BTC), 100000);
+        assert_eq!(queued_balance(&bob()), 0);
+    });
+}
+
+#[test]
+fn test_cancel_queued_withdrawal_over_threshold() {
+    ExtBuilder::default().build().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(XGatewayRecords::deposit(&bob(), X_BTC, 100000));
+        assert_ok!(XGatewayCommon::set_withdrawal_rate_limit(
+            RawOrigin::Root.into(),
+            X_BTC,
+            Some(WithdrawalRateLimit {
+                window: 10,
+                asset_cap: 50000,
+                account_cap: 50000,
+                delay: 5,
+                breaker_threshold: 30000,
+            })
+        ));
+
+        // the withdrawal over the threshold is only queued, cancelling it pauses nothing
+        assert_ok!(withdraw(bob(), 40000));
+        assert!(XGatewayCommon::queued_withdrawals(0).is_some());
+        assert_ok!(XGatewayCommon::cancel_queued_withdrawal(
+            RawOrigin::Signed(bob()).into(),
+            0
+        ));
+        assert!(XAssets::can_do(&X_BTC, AssetRestrictions::WITHDRAW));
+        assert!(XGatewayCommon::withdrawal_window_of(X_BTC).is_empty());
+
+        System::set_block_number(6);
+        XGatewayCommon::on_initialize(6);
+        assert!(XAssets::can_do(&X_BTC, AssetRestrictions::WITHDRAW));
+        assert_ok!(withdraw(bob(), 20000));
+        assert!(XGatewayRecords::pending_withdrawals(0).is_some());
+        assert_eq!(XAssets::usable_balance(&bob(), &X_BTC), 80000);
+    });
+}
+
+#[test]
+fn test_release_queued_withdrawals_per_block() {
+    ExtBuilder::default().build().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(XGatewayRecords::deposit(&alice(), X_BTC, 100000));
+        assert_ok!(XGatewayCommon::set_withdrawal_rate_limit(
+            RawOrigin::Root.into(),
+            X_BTC,
+            Some(WithdrawalRateLimit {
+                window: 10,
+                asset_cap: 0,
+                account_cap: 0,
+                delay: 5,
+                breaker_threshold: 1000000,
+            })
+        ));
+        for _ in 0..3 {
+            assert_ok!(withdraw(alice(), 1000));
+        }
+        assert_eq!(XGatewayCommon::queued_withdrawals_at(6), vec![0, 1, 2]);
+        // nothing is counted in the window until the withdrawal is executed
+        assert!(XGatewayCommon::withdrawal_window_of(X_BTC).is_empty());
+
+        // at most 2 withdrawals are released in a block, the rest is postponed
+        System::set_block_number(6);
+        XGatewayCommon::on_initialize(6);
+        assert!(XGatewayRecords::pending_withdrawals(1).is_some());
+        assert!(XGatewayRecords::pending_withdrawals(2).is_none());
+        assert_eq!(XGatewayCommon::queued_withdrawals_at(7), vec![2]);
+        assert_eq!(
+            XGatewayCommon::withdrawal_window_of(X_BTC),
+            vec![(6, 1000), (6, 1000)]
+        );
+
+        System::set_block_number(7);
+        XGatewayCommon::on_initialize(7);
+        assert!(XGatewayRecords::pending_withdrawals(2).is_some());
+        assert!(XGatewayCommon::queued_withdrawals(2).is_none());
+        assert_eq!(queued_balance(&alice()), 0);
+        assert_eq!(XAssets::usable_balance(&alice(), &X_BTC), 97000);
+    });
+}
@@ -6,7 +6,7 @@ use sp_std::{convert::TryFrom, prelude::Vec};
 use chainx_primitives::{AssetId, ReferralId};
 use xpallet_assets::Chain;
 
-use crate::types::{TrusteeInfoConfig, TrusteeIntentionProps, TrusteeSessionInfo};
+use crate::types::{TrusteeInfoConfig, TrusteeIntentionProps, TrusteeOffence, TrusteeSessionInfo};
 
 pub trait BytesLike: Into<Vec<u8>> + TryFrom<Vec<u8>> {}
 impl<T: Into<Vec<u8>> + TryFrom<Vec<u8>>> BytesLike for T {}
@@ -34,6 +34,26 @@ pub trait TrusteeSession<AccountId, TrusteeAddress: BytesLike> {
 
     fn last_trustee_session() -> Result<TrusteeSessionInfo<AccountId, TrusteeAddress>, DispatchError>;
 
+    /// The number of previous trustee session whose funds are being moved to the current trustees.
+    fn draining_session() -> Option<u32>;
+
+    /// Mark the funds of previous trustee session are all moved to the current trustees.
+    fn finish_draining();
+
+    /// The number of current trustee session, `None` if there is no trustee session yet.
+    fn current_session_number() -> Option<u32>;
+
+    /// Record the votes of a finished proposal into the statistics of the trustees of the
+    /// session `session_number`, the trustees not voting are counted as missed only if the
+    /// proposal is not `completed`, i.e. it's dropped or stalled.
+    fn record_trustee_votes(session_number: u32, votes: &[(AccountId, bool)], completed: bool);
+
+    /// Report the misbehavior of a trustee, the bond of the trustee would be slashed.
+    fn report_offence(who: &AccountId, offence: TrusteeOffence);
+
+    /// The raw hot entity of the trustee, e.g. the hot public key of the bitcoin trustee.
+    fn trustee_hot_entity(who: &AccountId) -> Option<Vec<u8>>;
+
     #[cfg(feature = "std")]
     fn genesis_trustee(chain: Chain, init: &[AccountId]);
 }
@@ -55,6 +75,24 @@ impl<AccountId, TrusteeAddress: BytesLike> TrusteeSession<AccountId, TrusteeAddr
         Err("NoTrustee".into())
     }
 
+    fn draining_session() -> Option<u32> {
+        None
+    }
+
+    fn finish_draining() {}
+
+    fn current_session_number() -> Option<u32> {
+        None
+    }
+
+    fn record_trustee_votes(_: u32, _: &[(AccountId, bool)], _: bool) {}
+
+    fn report_offence(_: &AccountId, _: TrusteeOffence) {}
+
+    fn trustee_hot_entity(_: &AccountId) -> Option<Vec<u8>> {
+        None
+    }
+
     #[cfg(feature = "std")]
     fn genesis_trustee(_: Chain, _: &[AccountId]) {}
 }
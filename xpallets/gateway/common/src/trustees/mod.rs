@@ -13,7 +13,7 @@ use xpallet_assets::Chain;
 use xpallet_support::traits::MultiSig;
 
 use crate::traits::{BytesLike, ChainProvider, TrusteeSession};
-use crate::types::TrusteeSessionInfo;
+use crate::types::{TrusteeOffence, TrusteeSessionInfo};
 use crate::{Config, Error, Pallet};
 
 pub struct TrusteeSessionManager<T: Config, TrusteeAddress>(
@@ -70,9 +70,39 @@ impl<T: Config, TrusteeAddress: BytesLike + ChainProvider>
         })
     }
 
+    fn draining_session() -> Option<u32> {
+        Pallet::<T>::draining_session_of(TrusteeAddress::chain())
+    }
+
+    fn finish_draining() {
+        Pallet::<T>::finish_draining_impl(TrusteeAddress::chain())
+    }
+
+    fn current_session_number() -> Option<u32> {
+        Pallet::<T>::trustee_session_info_len(TrusteeAddress::chain()).checked_sub(1)
+    }
+
+    fn record_trustee_votes(session_number: u32, votes: &[(T::AccountId, bool)], completed: bool) {
+        Pallet::<T>::record_trustee_votes_impl(
+            TrusteeAddress::chain(),
+            session_number,
+            votes,
+            completed,
+        )
+    }
+
+    fn report_offence(who: &T::AccountId, offence: TrusteeOffence) {
+        Pallet::<T>::report_offence_impl(TrusteeAddress::chain(), who, offence)
+    }
+
+    fn trustee_hot_entity(who: &T::AccountId) -> Option<Vec<u8>> {
+        Pallet::<T>::trustee_intention_props_of(who, TrusteeAddress::chain())
+            .map(|props| props.0.hot_entity)
+    }
+
     #[cfg(feature = "std")]
     fn genesis_trustee(chain: Chain, trustees: &[T::AccountId]) {
-        Pallet::<T>::transition_trustee_session_impl(chain, trustees.to_vec())
+        Pallet::<T>::transition_trustee_session_impl(chain, trustees.to_vec(), false)
             .expect("trustee session transition can not fail; qed");
     }
 }
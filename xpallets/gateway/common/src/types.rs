@@ -8,7 +8,8 @@ use serde::{Deserialize, Serialize};
 use sp_runtime::RuntimeDebug;
 use sp_std::{convert::TryFrom, prelude::Vec};
 
-use chainx_primitives::Text;
+use chainx_primitives::{AddrStr, AssetId, Text};
+use xp_runtime::Memo;
 
 use crate::traits::BytesLike;
 
@@ -21,6 +22,57 @@ pub struct TrusteeInfoConfig {
     pub max_trustee_count: u32,
 }
 
+/// The signing participation statistics of a trustee in a trustee session.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Default, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct TrusteeStats {
+    /// The number of finished proposals during the session.
+    pub seen: u32,
+    /// The number of proposals signed by the trustee.
+    pub signed: u32,
+    /// The number of proposals rejected by the trustee.
+    pub rejected: u32,
+    /// The number of dropped or stalled proposals the trustee did not vote for.
+    pub missed: u32,
+}
+
+/// The provable misbehavior of a trustee.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum TrusteeOffence {
+    /// Signed a transaction spending the trustee funds outside any approved withdrawal.
+    UnauthorizedSpend,
+    /// Rejected a withdrawal proposal which was approved by the other trustees.
+    RejectValidProposal,
+}
+
+/// The sliding window rate limit of the withdrawals of an asset.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Default, RuntimeDebug, TypeInfo)]
+pub struct WithdrawalRateLimit<Balance, BlockNumber> {
+    /// The length of the sliding window in blocks.
+    pub window: BlockNumber,
+    /// The maximum total withdrawal amount of the asset in the window.
+    pub asset_cap: Balance,
+    /// The maximum withdrawal amount of an account in the window.
+    pub account_cap: Balance,
+    /// The number of blocks the withdrawals over the cap are delayed.
+    pub delay: BlockNumber,
+    /// The WITHDRAW of the asset is paused once the total in the window exceeds it.
+    pub breaker_threshold: Balance,
+}
+
+/// A withdrawal delayed by the rate limit, the balance is locked until it's released.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct QueuedWithdrawal<AccountId, Balance, BlockNumber> {
+    pub applicant: AccountId,
+    pub asset_id: AssetId,
+    pub balance: Balance,
+    pub addr: AddrStr,
+    pub ext: Memo,
+    pub unlock_at: BlockNumber,
+}
+
 /// The trustee session info.
 #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
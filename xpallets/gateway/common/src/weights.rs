@@ -19,6 +19,10 @@
 // --output=./xpallets/gateway/common/src/weights.rs
 // --template=./scripts/xpallet-weight-template.hbs
 
+// TODO: re-run the benchmarks, the weights of `withdraw`, `cancel_withdrawal`, `setup_trustee`,
+// `transition_trustee_session` and `set_withdrawal_state` are adjusted by hand, and the weights
+// from `force_finish_draining` to `release_queued_withdrawal` are estimated by hand.
+
 #![allow(unused_parens)]
 #![allow(unused_imports)]
 
@@ -37,36 +41,43 @@ pub trait WeightInfo {
     fn set_withdrawal_state() -> Weight;
     fn set_trustee_info_config() -> Weight;
     fn force_set_referral_binding() -> Weight;
+    fn force_finish_draining() -> Weight;
+    fn set_max_missed_proposals() -> Weight;
+    fn withdraw_trustee_bond() -> Weight;
+    fn set_withdrawal_rate_limit() -> Weight;
+    fn cancel_queued_withdrawal() -> Weight;
+    fn release_queued_withdrawal() -> Weight;
 }
 
 /// Weights for xpallet_gateway_common using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
     fn withdraw() -> Weight {
-        (226_245_000_u64)
-            .saturating_add(T::DbWeight::get().reads(10_u64))
-            .saturating_add(T::DbWeight::get().writes(6_u64))
+        (238_512_000_u64)
+            .saturating_add(T::DbWeight::get().reads(13_u64))
+            .saturating_add(T::DbWeight::get().writes(9_u64))
     }
     fn cancel_withdrawal() -> Weight {
         (130_921_000_u64)
-            .saturating_add(T::DbWeight::get().reads(6_u64))
-            .saturating_add(T::DbWeight::get().writes(4_u64))
+            .saturating_add(T::DbWeight::get().reads(7_u64))
+            .saturating_add(T::DbWeight::get().writes(10_u64))
     }
     fn setup_trustee() -> Weight {
         (40_920_000_u64)
-            .saturating_add(T::DbWeight::get().reads(1_u64))
-            .saturating_add(T::DbWeight::get().writes(1_u64))
+            .saturating_add(T::DbWeight::get().reads(3_u64))
+            .saturating_add(T::DbWeight::get().writes(3_u64))
     }
     fn transition_trustee_session(u: u32) -> Weight {
         (135_412_000_u64)
             .saturating_add((2_000_u64).saturating_mul(u as Weight))
             .saturating_add(T::DbWeight::get().reads(8_u64))
+            .saturating_add(T::DbWeight::get().reads((u as Weight).saturating_add(1)))
             .saturating_add(T::DbWeight::get().writes(3_u64))
     }
     fn set_withdrawal_state() -> Weight {
         (217_002_000_u64)
-            .saturating_add(T::DbWeight::get().reads(11_u64))
-            .saturating_add(T::DbWeight::get().writes(6_u64))
+            .saturating_add(T::DbWeight::get().reads(12_u64))
+            .saturating_add(T::DbWeight::get().writes(10_u64))
     }
     fn set_trustee_info_config() -> Weight {
         (6_432_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
@@ -74,35 +85,62 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
     fn force_set_referral_binding() -> Weight {
         (30_667_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
     }
+    fn force_finish_draining() -> Weight {
+        (8_517_000_u64)
+            .saturating_add(T::DbWeight::get().reads(1_u64))
+            .saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    fn set_max_missed_proposals() -> Weight {
+        (6_208_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    fn withdraw_trustee_bond() -> Weight {
+        (52_384_000_u64)
+            .saturating_add(T::DbWeight::get().reads(5_u64))
+            .saturating_add(T::DbWeight::get().writes(3_u64))
+    }
+    fn set_withdrawal_rate_limit() -> Weight {
+        (7_135_000_u64).saturating_add(T::DbWeight::get().writes(1_u64))
+    }
+    fn cancel_queued_withdrawal() -> Weight {
+        (61_872_000_u64)
+            .saturating_add(T::DbWeight::get().reads(6_u64))
+            .saturating_add(T::DbWeight::get().writes(3_u64))
+    }
+    fn release_queued_withdrawal() -> Weight {
+        (281_406_000_u64)
+            .saturating_add(T::DbWeight::get().reads(16_u64))
+            .saturating_add(T::DbWeight::get().writes(11_u64))
+    }
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
     fn withdraw() -> Weight {
-        (226_245_000_u64)
-            .saturating_add(RocksDbWeight::get().reads(10_u64))
-            .saturating_add(RocksDbWeight::get().writes(6_u64))
+        (238_512_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(13_u64))
+            .saturating_add(RocksDbWeight::get().writes(9_u64))
     }
     fn cancel_withdrawal() -> Weight {
         (130_921_000_u64)
-            .saturating_add(RocksDbWeight::get().reads(6_u64))
-            .saturating_add(RocksDbWeight::get().writes(4_u64))
+            .saturating_add(RocksDbWeight::get().reads(7_u64))
+            .saturating_add(RocksDbWeight::get().writes(10_u64))
     }
     fn setup_trustee() -> Weight {
         (40_920_000_u64)
-            .saturating_add(RocksDbWeight::get().reads(1_u64))
-            .saturating_add(RocksDbWeight::get().writes(1_u64))
+            .saturating_add(RocksDbWeight::get().reads(3_u64))
+            .saturating_add(RocksDbWeight::get().writes(3_u64))
     }
     fn transition_trustee_session(u: u32) -> Weight {
         (135_412_000_u64)
             .saturating_add((2_000_u64).saturating_mul(u as Weight))
             .saturating_add(RocksDbWeight::get().reads(8_u64))
+            .saturating_add(RocksDbWeight::get().reads((u as Weight).saturating_add(1)))
             .saturating_add(RocksDbWeight::get().writes(3_u64))
     }
     fn set_withdrawal_state() -> Weight {
         (217_002_000_u64)
-            .saturating_add(RocksDbWeight::get().reads(11_u64))
-            .saturating_add(RocksDbWeight::get().writes(6_u64))
+            .saturating_add(RocksDbWeight::get().reads(12_u64))
+            .saturating_add(RocksDbWeight::get().writes(10_u64))
     }
     fn set_trustee_info_config() -> Weight {
         (6_432_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
@@ -110,4 +148,30 @@ impl WeightInfo for () {
     fn force_set_referral_binding() -> Weight {
         (30_667_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
     }
+    fn force_finish_draining() -> Weight {
+        (8_517_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(1_u64))
+            .saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    fn set_max_missed_proposals() -> Weight {
+        (6_208_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    fn withdraw_trustee_bond() -> Weight {
+        (52_384_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(5_u64))
+            .saturating_add(RocksDbWeight::get().writes(3_u64))
+    }
+    fn set_withdrawal_rate_limit() -> Weight {
+        (7_135_000_u64).saturating_add(RocksDbWeight::get().writes(1_u64))
+    }
+    fn cancel_queued_withdrawal() -> Weight {
+        (61_872_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(6_u64))
+            .saturating_add(RocksDbWeight::get().writes(3_u64))
+    }
+    fn release_queued_withdrawal() -> Weight {
+        (281_406_000_u64)
+            .saturating_add(RocksDbWeight::get().reads(16_u64))
+            .saturating_add(RocksDbWeight::get().writes(11_u64))
+    }
 }
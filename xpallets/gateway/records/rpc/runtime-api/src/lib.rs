@@ -3,13 +3,16 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![allow(clippy::too_many_arguments, clippy::unnecessary_mut_passed)]
 
-use sp_std::collections::btree_map::BTreeMap;
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
 
 use codec::Codec;
 
 pub use chainx_primitives::{AssetId, Decimals};
 pub use xpallet_assets::Chain;
-pub use xpallet_gateway_records::{Withdrawal, WithdrawalRecordId, WithdrawalState};
+pub use xpallet_gateway_records::{
+    ArchivedWithdrawal, Withdrawal, WithdrawalFilter, WithdrawalOperator, WithdrawalPage,
+    WithdrawalRecordId, WithdrawalState, WithdrawalTransition,
+};
 
 sp_api::decl_runtime_apis! {
     pub trait XGatewayRecordsApi<AccountId, Balance, BlockNumber>
@@ -21,5 +24,11 @@ sp_api::decl_runtime_apis! {
         fn withdrawal_list() -> BTreeMap<WithdrawalRecordId, Withdrawal<AccountId, Balance, BlockNumber>>;
 
         fn withdrawal_list_by_chain(chain: Chain) -> BTreeMap<WithdrawalRecordId, Withdrawal<AccountId, Balance, BlockNumber>>;
+
+        fn withdrawal_page(filter: WithdrawalFilter<AccountId, BlockNumber>, cursor: Option<WithdrawalRecordId>, page_size: u32) -> WithdrawalPage<AccountId, Balance, BlockNumber>;
+
+        fn archived_withdrawal(id: WithdrawalRecordId) -> Option<ArchivedWithdrawal<AccountId, Balance, BlockNumber>>;
+
+        fn archived_withdrawal_list(offset: u32, limit: u32) -> Vec<(WithdrawalRecordId, ArchivedWithdrawal<AccountId, Balance, BlockNumber>)>;
     }
 }
@@ -16,7 +16,8 @@ use sp_runtime::{generic::BlockId, traits::Block as BlockT};
 use xp_rpc::{runtime_error_into_rpc_err, Result};
 
 use xpallet_gateway_records_rpc_runtime_api::{
-    AssetId, Chain, Withdrawal, WithdrawalRecordId, WithdrawalState,
+    ArchivedWithdrawal, AssetId, Chain, Withdrawal, WithdrawalFilter, WithdrawalPage,
+    WithdrawalRecordId, WithdrawalState, WithdrawalTransition,
     XGatewayRecordsApi as GatewayRecordsRuntimeApi,
 };
 
@@ -62,6 +63,40 @@ where
         chain: Chain,
         at: Option<BlockHash>,
     ) -> Result<BTreeMap<WithdrawalRecordId, RpcWithdrawalRecord<AccountId, Balance, BlockNumber>>>;
+
+    /// Return a page of the pending withdrawals matching the filter, at most 100 per page.
+    ///
+    /// Pass the `nextCursor` of the previous page as `cursor` to fetch the next page.
+    #[rpc(name = "xgatewayrecords_withdrawalPage")]
+    fn withdrawal_page(
+        &self,
+        filter: WithdrawalFilter<AccountId, BlockNumber>,
+        cursor: Option<WithdrawalRecordId>,
+        page_size: u32,
+        at: Option<BlockHash>,
+    ) -> Result<RpcWithdrawalPage<AccountId, Balance, BlockNumber>>;
+
+    /// Return the finished or canceled withdrawal in the archive
+    #[rpc(name = "xgatewayrecords_archivedWithdrawal")]
+    fn archived_withdrawal(
+        &self,
+        id: WithdrawalRecordId,
+        at: Option<BlockHash>,
+    ) -> Result<Option<RpcArchivedWithdrawal<AccountId, Balance, BlockNumber>>>;
+
+    /// Return a page of the archived withdrawals from newest to oldest, at most 100 per page
+    #[rpc(name = "xgatewayrecords_archivedWithdrawalList")]
+    fn archived_withdrawal_list(
+        &self,
+        offset: u32,
+        limit: u32,
+        at: Option<BlockHash>,
+    ) -> Result<
+        Vec<(
+            WithdrawalRecordId,
+            RpcArchivedWithdrawal<AccountId, Balance, BlockNumber>,
+        )>,
+    >;
 }
 
 impl<C, Block, AccountId, Balance, BlockNumber>
@@ -127,6 +162,54 @@ where
             })
             .map_err(runtime_error_into_rpc_err)
     }
+
+    fn withdrawal_page(
+        &self,
+        filter: WithdrawalFilter<AccountId, BlockNumber>,
+        cursor: Option<WithdrawalRecordId>,
+        page_size: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<RpcWithdrawalPage<AccountId, Balance, BlockNumber>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.withdrawal_page(&at, filter, cursor, page_size)
+            .map(Into::into)
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn archived_withdrawal(
+        &self,
+        id: WithdrawalRecordId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<RpcArchivedWithdrawal<AccountId, Balance, BlockNumber>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.archived_withdrawal(&at, id)
+            .map(|archived| archived.map(Into::into))
+            .map_err(runtime_error_into_rpc_err)
+    }
+
+    fn archived_withdrawal_list(
+        &self,
+        offset: u32,
+        limit: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<
+        Vec<(
+            WithdrawalRecordId,
+            RpcArchivedWithdrawal<AccountId, Balance, BlockNumber>,
+        )>,
+    > {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        api.archived_withdrawal_list(&at, offset, limit)
+            .map(|list| {
+                list.into_iter()
+                    .map(|(id, archived)| (id, archived.into()))
+                    .collect()
+            })
+            .map_err(runtime_error_into_rpc_err)
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -158,3 +241,56 @@ impl<AccountId, Balance: Display + FromStr, BlockNumber>
         }
     }
 }
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcWithdrawalPage<AccountId, Balance: Display + FromStr, BlockNumber> {
+    pub withdrawals: Vec<(
+        WithdrawalRecordId,
+        RpcWithdrawalRecord<AccountId, Balance, BlockNumber>,
+    )>,
+    pub next_cursor: Option<WithdrawalRecordId>,
+}
+
+impl<AccountId, Balance: Display + FromStr, BlockNumber>
+    From<WithdrawalPage<AccountId, Balance, BlockNumber>>
+    for RpcWithdrawalPage<AccountId, Balance, BlockNumber>
+{
+    fn from(page: WithdrawalPage<AccountId, Balance, BlockNumber>) -> Self {
+        Self {
+            withdrawals: page
+                .withdrawals
+                .into_iter()
+                .map(|(id, withdrawal)| (id, withdrawal.into()))
+                .collect(),
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcArchivedWithdrawal<AccountId, Balance: Display + FromStr, BlockNumber> {
+    pub withdrawal: RpcWithdrawalRecord<AccountId, Balance, BlockNumber>,
+    pub tx_hash: Option<String>,
+    pub transitions: Vec<WithdrawalTransition<BlockNumber>>,
+}
+
+impl<AccountId, Balance: Display + FromStr, BlockNumber>
+    From<ArchivedWithdrawal<AccountId, Balance, BlockNumber>>
+    for RpcArchivedWithdrawal<AccountId, Balance, BlockNumber>
+{
+    fn from(archived: ArchivedWithdrawal<AccountId, Balance, BlockNumber>) -> Self {
+        Self {
+            withdrawal: archived.withdrawal.into(),
+            tx_hash: archived.tx_hash.map(|hash| {
+                let hex = hash
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                format!("0x{}", hex)
+            }),
+            transitions: archived.transitions,
+        }
+    }
+}
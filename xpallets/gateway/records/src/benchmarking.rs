@@ -79,13 +79,26 @@ benchmarks! {
         let u in 1 .. 64 => ();
 
         let receiver: T::AccountId = whitelisted_caller();
-        let receiver_lookup: <T::Lookup as StaticLookup>::Source = T::Lookup::unlookup(receiver.clone());
         let amount: BalanceOf<T> = 1000u32.into();
-        deposit_and_withdraw::<T>(receiver, amount);
-        let state = WithdrawalState::RootFinish;
-    }: _(RawOrigin::Root, vec![(0, state)])
+        deposit::<T>(receiver.clone(), amount * u.into());
+        for _ in 0..u {
+            let receiver_lookup: <T::Lookup as StaticLookup>::Source = T::Lookup::unlookup(receiver.clone());
+            XGatewayRecords::<T>::root_withdraw(
+                RawOrigin::Root.into(),
+                receiver_lookup,
+                ASSET_ID,
+                amount - 500u32.into(),
+                b"3LFSUKkP26hun42J1Dy6RATsbgmBJb27NF".to_vec(),
+                b"memo".to_vec().into(),
+            )
+            .unwrap();
+        }
+        let item = (0..u).map(|id| (id, WithdrawalState::RootFinish)).collect::<Vec<_>>();
+    }: _(RawOrigin::Root, item)
     verify {
-        assert_eq!(XGatewayRecords::<T>::state_of(0), None);
+        for id in 0..u {
+            assert_eq!(XGatewayRecords::<T>::state_of(id), None);
+        }
     }
 }
 
@@ -101,6 +114,7 @@ mod tests {
             assert_ok!(Pallet::<Test>::test_benchmark_root_deposit());
             assert_ok!(Pallet::<Test>::test_benchmark_root_withdraw());
             assert_ok!(Pallet::<Test>::test_benchmark_set_withdrawal_state());
+            assert_ok!(Pallet::<Test>::test_benchmark_set_withdrawal_state_list());
         });
     }
 }
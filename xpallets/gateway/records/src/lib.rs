@@ -18,6 +18,8 @@ use frame_support::{
     dispatch::{DispatchError, DispatchResult},
     ensure,
     log::{error, info},
+    traits::{Get, StorageVersion},
+    weights::Weight,
 };
 use frame_system::ensure_root;
 use sp_runtime::traits::StaticLookup;
@@ -29,7 +31,10 @@ use xp_runtime::Memo;
 use xpallet_assets::{AssetType, BalanceOf, Chain};
 use xpallet_support::try_addr;
 
-pub use self::types::{Withdrawal, WithdrawalRecord, WithdrawalRecordId, WithdrawalState};
+pub use self::types::{
+    ArchivedWithdrawal, Withdrawal, WithdrawalFilter, WithdrawalOperator, WithdrawalPage,
+    WithdrawalRecord, WithdrawalRecordId, WithdrawalState, WithdrawalTransition,
+};
 pub use self::weights::WeightInfo;
 
 pub type WithdrawalRecordOf<T> = WithdrawalRecord<
@@ -38,6 +43,25 @@ pub type WithdrawalRecordOf<T> = WithdrawalRecord<
     <T as frame_system::Config>::BlockNumber,
 >;
 
+pub type ArchivedWithdrawalOf<T> = ArchivedWithdrawal<
+    <T as frame_system::Config>::AccountId,
+    BalanceOf<T>,
+    <T as frame_system::Config>::BlockNumber,
+>;
+
+/// Max count of archived withdrawals in a page of `archived_withdrawal_list`.
+pub const MAX_ARCHIVE_PAGE_SIZE: u32 = 100;
+
+/// Max count of withdrawals in a page of `withdrawal_page`.
+pub const MAX_WITHDRAWAL_PAGE_SIZE: u32 = 100;
+
+/// Max count of archived withdrawals pruned by an archiving, the archive exceeding
+/// `MaxArchivedWithdrawals` after it's lowered is drained gradually.
+const MAX_ARCHIVE_PRUNE: u32 = 2;
+
+/// The storage version 1 adds the index of withdrawals by applicant.
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 pub use pallet::*;
 
 #[frame_support::pallet]
@@ -51,14 +75,27 @@ pub mod pallet {
         /// The overarching event type.
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
+        /// The maximum number of finished withdrawals kept in the archive, the oldest one is
+        /// pruned when it's full.
+        #[pallet::constant]
+        type MaxArchivedWithdrawals: Get<u32>;
+
         /// Weight information for extrinsics in this pallet.
         type WeightInfo: WeightInfo;
     }
 
     #[pallet::pallet]
     #[pallet::generate_store(pub(crate) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(PhantomData<T>);
 
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_runtime_upgrade() -> Weight {
+            migrate_applicant_index::<T>()
+        }
+    }
+
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         /// Deposit asset token.
@@ -90,7 +127,14 @@ pub mod pallet {
         ) -> DispatchResult {
             ensure_root(origin)?;
             let who = T::Lookup::lookup(who)?;
-            Self::withdraw(&who, asset_id, balance, addr, memo)
+            Self::withdraw_impl(
+                &who,
+                asset_id,
+                balance,
+                addr,
+                memo,
+                WithdrawalOperator::Root,
+            )
         }
 
         /// Set the state of withdrawal record with given id and state.
@@ -177,6 +221,50 @@ pub mod pallet {
     #[pallet::getter(fn state_of)]
     pub(crate) type WithdrawalStateOf<T: Config> =
         StorageMap<_, Twox64Concat, WithdrawalRecordId, WithdrawalState>;
+
+    /// The index of the pending withdrawals by applicant.
+    #[pallet::storage]
+    pub(crate) type WithdrawalsOfApplicant<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Twox64Concat,
+        WithdrawalRecordId,
+        (),
+        OptionQuery,
+    >;
+
+    /// The state transitions of the pending withdrawal.
+    #[pallet::storage]
+    #[pallet::getter(fn transitions_of)]
+    pub(crate) type WithdrawalTransitionsOf<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        WithdrawalRecordId,
+        Vec<WithdrawalTransition<T::BlockNumber>>,
+        ValueQuery,
+    >;
+
+    /// The finished or canceled withdrawals, at most `MaxArchivedWithdrawals` are kept.
+    #[pallet::storage]
+    #[pallet::getter(fn archived_withdrawal)]
+    pub(crate) type ArchivedWithdrawals<T: Config> =
+        StorageMap<_, Twox64Concat, WithdrawalRecordId, ArchivedWithdrawalOf<T>>;
+
+    /// The withdrawal archived in order, archive_index => withdrawal_id.
+    #[pallet::storage]
+    pub(crate) type ArchiveIndexOf<T: Config> =
+        StorageMap<_, Twox64Concat, u32, WithdrawalRecordId>;
+
+    /// The index of next archived withdrawal.
+    #[pallet::storage]
+    #[pallet::getter(fn next_archive_index)]
+    pub(crate) type NextArchiveIndex<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// The index of the oldest archived withdrawal which is not pruned.
+    #[pallet::storage]
+    #[pallet::getter(fn first_archive_index)]
+    pub(crate) type FirstArchiveIndex<T: Config> = StorageValue<_, u32, ValueQuery>;
 }
 
 impl<T: Config> Pallet<T> {
@@ -237,6 +325,24 @@ impl<T: Config> Pallet<T> {
         balance: BalanceOf<T>,
         addr: AddrStr,
         ext: Memo,
+    ) -> DispatchResult {
+        Self::withdraw_impl(
+            who,
+            asset_id,
+            balance,
+            addr,
+            ext,
+            WithdrawalOperator::Applicant,
+        )
+    }
+
+    fn withdraw_impl(
+        who: &T::AccountId,
+        asset_id: AssetId,
+        balance: BalanceOf<T>,
+        addr: AddrStr,
+        ext: Memo,
+        operator: WithdrawalOperator,
     ) -> DispatchResult {
         xpallet_assets::Pallet::<T>::ensure_not_native_asset(&asset_id)?;
         Self::ensure_withdrawal_available_balance(who, asset_id, balance)?;
@@ -261,7 +367,9 @@ impl<T: Config> Pallet<T> {
 
         // Set storages
         PendingWithdrawals::<T>::insert(id, record.clone());
+        WithdrawalsOfApplicant::<T>::insert(record.applicant(), id, ());
         WithdrawalStateOf::<T>::insert(id, WithdrawalState::Applying);
+        Self::note_transition(id, WithdrawalState::Applying, operator);
         let next_id = id.checked_add(1_u32).unwrap_or(0);
         NextWithdrawalRecordId::<T>::put(next_id);
 
@@ -275,12 +383,13 @@ impl<T: Config> Pallet<T> {
     pub fn process_withdrawal(id: WithdrawalRecordId, chain: Chain) -> DispatchResult {
         let (record, curr_state) = Self::ensure_withdrawal_records_exists(id)?;
         Self::ensure_asset_belongs_to_chain(record.asset_id(), chain)?;
-        Self::process_withdrawal_impl(id, curr_state)
+        Self::process_withdrawal_impl(id, curr_state, WithdrawalOperator::Trustees)
     }
 
     fn process_withdrawal_impl(
         id: WithdrawalRecordId,
         curr_state: WithdrawalState,
+        operator: WithdrawalOperator,
     ) -> DispatchResult {
         if curr_state != WithdrawalState::Applying {
             error!(
@@ -291,6 +400,7 @@ impl<T: Config> Pallet<T> {
             return Err(Error::<T>::NotApplyingState.into());
         }
         WithdrawalStateOf::<T>::insert(id, WithdrawalState::Processing);
+        Self::note_transition(id, WithdrawalState::Processing, operator);
         Self::deposit_event(Event::<T>::WithdrawalProcessed(id));
         Ok(())
     }
@@ -311,12 +421,13 @@ impl<T: Config> Pallet<T> {
     pub fn recover_withdrawal(id: WithdrawalRecordId, chain: Chain) -> DispatchResult {
         let (record, curr_state) = Self::ensure_withdrawal_records_exists(id)?;
         Self::ensure_asset_belongs_to_chain(record.asset_id(), chain)?;
-        Self::recover_withdrawal_impl(id, curr_state)
+        Self::recover_withdrawal_impl(id, curr_state, WithdrawalOperator::Trustees)
     }
 
     fn recover_withdrawal_impl(
         id: WithdrawalRecordId,
         curr_state: WithdrawalState,
+        operator: WithdrawalOperator,
     ) -> DispatchResult {
         if curr_state != WithdrawalState::Processing {
             error!(
@@ -327,6 +438,7 @@ impl<T: Config> Pallet<T> {
             return Err(Error::<T>::NotProcessingState.into());
         }
         WithdrawalStateOf::<T>::insert(id, WithdrawalState::Applying);
+        Self::note_transition(id, WithdrawalState::Applying, operator);
         Self::deposit_event(Event::<T>::WithdrawalRecovered(id));
         Ok(())
     }
@@ -347,7 +459,13 @@ impl<T: Config> Pallet<T> {
             return Err(Error::<T>::InvalidAccount.into());
         }
 
-        Self::cancel_withdrawal_impl(id, record, curr_state, WithdrawalState::NormalCancel)
+        Self::cancel_withdrawal_impl(
+            id,
+            record,
+            curr_state,
+            WithdrawalState::NormalCancel,
+            WithdrawalOperator::Applicant,
+        )
     }
 
     fn cancel_withdrawal_impl(
@@ -355,6 +473,7 @@ impl<T: Config> Pallet<T> {
         record: WithdrawalRecordOf<T>,
         curr_state: WithdrawalState,
         new_state: WithdrawalState,
+        operator: WithdrawalOperator,
     ) -> DispatchResult {
         if curr_state != WithdrawalState::Applying {
             error!(
@@ -370,7 +489,9 @@ impl<T: Config> Pallet<T> {
 
         // Remove storage
         PendingWithdrawals::<T>::remove(id);
+        WithdrawalsOfApplicant::<T>::remove(record.applicant(), id);
         WithdrawalStateOf::<T>::remove(id);
+        Self::archive_withdrawal(id, record, new_state, operator, None);
 
         Self::deposit_event(Event::<T>::WithdrawalCanceled(id, new_state));
         Ok(())
@@ -392,12 +513,29 @@ impl<T: Config> Pallet<T> {
     pub fn finish_withdrawal(
         id: WithdrawalRecordId,
         expected_chain: Option<Chain>,
+    ) -> DispatchResult {
+        Self::finish_withdrawal_with_tx(id, expected_chain, None)
+    }
+
+    /// Finish withdrawal, `tx_hash` is the hash of the transaction on the target chain which
+    /// fulfilled the withdrawal, it's kept in the archive.
+    pub fn finish_withdrawal_with_tx(
+        id: WithdrawalRecordId,
+        expected_chain: Option<Chain>,
+        tx_hash: Option<Vec<u8>>,
     ) -> DispatchResult {
         let (record, curr_state) = Self::ensure_withdrawal_records_exists(id)?;
         if let Some(chain) = expected_chain {
             Self::ensure_asset_belongs_to_chain(record.asset_id(), chain)?;
         }
-        Self::finish_withdrawal_impl(id, record, curr_state, WithdrawalState::NormalFinish)
+        Self::finish_withdrawal_impl(
+            id,
+            record,
+            curr_state,
+            WithdrawalState::NormalFinish,
+            WithdrawalOperator::Trustees,
+            tx_hash,
+        )
     }
 
     fn finish_withdrawal_impl(
@@ -405,6 +543,8 @@ impl<T: Config> Pallet<T> {
         record: WithdrawalRecordOf<T>,
         curr_state: WithdrawalState,
         new_state: WithdrawalState,
+        operator: WithdrawalOperator,
+        tx_hash: Option<Vec<u8>>,
     ) -> DispatchResult {
         if curr_state != WithdrawalState::Processing {
             error!(
@@ -420,7 +560,9 @@ impl<T: Config> Pallet<T> {
 
         // Remove storage
         PendingWithdrawals::<T>::remove(id);
+        WithdrawalsOfApplicant::<T>::remove(record.applicant(), id);
         WithdrawalStateOf::<T>::remove(id);
+        Self::archive_withdrawal(id, record, new_state, operator, tx_hash);
 
         Self::deposit_event(Event::<T>::WithdrawalFinished(id, new_state));
         Ok(())
@@ -442,34 +584,42 @@ impl<T: Config> Pallet<T> {
     pub fn set_withdrawal_state_by_root(
         id: WithdrawalRecordId,
         new_state: WithdrawalState,
+    ) -> DispatchResult {
+        Self::set_withdrawal_state_impl(id, new_state, WithdrawalOperator::Root)
+    }
+
+    fn set_withdrawal_state_impl(
+        id: WithdrawalRecordId,
+        new_state: WithdrawalState,
+        operator: WithdrawalOperator,
     ) -> DispatchResult {
         let (record, curr_state) = Self::ensure_withdrawal_records_exists(id)?;
         match (curr_state, new_state) {
             (curr, new) if curr == new => Ok(()),
             (WithdrawalState::Applying, WithdrawalState::Processing) => {
                 // State: `Applying` ==> `Processing`
-                Self::process_withdrawal_impl(id, curr_state)
+                Self::process_withdrawal_impl(id, curr_state, operator)
             }
             (WithdrawalState::Processing, WithdrawalState::Applying) => {
                 // State: `Processing` ==> `Applying`
-                Self::recover_withdrawal_impl(id, curr_state)
+                Self::recover_withdrawal_impl(id, curr_state, operator)
             }
             (WithdrawalState::Applying, WithdrawalState::NormalCancel)
             | (WithdrawalState::Applying, WithdrawalState::RootCancel) => {
                 // State: `Applying` ==> `NormalCancel`|`RootCancel`
-                Self::cancel_withdrawal_impl(id, record, curr_state, new_state)
+                Self::cancel_withdrawal_impl(id, record, curr_state, new_state, operator)
             }
             (WithdrawalState::Applying, WithdrawalState::NormalFinish)
             | (WithdrawalState::Applying, WithdrawalState::RootFinish) => {
                 // State: `Applying` ==> `Processing` ==> `NormalFinish`|`RootFinish`
-                Self::process_withdrawal_impl(id, curr_state)?;
+                Self::process_withdrawal_impl(id, curr_state, operator)?;
                 let curr_state = Self::state_of(id).ok_or(Error::<T>::NotExisted)?;
-                Self::finish_withdrawal_impl(id, record, curr_state, new_state)
+                Self::finish_withdrawal_impl(id, record, curr_state, new_state, operator, None)
             }
             (WithdrawalState::Processing, WithdrawalState::NormalFinish)
             | (WithdrawalState::Processing, WithdrawalState::RootFinish) => {
                 // State: `Processing` ==> `NormalFinish`|`RootFinish`
-                Self::finish_withdrawal_impl(id, record, curr_state, new_state)
+                Self::finish_withdrawal_impl(id, record, curr_state, new_state, operator, None)
             }
             _ => {
                 error!(
@@ -510,7 +660,7 @@ impl<T: Config> Pallet<T> {
                 return Err(Error::<T>::InvalidState.into());
             }
         }
-        Self::set_withdrawal_state(frame_system::RawOrigin::Root.into(), id, new_state)
+        Self::set_withdrawal_state_impl(id, new_state, WithdrawalOperator::Trustees)
     }
 
     fn lock(who: &T::AccountId, asset_id: AssetId, value: BalanceOf<T>) -> DispatchResult {
@@ -543,6 +693,62 @@ impl<T: Config> Pallet<T> {
         xpallet_assets::Pallet::<T>::destroy_reserved_withdrawal(&asset_id, who, value)?;
         Ok(())
     }
+
+    fn note_transition(
+        id: WithdrawalRecordId,
+        state: WithdrawalState,
+        operator: WithdrawalOperator,
+    ) {
+        let transition = WithdrawalTransition {
+            state,
+            height: frame_system::Pallet::<T>::block_number(),
+            operator,
+        };
+        WithdrawalTransitionsOf::<T>::append(id, transition);
+    }
+
+    /// Move the finished or canceled withdrawal into the archive, the oldest archived withdrawals
+    /// are pruned if the archive is full.
+    fn archive_withdrawal(
+        id: WithdrawalRecordId,
+        record: WithdrawalRecordOf<T>,
+        state: WithdrawalState,
+        operator: WithdrawalOperator,
+        tx_hash: Option<Vec<u8>>,
+    ) {
+        Self::note_transition(id, state, operator);
+        let archived = ArchivedWithdrawal {
+            withdrawal: Withdrawal::new(record, state),
+            tx_hash,
+            transitions: WithdrawalTransitionsOf::<T>::take(id),
+        };
+
+        let max = T::MaxArchivedWithdrawals::get();
+        let index = Self::next_archive_index();
+        if max > 0 {
+            ArchivedWithdrawals::<T>::insert(id, archived);
+            ArchiveIndexOf::<T>::insert(index, id);
+            NextArchiveIndex::<T>::put(index.wrapping_add(1));
+        }
+        Self::prune_archive(max);
+    }
+
+    /// Prune at most `MAX_ARCHIVE_PRUNE` oldest archived withdrawals exceeding `max`.
+    fn prune_archive(max: u32) {
+        let end = Self::next_archive_index();
+        let mut first = Self::first_archive_index();
+        let mut pruned = 0;
+        while end.saturating_sub(first) > max && pruned < MAX_ARCHIVE_PRUNE {
+            if let Some(id) = ArchiveIndexOf::<T>::take(first) {
+                ArchivedWithdrawals::<T>::remove(id);
+            }
+            first = first.saturating_add(1);
+            pruned += 1;
+        }
+        if pruned > 0 {
+            FirstArchiveIndex::<T>::put(first);
+        }
+    }
 }
 
 impl<T: Config> Pallet<T> {
@@ -569,7 +775,93 @@ impl<T: Config> Pallet<T> {
             .collect()
     }
 
+    /// Returns a page of the pending withdrawals matching the `filter`, continuing after the
+    /// withdrawal `cursor` in the storage order.
+    ///
+    /// The index by applicant is used if the applicant is specified in the filter.
+    pub fn withdrawal_page(
+        filter: WithdrawalFilter<T::AccountId, T::BlockNumber>,
+        cursor: Option<WithdrawalRecordId>,
+        page_size: u32,
+    ) -> WithdrawalPage<T::AccountId, BalanceOf<T>, T::BlockNumber> {
+        let ids: Box<dyn Iterator<Item = WithdrawalRecordId>> = match (&filter.applicant, cursor) {
+            (Some(applicant), Some(cursor)) => {
+                Box::new(WithdrawalsOfApplicant::<T>::iter_key_prefix_from(
+                    applicant,
+                    WithdrawalsOfApplicant::<T>::hashed_key_for(applicant, cursor),
+                ))
+            }
+            (Some(applicant), None) => {
+                Box::new(WithdrawalsOfApplicant::<T>::iter_key_prefix(applicant))
+            }
+            (None, Some(cursor)) => Box::new(PendingWithdrawals::<T>::iter_keys_from(
+                PendingWithdrawals::<T>::hashed_key_for(cursor),
+            )),
+            (None, None) => Box::new(PendingWithdrawals::<T>::iter_keys()),
+        };
+
+        let page_size = page_size.min(MAX_WITHDRAWAL_PAGE_SIZE).max(1) as usize;
+        let mut withdrawals = Vec::new();
+        let mut next_cursor = None;
+        for id in ids {
+            let withdrawal = match Self::pending_withdrawals(id) {
+                Some(record) => Withdrawal::new(record, Self::state_of(id).unwrap_or_default()),
+                None => continue,
+            };
+            if !filter.matches(&withdrawal) {
+                continue;
+            }
+            // there is another matched withdrawal after the page
+            if withdrawals.len() == page_size {
+                next_cursor = withdrawals.last().map(|(id, _)| *id);
+                break;
+            }
+            withdrawals.push((id, withdrawal));
+        }
+        WithdrawalPage {
+            withdrawals,
+            next_cursor,
+        }
+    }
+
+    /// Returns at most `limit` archived withdrawals from newest to oldest, skipping the newest
+    /// `offset` ones.
+    pub fn archived_withdrawal_list(
+        offset: u32,
+        limit: u32,
+    ) -> Vec<(WithdrawalRecordId, ArchivedWithdrawalOf<T>)> {
+        let end = Self::next_archive_index();
+        let start = end.saturating_sub(T::MaxArchivedWithdrawals::get());
+        (start..end.saturating_sub(offset))
+            .rev()
+            .take(limit.min(MAX_ARCHIVE_PAGE_SIZE) as usize)
+            .filter_map(|index| {
+                let id = ArchiveIndexOf::<T>::get(index)?;
+                Self::archived_withdrawal(id).map(|archived| (id, archived))
+            })
+            .collect()
+    }
+
     pub fn withdrawal_state_insert(id: WithdrawalRecordId, state: WithdrawalState) {
         WithdrawalStateOf::<T>::insert(id, state)
     }
 }
+
+/// Build the index by applicant for the existing pending withdrawals.
+fn migrate_applicant_index<T: Config>() -> Weight {
+    if StorageVersion::get::<Pallet<T>>() >= STORAGE_VERSION {
+        return T::DbWeight::get().reads(1);
+    }
+    let mut count = 0;
+    for (id, record) in PendingWithdrawals::<T>::iter() {
+        WithdrawalsOfApplicant::<T>::insert(record.applicant(), id, ());
+        count += 1;
+    }
+    STORAGE_VERSION.put::<Pallet<T>>();
+    info!(
+        target: "runtime::gateway::records",
+        "[migrate_applicant_index] Index {} pending withdrawals by applicant",
+        count
+    );
+    T::DbWeight::get().reads_writes(count + 1, count + 1)
+}
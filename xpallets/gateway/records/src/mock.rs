@@ -107,8 +107,13 @@ impl xpallet_assets::Config for Test {
     type WeightInfo = ();
 }
 
+parameter_types! {
+    pub static MaxArchivedWithdrawals: u32 = 3;
+}
+
 impl Config for Test {
     type Event = ();
+    type MaxArchivedWithdrawals = MaxArchivedWithdrawals;
     type WeightInfo = ();
 }
 
@@ -206,3 +206,196 @@ fn test_withdrawal_chainx() {
         );
     })
 }
+
+#[test]
+fn test_archived_withdrawals() {
+    ExtBuilder::default().build_and_execute(|| {
+        let withdraw = || {
+            XGatewayRecords::withdraw(&ALICE, X_BTC, 10, b"addr".to_vec(), b"ext".to_vec().into())
+        };
+        for _ in 0..4 {
+            assert_ok!(withdraw());
+        }
+
+        // processed by trustees and finished by the tx
+        System::set_block_number(2);
+        assert_ok!(XGatewayRecords::process_withdrawals(&[0], Chain::Bitcoin));
+        System::set_block_number(3);
+        assert_ok!(XGatewayRecords::finish_withdrawal_with_tx(
+            0,
+            None,
+            Some(vec![1; 32])
+        ));
+        let archived = XGatewayRecords::archived_withdrawal(0).unwrap();
+        assert_eq!(archived.withdrawal.state, WithdrawalState::NormalFinish);
+        assert_eq!(archived.tx_hash, Some(vec![1; 32]));
+        assert_eq!(
+            archived.transitions,
+            vec![
+                WithdrawalTransition {
+                    state: WithdrawalState::Applying,
+                    height: 1,
+                    operator: WithdrawalOperator::Applicant,
+                },
+                WithdrawalTransition {
+                    state: WithdrawalState::Processing,
+                    height: 2,
+                    operator: WithdrawalOperator::Trustees,
+                },
+                WithdrawalTransition {
+                    state: WithdrawalState::NormalFinish,
+                    height: 3,
+                    operator: WithdrawalOperator::Trustees,
+                },
+            ]
+        );
+        assert!(XGatewayRecords::transitions_of(0).is_empty());
+
+        // canceled by the applicant and root, finished by root
+        assert_ok!(XGatewayRecords::cancel_withdrawal(1, &ALICE));
+        assert_ok!(XGatewayRecords::set_withdrawal_state(
+            RawOrigin::Root.into(),
+            2,
+            WithdrawalState::RootCancel
+        ));
+        assert_ok!(XGatewayRecords::set_withdrawal_state(
+            RawOrigin::Root.into(),
+            3,
+            WithdrawalState::RootFinish
+        ));
+        let archived = XGatewayRecords::archived_withdrawal(3).unwrap();
+        assert_eq!(archived.tx_hash, None);
+        assert_eq!(
+            archived
+                .transitions
+                .iter()
+                .map(|t| (t.state, t.operator))
+                .collect::<Vec<_>>(),
+            vec![
+                (WithdrawalState::Applying, WithdrawalOperator::Applicant),
+                (WithdrawalState::Processing, WithdrawalOperator::Root),
+                (WithdrawalState::RootFinish, WithdrawalOperator::Root),
+            ]
+        );
+
+        // the oldest one is pruned, the list is from newest to oldest
+        assert!(XGatewayRecords::archived_withdrawal(0).is_none());
+        let ids = |offset, limit| {
+            XGatewayRecords::archived_withdrawal_list(offset, limit)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(0, 10), vec![3, 2, 1]);
+        assert_eq!(ids(1, 1), vec![2]);
+        assert_eq!(ids(3, 10), Vec::<u32>::new());
+
+        // the archive exceeding the lowered limit is drained by the following archivings
+        MaxArchivedWithdrawals::set(1);
+        for _ in 0..2 {
+            assert_ok!(withdraw());
+        }
+        assert_ok!(XGatewayRecords::cancel_withdrawal(4, &ALICE));
+        assert_eq!(XGatewayRecords::first_archive_index(), 3);
+        assert!(XGatewayRecords::archived_withdrawal(2).is_none());
+        assert!(XGatewayRecords::archived_withdrawal(3).is_some());
+        assert_ok!(XGatewayRecords::cancel_withdrawal(5, &ALICE));
+        assert_eq!(XGatewayRecords::first_archive_index(), 5);
+        assert!(XGatewayRecords::archived_withdrawal(4).is_none());
+        assert_eq!(ids(0, 10), vec![5]);
+    })
+}
+
+#[test]
+fn test_withdrawal_page() {
+    ExtBuilder::default().build_and_execute(|| {
+        let withdraw = |who, asset_id| {
+            XGatewayRecords::withdraw(&who, asset_id, 10, b"addr".to_vec(), b"ext".to_vec().into())
+        };
+        assert_ok!(withdraw(ALICE, X_BTC));
+        assert_ok!(withdraw(BOB, X_BTC));
+        assert_ok!(withdraw(ALICE, X_ETH));
+        System::set_block_number(2);
+        assert_ok!(withdraw(ALICE, X_BTC));
+        assert_ok!(withdraw(BOB, X_ETH));
+        assert_ok!(XGatewayRecords::process_withdrawals(&[3], Chain::Bitcoin));
+
+        // the withdrawals are in the storage order, sort them for the comparison
+        let page = |filter, cursor, page_size| {
+            let page = XGatewayRecords::withdrawal_page(filter, cursor, page_size);
+            let mut ids = page
+                .withdrawals
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>();
+            ids.sort_unstable();
+            (ids, page.next_cursor)
+        };
+        // fetch all the pages by the cursor
+        let pages = |filter: WithdrawalFilter<AccountId, BlockNumber>, page_size| {
+            let mut ids = vec![];
+            let mut cursor = None;
+            loop {
+                let page = XGatewayRecords::withdrawal_page(filter.clone(), cursor, page_size);
+                assert!(page.withdrawals.len() <= page_size as usize);
+                ids.extend(page.withdrawals.into_iter().map(|(id, _)| id));
+                // the cursor is the last withdrawal of the page
+                cursor = page.next_cursor;
+                match cursor {
+                    Some(cursor) => assert_eq!(ids.last(), Some(&cursor)),
+                    None => break,
+                }
+            }
+            ids.sort_unstable();
+            ids
+        };
+        let all = WithdrawalFilter::default();
+        let alice = WithdrawalFilter {
+            applicant: Some(ALICE),
+            ..Default::default()
+        };
+
+        // filters
+        assert_eq!(page(alice.clone(), None, 10), (vec![0, 2, 3], None));
+        let filter = WithdrawalFilter {
+            asset_id: Some(X_BTC),
+            ..alice.clone()
+        };
+        assert_eq!(page(filter, None, 10), (vec![0, 3], None));
+        let filter = WithdrawalFilter {
+            state: Some(WithdrawalState::Processing),
+            ..Default::default()
+        };
+        assert_eq!(page(filter, None, 10), (vec![3], None));
+        let filter = WithdrawalFilter {
+            from_height: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(page(filter, None, 10), (vec![3, 4], None));
+        let filter = WithdrawalFilter {
+            to_height: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(page(filter, None, 10), (vec![0, 1, 2], None));
+
+        // cursor
+        assert_eq!(pages(all.clone(), 2), vec![0, 1, 2, 3, 4]);
+        assert_eq!(pages(all.clone(), 1), vec![0, 1, 2, 3, 4]);
+        assert_eq!(pages(alice.clone(), 2), vec![0, 2, 3]);
+        assert_eq!(pages(alice.clone(), 1), vec![0, 2, 3]);
+        let (ids, cursor) = page(all.clone(), None, 4);
+        assert_eq!(ids.len(), 4);
+        let (rest, next) = page(all.clone(), cursor, 4);
+        assert_eq!(rest.len(), 1);
+        assert!(!ids.contains(&rest[0]));
+        assert_eq!(next, None);
+
+        // the index is removed once the withdrawal is finished or canceled
+        assert_ok!(XGatewayRecords::finish_withdrawal(3, None));
+        assert_ok!(XGatewayRecords::cancel_withdrawal(0, &ALICE));
+        assert!(!WithdrawalsOfApplicant::<Test>::contains_key(ALICE, 0));
+        assert!(!WithdrawalsOfApplicant::<Test>::contains_key(ALICE, 3));
+        assert_eq!(page(alice, None, 10), (vec![2], None));
+        assert_eq!(page(all, None, 10), (vec![1, 2, 4], None));
+    })
+}
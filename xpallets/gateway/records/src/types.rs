@@ -7,6 +7,8 @@ use serde::{Deserialize, Serialize};
 
 use sp_runtime::RuntimeDebug;
 
+use sp_std::prelude::Vec;
+
 use chainx_primitives::{AddrStr, AssetId};
 use xp_runtime::Memo;
 
@@ -94,7 +96,7 @@ where
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Encode, Decode, TypeInfo)]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
 pub struct Withdrawal<AccountId, Balance, BlockNumber> {
     pub asset_id: AssetId,
     pub applicant: AccountId,
@@ -121,3 +123,84 @@ impl<AccountId, Balance, BlockNumber> Withdrawal<AccountId, Balance, BlockNumber
         }
     }
 }
+
+/// The operator which triggered the state transition of a withdrawal.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum WithdrawalOperator {
+    /// The applicant of the withdrawal.
+    Applicant,
+    /// The trustees of the target chain.
+    Trustees,
+    /// The root.
+    Root,
+}
+
+/// A state transition of a withdrawal, the state lasts until the height of the next transition.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct WithdrawalTransition<BlockNumber> {
+    pub state: WithdrawalState,
+    pub height: BlockNumber,
+    pub operator: WithdrawalOperator,
+}
+
+/// A finished or canceled withdrawal kept in the archive.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct ArchivedWithdrawal<AccountId, Balance, BlockNumber> {
+    /// The withdrawal with the final state.
+    pub withdrawal: Withdrawal<AccountId, Balance, BlockNumber>,
+    /// The hash of the transaction on the target chain which fulfilled the withdrawal.
+    pub tx_hash: Option<Vec<u8>>,
+    /// The state transitions from the application.
+    pub transitions: Vec<WithdrawalTransition<BlockNumber>>,
+}
+
+/// The filter of the paginated withdrawal list, all the conditions must be matched.
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct WithdrawalFilter<AccountId, BlockNumber> {
+    pub applicant: Option<AccountId>,
+    pub asset_id: Option<AssetId>,
+    pub state: Option<WithdrawalState>,
+    /// The withdrawals applied at or after the height.
+    pub from_height: Option<BlockNumber>,
+    /// The withdrawals applied at or before the height.
+    pub to_height: Option<BlockNumber>,
+}
+
+impl<AccountId: PartialEq, BlockNumber: PartialOrd> WithdrawalFilter<AccountId, BlockNumber> {
+    pub fn matches<Balance>(
+        &self,
+        withdrawal: &Withdrawal<AccountId, Balance, BlockNumber>,
+    ) -> bool {
+        self.applicant
+            .as_ref()
+            .map_or(true, |applicant| *applicant == withdrawal.applicant)
+            && self
+                .asset_id
+                .map_or(true, |asset_id| asset_id == withdrawal.asset_id)
+            && self.state.map_or(true, |state| state == withdrawal.state)
+            && self
+                .from_height
+                .as_ref()
+                .map_or(true, |from| withdrawal.height >= *from)
+            && self
+                .to_height
+                .as_ref()
+                .map_or(true, |to| withdrawal.height <= *to)
+    }
+}
+
+/// A page of the pending withdrawals in the storage order.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct WithdrawalPage<AccountId, Balance, BlockNumber> {
+    pub withdrawals: Vec<(
+        WithdrawalRecordId,
+        Withdrawal<AccountId, Balance, BlockNumber>,
+    )>,
+    /// The id of the last withdrawal in this page to continue from, `None` if it's the last page.
+    pub next_cursor: Option<WithdrawalRecordId>,
+}